@@ -0,0 +1,596 @@
+// Copyright (c) 2018-2021 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use primitive_types::H256;
+use super::crypto::common::ReadLE64;
+use super::hash::{CHash256, CSipHasher};
+use super::primitives::block::CBlock;
+use super::script::{CScript, opcodetype::OP_RETURN};
+use super::serialize::{Serialize, Unserialize, WriteStream, ReadStream, SER, ReadCompactSize, WriteCompactSize};
+use super::streams::{BitStreamReader, BitStreamWriter, CDataStream};
+use std::collections::HashSet;
+use std::io;
+
+/// SerType used to serialize parameters in GCS filter encoding.
+const GCS_SER_TYPE: SER = SER::NETWORK;
+
+/// Protocol version used to serialize parameters in GCS filter encoding.
+const GCS_SER_VERSION: i32 = 0;
+
+pub type Element = Vec<u8>;
+pub type ElementSet = HashSet<Element>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    pub m_siphash_k0: u64,
+    pub m_siphash_k1: u64,
+    pub m_P: u8,  // Golomb-Rice coding parameter
+    pub m_M: u32, // Inverse false positive rate
+}
+
+impl Params {
+    pub fn new(siphash_k0: u64, siphash_k1: u64, P: u8, M: u32) -> Params
+    {
+        Params { m_siphash_k0: siphash_k0, m_siphash_k1: siphash_k1, m_P: P, m_M: M }
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self
+    {
+        Params::new(0, 0, 0, 1)
+    }
+}
+
+//template <typename OStream>
+//static void GolombRiceEncode(BitStreamWriter<OStream>& bitwriter, uint8_t P, uint64_t x)
+fn GolombRiceEncode<S: WriteStream>(bitwriter: &mut BitStreamWriter<S>, P: u8, x: u64)
+{
+    // Write quotient as unary-encoded: q 1's followed by one 0.
+    let mut q = x >> P;
+    while q > 0 {
+        let nbits = if q <= 64 { q as i32 } else { 64 };
+        bitwriter.Write(!0u64, nbits);
+        q -= nbits as u64;
+    }
+    bitwriter.Write(0, 1);
+
+    // Write the remainder in P bits. Since the remainder is just the bottom
+    // P bits of x, there is no need to mask first.
+    bitwriter.Write(x, P as i32);
+}
+
+//template <typename IStream>
+//static uint64_t GolombRiceDecode(BitStreamReader<IStream>& bitreader, uint8_t P)
+fn GolombRiceDecode<S: ReadStream>(bitreader: &mut BitStreamReader<S>, P: u8) -> io::Result<u64>
+{
+    // Read unary-encoded quotient: q 1's followed by one 0.
+    let mut q: u64 = 0;
+    while bitreader.Read(1)? == 1 {
+        q += 1;
+    }
+
+    let r = bitreader.Read(P as i32)?;
+
+    Ok((q << P) + r)
+}
+
+// Map a value x that is uniformly distributed in the range [0, 2^64) to a
+// value uniformly distributed in [0, n) by returning the upper 64 bits of
+// x * n.
+//
+// See: https://lemire.me/blog/2016/06/27/a-fast-alternative-to-the-modulo-reduction/
+//static uint64_t MapIntoRange(uint64_t x, uint64_t n)
+fn MapIntoRange(x: u64, n: u64) -> u64
+{
+    ((x as u128 * n as u128) >> 64) as u64
+}
+
+/**
+ * This implements a Golomb-coded set as defined in BIP 158. It is a
+ * compact, probabilistic data structure for testing set membership.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GCSFilter {
+    m_params: Params,
+    m_N: u32, // Number of elements in the filter
+    m_F: u64, // Range of element hashes, F = N * M
+    m_encoded: Vec<u8>,
+}
+
+impl GCSFilter {
+    /** Hash a data element to an integer in the range [0, N * M). */
+    //uint64_t GCSFilter::HashToRange(const Element& element) const
+    fn HashToRange(&self, element: &[u8]) -> u64
+    {
+        let hash = CSipHasher::new(self.m_params.m_siphash_k0, self.m_params.m_siphash_k1)
+            .Write(element)
+            .Finalize();
+        MapIntoRange(hash, self.m_F)
+    }
+
+    //std::vector<uint64_t> GCSFilter::BuildHashedSet(const ElementSet& elements) const
+    fn BuildHashedSet(&self, elements: &ElementSet) -> Vec<u64>
+    {
+        let mut hashed_elements: Vec<u64> = elements.iter().map(|element| self.HashToRange(element)).collect();
+        hashed_elements.sort_unstable();
+        hashed_elements
+    }
+
+    /** Constructs an empty filter. */
+    //explicit GCSFilter(const Params& params = Params());
+    pub fn new(params: Params) -> GCSFilter
+    {
+        GCSFilter { m_params: params, m_N: 0, m_F: 0, m_encoded: vec![0] }
+    }
+
+    /** Reconstructs an already-created filter from an encoding. */
+    //GCSFilter(const Params& params, std::vector<unsigned char> encoded_filter, bool skip_decode_check);
+    pub fn from_encoded(params: Params, encoded_filter: Vec<u8>, skip_decode_check: bool) -> io::Result<GCSFilter>
+    {
+        let mut stream = CDataStream::from_bytes(&encoded_filter, GCS_SER_TYPE, GCS_SER_VERSION);
+
+        let N = ReadCompactSize(&mut stream, true)?;
+        let m_N = N as u32;
+        if m_N as u64 != N {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "N must be <2^32"));
+        }
+        let filter = GCSFilter {
+            m_params: params,
+            m_N,
+            m_F: m_N as u64 * params.m_M as u64,
+            m_encoded: encoded_filter,
+        };
+
+        if skip_decode_check {
+            return Ok(filter);
+        }
+
+        // Verify that the encoded filter contains exactly N elements. If it has too much or too little
+        // data, an error will be returned.
+        let mut bitreader = BitStreamReader::new(&mut stream);
+        for _ in 0..m_N {
+            GolombRiceDecode(&mut bitreader, params.m_P)?;
+        }
+        if !stream.empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "encoded_filter contains excess data"));
+        }
+        Ok(filter)
+    }
+
+    /** Builds a new filter from the params and set of elements. */
+    //GCSFilter(const Params& params, const ElementSet& elements);
+    pub fn from_elements(params: Params, elements: &ElementSet) -> GCSFilter
+    {
+        let N = elements.len();
+        let m_N = N as u32;
+        assert!(m_N as usize == N, "N must be <2^32");
+        let mut filter = GCSFilter {
+            m_params: params,
+            m_N,
+            m_F: m_N as u64 * params.m_M as u64,
+            m_encoded: vec![],
+        };
+
+        let mut stream = CDataStream::new(GCS_SER_TYPE, GCS_SER_VERSION);
+
+        WriteCompactSize(&mut stream, m_N as u64);
+
+        if !elements.is_empty() {
+            let mut bitwriter = BitStreamWriter::new(&mut stream);
+
+            let mut last_value: u64 = 0;
+            for value in filter.BuildHashedSet(elements) {
+                let delta = value - last_value;
+                GolombRiceEncode(&mut bitwriter, params.m_P, delta);
+                last_value = value;
+            }
+
+            bitwriter.Flush();
+        }
+
+        filter.m_encoded = stream.to_vec();
+        filter
+    }
+
+    pub fn GetN(&self) -> u32 { self.m_N }
+    pub fn GetParams(&self) -> &Params { &self.m_params }
+    pub fn GetEncoded(&self) -> &Vec<u8> { &self.m_encoded }
+
+    /** Helper method used to implement Match and MatchAny */
+    //bool GCSFilter::MatchInternal(const uint64_t* element_hashes, size_t size) const
+    fn MatchInternal(&self, element_hashes: &[u64]) -> bool
+    {
+        let mut stream = CDataStream::from_bytes(&self.m_encoded, GCS_SER_TYPE, GCS_SER_VERSION);
+
+        // Seek forward by size of N
+        let N = ReadCompactSize(&mut stream, true).expect("filter was validated on construction");
+        assert!(N == self.m_N as u64);
+
+        let mut bitreader = BitStreamReader::new(&mut stream);
+
+        let mut value: u64 = 0;
+        let mut hashes_index: usize = 0;
+        for _ in 0..self.m_N {
+            let delta = match GolombRiceDecode(&mut bitreader, self.m_params.m_P) {
+                Ok(delta) => delta,
+                Err(_) => return false,
+            };
+            value += delta;
+
+            loop {
+                if hashes_index == element_hashes.len() {
+                    return false;
+                } else if element_hashes[hashes_index] == value {
+                    return true;
+                } else if element_hashes[hashes_index] > value {
+                    break;
+                }
+
+                hashes_index += 1;
+            }
+        }
+
+        false
+    }
+
+    /** Checks if the element may be in the set. False positives are possible
+     * with probability 1/M.
+     */
+    //bool GCSFilter::Match(const Element& element) const
+    pub fn Match(&self, element: &[u8]) -> bool
+    {
+        let query = self.HashToRange(element);
+        self.MatchInternal(&[query])
+    }
+
+    /** Checks if any of the given elements may be in the set. False positives
+     * are possible with probability 1/M per element checked. This is more
+     * efficient that checking Match on multiple elements separately.
+     */
+    //bool GCSFilter::MatchAny(const ElementSet& elements) const
+    pub fn MatchAny(&self, elements: &ElementSet) -> bool
+    {
+        let queries = self.BuildHashedSet(elements);
+        self.MatchInternal(&queries)
+    }
+}
+
+impl Default for GCSFilter {
+    fn default() -> Self
+    {
+        GCSFilter::new(Params::default())
+    }
+}
+
+pub const BASIC_FILTER_P: u8 = 19;
+pub const BASIC_FILTER_M: u32 = 784931;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BlockFilterType {
+    BASIC = 0,
+    #[default]
+    INVALID = 255,
+}
+
+impl BlockFilterType {
+    fn from_u8(n: u8) -> BlockFilterType
+    {
+        match n {
+            0 => BlockFilterType::BASIC,
+            _ => BlockFilterType::INVALID,
+        }
+    }
+}
+
+/** Get the human-readable name for a filter type. Returns empty string for unknown types. */
+//const std::string& BlockFilterTypeName(BlockFilterType filter_type)
+pub fn BlockFilterTypeName(filter_type: BlockFilterType) -> &'static str
+{
+    match filter_type {
+        BlockFilterType::BASIC => "basic",
+        BlockFilterType::INVALID => "",
+    }
+}
+
+/** Find a filter type by its human-readable name. */
+//bool BlockFilterTypeByName(const std::string& name, BlockFilterType& filter_type)
+pub fn BlockFilterTypeByName(name: &str, filter_type: &mut BlockFilterType) -> bool
+{
+    for entry in AllBlockFilterTypes() {
+        if BlockFilterTypeName(entry) == name {
+            *filter_type = entry;
+            return true;
+        }
+    }
+    false
+}
+
+/** Get a list of known filter types. */
+//const std::set<BlockFilterType>& AllBlockFilterTypes()
+pub fn AllBlockFilterTypes() -> Vec<BlockFilterType>
+{
+    vec![BlockFilterType::BASIC]
+}
+
+/** Get a comma-separated list of known filter type names. */
+//const std::string& ListBlockFilterTypes()
+pub fn ListBlockFilterTypes() -> String
+{
+    AllBlockFilterTypes().into_iter().map(BlockFilterTypeName).collect::<Vec<_>>().join(", ")
+}
+
+//static GCSFilter::ElementSet BasicFilterElements(const CBlock& block, const CBlockUndo& block_undo)
+fn BasicFilterElements(block: &CBlock, spent_scripts: &[CScript]) -> ElementSet
+{
+    let mut elements = ElementSet::new();
+
+    for tx in &block.vtx {
+        for txout in &tx.vout {
+            let script = &txout.scriptPubKey.v;
+            if script.is_empty() || script[0] == OP_RETURN as u8 {
+                continue;
+            }
+            elements.insert(script.clone());
+        }
+    }
+
+    for script in spent_scripts {
+        if script.v.is_empty() {
+            continue;
+        }
+        elements.insert(script.v.clone());
+    }
+
+    elements
+}
+
+/**
+ * Complete block filter struct as defined in BIP 157. Serialization matches
+ * payload of "cfilter" messages.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockFilter {
+    m_filter_type: BlockFilterType,
+    m_block_hash: H256,
+    m_filter: GCSFilter,
+}
+
+impl BlockFilter {
+    //bool BlockFilter::BuildParams(GCSFilter::Params& params) const
+    fn BuildParams(filter_type: BlockFilterType, block_hash: &H256) -> Option<Params>
+    {
+        match filter_type {
+            BlockFilterType::BASIC => Some(Params::new(
+                ReadLE64(&block_hash.as_bytes()[0..8]),
+                ReadLE64(&block_hash.as_bytes()[8..16]),
+                BASIC_FILTER_P,
+                BASIC_FILTER_M,
+            )),
+            BlockFilterType::INVALID => None,
+        }
+    }
+
+    /** Reconstruct a BlockFilter from parts. */
+    //BlockFilter(BlockFilterType filter_type, const uint256& block_hash, std::vector<unsigned char> filter, bool skip_decode_check);
+    pub fn from_encoded(filter_type: BlockFilterType, block_hash: &H256, filter: Vec<u8>, skip_decode_check: bool) -> io::Result<BlockFilter>
+    {
+        let params = BlockFilter::BuildParams(filter_type, block_hash)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown filter_type"))?;
+        Ok(BlockFilter {
+            m_filter_type: filter_type,
+            m_block_hash: *block_hash,
+            m_filter: GCSFilter::from_encoded(params, filter, skip_decode_check)?,
+        })
+    }
+
+    /** Construct a new BlockFilter of the specified type from a block and the
+     * scriptPubKeys of the outputs it spends (the block's undo data).
+     */
+    //BlockFilter(BlockFilterType filter_type, const CBlock& block, const CBlockUndo& block_undo);
+    pub fn new(filter_type: BlockFilterType, block: &CBlock, spent_scripts: &[CScript]) -> io::Result<BlockFilter>
+    {
+        let block_hash = block.GetHash();
+        let params = BlockFilter::BuildParams(filter_type, &block_hash)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown filter_type"))?;
+        Ok(BlockFilter {
+            m_filter_type: filter_type,
+            m_block_hash: block_hash,
+            m_filter: GCSFilter::from_elements(params, &BasicFilterElements(block, spent_scripts)),
+        })
+    }
+
+    pub fn GetFilterType(&self) -> BlockFilterType { self.m_filter_type }
+    pub fn GetBlockHash(&self) -> &H256 { &self.m_block_hash }
+    pub fn GetFilter(&self) -> &GCSFilter { &self.m_filter }
+
+    pub fn GetEncodedFilter(&self) -> &Vec<u8>
+    {
+        self.m_filter.GetEncoded()
+    }
+
+    /** Compute the filter hash. */
+    //uint256 BlockFilter::GetHash() const
+    pub fn GetHash(&self) -> H256
+    {
+        let data = self.GetFilter().GetEncoded();
+
+        let mut result: [u8; CHash256::OUTPUT_SIZE] = [0; CHash256::OUTPUT_SIZE];
+        CHash256::new().write(data).finalize(&mut result);
+        H256::from(result)
+    }
+
+    /** Compute the filter header given the previous one. */
+    //uint256 BlockFilter::ComputeHeader(const uint256& prev_header) const
+    pub fn ComputeHeader(&self, prev_header: &H256) -> H256
+    {
+        let filter_hash = self.GetHash();
+
+        let mut result: [u8; CHash256::OUTPUT_SIZE] = [0; CHash256::OUTPUT_SIZE];
+        CHash256::new().write(filter_hash.as_bytes()).write(prev_header.as_bytes()).finalize(&mut result);
+        H256::from(result)
+    }
+}
+
+impl Serialize for BlockFilter {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        (self.m_filter_type as u8).Serialize(s);
+        self.m_block_hash.Serialize(s);
+        self.m_filter.GetEncoded().Serialize(s);
+    }
+}
+
+impl Unserialize for BlockFilter {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        let mut filter_type: u8 = 0;
+        let mut block_hash = H256::zero();
+        let mut encoded_filter: Vec<u8> = vec![];
+        filter_type.Unserialize(s)?;
+        block_hash.Unserialize(s)?;
+        encoded_filter.Unserialize(s)?;
+
+        *self = BlockFilter::from_encoded(BlockFilterType::from_u8(filter_type), &block_hash, encoded_filter, false)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::transaction::{CMutableTransaction, CTxIn, CTxOut, MakeTransactionRef};
+    use crate::version::PROTOCOL_VERSION;
+    use crate::uint256::uint256S;
+
+    #[test]
+    fn gcsfilter_test() {
+        let mut included_elements = ElementSet::new();
+        let mut excluded_elements = ElementSet::new();
+        for i in 0..100u8 {
+            included_elements.insert(vec![i; 32]);
+            excluded_elements.insert(vec![100 + i; 32]);
+        }
+
+        let filter = GCSFilter::from_elements(Params::new(0, 0, 10, 1 << 10), &included_elements);
+        for element in &included_elements {
+            assert!(filter.Match(element));
+
+            let mut insertion = excluded_elements.clone();
+            insertion.insert(element.clone());
+            assert!(filter.MatchAny(&insertion));
+        }
+
+        // Decoding the same bytes gives back the same filter
+        let decoded = GCSFilter::from_encoded(*filter.GetParams(), filter.GetEncoded().clone(), false).unwrap();
+        assert_eq!(decoded, filter);
+
+        // Truncated or padded encodings are rejected
+        let mut encoded = filter.GetEncoded().clone();
+        encoded.push(0);
+        assert!(GCSFilter::from_encoded(*filter.GetParams(), encoded.clone(), false).is_err());
+        encoded.truncate(encoded.len() - 10);
+        assert!(GCSFilter::from_encoded(*filter.GetParams(), encoded, false).is_err());
+    }
+
+    #[test]
+    fn gcsfilter_default_constructor() {
+        let filter = GCSFilter::default();
+        assert_eq!(filter.GetN(), 0);
+        assert_eq!(filter.GetEncoded().len(), 1);
+
+        let params = filter.GetParams();
+        assert_eq!(params.m_siphash_k0, 0);
+        assert_eq!(params.m_siphash_k1, 0);
+        assert_eq!(params.m_P, 0);
+        assert_eq!(params.m_M, 1);
+    }
+
+    #[test]
+    fn blockfilter_basic_test() {
+        let included_scripts: Vec<CScript> = vec![
+            // P2PK
+            CScript::new([vec![65], vec![0; 65], vec![0xac]].concat()),
+            // P2PKH
+            CScript::new([vec![0x76, 0xa9, 20], vec![1; 20], vec![0x88, 0xac]].concat()),
+            // Multisig
+            CScript::new([vec![0x51, 33], vec![2; 33], vec![0x51, 0xae]].concat()),
+        ];
+        // OP_RETURN outputs and empty scripts are not included
+        let excluded_scripts: Vec<CScript> = vec![
+            CScript::new([vec![OP_RETURN as u8, 4], vec![3; 4]].concat()),
+            CScript::default(),
+        ];
+        let spent_scripts: Vec<CScript> = vec![
+            CScript::new([vec![0x76, 0xa9, 20], vec![4; 20], vec![0x88, 0xac]].concat()),
+            CScript::new([vec![0x00, 20], vec![5; 20]].concat()),
+            CScript::default(),
+        ];
+
+        let mut tx = CMutableTransaction::new();
+        tx.vin.push(CTxIn::default());
+        for script in included_scripts.iter().chain(excluded_scripts.iter()) {
+            tx.vout.push(CTxOut::new(100, script.clone()));
+        }
+
+        let mut block = CBlock::new();
+        block.vtx.push(MakeTransactionRef(tx));
+
+        let block_filter = BlockFilter::new(BlockFilterType::BASIC, &block, &spent_scripts).unwrap();
+        let filter = block_filter.GetFilter();
+        assert_eq!(filter.GetN(), 5);
+
+        for script in &included_scripts {
+            assert!(filter.Match(&script.v));
+        }
+        for script in &spent_scripts[..2] {
+            assert!(filter.Match(&script.v));
+        }
+        assert!(!filter.Match(&excluded_scripts[0].v));
+
+        // Test serialization/unserialization.
+        let mut stream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &block_filter;
+        let mut block_filter2 = BlockFilter::default();
+        stream.unserialize(&mut block_filter2).unwrap();
+        assert_eq!(block_filter.GetFilterType(), block_filter2.GetFilterType());
+        assert_eq!(block_filter.GetBlockHash(), block_filter2.GetBlockHash());
+        assert_eq!(block_filter.GetEncodedFilter(), block_filter2.GetEncodedFilter());
+
+        let block_filter3 = BlockFilter::from_encoded(block_filter.GetFilterType(), block_filter.GetBlockHash(),
+            block_filter.GetEncodedFilter().clone(), false).unwrap();
+        assert_eq!(block_filter, block_filter3);
+
+        assert!(BlockFilter::from_encoded(BlockFilterType::INVALID, block_filter.GetBlockHash(),
+            block_filter.GetEncodedFilter().clone(), false).is_err());
+    }
+
+    #[test]
+    fn blockfilters_bip158_genesis() {
+        // Testnet genesis block, from the BIP 158 test vectors
+        let raw = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff001d1aa4ae180101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+        let mut stream = CDataStream::from_bytes(&hex::decode(raw).unwrap(), SER::NETWORK, PROTOCOL_VERSION);
+        let mut block = CBlock::new();
+        stream.unserialize(&mut block).unwrap();
+        assert_eq!(block.GetHash(), uint256S("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943"));
+
+        let filter = BlockFilter::new(BlockFilterType::BASIC, &block, &[]).unwrap();
+        assert_eq!(hex::encode(filter.GetEncodedFilter()), "019dfca8");
+
+        let header = filter.ComputeHeader(&H256::zero());
+        assert_eq!(header, uint256S("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"));
+    }
+
+    #[test]
+    fn blockfilter_type_names() {
+        assert_eq!(BlockFilterTypeName(BlockFilterType::BASIC), "basic");
+        assert_eq!(BlockFilterTypeName(BlockFilterType::INVALID), "");
+
+        let mut filter_type = BlockFilterType::INVALID;
+        assert!(BlockFilterTypeByName("basic", &mut filter_type));
+        assert_eq!(filter_type, BlockFilterType::BASIC);
+
+        assert!(!BlockFilterTypeByName("unknown", &mut filter_type));
+
+        assert_eq!(ListBlockFilterTypes(), "basic");
+    }
+}
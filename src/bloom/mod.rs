@@ -6,18 +6,23 @@ use super::script::{opcodetype, standard::TxoutType, standard::Solver, CScript};
 use super::hash::MurmurHash3;
 use super::primitives::transaction::{COutPoint, CTransaction};
 use super::streams::CDataStream;
-use super::serialize::SER;
+use super::serialize::{SER, Serialize, Unserialize, WriteStream, ReadStream};
 use super::version::PROTOCOL_VERSION;
+use super::random::GetRand;
 use primitive_types::H256;
-use std::cell::RefCell;
+use std::io;
 
 /// 20,000 items with fp rate < 0.1% or 10,000 items and <0.0001%
 const MAX_BLOOM_FILTER_SIZE: u32 = 36000; // bytes
 const MAX_HASH_FUNCS: u32 = 50;
 
+static LN2SQUARED: f64 = 0.4804530139182014246671025263266649717305529515945455;
+static LN2: f64 = std::f64::consts::LN_2;
+
 /// First two bits of nFlags control how much IsRelevantAndUpdate actually updates
 /// The remaining bits are reserved
-enum bloomflags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum bloomflags
 {
     BLOOM_UPDATE_NONE = 0,
     BLOOM_UPDATE_ALL = 1,
@@ -26,7 +31,19 @@ enum bloomflags
     BLOOM_UPDATE_MASK = 3,
 }
 
-struct CBloomFilter {
+/**
+ * BloomFilter is a probabilistic filter which SPV clients provide
+ * so that we can filter the transactions we send them.
+ *
+ * This allows for significantly more efficient transaction and block downloads.
+ *
+ * Because bloom filters are probabilistic, a SPV node can increase the false-
+ * positive rate, making us send it transactions which aren't actually its,
+ * allowing clients to trade more bandwidth for more privacy by obfuscating which
+ * keys are controlled by them.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CBloomFilter {
     vData: Vec<u8>,
     nHashFuncs: u32,
     nTweak: u32,
@@ -35,17 +52,47 @@ struct CBloomFilter {
 
 
 impl CBloomFilter {
-    //SERIALIZE_METHODS(CBloomFilter, obj) { READWRITE(obj.vData, obj.nHashFuncs, obj.nTweak, obj.nFlags); }
+    /**
+     * Creates a new bloom filter which will provide the given fp rate when filled with the given number of elements
+     * Note that if the given parameters will result in a filter outside the bounds of the protocol limits,
+     * the filter created will be as close to the given parameters as possible within the protocol limits.
+     * This will apply if nFPRate is very low or nElements is unreasonably high.
+     * nTweak is a constant which is added to the seed value passed to the hash function
+     * It should generally always be a random value (and is largely only exposed for unit testing)
+     * nFlags should be one of the BLOOM_UPDATE_* enums (not _MASK)
+     */
+    //CBloomFilter(const unsigned int nElements, const double nFPRate, const unsigned int nTweak, unsigned char nFlagsIn);
+    pub fn new(nElements: u32, nFPRate: f64, nTweakIn: u32, nFlagsIn: u8) -> CBloomFilter
+    {
+        /*
+         * The ideal size for a bloom filter with a given number of elements and false positive rate is:
+         * - nElements * log(fp rate) / ln(2)^2
+         * We ignore filter parameters which will create a bloom filter larger than the protocol limits
+         */
+        let nSize = std::cmp::min((-1.0 / LN2SQUARED * nElements as f64 * nFPRate.ln()) as u32, MAX_BLOOM_FILTER_SIZE * 8) / 8;
+        /*
+         * The ideal number of hash functions is filter size * ln(2) / number of elements
+         * Again, we ignore filter parameters which will create a bloom filter with more hash functions than the protocol limits
+         * See https://en.wikipedia.org/wiki/Bloom_filter for an explanation of these formulas
+         */
+        let nHashFuncs = std::cmp::min((nSize as f64 * 8.0 / nElements as f64 * LN2) as u32, MAX_HASH_FUNCS);
+        CBloomFilter {
+            vData: vec![0; nSize as usize],
+            nHashFuncs,
+            nTweak: nTweakIn,
+            nFlags: nFlagsIn,
+        }
+    }
 
     // inline unsigned int CBloomFilter::Hash(unsigned int nHashNum, Span<const unsigned char> vDataToHash) const
     fn Hash(&self, nHashNum:u32, vDataToHash: &[u8]) -> u32
     {
         // 0xFBA4C795 chosen as it guarantees a reasonable bit difference between nHashNum values.
-        return MurmurHash3(nHashNum * 0xFBA4C795 + self.nTweak, vDataToHash) % (self.vData.len() as u32 * 8);
+        return MurmurHash3(nHashNum.wrapping_mul(0xFBA4C795).wrapping_add(self.nTweak), vDataToHash) % (self.vData.len() as u32 * 8);
     }
 
     //void insert(Span<const unsigned char> vKey);
-    pub fn insert_span(mut self, vKey: &[u8]) {
+    pub fn insert_span(&mut self, vKey: &[u8]) {
         // Avoid divide-by-zero (CVE-2013-5700)
         if self.vData.len() == 0
         {
@@ -61,11 +108,10 @@ impl CBloomFilter {
     }
 
     //void insert(const COutPoint& outpoint);
-    pub fn insert(&self, outpoint: &COutPoint)
+    pub fn insert(&mut self, outpoint: &COutPoint)
     {
-        let stream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION);
-        stream << outpoint;
-        //self.insert(MakeUCharSpan(stream));
+        let stream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << outpoint;
+        self.insert_span(stream.data());
     }
 
     //bool contains(Span<const unsigned char> vKey) const;
@@ -78,10 +124,9 @@ impl CBloomFilter {
 
         for i in 0..self.nHashFuncs
         {
-            // SHAWN: optimize this so we don't have to copy the vector
-            let nIndex: u32 = self.Hash(i, vKey.try_into().unwrap());
+            let nIndex: u32 = self.Hash(i, vKey);
             // Checks bit nIndex of vData
-            if (self.vData[nIndex as usize >> 3] & (1 << (7 & nIndex))) != 0
+            if (self.vData[nIndex as usize >> 3] & (1 << (7 & nIndex))) == 0
             {
                 return false;
             }
@@ -91,10 +136,8 @@ impl CBloomFilter {
 
     //bool contains(const COutPoint& outpoint) const;
     pub fn contains(&self, outpoint: &COutPoint) -> bool {
-        let stream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION);
-        stream << outpoint;
-        //return self.contains_slice(MakeUCharSpan(stream));
-        false
+        let stream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << outpoint;
+        self.contains_slice(stream.data())
     }
 
     /// True if the size is <= MAX_BLOOM_FILTER_SIZE and the number of hash functions is <= MAX_HASH_FUNCS
@@ -106,7 +149,7 @@ impl CBloomFilter {
 
     /// Also adds any outputs which match the filter to the filter (to match their spending txes)
     //bool IsRelevantAndUpdate(const CTransaction& tx);
-    pub fn IsRelevantAndUpdate(&self, tx: &mut CTransaction) -> bool
+    pub fn IsRelevantAndUpdate(&mut self, tx: &CTransaction) -> bool
     {
         let mut fFound:bool = false;
         // Match if the filter contains the hash of tx
@@ -119,16 +162,13 @@ impl CBloomFilter {
             fFound = true;
         }
 
-
-        for i in 0..tx.vout.len()
+        for (i, txout) in tx.vout.iter().enumerate()
         {
-            let vout = RefCell::new(tx.vout[i].clone());
             // Match if the filter contains any arbitrary script data element in any scriptPubKey in tx
             // If this matches, also add the specific output that was matched.
             // This means clients don't have to update the filter themselves when a new relevant tx
             // is discovered in order to find spending transactions, which avoids round-tripping and race conditions.
-            //let mut pc = &mut tx.vout[i].scriptPubKey.v[0..];
-            let mut pc = &vout.borrow_mut().scriptPubKey.v[0..];
+            let mut pc = &txout.scriptPubKey.v[0..];
             //std::vector<unsigned char> data;
             let mut data:&[u8] = &[];
             //while (pc < txout.scriptPubKey.end())
@@ -146,17 +186,15 @@ impl CBloomFilter {
                     fFound = true;
                     if (self.nFlags & bloomflags::BLOOM_UPDATE_MASK as u8) == bloomflags::BLOOM_UPDATE_ALL as u8
                     {
-                        let cout = COutPoint::new(hash, i as u32);
-                        self.insert(&cout);
+                        self.insert(&COutPoint::new(hash, i as u32));
                     }
                     else if (self.nFlags & bloomflags::BLOOM_UPDATE_MASK as u8) == bloomflags::BLOOM_UPDATE_P2PUBKEY_ONLY as u8
                     {
                         let mut vSolutions: Vec<Vec<u8>> = vec![];
-                        let mut scriptPubKey = &mut vout.borrow_mut().scriptPubKey;
-                        let txout_type: TxoutType = Solver(&mut scriptPubKey, &mut vSolutions);
+                        let txout_type: TxoutType = Solver(&mut txout.scriptPubKey.clone(), &mut vSolutions);
                         if txout_type == TxoutType::PUBKEY || txout_type == TxoutType::MULTISIG
                         {
-                            self.insert(&COutPoint::new(hash, i as u32 ) );
+                            self.insert(&COutPoint::new(hash, i as u32));
                         }
                     }
                     break;
@@ -169,8 +207,7 @@ impl CBloomFilter {
             return true;
         }
     
-        // TODO: look at changing this to a map
-        for txin in tx.vin.iter_mut()
+        for txin in tx.vin.iter()
         {
             // Match if the filter contains an outpoint tx spends
             if self.contains(&txin.prevout)
@@ -178,9 +215,8 @@ impl CBloomFilter {
                 return true;
             }
 
-            let scriptSig = RefCell::new(txin.scriptSig.clone());
             // Match if the filter contains any arbitrary script data element in any scriptSig in tx
-            let mut pc = &scriptSig.borrow_mut().v[0..];
+            let mut pc = &txin.scriptSig.v[0..];
             let mut data: &[u8] = &[];
             while pc.len() > 0
             {
@@ -199,6 +235,367 @@ impl CBloomFilter {
         return false;
     
     }
+}
+
+//SERIALIZE_METHODS(CBloomFilter, obj) { READWRITE(obj.vData, obj.nHashFuncs, obj.nTweak, obj.nFlags); }
+impl Serialize for CBloomFilter {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.vData.Serialize(s);
+        self.nHashFuncs.Serialize(s);
+        self.nTweak.Serialize(s);
+        self.nFlags.Serialize(s);
+    }
+}
+
+impl Unserialize for CBloomFilter {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.vData.Unserialize(s)?;
+        self.nHashFuncs.Unserialize(s)?;
+        self.nTweak.Unserialize(s)?;
+        self.nFlags.Unserialize(s)
+    }
+}
+
+/**
+ * RollingBloomFilter is a probabilistic "keep track of most recently inserted" set.
+ * Construct it with the number of items to keep track of, and a false-positive
+ * rate. Unlike CBloomFilter, by default nTweak is set to a cryptographically
+ * secure random value for you. Similarly rather than clear() the method
+ * reset() is provided, which also changes nTweak to decrease the impact of
+ * false-positives.
+ *
+ * contains(item) will always return true if item was one of the last N to 1.5*N
+ * insert()'ed ... but may also return true for items that were not inserted.
+ *
+ * It needs around 1.8 bytes per element per factor 0.1 of false positive rate.
+ * For example, if we want 1000 elements, we'd need:
+ * - ~1800 bytes for a false positive rate of 0.1
+ * - ~3600 bytes for a false positive rate of 0.01
+ * - ~5400 bytes for a false positive rate of 0.001
+ *
+ * If we make these simplifying assumptions:
+ * - logFpRate / log(0.5) doesn't get rounded or clamped in the nHashFuncs calculation
+ * - nElements is even, so that nEntriesPerGeneration == nElements / 2
+ *
+ * Then we get a more accurate estimate for filter bytes:
+ *
+ *     3/(log(256)*log(2)) * log(1/fpRate) * nElements
+ */
+#[derive(Clone, Debug)]
+pub struct CRollingBloomFilter {
+    nEntriesPerGeneration: i32,
+    nEntriesThisGeneration: i32,
+    nGeneration: i32,
+    data: Vec<u64>,
+    nTweak: u32,
+    nHashFuncs: i32,
+}
+
+//static inline uint32_t RollingBloomHash(unsigned int nHashNum, uint32_t nTweak, Span<const unsigned char> vDataToHash)
+#[inline]
+fn RollingBloomHash(nHashNum: u32, nTweak: u32, vDataToHash: &[u8]) -> u32
+{
+    MurmurHash3(nHashNum.wrapping_mul(0xFBA4C795).wrapping_add(nTweak), vDataToHash)
+}
+
+// A replacement for x % n. This assumes that x and n are 32bit integers, and x is a uniformly random distributed 32bit value
+// which should be the case for a good hash.
+// See https://lemire.me/blog/2016/06/27/a-fast-alternative-to-the-modulo-reduction/
+//static inline uint32_t FastMod(uint32_t x, size_t n)
+#[inline]
+fn FastMod(x: u32, n: usize) -> u32
+{
+    ((x as u64 * n as u64) >> 32) as u32
+}
+
+impl CRollingBloomFilter {
+    //CRollingBloomFilter::CRollingBloomFilter(const unsigned int nElements, const double fpRate)
+    pub fn new(nElements: u32, fpRate: f64) -> CRollingBloomFilter
+    {
+        let logFpRate = fpRate.ln();
+        /* The optimal number of hash functions is log(fpRate) / log(0.5), but
+         * restrict it to the range 1-50. */
+        let nHashFuncs = std::cmp::max(1, std::cmp::min((logFpRate / 0.5f64.ln()).round() as i32, 50));
+        /* In this rolling bloom filter, we'll store between 2 and 3 generations of nElements / 2 entries. */
+        let nEntriesPerGeneration = ((nElements + 1) / 2) as i32;
+        let nMaxElements = nEntriesPerGeneration as u32 * 3;
+        /* The maximum fpRate = pow(1.0 - exp(-nHashFuncs * nMaxElements / nFilterBits), nHashFuncs)
+         * =>          pow(fpRate, 1.0 / nHashFuncs) = 1.0 - exp(-nHashFuncs * nMaxElements / nFilterBits)
+         * =>          1.0 - pow(fpRate, 1.0 / nHashFuncs) = exp(-nHashFuncs * nMaxElements / nFilterBits)
+         * =>          log(1.0 - pow(fpRate, 1.0 / nHashFuncs)) = -nHashFuncs * nMaxElements / nFilterBits
+         * =>          nFilterBits = -nHashFuncs * nMaxElements / log(1.0 - pow(fpRate, 1.0 / nHashFuncs))
+         * =>          nFilterBits = -nHashFuncs * nMaxElements / log(1.0 - exp(logFpRate / nHashFuncs))
+         */
+        let nFilterBits = (-1.0 * nHashFuncs as f64 * nMaxElements as f64 / (1.0 - (logFpRate / nHashFuncs as f64).exp()).ln()).ceil() as u32;
+        /* For each data element we need to store 2 bits. If both bits are 0, the
+         * bit is treated as unset. If the bits are (01), (10), or (11), the bit is
+         * treated as set in generation 1, 2, or 3 respectively.
+         * These bits are stored in separate integers: position P corresponds to bit
+         * (P & 63) of the integers data[(P >> 6) * 2] and data[(P >> 6) * 2 + 1]. */
+        let mut filter = CRollingBloomFilter {
+            nEntriesPerGeneration,
+            nEntriesThisGeneration: 0,
+            nGeneration: 1,
+            data: vec![0; (((nFilterBits + 63) / 64) << 1) as usize],
+            nTweak: 0,
+            nHashFuncs,
+        };
+        filter.reset();
+        filter
+    }
+
+    //void CRollingBloomFilter::insert(Span<const unsigned char> vKey)
+    pub fn insert(&mut self, vKey: &[u8])
+    {
+        if self.nEntriesThisGeneration == self.nEntriesPerGeneration {
+            self.nEntriesThisGeneration = 0;
+            self.nGeneration += 1;
+            if self.nGeneration == 4 {
+                self.nGeneration = 1;
+            }
+            let nGenerationMask1: u64 = 0u64.wrapping_sub((self.nGeneration & 1) as u64);
+            let nGenerationMask2: u64 = 0u64.wrapping_sub((self.nGeneration >> 1) as u64);
+            /* Wipe old entries that used this generation number. */
+            for p in (0..self.data.len()).step_by(2) {
+                let p1 = self.data[p];
+                let p2 = self.data[p + 1];
+                let mask = (p1 ^ nGenerationMask1) | (p2 ^ nGenerationMask2);
+                self.data[p] = p1 & mask;
+                self.data[p + 1] = p2 & mask;
+            }
+        }
+        self.nEntriesThisGeneration += 1;
+
+        for n in 0..self.nHashFuncs {
+            let h = RollingBloomHash(n as u32, self.nTweak, vKey);
+            let bit = h & 0x3F;
+            /* FastMod works with the upper bits of h, so it is safe to ignore that the lower bits of h are already used for bit. */
+            let pos = FastMod(h, self.data.len()) as usize;
+            /* The lowest bit of pos is ignored, and set to zero for the first bit, and to one for the second. */
+            self.data[pos & !1] = (self.data[pos & !1] & !(1u64 << bit)) | ((self.nGeneration & 1) as u64) << bit;
+            self.data[pos | 1] = (self.data[pos | 1] & !(1u64 << bit)) | ((self.nGeneration >> 1) as u64) << bit;
+        }
+    }
+
+    //bool CRollingBloomFilter::contains(Span<const unsigned char> vKey) const
+    pub fn contains(&self, vKey: &[u8]) -> bool
+    {
+        for n in 0..self.nHashFuncs {
+            let h = RollingBloomHash(n as u32, self.nTweak, vKey);
+            let bit = h & 0x3F;
+            let pos = FastMod(h, self.data.len()) as usize;
+            /* If the relevant bit is not set in either data[pos & ~1] or data[pos | 1], the filter does not contain vKey */
+            if ((self.data[pos & !1] | self.data[pos | 1]) >> bit) & 1 == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    //void CRollingBloomFilter::reset()
+    pub fn reset(&mut self)
+    {
+        self.nTweak = GetRand(u32::MAX);
+        self.nEntriesThisGeneration = 0;
+        self.nGeneration = 1;
+        self.data.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uint256::uint256S;
+
+    fn check_serialization(filter: &CBloomFilter, expected: &str) {
+        let stream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << filter;
+        assert_eq!(hex::encode(stream.data()), expected);
+
+        let mut stream = stream;
+        let mut filter2 = CBloomFilter::default();
+        stream.unserialize(&mut filter2).unwrap();
+        assert_eq!(filter, &filter2);
+    }
 
+    #[test]
+    fn bloom_create_insert_serialize() {
+        let mut filter = CBloomFilter::new(3, 0.01, 0, bloomflags::BLOOM_UPDATE_ALL as u8);
 
+        assert!(!filter.contains_slice(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()), "Bloom filter should be empty!");
+        filter.insert_span(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap());
+        assert!(filter.contains_slice(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()), "Bloom filter doesn't contain just-inserted object!");
+        // One bit different in first byte
+        assert!(!filter.contains_slice(&hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()), "Bloom filter contains something it shouldn't!");
+
+        filter.insert_span(&hex::decode("b5a2c786d9ef4658287ced5914b37a1b4aa32eee").unwrap());
+        assert!(filter.contains_slice(&hex::decode("b5a2c786d9ef4658287ced5914b37a1b4aa32eee").unwrap()), "Bloom filter doesn't contain just-inserted object (2)!");
+
+        filter.insert_span(&hex::decode("b9300670b4c5366e95b2699e8b18bc75e5f729c5").unwrap());
+        assert!(filter.contains_slice(&hex::decode("b9300670b4c5366e95b2699e8b18bc75e5f729c5").unwrap()), "Bloom filter doesn't contain just-inserted object (3)!");
+
+        check_serialization(&filter, "03614e9b050000000000000001");
+    }
+
+    #[test]
+    fn bloom_create_insert_serialize_with_tweak() {
+        // Same test as bloom_create_insert_serialize, but we add a nTweak of 100
+        let mut filter = CBloomFilter::new(3, 0.01, 2147483649, bloomflags::BLOOM_UPDATE_ALL as u8);
+
+        filter.insert_span(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap());
+        assert!(filter.contains_slice(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()), "Bloom filter doesn't contain just-inserted object!");
+        // One bit different in first byte
+        assert!(!filter.contains_slice(&hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()), "Bloom filter contains something it shouldn't!");
+
+        filter.insert_span(&hex::decode("b5a2c786d9ef4658287ced5914b37a1b4aa32eee").unwrap());
+        filter.insert_span(&hex::decode("b9300670b4c5366e95b2699e8b18bc75e5f729c5").unwrap());
+
+        check_serialization(&filter, "03ce4299050000000100008001");
+    }
+
+    #[test]
+    fn bloom_match() {
+        // Random real transaction (b4749f017444b051c44dfd2720e88f314ff94f3dd6d56d40ef65854fcd7fff6b)
+        let mut stream = CDataStream::from_bytes(&hex::decode("01000000010b26e9b7735eb6aabdf358bab62f9816a21ba9ebdb719d5299e88607d722c190000000008b4830450220070aca44506c5cef3a16ed519d7c3c39f8aab192c4e1c90d065f37b8a4af6141022100a8e160b856c2d43d27d8fba71e5aef6405b8643ac4cb7cb3c462aced7f14711a0141046d11fee51b0e60666d5049a9101a72741df480b96ee26488a4d3466b95c9a40ac5eeef87e10a5cd336c19a84565f80fa6c547957b7700ff4dfbdefe76036c339ffffffff021bff3d11000000001976a91404943fdd508053c75000106d3bc6e2754dbcff1988ac2f15de00000000001976a914a266436d2965547608b9e15d9032a7b9d64fa43188ac00000000").unwrap(), SER::NETWORK, PROTOCOL_VERSION);
+        let mut tx = CTransaction::default();
+        stream.unserialize(&mut tx).unwrap();
+        assert_eq!(tx.GetHash(), uint256S("b4749f017444b051c44dfd2720e88f314ff94f3dd6d56d40ef65854fcd7fff6b"));
+
+        // and one which spends it
+        let mut spendStream = CDataStream::from_bytes(&hex::decode("01000000016bff7fcd4f8565ef406dd5d63d4ff94f318fe82027fd4dc451b04474019f74b4000000008c493046022100da0dc6aecefe1e06efdf05773757deb168820930e3b0d03f46f5fcf150bf990c022100d25b5c87040076e4f253f8262e763e2dd51e7ff0be157727c4bc42807f17bd39014104e6c26ef67dc610d2cd192484789a6cf9aea9930b944b7e2db5342b9d9e5b9ff79aff9a2ee1978dd7fd01dfc522ee02283d3b06a9d03acf8096968d7dbb0f9178ffffffff028ba7940e000000001976a914badeecfdef0507247fc8f74241d73bc039972d7b88ac4094a802000000001976a914c10932483fec93ed51f5fe95e72559f2cc7043f988ac00000000").unwrap(), SER::NETWORK, PROTOCOL_VERSION);
+        let mut spendingTx = CTransaction::default();
+        spendStream.unserialize(&mut spendingTx).unwrap();
+        assert_eq!(spendingTx.vin[0].prevout.hash, tx.GetHash());
+
+        let new_filter = || CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_ALL as u8);
+
+        let mut filter = new_filter();
+        filter.insert_span(uint256S("0xb4749f017444b051c44dfd2720e88f314ff94f3dd6d56d40ef65854fcd7fff6b").as_bytes());
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match tx hash");
+
+        let mut filter = new_filter();
+        // byte-reversed tx hash
+        filter.insert_span(&hex::decode("6bff7fcd4f8565ef406dd5d63d4ff94f318fe82027fd4dc451b04474019f74b4").unwrap());
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match manually serialized tx hash");
+
+        let mut filter = new_filter();
+        filter.insert_span(&hex::decode("30450220070aca44506c5cef3a16ed519d7c3c39f8aab192c4e1c90d065f37b8a4af6141022100a8e160b856c2d43d27d8fba71e5aef6405b8643ac4cb7cb3c462aced7f14711a01").unwrap());
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match input signature");
+
+        let mut filter = new_filter();
+        filter.insert_span(&hex::decode("046d11fee51b0e60666d5049a9101a72741df480b96ee26488a4d3466b95c9a40ac5eeef87e10a5cd336c19a84565f80fa6c547957b7700ff4dfbdefe76036c339").unwrap());
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match input pub key");
+
+        let mut filter = new_filter();
+        filter.insert_span(&hex::decode("04943fdd508053c75000106d3bc6e2754dbcff19").unwrap());
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match output address");
+        assert!(filter.IsRelevantAndUpdate(&spendingTx), "Simple Bloom filter didn't add output");
+
+        let mut filter = new_filter();
+        filter.insert_span(&hex::decode("a266436d2965547608b9e15d9032a7b9d64fa431").unwrap());
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match output address");
+
+        let mut filter = new_filter();
+        filter.insert(&COutPoint::new(uint256S("0x90c122d70786e899529d71dbeba91ba216982fb6ba58f3bdaab65e73b7e9260b"), 0));
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match COutPoint");
+
+        let mut filter = new_filter();
+        let prevOutPoint = COutPoint::new(uint256S("0x90c122d70786e899529d71dbeba91ba216982fb6ba58f3bdaab65e73b7e9260b"), 0);
+        let mut data = prevOutPoint.hash.as_bytes().to_vec();
+        data.extend_from_slice(&prevOutPoint.n.to_le_bytes());
+        filter.insert_span(&data);
+        assert!(filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter didn't match manually serialized COutPoint");
+
+        let mut filter = new_filter();
+        filter.insert_span(uint256S("00000009e784f32f62ef849763d4f45b98e07ba658647343b915ff832b110436").as_bytes());
+        assert!(!filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter matched random tx hash");
+
+        let mut filter = new_filter();
+        filter.insert_span(&hex::decode("0000006d2965547608b9e15d9032a7b9d64fa431").unwrap());
+        assert!(!filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter matched random address");
+
+        let mut filter = new_filter();
+        filter.insert(&COutPoint::new(uint256S("0x90c122d70786e899529d71dbeba91ba216982fb6ba58f3bdaab65e73b7e9260b"), 1));
+        assert!(!filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter matched COutPoint for an output we didn't care about");
+
+        let mut filter = new_filter();
+        filter.insert(&COutPoint::new(uint256S("0x000000d70786e899529d71dbeba91ba216982fb6ba58f3bdaab65e73b7e9260b"), 0));
+        assert!(!filter.IsRelevantAndUpdate(&tx), "Simple Bloom filter matched COutPoint for an output we didn't care about");
+    }
+
+    #[test]
+    fn bloom_size_constraints() {
+        let filter = CBloomFilter::new(1000000, 0.0000001, 0, bloomflags::BLOOM_UPDATE_NONE as u8);
+        assert!(filter.IsWithinSizeConstraints());
+
+        // An empty filter matches everything
+        let mut empty = CBloomFilter::default();
+        assert!(empty.contains_slice(&[1, 2, 3]));
+        assert!(empty.IsRelevantAndUpdate(&CTransaction::default()));
+    }
+
+    #[test]
+    fn rolling_bloom() {
+        // last-100-entry, 1% false positive:
+        let mut rb1 = CRollingBloomFilter::new(100, 0.01);
+
+        // Overfill:
+        let data: Vec<Vec<u8>> = (0..399u32).map(|i| RandomData(i)).collect();
+        for d in &data {
+            rb1.insert(d);
+            assert!(rb1.contains(d));
+        }
+
+        // Last 100 guaranteed to be remembered:
+        for d in &data[299..] {
+            assert!(rb1.contains(d));
+        }
+
+        // false positive rate is 1%, so we should get about 100 hits if
+        // testing 10,000 random keys. We get worst-case false positive
+        // behavior when the filter is as full as possible, which is
+        // when we've inserted one minus an integer multiple of nElement*2.
+        let nHits = (0..10000u32).filter(|i| rb1.contains(&RandomData(1000000 + i))).count();
+        // Expect about 100 hits
+        assert!(nHits > 25 && nHits < 175, "nHits = {}", nHits);
+
+        assert!(rb1.contains(&data[398]));
+        rb1.reset();
+        assert!(!rb1.contains(&data[398]));
+
+        // Now roll through data, make sure last 100 entries
+        // are always remembered:
+        for (i, d) in data.iter().enumerate() {
+            if i >= 100 {
+                assert!(rb1.contains(&data[i - 100]));
+            }
+            rb1.insert(d);
+            assert!(rb1.contains(d));
+        }
+
+        // Insert 999 more random entries:
+        for i in 0..999u32 {
+            rb1.insert(&RandomData(2000000 + i));
+        }
+        // Sanity check to make sure the filter isn't just filling up:
+        let nHits = data.iter().filter(|d| rb1.contains(d)).count();
+        // Expect about 5 false positives
+        assert!(nHits < 30, "nHits = {}", nHits);
+
+        // last-1000-entry, 0.01% false positive:
+        let mut rb2 = CRollingBloomFilter::new(1000, 0.001);
+        for d in &data {
+            rb2.insert(d);
+        }
+        // ... room for all of them:
+        for d in &data {
+            assert!(rb2.contains(d));
+        }
+    }
+
+    // Distinct, well mixed keys standing in for random data
+    fn RandomData(i: u32) -> Vec<u8> {
+        let mut result = [0u8; 32];
+        crate::hash::CHash256::new().write(&i.to_le_bytes()).finalize(&mut result);
+        result.to_vec()
+    }
 }
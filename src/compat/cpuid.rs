@@ -0,0 +1,17 @@
+// Copyright (c) 2017-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use std::arch::x86_64::__cpuid_count;
+
+// We can't use cpuid.h's __get_cpuid as it does not support subleafs.
+//void static inline GetCPUID(uint32_t leaf, uint32_t subleaf, uint32_t& a, uint32_t& b, uint32_t& c, uint32_t& d)
+pub fn GetCPUID(leaf: u32, subleaf: u32, a: &mut u32, b: &mut u32, c: &mut u32, d: &mut u32)
+{
+    // CPUID is available on every x86_64 processor.
+    let r = unsafe { __cpuid_count(leaf, subleaf) };
+    *a = r.eax;
+    *b = r.ebx;
+    *c = r.ecx;
+    *d = r.edx;
+}
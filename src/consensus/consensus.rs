@@ -0,0 +1,18 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2020 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+/** The maximum allowed size for a serialized block, in bytes (only for buffer size limits) */
+pub const MAX_BLOCK_SERIALIZED_SIZE: u32 = 4000000;
+/** The maximum allowed weight for a block, see BIP 141 (network rule) */
+pub const MAX_BLOCK_WEIGHT: u32 = 4000000;
+/** The maximum allowed number of signature check operations in a block (network rule) */
+pub const MAX_BLOCK_SIGOPS_COST: i64 = 80000;
+/** Coinbase transaction outputs can only be spent after this number of new blocks (network rule) */
+pub const COINBASE_MATURITY: i32 = 100;
+
+pub const WITNESS_SCALE_FACTOR: i32 = 4;
+
+pub const MIN_TRANSACTION_WEIGHT: usize = WITNESS_SCALE_FACTOR as usize * 60; // 60 is the lower bound for the size of a valid serialized CTransaction
+pub const MIN_SERIALIZABLE_TRANSACTION_WEIGHT: usize = WITNESS_SCALE_FACTOR as usize * 10; // 10 is the lower bound for the size of a serialized CTransaction
@@ -0,0 +1,165 @@
+// Copyright (c) 2015-2020 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use primitive_types::H256;
+use crate::crypto::sha256::SHA256D64;
+use crate::primitives::block::CBlock;
+
+/*     WARNING! If you're reading this because you're learning about crypto
+       and/or designing a new system that will use merkle trees, keep in mind
+       that the following merkle tree algorithm has a serious flaw related to
+       duplicate txids, resulting in a vulnerability (CVE-2012-2459).
+
+       The reason is that if the number of hashes in the list at a given level
+       is odd, the last one is duplicated before computing the next level (which
+       is unusual in Merkle trees). This results in certain sequences of
+       transactions leading to the same merkle root. For example, these two
+       trees:
+
+                    A               A
+                  /  \            /   \
+                B     C         B       C
+               / \    |        / \     / \
+              D   E   F       D   E   F   F
+             / \ / \ / \     / \ / \ / \ / \
+             1 2 3 4 5 6     1 2 3 4 5 6 5 6
+
+       for transaction lists [1,2,3,4,5,6] and [1,2,3,4,5,6,5,6] (where 5 and
+       6 are repeated) result in the same root hash A (because the hash of both
+       of (F) and (F,F) is C).
+
+       The vulnerability results from being able to send a block with such a
+       transaction list, with the same merkle root, and the same block hash as
+       the original without duplication, resulting in failed validation. If the
+       receiving node proceeds to mark that block as permanently invalid
+       however, it will fail to accept further unmodified (and thus potentially
+       valid) versions of the same block. We defend against this by detecting
+       the case where we would hash two identical hashes at the end of the list
+       together, and treating that identically to the block having an invalid
+       merkle root. Assuming no double-SHA256 collisions, this will detect all
+       known ways of changing the transactions without affecting the merkle
+       root.
+*/
+
+//uint256 ComputeMerkleRoot(std::vector<uint256> hashes, bool* mutated)
+pub fn ComputeMerkleRoot(mut hashes: Vec<H256>, mutated: Option<&mut bool>) -> H256
+{
+    let mut mutation = false;
+    while hashes.len() > 1 {
+        if mutated.is_some() {
+            let mut pos = 0;
+            while pos + 1 < hashes.len() {
+                if hashes[pos] == hashes[pos + 1] {
+                    mutation = true;
+                }
+                pos += 2;
+            }
+        }
+        if hashes.len() & 1 != 0 {
+            hashes.push(hashes[hashes.len() - 1]);
+        }
+        //SHA256D64(hashes[0].begin(), hashes[0].begin(), hashes.size() / 2);
+        let input: Vec<u8> = hashes.iter().flat_map(|h| h.0).collect();
+        let mut output = vec![0u8; input.len() / 2];
+        SHA256D64(&mut output, &input, hashes.len() / 2);
+        hashes = output.chunks_exact(32).map(H256::from_slice).collect();
+    }
+    if let Some(mutated) = mutated {
+        *mutated = mutation;
+    }
+    if hashes.is_empty() {
+        return H256::zero();
+    }
+    hashes[0]
+}
+
+/*
+ * Compute the Merkle root of the transactions in a block.
+ * *mutated is set to true if a duplicated subtree was found.
+ */
+//uint256 BlockMerkleRoot(const CBlock& block, bool* mutated = nullptr);
+pub fn BlockMerkleRoot(block: &CBlock, mutated: Option<&mut bool>) -> H256
+{
+    let leaves: Vec<H256> = block.vtx.iter().map(|tx| tx.GetHash()).collect();
+    ComputeMerkleRoot(leaves, mutated)
+}
+
+/*
+ * Compute the Merkle root of the witness transactions in a block.
+ * *mutated is set to true if a duplicated subtree was found.
+ */
+//uint256 BlockWitnessMerkleRoot(const CBlock& block, bool* mutated = nullptr);
+pub fn BlockWitnessMerkleRoot(block: &CBlock, mutated: Option<&mut bool>) -> H256
+{
+    let mut leaves: Vec<H256> = block.vtx.iter().map(|tx| tx.GetWitnessHash()).collect();
+    if !leaves.is_empty() {
+        // The witness hash of the coinbase is 0.
+        leaves[0] = H256::zero();
+    }
+    ComputeMerkleRoot(leaves, mutated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::CHash256;
+    use crate::primitives::transaction::{CMutableTransaction, CTxIn, COutPoint, MakeTransactionRef};
+    use crate::script::CScript;
+
+    fn make_block(n: u32) -> CBlock {
+        let mut block = CBlock::new();
+        for i in 0..n {
+            let mut mtx = CMutableTransaction::new();
+            mtx.nLockTime = i;
+            mtx.vin.push(CTxIn::new(COutPoint::default(), CScript::default(), CTxIn::SEQUENCE_FINAL));
+            block.vtx.push(MakeTransactionRef(mtx));
+        }
+        block
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        let mut mutated = true;
+        assert_eq!(BlockMerkleRoot(&CBlock::new(), Some(&mut mutated)), H256::zero());
+        assert!(!mutated);
+
+        // A single transaction is its own root
+        let block = make_block(1);
+        assert_eq!(BlockMerkleRoot(&block, None), block.vtx[0].GetHash());
+
+        // Two levels, by hand
+        let block = make_block(3);
+        let hash = |a: &H256, b: &H256| {
+            let mut result = [0u8; 32];
+            CHash256::new().write(a.as_bytes()).write(b.as_bytes()).finalize(&mut result);
+            H256::from(result)
+        };
+        let h: Vec<H256> = block.vtx.iter().map(|tx| tx.GetHash()).collect();
+        let root = hash(&hash(&h[0], &h[1]), &hash(&h[2], &h[2]));
+        assert_eq!(BlockMerkleRoot(&block, Some(&mut mutated)), root);
+        assert!(!mutated);
+    }
+
+    #[test]
+    fn test_merkle_mutation() {
+        // [1,2,3,4,5,6] and [1,2,3,4,5,6,5,6] share a root, but the latter is flagged
+        let block = make_block(6);
+        let mut mutated = true;
+        let root = BlockMerkleRoot(&block, Some(&mut mutated));
+        assert!(!mutated);
+
+        let mut block2 = block.clone();
+        block2.vtx.push(block.vtx[4].clone());
+        block2.vtx.push(block.vtx[5].clone());
+        assert_eq!(BlockMerkleRoot(&block2, Some(&mut mutated)), root);
+        assert!(mutated);
+    }
+
+    #[test]
+    fn test_witness_merkle_root() {
+        let block = make_block(2);
+        let expected = ComputeMerkleRoot(vec![H256::zero(), block.vtx[1].GetWitnessHash()], None);
+        assert_eq!(BlockWitnessMerkleRoot(&block, None), expected);
+    }
+}
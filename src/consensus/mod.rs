@@ -1 +1,4 @@
-pub mod amount;
\ No newline at end of file
+pub mod amount;
+pub mod consensus;
+pub mod merkle;
+pub mod validation;
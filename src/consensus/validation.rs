@@ -0,0 +1,277 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2021 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use super::merkle::BlockWitnessMerkleRoot;
+use crate::hash::CHash256;
+use crate::primitives::block::CBlock;
+use crate::primitives::transaction::{CMutableTransaction, CTxOut, MakeTransactionRef};
+use crate::script::{CScript, opcodetype::OP_RETURN};
+
+/** Index marker for when no witness commitment is present in a coinbase transaction. */
+pub const NO_WITNESS_COMMITMENT: i32 = -1;
+
+/** Minimum size of a witness commitment structure. Defined in BIP 141. **/
+pub const MINIMUM_WITNESS_COMMITMENT: usize = 38;
+
+/** A "reason" why a block was invalid, suitable for determining whether the
+ * provider of the block should be banned/ignored/disconnected/etc.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockValidationResult {
+    #[default]
+    BLOCK_RESULT_UNSET,      // initial value. Block has not yet been rejected
+    BLOCK_CONSENSUS,         // invalid by consensus rules (excluding any below reasons)
+    BLOCK_CACHED_INVALID,    // this block was cached as being invalid and we didn't store the reason why
+    BLOCK_INVALID_HEADER,    // invalid proof of work or time too old
+    BLOCK_MUTATED,           // the block's data didn't match the data committed to by the PoW
+    BLOCK_MISSING_PREV,      // We don't have the previous block the checked one is built on
+    BLOCK_INVALID_PREV,      // A block this one builds on is invalid
+    BLOCK_TIME_FUTURE,       // block timestamp was > 2 hours in the future (or our clock is bad)
+    BLOCK_CHECKPOINT,        // the block failed to meet one of our checkpoints
+    BLOCK_HEADER_LOW_WORK,   // the block header may be on a too-little-work chain
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ModeState {
+    #[default]
+    M_VALID,   // everything ok
+    M_INVALID, // network rule violation (DoS value may be set)
+    M_ERROR,   // run-time error
+}
+
+/** Capture information about block validation. This is passed to
+ * validation functions and updated by the function that detects the failure.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct BlockValidationState {
+    m_mode: ModeState,
+    m_result: BlockValidationResult,
+    m_reject_reason: String,
+    m_debug_message: String,
+}
+
+impl BlockValidationState {
+    pub fn Invalid(&mut self, result: BlockValidationResult, reject_reason: &str, debug_message: &str) -> bool
+    {
+        self.m_result = result;
+        self.m_reject_reason = reject_reason.to_string();
+        self.m_debug_message = debug_message.to_string();
+        if self.m_mode != ModeState::M_ERROR {
+            self.m_mode = ModeState::M_INVALID;
+        }
+        false
+    }
+
+    pub fn Error(&mut self, reject_reason: &str) -> bool
+    {
+        if self.m_mode == ModeState::M_VALID {
+            self.m_reject_reason = reject_reason.to_string();
+        }
+        self.m_mode = ModeState::M_ERROR;
+        false
+    }
+
+    pub fn IsValid(&self) -> bool { self.m_mode == ModeState::M_VALID }
+    pub fn IsInvalid(&self) -> bool { self.m_mode == ModeState::M_INVALID }
+    pub fn IsError(&self) -> bool { self.m_mode == ModeState::M_ERROR }
+    pub fn GetResult(&self) -> BlockValidationResult { self.m_result }
+    pub fn GetRejectReason(&self) -> String { self.m_reject_reason.clone() }
+    pub fn GetDebugMessage(&self) -> String { self.m_debug_message.clone() }
+
+    pub fn ToString(&self) -> String
+    {
+        if self.IsValid() {
+            return "Valid".to_string();
+        }
+        if !self.m_debug_message.is_empty() {
+            return format!("{}, {}", self.m_reject_reason, self.m_debug_message);
+        }
+        self.m_reject_reason.clone()
+    }
+}
+
+/** Compute at which vout of the block's coinbase transaction the witness commitment occurs, or -1 if not found */
+//inline int GetWitnessCommitmentIndex(const CBlock& block)
+pub fn GetWitnessCommitmentIndex(block: &CBlock) -> i32
+{
+    let mut commitpos = NO_WITNESS_COMMITMENT;
+    if !block.vtx.is_empty() {
+        for (o, vout) in block.vtx[0].vout.iter().enumerate() {
+            let script = &vout.scriptPubKey.v;
+            if script.len() >= MINIMUM_WITNESS_COMMITMENT &&
+                script[0] == OP_RETURN as u8 &&
+                script[1] == 0x24 &&
+                script[2] == 0xaa &&
+                script[3] == 0x21 &&
+                script[4] == 0xa9 &&
+                script[5] == 0xed {
+                commitpos = o as i32;
+            }
+        }
+    }
+    commitpos
+}
+
+/** Hash a witness merkle root together with the coinbase witness reserved value. */
+fn WitnessCommitmentHash(block: &CBlock, nonce: &[u8]) -> [u8; CHash256::OUTPUT_SIZE]
+{
+    // The malleation check is ignored; as the transaction tree itself
+    // already does not permit it, it is impossible to trigger in the
+    // witness tree.
+    let hash_witness = BlockWitnessMerkleRoot(block, None);
+    let mut result: [u8; CHash256::OUTPUT_SIZE] = [0; CHash256::OUTPUT_SIZE];
+    CHash256::new().write(hash_witness.as_bytes()).write(nonce).finalize(&mut result);
+    result
+}
+
+/** CheckWitnessMalleation performs checks for block malleation with regard to
+ * its witnesses.
+ *
+ * Note: If the witness commitment is expected (i.e. `expect_witness_commitment
+ * = true`), then the block is required to have at least one transaction and the
+ * first transaction needs to have at least one input. */
+//static bool CheckWitnessMalleation(const CBlock& block, bool expect_witness_commitment, BlockValidationState& state)
+pub fn CheckWitnessMalleation(block: &mut CBlock, expect_witness_commitment: bool, state: &mut BlockValidationState) -> bool
+{
+    if expect_witness_commitment {
+        if block.m_checked_witness_commitment {
+            return true;
+        }
+
+        let commitpos = GetWitnessCommitmentIndex(block);
+        if commitpos != NO_WITNESS_COMMITMENT {
+            assert!(!block.vtx.is_empty() && !block.vtx[0].vin.is_empty());
+            let witness_stack = &block.vtx[0].vin[0].scriptWitness.stack;
+
+            if witness_stack.len() != 1 || witness_stack[0].len() != 32 {
+                return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "bad-witness-nonce-size",
+                    "CheckWitnessMalleation : invalid witness reserved value size");
+            }
+
+            let hash_witness = WitnessCommitmentHash(block, &witness_stack[0]);
+            if hash_witness[..] != block.vtx[0].vout[commitpos as usize].scriptPubKey.v[6..38] {
+                return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "bad-witness-merkle-match",
+                    "CheckWitnessMalleation : witness merkle commitment mismatch");
+            }
+
+            block.m_checked_witness_commitment = true;
+            return true;
+        }
+    }
+
+    // No witness data is allowed in blocks that don't commit to witness data, as this would otherwise leave room for spam
+    for tx in &block.vtx {
+        if tx.HasWitness() {
+            return state.Invalid(BlockValidationResult::BLOCK_MUTATED, "unexpected-witness",
+                "CheckWitnessMalleation : unexpected witness data found");
+        }
+    }
+
+    true
+}
+
+/** Update uncommitted block structures (currently: only the witness reserved value). This is safe for submitted blocks. */
+//void ChainstateManager::UpdateUncommittedBlockStructures(CBlock& block, const CBlockIndex* pindexPrev) const
+pub fn UpdateUncommittedBlockStructures(block: &mut CBlock)
+{
+    let commitpos = GetWitnessCommitmentIndex(block);
+    if commitpos != NO_WITNESS_COMMITMENT && !block.vtx[0].HasWitness() {
+        let mut tx = CMutableTransaction::from(&*block.vtx[0]);
+        tx.vin[0].scriptWitness.stack = vec![vec![0; 32]];
+        block.vtx[0] = MakeTransactionRef(tx);
+    }
+}
+
+/** Produce the necessary coinbase commitment for a block (modifies the hash, don't call for mined blocks). */
+//std::vector<unsigned char> ChainstateManager::GenerateCoinbaseCommitment(CBlock& block, const CBlockIndex* pindexPrev) const
+pub fn GenerateCoinbaseCommitment(block: &mut CBlock) -> Vec<u8>
+{
+    let mut commitment: Vec<u8> = vec![];
+    let commitpos = GetWitnessCommitmentIndex(block);
+    let ret: Vec<u8> = vec![0; 32];
+    if commitpos == NO_WITNESS_COMMITMENT {
+        let witnessroot = WitnessCommitmentHash(block, &ret);
+        let mut script = vec![OP_RETURN as u8, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+        script.extend_from_slice(&witnessroot);
+        let out = CTxOut::new(0, CScript::new(script));
+        commitment = out.scriptPubKey.to_vec();
+        let mut tx = CMutableTransaction::from(&*block.vtx[0]);
+        tx.vout.push(out);
+        block.vtx[0] = MakeTransactionRef(tx);
+    }
+    UpdateUncommittedBlockStructures(block);
+    commitment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H256;
+    use crate::primitives::transaction::{CTxIn, COutPoint, CTransaction};
+
+    fn make_block() -> CBlock {
+        let mut coinbase = CMutableTransaction::new();
+        coinbase.vin.push(CTxIn::default());
+        coinbase.vout.push(CTxOut::new(50, CScript::default()));
+
+        let mut spend = CMutableTransaction::new();
+        spend.vin.push(CTxIn::new(COutPoint::new(H256::repeat_byte(1), 0), CScript::default(), CTxIn::SEQUENCE_FINAL));
+        spend.vin[0].scriptWitness.stack.push(vec![1, 2, 3]);
+        spend.vout.push(CTxOut::new(49, CScript::default()));
+
+        let mut block = CBlock::new();
+        block.vtx.push(MakeTransactionRef(coinbase));
+        block.vtx.push(MakeTransactionRef(spend));
+        block
+    }
+
+    #[test]
+    fn test_witness_commitment() {
+        let mut block = make_block();
+        assert_eq!(GetWitnessCommitmentIndex(&block), NO_WITNESS_COMMITMENT);
+
+        // Witness data without a commitment is rejected
+        let mut state = BlockValidationState::default();
+        assert!(!CheckWitnessMalleation(&mut block, true, &mut state));
+        assert_eq!(state.GetRejectReason(), "unexpected-witness");
+
+        let commitment = GenerateCoinbaseCommitment(&mut block);
+        assert_eq!(commitment.len(), MINIMUM_WITNESS_COMMITMENT);
+        assert_eq!(GetWitnessCommitmentIndex(&block), 1);
+        assert_eq!(block.vtx[0].vin[0].scriptWitness.stack, vec![vec![0u8; 32]]);
+
+        let mut state = BlockValidationState::default();
+        assert!(CheckWitnessMalleation(&mut block, true, &mut state));
+        assert!(state.IsValid());
+        assert!(block.m_checked_witness_commitment);
+
+        // Generating again leaves the existing commitment in place
+        assert!(GenerateCoinbaseCommitment(&mut block).is_empty());
+        assert_eq!(block.vtx[0].vout.len(), 2);
+    }
+
+    #[test]
+    fn test_witness_commitment_mismatch() {
+        let mut block = make_block();
+        GenerateCoinbaseCommitment(&mut block);
+
+        // Tampering with a witness changes the witness merkle root
+        let mut spend = CMutableTransaction::from(&*block.vtx[1]);
+        spend.vin[0].scriptWitness.stack[0].push(4);
+        block.vtx[1] = MakeTransactionRef(spend);
+        let mut state = BlockValidationState::default();
+        assert!(!CheckWitnessMalleation(&mut block, true, &mut state));
+        assert_eq!(state.GetResult(), BlockValidationResult::BLOCK_MUTATED);
+        assert_eq!(state.GetRejectReason(), "bad-witness-merkle-match");
+
+        // The reserved value must be a single 32 byte element
+        let mut coinbase = CMutableTransaction::from(&*block.vtx[0]);
+        coinbase.vin[0].scriptWitness.stack[0].pop();
+        block.vtx[0] = MakeTransactionRef(CTransaction::from(coinbase));
+        let mut state = BlockValidationState::default();
+        assert!(!CheckWitnessMalleation(&mut block, true, &mut state));
+        assert_eq!(state.GetRejectReason(), "bad-witness-nonce-size");
+    }
+}
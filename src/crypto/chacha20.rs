@@ -0,0 +1,425 @@
+// Copyright (c) 2017-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+// Based on the public domain implementation 'merged' by D. J. Bernstein
+// See https://cr.yp.to/chacha.html.
+
+use crate::crypto::common::{ReadLE32, WriteLE32};
+
+/** Type for 96-bit nonces used by the Seek function below.
+ *
+ * The first field corresponds to the LE32-encoded first 4 bytes of the nonce, also referred
+ * to as the '32-bit fixed-common part' in Example 2.8.2 of RFC8439.
+ *
+ * The second field corresponds to the LE64-encoded last 8 bytes of the nonce.
+ */
+pub type Nonce96 = (u32, u64);
+
+//#define QUARTERROUND(a,b,c,d) \
+//  a += b; d = std::rotl(d ^ a, 16); \
+//  c += d; b = std::rotl(b ^ c, 12); \
+//  a += b; d = std::rotl(d ^ a, 8); \
+//  c += d; b = std::rotl(b ^ c, 7);
+macro_rules! QUARTERROUND {
+    ($x:ident, $a:expr, $b:expr, $c:expr, $d:expr) => {
+        $x[$a] = $x[$a].wrapping_add($x[$b]); $x[$d] = ($x[$d] ^ $x[$a]).rotate_left(16);
+        $x[$c] = $x[$c].wrapping_add($x[$d]); $x[$b] = ($x[$b] ^ $x[$c]).rotate_left(12);
+        $x[$a] = $x[$a].wrapping_add($x[$b]); $x[$d] = ($x[$d] ^ $x[$a]).rotate_left(8);
+        $x[$c] = $x[$c].wrapping_add($x[$d]); $x[$b] = ($x[$b] ^ $x[$c]).rotate_left(7);
+    };
+}
+
+/** ChaCha20 cipher that only operates on multiples of 64 bytes. */
+#[derive(Clone)]
+pub struct ChaCha20Aligned
+{
+    input: [u32; 12],
+}
+
+impl ChaCha20Aligned {
+    /** Expected key length in constructor and SetKey. */
+    pub const KEYLEN: usize = 32;
+
+    /** Block size (inputs/outputs to Keystream / Crypt should be multiples of this). */
+    pub const BLOCKLEN: usize = 64;
+
+    /** Initialize a cipher with specified 32-byte key. */
+    //ChaCha20Aligned(Span<const std::byte> key) noexcept;
+    pub fn new(key: &[u8]) -> Self {
+        let mut chacha = Self { input: [0; 12] };
+        chacha.SetKey(key);
+        chacha
+    }
+
+    /** Set 32-byte key, and seek to nonce 0 and block position 0. */
+    //void SetKey(Span<const std::byte> key) noexcept;
+    pub fn SetKey(&mut self, key: &[u8])
+    {
+        assert!(key.len() == Self::KEYLEN);
+        for i in 0..8 {
+            self.input[i] = ReadLE32(&key[4 * i..]);
+        }
+        self.input[8] = 0;
+        self.input[9] = 0;
+        self.input[10] = 0;
+        self.input[11] = 0;
+    }
+
+    /** Set the 96-bit nonce and 32-bit block counter.
+     *
+     * Block_counter selects a position to seek to (to byte BLOCKLEN*block_counter). After 256 GiB,
+     * the block counter overflows, and nonce.first is incremented.
+     */
+    //void Seek(Nonce96 nonce, uint32_t block_counter) noexcept;
+    pub fn Seek(&mut self, nonce: Nonce96, block_counter: u32)
+    {
+        self.input[8] = block_counter;
+        self.input[9] = nonce.0;
+        self.input[10] = nonce.1 as u32;
+        self.input[11] = (nonce.1 >> 32) as u32;
+    }
+
+    /** Compute one block of keystream at the current position, and advance the position. */
+    fn Block(&mut self, output: &mut [u8])
+    {
+        let j: [u32; 16] = [
+            0x61707865, 0x3320646e, 0x79622d32, 0x6b206574,
+            self.input[0], self.input[1], self.input[2], self.input[3],
+            self.input[4], self.input[5], self.input[6], self.input[7],
+            self.input[8], self.input[9], self.input[10], self.input[11],
+        ];
+        let mut x = j;
+
+        for _ in 0..10 {
+            QUARTERROUND!(x, 0, 4, 8, 12);
+            QUARTERROUND!(x, 1, 5, 9, 13);
+            QUARTERROUND!(x, 2, 6, 10, 14);
+            QUARTERROUND!(x, 3, 7, 11, 15);
+            QUARTERROUND!(x, 0, 5, 10, 15);
+            QUARTERROUND!(x, 1, 6, 11, 12);
+            QUARTERROUND!(x, 2, 7, 8, 13);
+            QUARTERROUND!(x, 3, 4, 9, 14);
+        }
+
+        for i in 0..16 {
+            WriteLE32(&mut output[4 * i..], x[i].wrapping_add(j[i]));
+        }
+
+        //++j12;
+        //if (!j12) ++j13;
+        self.input[8] = self.input[8].wrapping_add(1);
+        if self.input[8] == 0 {
+            self.input[9] = self.input[9].wrapping_add(1);
+        }
+    }
+
+    /** outputs the keystream into out, whose length must be a multiple of BLOCKLEN. */
+    //void Keystream(Span<std::byte> out) noexcept;
+    pub fn Keystream(&mut self, out: &mut [u8])
+    {
+        assert!(out.len() % Self::BLOCKLEN == 0);
+        for block in out.chunks_exact_mut(Self::BLOCKLEN) {
+            self.Block(block);
+        }
+    }
+
+    /** en/deciphers the message <input> and write the result into <output>
+     *
+     * The size of input and output must be equal, and be a multiple of BLOCKLEN.
+     */
+    //void Crypt(Span<const std::byte> input, Span<std::byte> output) noexcept;
+    pub fn Crypt(&mut self, input: &[u8], output: &mut [u8])
+    {
+        assert!(input.len() == output.len());
+        assert!(input.len() % Self::BLOCKLEN == 0);
+        let mut block = [0u8; Self::BLOCKLEN];
+        for (inp, out) in input.chunks_exact(Self::BLOCKLEN).zip(output.chunks_exact_mut(Self::BLOCKLEN)) {
+            self.Block(&mut block);
+            for i in 0..Self::BLOCKLEN {
+                out[i] = inp[i] ^ block[i];
+            }
+        }
+    }
+}
+
+/** Unrestricted ChaCha20 cipher. */
+#[derive(Clone)]
+pub struct ChaCha20
+{
+    m_aligned: ChaCha20Aligned,
+    m_buffer: [u8; ChaCha20Aligned::BLOCKLEN],
+    m_bufleft: usize,
+}
+
+impl ChaCha20 {
+    /** Expected key length in constructor and SetKey. */
+    pub const KEYLEN: usize = ChaCha20Aligned::KEYLEN;
+
+    /** Initialize a cipher with specified 32-byte key. */
+    //ChaCha20(Span<const std::byte> key) noexcept : m_aligned(key) {}
+    pub fn new(key: &[u8]) -> Self {
+        Self { m_aligned: ChaCha20Aligned::new(key), m_buffer: [0; ChaCha20Aligned::BLOCKLEN], m_bufleft: 0 }
+    }
+
+    /** Set 32-byte key, and seek to nonce 0 and block position 0. */
+    //void SetKey(Span<const std::byte> key) noexcept
+    pub fn SetKey(&mut self, key: &[u8])
+    {
+        self.m_aligned.SetKey(key);
+        self.m_bufleft = 0;
+    }
+
+    /** Set the 96-bit nonce and 32-bit block counter. See ChaCha20Aligned::Seek. */
+    //void Seek(Nonce96 nonce, uint32_t block_counter) noexcept
+    pub fn Seek(&mut self, nonce: Nonce96, block_counter: u32)
+    {
+        self.m_aligned.Seek(nonce, block_counter);
+        self.m_bufleft = 0;
+    }
+
+    /** en/deciphers the message <in_bytes> and write the result into <out_bytes>
+     *
+     * The size of in_bytes and out_bytes must be equal.
+     */
+    //void Crypt(Span<const std::byte> in_bytes, Span<std::byte> out_bytes) noexcept;
+    pub fn Crypt(&mut self, mut input: &[u8], mut output: &mut [u8])
+    {
+        assert!(input.len() == output.len());
+        if input.is_empty() {
+            return;
+        }
+        if self.m_bufleft > 0 {
+            let reuse = std::cmp::min(self.m_bufleft, input.len());
+            let start = ChaCha20Aligned::BLOCKLEN - self.m_bufleft;
+            for i in 0..reuse {
+                output[i] = input[i] ^ self.m_buffer[start + i];
+            }
+            self.m_bufleft -= reuse;
+            input = &input[reuse..];
+            output = &mut output[reuse..];
+        }
+        if input.len() >= ChaCha20Aligned::BLOCKLEN {
+            let len = (input.len() / ChaCha20Aligned::BLOCKLEN) * ChaCha20Aligned::BLOCKLEN;
+            self.m_aligned.Crypt(&input[..len], &mut output[..len]);
+            input = &input[len..];
+            output = &mut output[len..];
+        }
+        if !input.is_empty() {
+            self.m_aligned.Keystream(&mut self.m_buffer);
+            for i in 0..input.len() {
+                output[i] = input[i] ^ self.m_buffer[i];
+            }
+            self.m_bufleft = ChaCha20Aligned::BLOCKLEN - input.len();
+        }
+    }
+
+    /** outputs the keystream to out. */
+    //void Keystream(Span<std::byte> out) noexcept;
+    pub fn Keystream(&mut self, mut out: &mut [u8])
+    {
+        if out.is_empty() {
+            return;
+        }
+        if self.m_bufleft > 0 {
+            let reuse = std::cmp::min(self.m_bufleft, out.len());
+            let start = ChaCha20Aligned::BLOCKLEN - self.m_bufleft;
+            out[..reuse].copy_from_slice(&self.m_buffer[start..start + reuse]);
+            self.m_bufleft -= reuse;
+            out = &mut out[reuse..];
+        }
+        if out.len() >= ChaCha20Aligned::BLOCKLEN {
+            let len = (out.len() / ChaCha20Aligned::BLOCKLEN) * ChaCha20Aligned::BLOCKLEN;
+            self.m_aligned.Keystream(&mut out[..len]);
+            out = &mut out[len..];
+        }
+        if !out.is_empty() {
+            self.m_aligned.Keystream(&mut self.m_buffer);
+            let len = out.len();
+            out.copy_from_slice(&self.m_buffer[..len]);
+            self.m_bufleft = ChaCha20Aligned::BLOCKLEN - len;
+        }
+    }
+}
+
+/** Forward-secure ChaCha20
+ *
+ * This implements a stream cipher that automatically transitions to a new stream with a new key
+ * and new nonce after a predefined number of encryptions or decryptions.
+ *
+ * See BIP324 for details.
+ */
+pub struct FSChaCha20
+{
+    /** Internal stream cipher. */
+    m_chacha20: ChaCha20,
+
+    /** The number of encryptions/decryptions before a rekey happens. */
+    m_rekey_interval: u32,
+
+    /** The number of encryptions/decryptions since the last rekey. */
+    m_chunk_counter: u32,
+
+    /** The number of rekey operations that have happened. */
+    m_rekey_counter: u64,
+}
+
+impl FSChaCha20 {
+    /** Length of keys expected by the constructor. */
+    pub const KEYLEN: usize = 32;
+
+    /** Construct an FSChaCha20 cipher that rekeys every rekey_interval Crypt() calls. */
+    //FSChaCha20(Span<const std::byte> key, uint32_t rekey_interval) noexcept;
+    pub fn new(key: &[u8], rekey_interval: u32) -> Self {
+        assert!(key.len() == Self::KEYLEN);
+        Self { m_chacha20: ChaCha20::new(key), m_rekey_interval: rekey_interval, m_chunk_counter: 0, m_rekey_counter: 0 }
+    }
+
+    /** Encrypt or decrypt a chunk. */
+    //void Crypt(Span<const std::byte> input, Span<std::byte> output) noexcept;
+    pub fn Crypt(&mut self, input: &[u8], output: &mut [u8])
+    {
+        assert!(input.len() == output.len());
+
+        // Invoke internal stream cipher for actual encryption/decryption.
+        self.m_chacha20.Crypt(input, output);
+
+        // Rekey after m_rekey_interval encryptions/decryptions.
+        self.m_chunk_counter += 1;
+        if self.m_chunk_counter == self.m_rekey_interval {
+            // Get new key from the stream cipher.
+            let mut new_key = [0u8; Self::KEYLEN];
+            self.m_chacha20.Keystream(&mut new_key);
+            // Update its key.
+            self.m_chacha20.SetKey(&new_key);
+            // Set the nonce for the new section of output.
+            self.m_rekey_counter += 1;
+            self.m_chacha20.Seek((0, self.m_rekey_counter), 0);
+            // Reset the chunk counter.
+            self.m_chunk_counter = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn TestChaCha20(hex_message: &str, hexkey: &str, nonce: Nonce96, seek: u32, hexout: &str)
+    {
+        let key = hex::decode(hexkey).unwrap();
+        let m = hex::decode(hex_message).unwrap();
+        let expected = hex::decode(hexout).unwrap();
+        let mut rng = ChaCha20::new(&key);
+        rng.Seek(nonce, seek);
+        let mut outres = vec![0u8; expected.len()];
+        if m.is_empty() {
+            rng.Keystream(&mut outres);
+        } else {
+            rng.Crypt(&m, &mut outres);
+        }
+        assert_eq!(hex::encode(&outres), hexout);
+
+        // Check that splitting the operation into arbitrary pieces gives the same result.
+        for split in [1, 7, 31, 64, 65, 100] {
+            let mut rng = ChaCha20::new(&key);
+            rng.Seek(nonce, seek);
+            let mut outres = vec![0u8; expected.len()];
+            let mut pos = 0;
+            while pos < outres.len() {
+                let end = std::cmp::min(pos + split, outres.len());
+                if m.is_empty() {
+                    rng.Keystream(&mut outres[pos..end]);
+                } else {
+                    rng.Crypt(&m[pos..end], &mut outres[pos..end]);
+                }
+                pos = end;
+            }
+            assert_eq!(hex::encode(&outres), hexout);
+        }
+    }
+
+    fn TestFSChaCha20(hex_plaintext: &str, hexkey: &str, rekey_interval: u32, ciphertext_after_rotation: &str)
+    {
+        let key = hex::decode(hexkey).unwrap();
+        let plaintext = hex::decode(hex_plaintext).unwrap();
+        let mut fsc20 = FSChaCha20::new(&key, rekey_interval);
+        let mut c20 = ChaCha20::new(&key);
+
+        let mut fsc20_output = vec![0u8; plaintext.len()];
+        let mut c20_output = vec![0u8; plaintext.len()];
+
+        for _ in 0..rekey_interval {
+            fsc20.Crypt(&plaintext, &mut fsc20_output);
+            c20.Crypt(&plaintext, &mut c20_output);
+            assert_eq!(c20_output, fsc20_output);
+        }
+
+        // At the rotation interval, the outputs will no longer match
+        fsc20.Crypt(&plaintext, &mut fsc20_output);
+        let c20_copy = c20.clone();
+        c20.Crypt(&plaintext, &mut c20_output);
+        assert_ne!(c20_output, fsc20_output);
+
+        // Now rotate the reference cipher by hand: the new key is the next 32 bytes of keystream.
+        let mut c20 = c20_copy;
+        let mut new_key = [0u8; FSChaCha20::KEYLEN];
+        c20.Keystream(&mut new_key);
+        c20.SetKey(&new_key);
+        c20.Seek((0, 1), 0);
+        c20.Crypt(&plaintext, &mut c20_output);
+        assert_eq!(c20_output, fsc20_output);
+
+        assert_eq!(hex::encode(&fsc20_output), ciphertext_after_rotation);
+    }
+
+    #[test]
+    fn test_chacha20_testvector() {
+        // RFC 8439 Example 2.3.2
+        TestChaCha20("",
+                     "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                     (0x09000000, 0x4a000000), 1,
+                     "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+                      d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e");
+
+        // RFC 8439 Example 2.4.2
+        TestChaCha20(&hex::encode("Ladies and Gentlemen of the class of '99: If I could offer you only one tip for \
+                                   the future, sunscreen would be it."),
+                     "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                     (0, 0x4a000000), 1,
+                     "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+                      f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+                      07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+                      5af90bbf74a35be6b40b8eedf2785e42874d");
+
+        // RFC 8439 Appendix A.1, test vectors 1, 2 and 5
+        TestChaCha20("",
+                     "0000000000000000000000000000000000000000000000000000000000000000",
+                     (0, 0), 0,
+                     "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7\
+                      da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586");
+        TestChaCha20("",
+                     "0000000000000000000000000000000000000000000000000000000000000000",
+                     (0, 0), 1,
+                     "9f07e7be5551387a98ba977c732d080dcb0f29a048e3656912c6533e32ee7aed\
+                      29b721769ce64e43d57133b074d839d531ed1f28510afb45ace10a1f4b794d6f");
+        TestChaCha20("",
+                     "0000000000000000000000000000000000000000000000000000000000000000",
+                     (0, 0x200000000000000), 0,
+                     "c2c64d378cd536374ae204b9ef933fcd1a8b2288b3dfa49672ab765b54ee27c7\
+                      8a970e0e955c14f3a88e741b97c286f75f8fc299e8148362fa198a39531bed6d");
+    }
+
+    #[test]
+    fn test_fschacha20() {
+        TestFSChaCha20("0000000000000000000000000000000000000000000000000000000000000000",
+                       "0000000000000000000000000000000000000000000000000000000000000000", 256,
+                       "a93cf6ec0704193ab1566ad29aec7652e5cf2aef2daadd71225ea14e7d8f9d56");
+        TestFSChaCha20("01",
+                       "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", 5,
+                       "ea");
+        TestFSChaCha20("e93fdb5c762804b9a706816aca31e35b11d2aa3080108ef46a5b1f1508819c0a",
+                       "8ec4c3ccdaea336bdeb245636970be01266509b33f3d2642504eaf412206207a", 4096,
+                       "8bfaa4eacff308fdb4a94a5ff25bd9d0c1f84b77f81239f67ff39d6e1ac280c9");
+    }
+}
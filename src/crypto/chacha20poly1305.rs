@@ -0,0 +1,352 @@
+// Copyright (c) 2023 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::crypto::chacha20::{ChaCha20, ChaCha20Aligned, Nonce96};
+use crate::crypto::common::WriteLE64;
+use crate::crypto::poly1305::Poly1305;
+
+/** The AEAD_CHACHA20_POLY1305 authenticated encryption algorithm from RFC8439 section 2.8. */
+pub struct AEADChaCha20Poly1305
+{
+    /** Internal stream cipher. */
+    m_chacha20: ChaCha20,
+}
+
+//int timingsafe_bcmp_internal(const unsigned char* b1, const unsigned char* b2, size_t n) noexcept
+fn timingsafe_bcmp_internal(b1: &[u8], b2: &[u8]) -> bool
+{
+    let mut ret: u8 = 0;
+    for (p1, p2) in b1.iter().zip(b2.iter()) {
+        ret |= p1 ^ p2;
+    }
+    ret != 0
+}
+
+/** Compute poly1305 tag. chacha20 must be set to the right nonce, block 0. Will be at block 1 after. */
+//void ComputeTag(ChaCha20& chacha20, Span<const std::byte> aad, Span<const std::byte> cipher, Span<std::byte> tag) noexcept
+fn ComputeTag(chacha20: &mut ChaCha20, aad: &[u8], cipher: &[u8], tag: &mut [u8])
+{
+    const PADDING: [u8; 16] = [0; 16];
+
+    // Get block of keystream (use a full 64 byte buffer to avoid the need for chacha20's own buffering).
+    let mut first_block = [0u8; ChaCha20Aligned::BLOCKLEN];
+    chacha20.Keystream(&mut first_block);
+
+    // Use the first 32 bytes of the first keystream block as poly1305 key.
+    let mut poly1305 = Poly1305::new(&first_block[..Poly1305::KEYLEN]);
+
+    // Compute tag:
+    // - Process the padded AAD with Poly1305.
+    let aad_padding_length = (16 - (aad.len() % 16)) % 16;
+    poly1305.Update(aad).Update(&PADDING[..aad_padding_length]);
+    // - Process the padded ciphertext with Poly1305.
+    let cipher_padding_length = (16 - (cipher.len() % 16)) % 16;
+    poly1305.Update(cipher).Update(&PADDING[..cipher_padding_length]);
+    // - Process the AAD and plaintext length with Poly1305.
+    let mut length_desc = [0u8; Poly1305::TAGLEN];
+    WriteLE64(&mut length_desc[0..8], aad.len() as u64);
+    WriteLE64(&mut length_desc[8..16], cipher.len() as u64);
+    poly1305.Update(&length_desc);
+
+    // Output tag.
+    poly1305.Finalize(tag);
+}
+
+impl AEADChaCha20Poly1305 {
+    /** Expected size of key argument in constructor. */
+    pub const KEYLEN: usize = 32;
+
+    /** Expansion when encrypting. */
+    pub const EXPANSION: usize = Poly1305::TAGLEN;
+
+    /** Initialize an AEAD instance with a specified 32-byte key. */
+    //AEADChaCha20Poly1305(Span<const std::byte> key) noexcept;
+    pub fn new(key: &[u8]) -> Self {
+        assert!(key.len() == Self::KEYLEN);
+        Self { m_chacha20: ChaCha20::new(key) }
+    }
+
+    /** Switch to another 32-byte key. */
+    //void SetKey(Span<const std::byte> key) noexcept;
+    pub fn SetKey(&mut self, key: &[u8])
+    {
+        self.m_chacha20.SetKey(key);
+    }
+
+    /** Encrypt a message (given split into plain1 + plain2) with a specified 96-bit nonce and aad.
+     *
+     * Requires cipher.len() = plain1.len() + plain2.len() + EXPANSION.
+     */
+    //void Encrypt(Span<const std::byte> plain1, Span<const std::byte> plain2, Span<const std::byte> aad, Nonce96 nonce, Span<std::byte> cipher) noexcept;
+    pub fn Encrypt(&mut self, plain1: &[u8], plain2: &[u8], aad: &[u8], nonce: Nonce96, cipher: &mut [u8])
+    {
+        assert!(cipher.len() == plain1.len() + plain2.len() + Self::EXPANSION);
+
+        // Encrypt using ChaCha20 (starting at block 1).
+        self.m_chacha20.Seek(nonce, 1);
+        self.m_chacha20.Crypt(plain1, &mut cipher[..plain1.len()]);
+        self.m_chacha20.Crypt(plain2, &mut cipher[plain1.len()..plain1.len() + plain2.len()]);
+
+        // Seek to block 0, and compute tag using key drawn from there.
+        self.m_chacha20.Seek(nonce, 0);
+        let (ciphertext, tag) = cipher.split_at_mut(plain1.len() + plain2.len());
+        ComputeTag(&mut self.m_chacha20, aad, ciphertext, tag);
+    }
+
+    /** Decrypt a message with a specified 96-bit nonce and aad. Returns true if valid.
+     *
+     * Requires cipher.len() = plain1.len() + plain2.len() + EXPANSION.
+     */
+    //bool Decrypt(Span<const std::byte> cipher, Span<const std::byte> aad, Nonce96 nonce, Span<std::byte> plain1, Span<std::byte> plain2) noexcept;
+    pub fn Decrypt(&mut self, cipher: &[u8], aad: &[u8], nonce: Nonce96, plain1: &mut [u8], plain2: &mut [u8]) -> bool
+    {
+        assert!(cipher.len() == plain1.len() + plain2.len() + Self::EXPANSION);
+
+        // Verify tag (using key drawn from block 0).
+        self.m_chacha20.Seek(nonce, 0);
+        let mut expected_tag = [0u8; Self::EXPANSION];
+        let (ciphertext, tag) = cipher.split_at(cipher.len() - Self::EXPANSION);
+        ComputeTag(&mut self.m_chacha20, aad, ciphertext, &mut expected_tag);
+        if timingsafe_bcmp_internal(&expected_tag, tag) {
+            return false;
+        }
+
+        // Decrypt (starting at block 1).
+        let len1 = plain1.len();
+        self.m_chacha20.Crypt(&ciphertext[..len1], plain1);
+        self.m_chacha20.Crypt(&ciphertext[len1..], plain2);
+        true
+    }
+
+    /** Get a number of keystream bytes from the underlying stream cipher.
+     *
+     * This is equivalent to Encrypt()ing keystream.len() zero bytes, and dropping the last EXPANSION
+     * bytes off the result.
+     */
+    //void Keystream(Nonce96 nonce, Span<std::byte> keystream) noexcept;
+    pub fn Keystream(&mut self, nonce: Nonce96, keystream: &mut [u8])
+    {
+        // Skip the first output block, as it's used for generating the poly1305 key.
+        self.m_chacha20.Seek(nonce, 1);
+        self.m_chacha20.Keystream(keystream);
+    }
+}
+
+/** Forward-secure wrapper around AEADChaCha20Poly1305.
+ *
+ * This implements an AEAD which automatically increments the nonce on every encryption or
+ * decryption, and cycles keys after a predetermined number of encryptions or decryptions.
+ *
+ * See BIP324 for details.
+ */
+pub struct FSChaCha20Poly1305
+{
+    /** Internal AEAD. */
+    m_aead: AEADChaCha20Poly1305,
+
+    /** Every how many iterations this cipher rekeys. */
+    m_rekey_interval: u32,
+
+    /** The number of encryptions/decryptions since the last rekey. */
+    m_packet_counter: u32,
+
+    /** The number of rekeys performed so far. */
+    m_rekey_counter: u64,
+}
+
+impl FSChaCha20Poly1305 {
+    /** Length of keys expected by the constructor. */
+    pub const KEYLEN: usize = AEADChaCha20Poly1305::KEYLEN;
+
+    /** Expansion when encrypting. */
+    pub const EXPANSION: usize = AEADChaCha20Poly1305::EXPANSION;
+
+    /** Construct an FSChaCha20Poly1305 cipher that rekeys every rekey_interval operations. */
+    //FSChaCha20Poly1305(Span<const std::byte> key, uint32_t rekey_interval) noexcept
+    pub fn new(key: &[u8], rekey_interval: u32) -> Self {
+        Self { m_aead: AEADChaCha20Poly1305::new(key), m_rekey_interval: rekey_interval, m_packet_counter: 0, m_rekey_counter: 0 }
+    }
+
+    /** Increment the packet counter, and rekey every m_rekey_interval packets. */
+    //void NextPacket() noexcept;
+    fn NextPacket(&mut self)
+    {
+        self.m_packet_counter += 1;
+        if self.m_packet_counter == self.m_rekey_interval {
+            // Generate a full block of keystream, to avoid needing the ChaCha20 buffer, even though
+            // we only need KEYLEN (32) bytes.
+            let mut one_block = [0u8; ChaCha20Aligned::BLOCKLEN];
+            self.m_aead.Keystream((0xFFFFFFFF, self.m_rekey_counter), &mut one_block);
+            // Switch keys.
+            self.m_aead.SetKey(&one_block[..Self::KEYLEN]);
+            // Update counters.
+            self.m_packet_counter = 0;
+            self.m_rekey_counter += 1;
+        }
+    }
+
+    /** Encrypt a message (given split into plain1 + plain2) with a specified aad.
+     *
+     * Requires cipher.len() = plain1.len() + plain2.len() + EXPANSION.
+     */
+    //void Encrypt(Span<const std::byte> plain1, Span<const std::byte> plain2, Span<const std::byte> aad, Span<std::byte> cipher) noexcept;
+    pub fn Encrypt(&mut self, plain1: &[u8], plain2: &[u8], aad: &[u8], cipher: &mut [u8])
+    {
+        self.m_aead.Encrypt(plain1, plain2, aad, (self.m_packet_counter, self.m_rekey_counter), cipher);
+        self.NextPacket();
+    }
+
+    /** Decrypt a message with a specified aad. Returns true if valid.
+     *
+     * Requires cipher.len() = plain1.len() + plain2.len() + EXPANSION.
+     */
+    //bool Decrypt(Span<const std::byte> cipher, Span<const std::byte> aad, Span<std::byte> plain1, Span<std::byte> plain2) noexcept;
+    pub fn Decrypt(&mut self, cipher: &[u8], aad: &[u8], plain1: &mut [u8], plain2: &mut [u8]) -> bool
+    {
+        let ret = self.m_aead.Decrypt(cipher, aad, (self.m_packet_counter, self.m_rekey_counter), plain1, plain2);
+        self.NextPacket();
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn TestChaCha20Poly1305(plain_hex: &str, aad_hex: &str, key_hex: &str, nonce: Nonce96, cipher_hex: &str)
+    {
+        let plain = hex::decode(plain_hex).unwrap();
+        let aad = hex::decode(aad_hex).unwrap();
+        let key = hex::decode(key_hex).unwrap();
+        let expected_cipher = hex::decode(cipher_hex).unwrap();
+
+        for i in 0..10 {
+            // During i=0, use single-plain Encrypt/Decrypt; others use a split at prefix.
+            let prefix = if i == 0 { plain.len() } else { (i * 11) % (plain.len() + 1) };
+            // Encrypt.
+            let mut cipher = vec![0u8; plain.len() + AEADChaCha20Poly1305::EXPANSION];
+            let mut aead = AEADChaCha20Poly1305::new(&key);
+            aead.Encrypt(&plain[..prefix], &plain[prefix..], &aad, nonce, &mut cipher);
+            assert_eq!(cipher, expected_cipher);
+
+            // Decrypt.
+            let mut decipher = vec![0u8; cipher.len() - AEADChaCha20Poly1305::EXPANSION];
+            let (d1, d2) = decipher.split_at_mut(prefix);
+            assert!(aead.Decrypt(&cipher, &aad, nonce, d1, d2));
+            assert_eq!(decipher, plain);
+
+            // A modified ciphertext or aad must be rejected.
+            let mut bad = cipher.clone();
+            bad[i % cipher.len()] ^= 1;
+            let (d1, d2) = decipher.split_at_mut(prefix);
+            assert!(!aead.Decrypt(&bad, &aad, nonce, d1, d2));
+            if !aad.is_empty() {
+                let mut bad_aad = aad.clone();
+                bad_aad[i % aad.len()] ^= 0x80;
+                let (d1, d2) = decipher.split_at_mut(prefix);
+                assert!(!aead.Decrypt(&cipher, &bad_aad, nonce, d1, d2));
+            }
+        }
+
+        // Test Keystream output.
+        let mut keystream = vec![0u8; plain.len()];
+        let mut aead = AEADChaCha20Poly1305::new(&key);
+        aead.Keystream(nonce, &mut keystream);
+        for i in 0..plain.len() {
+            assert_eq!(plain[i] ^ keystream[i], expected_cipher[i]);
+        }
+    }
+
+    fn TestFSChaCha20Poly1305(plain_hex: &str, aad_hex: &str, key_hex: &str, msg_idx: u64, cipher_hex: &str)
+    {
+        let plain = hex::decode(plain_hex).unwrap();
+        let aad = hex::decode(aad_hex).unwrap();
+        let key = hex::decode(key_hex).unwrap();
+        let expected_cipher = hex::decode(cipher_hex).unwrap();
+        let mut cipher = vec![0u8; plain.len() + FSChaCha20Poly1305::EXPANSION];
+
+        for it in 0..10 {
+            // During it==0 we use the single-plain Encrypt/Decrypt; others use a split at prefix.
+            let prefix = if it == 0 { plain.len() } else { (it * 23) % (plain.len() + 1) };
+            let mut enc_aead = FSChaCha20Poly1305::new(&key, 224);
+            let mut dec_aead = FSChaCha20Poly1305::new(&key, 224);
+
+            // Do msg_idx dummy encryptions to seek to the correct packet.
+            let mut dummy_tag = [0u8; FSChaCha20Poly1305::EXPANSION];
+            for _ in 0..msg_idx {
+                enc_aead.Encrypt(&[], &[], &[], &mut dummy_tag);
+            }
+
+            // Invoke single-plain or plain1/plain2 Encrypt.
+            enc_aead.Encrypt(&plain[..prefix], &plain[prefix..], &aad, &mut cipher);
+            assert_eq!(cipher, expected_cipher);
+
+            // Do msg_idx dummy decryptions to seek to the correct packet (only the last one can
+            // authenticate, so the results are ignored).
+            for _ in 0..msg_idx {
+                dec_aead.Decrypt(&dummy_tag, &[], &mut [], &mut []);
+            }
+
+            // Decrypt and check the result.
+            let mut decipher = vec![0u8; cipher.len() - FSChaCha20Poly1305::EXPANSION];
+            let (d1, d2) = decipher.split_at_mut(prefix);
+            assert!(dec_aead.Decrypt(&cipher, &aad, d1, d2));
+            assert_eq!(decipher, plain);
+        }
+    }
+
+    #[test]
+    fn test_chacha20poly1305_testvectors() {
+        // RFC 8439 Example 2.8.2
+        TestChaCha20Poly1305(&hex::encode("Ladies and Gentlemen of the class of '99: If I could offer you only one tip \
+                                           for the future, sunscreen would be it."),
+                             "50515253c0c1c2c3c4c5c6c7",
+                             "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+                             (7, 0x4746454443424140),
+                             "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+                              3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+                              92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+                              3ff4def08e4b7a9de576d26586cec64b61161ae10b594f09e26a7e902ecbd060\
+                              0691");
+
+        // RFC 8439 Appendix A.5
+        TestChaCha20Poly1305("496e7465726e65742d4472616674732061726520647261667420646f63756d65\
+                              6e74732076616c696420666f722061206d6178696d756d206f6620736978206d\
+                              6f6e74687320616e64206d617920626520757064617465642c207265706c6163\
+                              65642c206f72206f62736f6c65746564206279206f7468657220646f63756d65\
+                              6e747320617420616e792074696d652e20497420697320696e617070726f7072\
+                              6961746520746f2075736520496e7465726e65742d4472616674732061732072\
+                              65666572656e6365206d6174657269616c206f7220746f206369746520746865\
+                              6d206f74686572207468616e206173202fe2809c776f726b20696e2070726f67\
+                              726573732e2fe2809d",
+                             "f33388860000000000004e91",
+                             "1c9240a5eb55d38af333888604f6b5f0473917c1402b80099dca5cbc207075c0",
+                             (0, 0x0807060504030201),
+                             "64a0861575861af460f062c79be643bd5e805cfd345cf389f108670ac76c8cb2\
+                              4c6cfc18755d43eea09ee94e382d26b0bdb7b73c321b0100d4f03b7f355894cf\
+                              332f830e710b97ce98c8a84abd0b948114ad176e008d33bd60f982b1ff37c855\
+                              9797a06ef4f0ef61c186324e2b3506383606907b6a7c02b0f9f6157b53c867e4\
+                              b9166c767b804d46a59b5216cde7a4e99040c5a40433225ee282a1b0a06c523e\
+                              af4534d7f83fa1155b0047718cbc546a0d072b04b3564eea1b422273f548271a\
+                              0bb2316053fa76991955ebd63159434ecebb4e466dae5a1073a6727627097a10\
+                              49e617d91d361094fa68f0ff77987130305beaba2eda04df997b714d6c6f2c29\
+                              a6ad5cb4022b02709beead9d67890cbb22392336fea1851f38");
+    }
+
+    #[test]
+    fn test_fschacha20poly1305_testvectors() {
+        TestFSChaCha20Poly1305("", "",
+                               "0000000000000000000000000000000000000000000000000000000000000000", 0,
+                               "4eb972c9a8fb3a1b382bb4d36f5ffad1");
+        TestFSChaCha20Poly1305(&hex::encode("Ladies and Gentlemen of the class of '99: If I could offer you only one tip \
+                                             for the future, sunscreen would be it."),
+                               "50515253c0c1c2c3c4c5c6c7",
+                               "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f", 1000,
+                               "a7d54799406cf541cafcad537e9e0c4413e09d3d68f4132933e46d088be48bcc\
+                                fc13edec199a391a6d540d2650a497deff024934e277a3ccf34e34ad46b0b4f4\
+                                5a80f46101b31e33755fb17356c9c2857eb5c1cb72ac1087879cf78e0d6227cb\
+                                50e0c1c0ae8f3596e1341e3f1db563cd08fc52083cbd83e5113684b79d602cd6\
+                                566a");
+    }
+}
@@ -26,6 +26,11 @@ uint32_t static inline ReadLE32(const unsigned char* ptr)
     return le32toh(x);
 } */
 
+pub fn ReadLE32(ptr: &[u8]) -> u32
+{
+    LittleEndian::read_u32(ptr)
+}
+
 /*
 uint64_t static inline ReadLE64(const unsigned char* ptr)
 {
@@ -53,13 +58,25 @@ void static inline WriteLE32(unsigned char* ptr, uint32_t x)
     memcpy(ptr, (char*)&v, 4);
 } */
 
+pub fn WriteLE32(ptr: &mut [u8], x: u32)
+{
+    LittleEndian::write_u32(ptr, x)
+}
+
 /*
 void static inline WriteLE64(unsigned char* ptr, uint64_t x)
 {
     uint64_t v = htole64(x);
     memcpy(ptr, (char*)&v, 8);
+} */
+
+pub fn WriteLE64(ptr: &mut [u8], x: u64)
+{
+    LittleEndian::write_u64(ptr, x)
 }
 
+/*
+
 uint16_t static inline ReadBE16(const unsigned char* ptr)
 {
     uint16_t x;
@@ -112,3 +129,13 @@ pub fn WriteBE64(ptr: &mut [u8], x: u64)
 {
     BigEndian::write_u64(ptr, x);
 }
+
+/** Return the smallest number n such that (x >> n) == 0 (or 64 if the highest bit in x is set. */
+/* uint64_t constexpr inline CountBits(uint64_t x)
+{
+    return std::numeric_limits<decltype(x)>::digits - std::countl_zero(x);
+} */
+pub fn CountBits(x: u64) -> u32
+{
+    64 - x.leading_zeros()
+}
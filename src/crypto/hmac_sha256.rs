@@ -0,0 +1,109 @@
+// Copyright (c) 2014-2019 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::crypto::sha256::CSHA256;
+use crate::crypto::Hasher;
+
+/** A hasher class for HMAC-SHA-256. */
+#[derive(Clone)]
+pub struct CHMAC_SHA256
+{
+    outer: CSHA256,
+    inner: CSHA256,
+    rkey: [u8; 64],
+}
+
+impl CHMAC_SHA256 {
+    //CHMAC_SHA256(const unsigned char* key, size_t keylen);
+    pub fn new(key: &[u8]) -> Self {
+        let mut rkey: [u8; 64] = [0; 64];
+        if key.len() <= 64 {
+            rkey[..key.len()].copy_from_slice(key);
+        } else {
+            let mut hash: [u8; CSHA256::OUTPUT_SIZE] = [0; CSHA256::OUTPUT_SIZE];
+            CSHA256::new().Write(key, key.len()).Finalize(&mut hash);
+            rkey[..32].copy_from_slice(&hash);
+        }
+
+        let mut hmac = Self { outer: CSHA256::new(), inner: CSHA256::new(), rkey };
+        hmac.Reset();
+        hmac
+    }
+}
+
+impl Hasher for CHMAC_SHA256 {
+    const OUTPUT_SIZE: usize = 32;
+
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
+    {
+        self.inner.Write(data, len);
+        self
+    }
+
+    fn Finalize(&mut self, hash: &mut [u8])
+    {
+        assert!(hash.len() == Self::OUTPUT_SIZE);
+        let mut temp: [u8; 32] = [0; 32];
+        self.inner.Finalize(&mut temp);
+        let mut out: [u8; 32] = [0; 32];
+        self.outer.Write(&temp, 32).Finalize(&mut out);
+        hash.copy_from_slice(&out);
+    }
+
+    fn Reset(&mut self) -> &mut Self
+    {
+        let mut rkey = self.rkey;
+        for n in rkey.iter_mut() {
+            *n ^= 0x5c;
+        }
+        self.outer.Reset().Write(&rkey, 64);
+
+        for n in rkey.iter_mut() {
+            *n ^= 0x5c ^ 0x36;
+        }
+        self.inner.Reset().Write(&rkey, 64);
+        self
+    }
+
+    fn Size(&self) -> usize
+    {
+        self.inner.Size() - 64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::TestVector;
+
+    fn TestHMACSHA256(hexkey: &str, hexin: &str, hexout: &str) {
+        let key = hex::decode(hexkey).unwrap();
+        TestVector(&CHMAC_SHA256::new(&key), &hex::decode(hexin).unwrap(), &hex::decode(hexout).unwrap());
+    }
+
+    #[test]
+    fn test_hmac_sha256_testvectors() {
+        // test cases 1, 2, 3, 4, 6 and 7 of RFC 4231
+        TestHMACSHA256("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+                       "4869205468657265",
+                       "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+        TestHMACSHA256("4a656665",
+                       "7768617420646f2079612077616e7420666f72206e6f7468696e673f",
+                       "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        TestHMACSHA256("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                       &"dd".repeat(50),
+                       "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe");
+        TestHMACSHA256("0102030405060708090a0b0c0d0e0f10111213141516171819",
+                       &"cd".repeat(50),
+                       "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b");
+        TestHMACSHA256(&"aa".repeat(131),
+                       "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a\
+                        65204b6579202d2048617368204b6579204669727374",
+                       "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+        TestHMACSHA256(&"aa".repeat(131),
+                       &hex::encode("This is a test using a larger than block-size key and a larger than block-size data. \
+                                     The key needs to be hashed before being used by the HMAC algorithm."),
+                       "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2");
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2014-2019 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::crypto::sha512::CSHA512;
+use crate::crypto::Hasher;
+
+/** A hasher class for HMAC-SHA-512. */
+#[derive(Clone)]
+pub struct CHMAC_SHA512
+{
+    outer: CSHA512,
+    inner: CSHA512,
+    rkey: [u8; 128],
+}
+
+impl CHMAC_SHA512 {
+    //CHMAC_SHA512(const unsigned char* key, size_t keylen);
+    pub fn new(key: &[u8]) -> Self {
+        let mut rkey: [u8; 128] = [0; 128];
+        if key.len() <= 128 {
+            rkey[..key.len()].copy_from_slice(key);
+        } else {
+            let mut hash: [u8; CSHA512::OUTPUT_SIZE] = [0; CSHA512::OUTPUT_SIZE];
+            CSHA512::new().Write(key, key.len()).Finalize(&mut hash);
+            rkey[..64].copy_from_slice(&hash);
+        }
+
+        let mut hmac = Self { outer: CSHA512::new(), inner: CSHA512::new(), rkey };
+        hmac.Reset();
+        hmac
+    }
+}
+
+impl Hasher for CHMAC_SHA512 {
+    const OUTPUT_SIZE: usize = 64;
+
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
+    {
+        self.inner.Write(data, len);
+        self
+    }
+
+    fn Finalize(&mut self, hash: &mut [u8])
+    {
+        assert!(hash.len() == Self::OUTPUT_SIZE);
+        let mut temp: [u8; 64] = [0; 64];
+        self.inner.Finalize(&mut temp);
+        self.outer.Write(&temp, 64).Finalize(hash);
+    }
+
+    fn Reset(&mut self) -> &mut Self
+    {
+        let mut rkey = self.rkey;
+        for n in rkey.iter_mut() {
+            *n ^= 0x5c;
+        }
+        self.outer.Reset().Write(&rkey, 128);
+
+        for n in rkey.iter_mut() {
+            *n ^= 0x5c ^ 0x36;
+        }
+        self.inner.Reset().Write(&rkey, 128);
+        self
+    }
+
+    fn Size(&self) -> usize
+    {
+        self.inner.Size() - 128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::TestVector;
+
+    fn TestHMACSHA512(hexkey: &str, hexin: &str, hexout: &str) {
+        let key = hex::decode(hexkey).unwrap();
+        TestVector(&CHMAC_SHA512::new(&key), &hex::decode(hexin).unwrap(), &hex::decode(hexout).unwrap());
+    }
+
+    #[test]
+    fn test_hmac_sha512_testvectors() {
+        // test cases 1, 2, 3, 4, 6 and 7 of RFC 4231
+        TestHMACSHA512("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b",
+                       "4869205468657265",
+                       "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+                        daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854");
+        TestHMACSHA512("4a656665",
+                       "7768617420646f2079612077616e7420666f72206e6f7468696e673f",
+                       "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+                        9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737");
+        TestHMACSHA512("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                       &"dd".repeat(50),
+                       "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39\
+                        bf3e848279a722c806b485a47e67c807b946a337bee8942674278859e13292fb");
+        TestHMACSHA512("0102030405060708090a0b0c0d0e0f10111213141516171819",
+                       &"cd".repeat(50),
+                       "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3db\
+                        a91ca5c11aa25eb4d679275cc5788063a5f19741120c4f2de2adebeb10a298dd");
+        TestHMACSHA512(&"aa".repeat(131),
+                       "54657374205573696e67204c6172676572205468616e20426c6f636b2d53697a\
+                        65204b6579202d2048617368204b6579204669727374",
+                       "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+                        6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598");
+        TestHMACSHA512(&"aa".repeat(131),
+                       &hex::encode("This is a test using a larger than block-size key and a larger than block-size data. \
+                                     The key needs to be hashed before being used by the HMAC algorithm."),
+                       "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944\
+                        b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58");
+    }
+}
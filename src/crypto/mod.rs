@@ -2,15 +2,105 @@
 // Distributed under the MIT software license, see the accompanying
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
 
+pub mod chacha20;
+pub mod chacha20poly1305;
 pub mod common;
+pub mod hmac_sha256;
+pub mod hmac_sha512;
+pub mod poly1305;
+pub mod ripemd160;
+pub mod sha1;
 pub mod sha256;
+#[cfg(target_arch = "x86_64")]
+pub mod sha256_avx2;
+#[cfg(target_arch = "x86_64")]
+pub mod sha256_sse41;
+#[cfg(target_arch = "x86_64")]
+pub mod sha256_x86_shani;
 pub mod sha512;
 
+#[cfg(test)]
+use crate::random::FastRandomContext;
+
 
 pub trait Hasher {
     const OUTPUT_SIZE: usize;
-    fn Write(&mut self, data: &mut [u8], len: usize) -> &mut Self;
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self;
     fn Finalize(&mut self, data: &mut [u8]);
     fn Reset(&mut self) -> &mut Self;
     fn Size(&self) -> usize;
-}
\ No newline at end of file
+}
+
+/** Check a hasher against a known answer: in one go, in pieces of every size,
+ * from a copy taken part way through, and again after a Reset.
+ * Splitting at every size is quadratic in the input length, so inputs longer than
+ * MAX_EVERY_SPLIT_SIZE (the million-byte vectors) are instead written in random
+ * pieces from a deterministic FastRandomContext, as Core does.
+ */
+//template<typename Hasher, typename In, typename Out>
+//static void TestVector(const Hasher &h, const In &in, const Out &out)
+#[cfg(test)]
+pub fn TestVector<H: Hasher + Clone>(h: &H, input: &[u8], out: &[u8])
+{
+    const MAX_EVERY_SPLIT_SIZE: usize = 4096;
+
+    assert_eq!(out.len(), H::OUTPUT_SIZE);
+    let mut hash = vec![0u8; out.len()];
+
+    // Test that writing the whole input string at once works.
+    h.clone().Write(input, input.len()).Finalize(&mut hash);
+    assert_eq!(hash, out);
+
+    // Write the input in pieces whose lengths come from next_len, checking the
+    // result and the result of finishing a copy near the end.
+    let mut test_pieces = |next_len: &mut dyn FnMut(usize) -> usize| {
+        let mut hasher = h.clone();
+        let mut pos = 0;
+        while pos < input.len() {
+            let len = next_len(input.len() - pos);
+            hasher.Write(&input[pos..], len);
+            pos += len;
+            if pos > 0 && pos + 2 * out.len() > input.len() && pos < input.len() {
+                // Test that writing the rest at once to a copy of a hasher works.
+                hasher.clone().Write(&input[pos..], input.len() - pos).Finalize(&mut hash);
+                assert_eq!(hash, out);
+            }
+        }
+        hasher.Finalize(&mut hash);
+        assert_eq!(hash, out);
+    };
+
+    if input.len() <= MAX_EVERY_SPLIT_SIZE {
+        for size in 1..=input.len() {
+            // Test that writing the string broken up in pieces works.
+            test_pieces(&mut |remaining| size.min(remaining));
+        }
+    } else {
+        let mut rng = FastRandomContext::new(true);
+        for _ in 0..32 {
+            // Test that writing the string broken up in random pieces works.
+            test_pieces(&mut |remaining| rng.randrange(((remaining + 1) / 2 + 1) as u64) as usize);
+        }
+    }
+
+    // Test that a reset hasher starts over.
+    let mut hasher = h.clone();
+    hasher.Write(input, input.len()).Write(b"garbage", 7).Reset();
+    hasher.Write(input, input.len()).Finalize(&mut hash);
+    assert_eq!(hash, out);
+}
+
+//std::string LongTestString()
+#[cfg(test)]
+pub fn LongTestString() -> Vec<u8>
+{
+    let mut ret = Vec::with_capacity(1000000);
+    for i in 0..200000u32 {
+        ret.push(i as u8);
+        ret.push((i >> 4) as u8);
+        ret.push((i >> 8) as u8);
+        ret.push((i >> 12) as u8);
+        ret.push((i >> 16) as u8);
+    }
+    ret
+}
@@ -0,0 +1,285 @@
+// Copyright (c) 2019-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+// Based on the public domain implementation by Andrew Moon
+// poly1305-donna-32.h from https://github.com/floodyberry/poly1305-donna
+
+use crate::crypto::common::{ReadLE32, WriteLE32};
+
+const POLY1305_BLOCK_SIZE: usize = 16;
+
+//typedef struct {
+//    uint32_t r[5];
+//    uint32_t h[5];
+//    uint32_t pad[4];
+//    size_t leftover;
+//    unsigned char buffer[POLY1305_BLOCK_SIZE];
+//    unsigned char final;
+//} poly1305_context;
+#[derive(Clone)]
+struct poly1305_context
+{
+    r: [u32; 5],
+    h: [u32; 5],
+    pad: [u32; 4],
+    leftover: usize,
+    buffer: [u8; POLY1305_BLOCK_SIZE],
+    last: bool,
+}
+
+//void poly1305_init(poly1305_context *st, const unsigned char key[32]) noexcept;
+fn poly1305_init(key: &[u8]) -> poly1305_context
+{
+    poly1305_context {
+        /* r &= 0xffffffc0ffffffc0ffffffc0fffffff */
+        r: [
+            (ReadLE32(&key[0..])) & 0x3ffffff,
+            (ReadLE32(&key[3..]) >> 2) & 0x3ffff03,
+            (ReadLE32(&key[6..]) >> 4) & 0x3ffc0ff,
+            (ReadLE32(&key[9..]) >> 6) & 0x3f03fff,
+            (ReadLE32(&key[12..]) >> 8) & 0x00fffff,
+        ],
+        /* h = 0 */
+        h: [0; 5],
+        /* save pad for later */
+        pad: [ReadLE32(&key[16..]), ReadLE32(&key[20..]), ReadLE32(&key[24..]), ReadLE32(&key[28..])],
+        leftover: 0,
+        buffer: [0; POLY1305_BLOCK_SIZE],
+        last: false,
+    }
+}
+
+//static void poly1305_blocks(poly1305_context *st, const unsigned char *m, size_t bytes) noexcept;
+fn poly1305_blocks(st: &mut poly1305_context, mut m: &[u8])
+{
+    let hibit: u32 = if st.last { 0 } else { 1 << 24 }; /* 1 << 128 */
+    let [r0, r1, r2, r3, r4] = st.r;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+    let [mut h0, mut h1, mut h2, mut h3, mut h4] = st.h;
+
+    while m.len() >= POLY1305_BLOCK_SIZE {
+        /* h += m[i] */
+        h0 += (ReadLE32(&m[0..])) & 0x3ffffff;
+        h1 += (ReadLE32(&m[3..]) >> 2) & 0x3ffffff;
+        h2 += (ReadLE32(&m[6..]) >> 4) & 0x3ffffff;
+        h3 += (ReadLE32(&m[9..]) >> 6) & 0x3ffffff;
+        h4 += (ReadLE32(&m[12..]) >> 8) | hibit;
+
+        /* h *= r */
+        let d0 = (h0 as u64 * r0 as u64) + (h1 as u64 * s4 as u64) + (h2 as u64 * s3 as u64) + (h3 as u64 * s2 as u64) + (h4 as u64 * s1 as u64);
+        let mut d1 = (h0 as u64 * r1 as u64) + (h1 as u64 * r0 as u64) + (h2 as u64 * s4 as u64) + (h3 as u64 * s3 as u64) + (h4 as u64 * s2 as u64);
+        let mut d2 = (h0 as u64 * r2 as u64) + (h1 as u64 * r1 as u64) + (h2 as u64 * r0 as u64) + (h3 as u64 * s4 as u64) + (h4 as u64 * s3 as u64);
+        let mut d3 = (h0 as u64 * r3 as u64) + (h1 as u64 * r2 as u64) + (h2 as u64 * r1 as u64) + (h3 as u64 * r0 as u64) + (h4 as u64 * s4 as u64);
+        let mut d4 = (h0 as u64 * r4 as u64) + (h1 as u64 * r3 as u64) + (h2 as u64 * r2 as u64) + (h3 as u64 * r1 as u64) + (h4 as u64 * r0 as u64);
+
+        /* (partial) h %= p */
+        let mut c = (d0 >> 26) as u32; h0 = d0 as u32 & 0x3ffffff;
+        d1 += c as u64; c = (d1 >> 26) as u32; h1 = d1 as u32 & 0x3ffffff;
+        d2 += c as u64; c = (d2 >> 26) as u32; h2 = d2 as u32 & 0x3ffffff;
+        d3 += c as u64; c = (d3 >> 26) as u32; h3 = d3 as u32 & 0x3ffffff;
+        d4 += c as u64; c = (d4 >> 26) as u32; h4 = d4 as u32 & 0x3ffffff;
+        h0 += c * 5;    c = h0 >> 26;          h0 &= 0x3ffffff;
+        h1 += c;
+
+        m = &m[POLY1305_BLOCK_SIZE..];
+    }
+
+    st.h = [h0, h1, h2, h3, h4];
+}
+
+//void poly1305_finish(poly1305_context *st, unsigned char mac[16]) noexcept;
+fn poly1305_finish(st: &mut poly1305_context, mac: &mut [u8])
+{
+    /* process the remaining block */
+    if st.leftover > 0 {
+        let mut i = st.leftover;
+        st.buffer[i] = 1;
+        i += 1;
+        st.buffer[i..].fill(0);
+        st.last = true;
+        let buffer = st.buffer;
+        poly1305_blocks(st, &buffer);
+    }
+
+    /* fully carry h */
+    let [mut h0, mut h1, mut h2, mut h3, mut h4] = st.h;
+
+    let mut c = h1 >> 26; h1 &= 0x3ffffff;
+    h2 += c;     c = h2 >> 26; h2 &= 0x3ffffff;
+    h3 += c;     c = h3 >> 26; h3 &= 0x3ffffff;
+    h4 += c;     c = h4 >> 26; h4 &= 0x3ffffff;
+    h0 += c * 5; c = h0 >> 26; h0 &= 0x3ffffff;
+    h1 += c;
+
+    /* compute h + -p */
+    let mut g0 = h0 + 5; c = g0 >> 26; g0 &= 0x3ffffff;
+    let mut g1 = h1 + c; c = g1 >> 26; g1 &= 0x3ffffff;
+    let mut g2 = h2 + c; c = g2 >> 26; g2 &= 0x3ffffff;
+    let mut g3 = h3 + c; c = g3 >> 26; g3 &= 0x3ffffff;
+    let mut g4 = (h4 + c).wrapping_sub(1 << 26);
+
+    /* select h if h < p, or h + -p if h >= p */
+    let mut mask = (g4 >> 31).wrapping_sub(1);
+    g0 &= mask;
+    g1 &= mask;
+    g2 &= mask;
+    g3 &= mask;
+    g4 &= mask;
+    mask = !mask;
+    h0 = (h0 & mask) | g0;
+    h1 = (h1 & mask) | g1;
+    h2 = (h2 & mask) | g2;
+    h3 = (h3 & mask) | g3;
+    h4 = (h4 & mask) | g4;
+
+    /* h = h % (2^128) */
+    h0 |= h1 << 26;
+    h1 = (h1 >> 6) | (h2 << 20);
+    h2 = (h2 >> 12) | (h3 << 14);
+    h3 = (h3 >> 18) | (h4 << 8);
+
+    /* mac = (h + pad) % (2^128) */
+    let mut f = h0 as u64 + st.pad[0] as u64;               h0 = f as u32;
+    f = h1 as u64 + st.pad[1] as u64 + (f >> 32); h1 = f as u32;
+    f = h2 as u64 + st.pad[2] as u64 + (f >> 32); h2 = f as u32;
+    f = h3 as u64 + st.pad[3] as u64 + (f >> 32); h3 = f as u32;
+
+    WriteLE32(&mut mac[0..], h0);
+    WriteLE32(&mut mac[4..], h1);
+    WriteLE32(&mut mac[8..], h2);
+    WriteLE32(&mut mac[12..], h3);
+}
+
+//void poly1305_update(poly1305_context *st, const unsigned char *m, size_t bytes) noexcept;
+fn poly1305_update(st: &mut poly1305_context, mut m: &[u8])
+{
+    /* handle leftover */
+    if st.leftover > 0 {
+        let want = std::cmp::min(POLY1305_BLOCK_SIZE - st.leftover, m.len());
+        st.buffer[st.leftover..st.leftover + want].copy_from_slice(&m[..want]);
+        m = &m[want..];
+        st.leftover += want;
+        if st.leftover < POLY1305_BLOCK_SIZE {
+            return;
+        }
+        let buffer = st.buffer;
+        poly1305_blocks(st, &buffer);
+        st.leftover = 0;
+    }
+
+    /* process full blocks */
+    if m.len() >= POLY1305_BLOCK_SIZE {
+        let want = m.len() & !(POLY1305_BLOCK_SIZE - 1);
+        poly1305_blocks(st, &m[..want]);
+        m = &m[want..];
+    }
+
+    /* store leftover */
+    if !m.is_empty() {
+        st.buffer[st.leftover..st.leftover + m.len()].copy_from_slice(m);
+        st.leftover += m.len();
+    }
+}
+
+/** C++ wrapper with std::byte Span interface around poly1305_donna code. */
+#[derive(Clone)]
+pub struct Poly1305
+{
+    m_ctx: poly1305_context,
+}
+
+impl Poly1305 {
+    /** Length of the output produced by Finalize(). */
+    pub const TAGLEN: usize = 16;
+
+    /** Length of the keys expected by the constructor. */
+    pub const KEYLEN: usize = 32;
+
+    /** Construct a Poly1305 object with a given 32-byte key. */
+    //Poly1305(Span<const std::byte> key) noexcept
+    pub fn new(key: &[u8]) -> Self {
+        assert!(key.len() == Self::KEYLEN);
+        Self { m_ctx: poly1305_init(key) }
+    }
+
+    /** Process message bytes. */
+    //Poly1305& Update(Span<const std::byte> msg) noexcept
+    pub fn Update(&mut self, msg: &[u8]) -> &mut Self
+    {
+        poly1305_update(&mut self.m_ctx, msg);
+        self
+    }
+
+    /** Write authentication tag to 16-byte out. */
+    //void Finalize(Span<std::byte> out) noexcept
+    pub fn Finalize(&mut self, out: &mut [u8])
+    {
+        assert!(out.len() == Self::TAGLEN);
+        poly1305_finish(&mut self.m_ctx, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn TestPoly1305(hexmessage: &str, hexkey: &str, hextag: &str)
+    {
+        let key = hex::decode(hexkey).unwrap();
+        let m = hex::decode(hexmessage).unwrap();
+        let mut tagres = [0u8; Poly1305::TAGLEN];
+        Poly1305::new(&key).Update(&m).Finalize(&mut tagres);
+        assert_eq!(hex::encode(tagres), hextag);
+
+        // Test incremental interface
+        for splits in 0..4 {
+            for iter in 0..10 {
+                let mut poly1305 = Poly1305::new(&key);
+                let mut data = &m[..];
+                // Process in pieces of a size that depends on the iteration.
+                for _ in 0..splits {
+                    let now = std::cmp::min(data.len(), (iter * 7 + 1) % 40);
+                    poly1305.Update(&data[..now]);
+                    data = &data[now..];
+                }
+                poly1305.Update(data);
+                let mut tag = [0u8; Poly1305::TAGLEN];
+                poly1305.Finalize(&mut tag);
+                assert_eq!(hex::encode(tag), hextag);
+            }
+        }
+    }
+
+    #[test]
+    fn test_poly1305_testvector() {
+        // RFC 8439 Section 2.5.2
+        TestPoly1305(&hex::encode("Cryptographic Forum Research Group"),
+                     "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b",
+                     "a8061dc1305136c6c22b8baf0c0127a9");
+
+        // RFC 8439 Appendix A.3, test vectors 1, 5, 6, 7, 9, 10 and 11
+        TestPoly1305(&"00".repeat(64),
+                     "0000000000000000000000000000000000000000000000000000000000000000",
+                     "00000000000000000000000000000000");
+        TestPoly1305("ffffffffffffffffffffffffffffffff",
+                     "0200000000000000000000000000000000000000000000000000000000000000",
+                     "03000000000000000000000000000000");
+        TestPoly1305("02000000000000000000000000000000",
+                     "02000000000000000000000000000000ffffffffffffffffffffffffffffffff",
+                     "03000000000000000000000000000000");
+        TestPoly1305("fffffffffffffffffffffffffffffffff0ffffffffffffffffffffffffffffff\
+                      11000000000000000000000000000000",
+                     "0100000000000000000000000000000000000000000000000000000000000000",
+                     "05000000000000000000000000000000");
+        TestPoly1305("fdffffffffffffffffffffffffffffff",
+                     "0200000000000000000000000000000000000000000000000000000000000000",
+                     "faffffffffffffffffffffffffffffff");
+        TestPoly1305("e33594d7505e43b900000000000000003394d7505e4379cd01000000000000000000000000000000000000000000000001000000000000000000000000000000",
+                     "0100000000000000040000000000000000000000000000000000000000000000",
+                     "14000000000000005500000000000000");
+        TestPoly1305("e33594d7505e43b900000000000000003394d7505e4379cd010000000000000000000000000000000000000000000000",
+                     "0100000000000000040000000000000000000000000000000000000000000000",
+                     "13000000000000000000000000000000");
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) 2014-2019 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::crypto::common::{ReadLE32, WriteLE32, WriteLE64};
+use crate::crypto::Hasher;
+
+/** A hasher class for RIPEMD-160. */
+#[derive(Clone)]
+pub struct CRIPEMD160
+{
+    s: [u32; 5],
+    buf: [u8; 64],
+    bytes: u64,
+}
+
+impl CRIPEMD160 {
+    pub fn new() -> Self {
+        let mut s = [0 as u32; 5];
+        Initialize(&mut s);
+        Self {s, buf: [0; 64], bytes: 0}
+    }
+}
+
+impl Default for CRIPEMD160 {
+    fn default() -> Self {
+        CRIPEMD160::new()
+    }
+}
+
+impl Hasher for CRIPEMD160 {
+    const OUTPUT_SIZE: usize = 20;
+
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
+    {
+        let mut data = &data[..len];
+        let mut bufsize: usize = (self.bytes % 64) as usize;
+        //if (bufsize && bufsize + len >= 64) {
+        if bufsize > 0 && bufsize + len >= 64 {
+            // Fill the buffer, and process it.
+            self.buf[bufsize..].copy_from_slice(&data[0..(64 - bufsize)]);
+            self.bytes += 64 - bufsize as u64;
+            data = &data[(64 - bufsize)..];
+            Transform(&mut self.s, &self.buf);
+            bufsize = 0;
+        }
+        //while (end - data >= 64) {
+        while data.len() >= 64 {
+            // Process full chunks directly from the source.
+            Transform(&mut self.s, &data[..64]);
+            self.bytes += 64;
+            data = &data[64..];
+        }
+        //if (end > data) {
+        if !data.is_empty() {
+            // Fill the buffer with what remains.
+            self.buf[bufsize..(bufsize + data.len())].copy_from_slice(data);
+            self.bytes += data.len() as u64;
+        }
+        self
+    }
+
+    fn Finalize(&mut self, hash: &mut [u8])
+    {
+        assert!(hash.len() == Self::OUTPUT_SIZE);
+        //static const unsigned char pad[64] = {0x80};
+        let mut pad: [u8; 64] = [0; 64];
+        pad[0] = 0x80;
+        let mut sizedesc: [u8; 8] = [0; 8];
+        WriteLE64(&mut sizedesc, self.bytes << 3);
+        self.Write(&pad, 1 + ((119 - (self.bytes % 64)) % 64) as usize);
+        self.Write(&sizedesc, 8);
+        WriteLE32(&mut hash[0..4], self.s[0]);
+        WriteLE32(&mut hash[4..8], self.s[1]);
+        WriteLE32(&mut hash[8..12], self.s[2]);
+        WriteLE32(&mut hash[12..16], self.s[3]);
+        WriteLE32(&mut hash[16..20], self.s[4]);
+    }
+
+    fn Reset(&mut self) -> &mut Self
+    {
+        self.bytes = 0;
+        Initialize(&mut self.s);
+        self
+    }
+
+    fn Size(&self) -> usize
+    {
+        self.bytes as usize
+    }
+}
+
+//uint32_t inline f1(uint32_t x, uint32_t y, uint32_t z) { return x ^ y ^ z; }
+fn f1(x: u32, y: u32, z: u32) -> u32 { x ^ y ^ z }
+//uint32_t inline f2(uint32_t x, uint32_t y, uint32_t z) { return (x & y) | (~x & z); }
+fn f2(x: u32, y: u32, z: u32) -> u32 { (x & y) | (!x & z) }
+//uint32_t inline f3(uint32_t x, uint32_t y, uint32_t z) { return (x | ~y) ^ z; }
+fn f3(x: u32, y: u32, z: u32) -> u32 { (x | !y) ^ z }
+//uint32_t inline f4(uint32_t x, uint32_t y, uint32_t z) { return (x & z) | (y & ~z); }
+fn f4(x: u32, y: u32, z: u32) -> u32 { (x & z) | (y & !z) }
+//uint32_t inline f5(uint32_t x, uint32_t y, uint32_t z) { return x ^ (y | ~z); }
+fn f5(x: u32, y: u32, z: u32) -> u32 { x ^ (y | !z) }
+
+/** Initialize RIPEMD-160 state. */
+fn Initialize(s: &mut [u32])
+{
+    s[0] = 0x67452301;
+    s[1] = 0xEFCDAB89;
+    s[2] = 0x98BADCFE;
+    s[3] = 0x10325476;
+    s[4] = 0xC3D2E1F0;
+}
+
+//void inline Round(uint32_t& a, uint32_t b, uint32_t& c, uint32_t d, uint32_t e, uint32_t f, uint32_t x, uint32_t k, int r)
+fn Round(a: &mut u32, c: &mut u32, e: u32, f: u32, x: u32, k: u32, r: u32)
+{
+    *a = a.wrapping_add(f).wrapping_add(x).wrapping_add(k).rotate_left(r).wrapping_add(e);
+    *c = c.rotate_left(10);
+}
+
+/* Message word selection and rotation amounts for the left (r, s) and right
+ * (rr, ss) lines, in the order of the unrolled rounds in ripemd160.cpp. */
+const R: [usize; 80] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8,
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12,
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2,
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13];
+const RR: [usize; 80] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12,
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2,
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13,
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14,
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11];
+const S: [u32; 80] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8,
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12,
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5,
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12,
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6];
+const SS: [u32; 80] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6,
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11,
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5,
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8,
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11];
+
+/** Perform a RIPEMD-160 transformation, processing a 64-byte chunk. */
+//void Transform(uint32_t* s, const unsigned char* chunk)
+fn Transform(s: &mut [u32], chunk: &[u8])
+{
+    let mut w = [0u32; 16];
+    for (i, word) in w.iter_mut().enumerate() {
+        *word = ReadLE32(&chunk[4 * i..]);
+    }
+
+    //uint32_t a1 = s[0], b1 = s[1], c1 = s[2], d1 = s[3], e1 = s[4];
+    let mut v1 = [s[0], s[1], s[2], s[3], s[4]];
+    let mut v2 = v1;
+
+    for j in 0..80 {
+        /* Each round rotates the roles of a..e one step to the right. */
+        let (a, b, c, d, e) = ((80 - j) % 5, (81 - j) % 5, (82 - j) % 5, (83 - j) % 5, (84 - j) % 5);
+        let (f, k) = match j / 16 {
+            0 => (f1(v1[b], v1[c], v1[d]), 0),
+            1 => (f2(v1[b], v1[c], v1[d]), 0x5A827999),
+            2 => (f3(v1[b], v1[c], v1[d]), 0x6ED9EBA1),
+            3 => (f4(v1[b], v1[c], v1[d]), 0x8F1BBCDC),
+            _ => (f5(v1[b], v1[c], v1[d]), 0xA953FD4E),
+        };
+        let (mut va, mut vc) = (v1[a], v1[c]);
+        Round(&mut va, &mut vc, v1[e], f, w[R[j]], k, S[j]);
+        v1[a] = va;
+        v1[c] = vc;
+
+        let (f, k) = match j / 16 {
+            0 => (f5(v2[b], v2[c], v2[d]), 0x50A28BE6),
+            1 => (f4(v2[b], v2[c], v2[d]), 0x5C4DD124),
+            2 => (f3(v2[b], v2[c], v2[d]), 0x6D703EF3),
+            3 => (f2(v2[b], v2[c], v2[d]), 0x7A6D76E9),
+            _ => (f1(v2[b], v2[c], v2[d]), 0),
+        };
+        let (mut va, mut vc) = (v2[a], v2[c]);
+        Round(&mut va, &mut vc, v2[e], f, w[RR[j]], k, SS[j]);
+        v2[a] = va;
+        v2[c] = vc;
+    }
+
+    //uint32_t t = s[0];
+    //s[0] = s[1] + c1 + d2;
+    //s[1] = s[2] + d1 + e2;
+    //s[2] = s[3] + e1 + a2;
+    //s[3] = s[4] + a1 + b2;
+    //s[4] = t + b1 + c2;
+    let t = s[0];
+    s[0] = s[1].wrapping_add(v1[2]).wrapping_add(v2[3]);
+    s[1] = s[2].wrapping_add(v1[3]).wrapping_add(v2[4]);
+    s[2] = s[3].wrapping_add(v1[4]).wrapping_add(v2[0]);
+    s[3] = s[4].wrapping_add(v1[0]).wrapping_add(v2[1]);
+    s[4] = t.wrapping_add(v1[1]).wrapping_add(v2[2]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{LongTestString, TestVector};
+
+    fn TestRIPEMD160(inStr: impl AsRef<[u8]>, hexout: &str) {
+        TestVector(&CRIPEMD160::new(), inStr.as_ref(), &hex::decode(hexout).unwrap());
+    }
+
+    #[test]
+    fn test_ripemd160_testvectors() {
+        TestRIPEMD160("", "9c1185a5c5e9fc54612808977ee8f548b2258d31");
+        TestRIPEMD160("abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc");
+        TestRIPEMD160("message digest", "5d0689ef49d2fae572b881b123a85ffa21595f36");
+        TestRIPEMD160("secure hash algorithm", "20397528223b6a5f4cbc2808aba0464e645544f9");
+        TestRIPEMD160("RIPEMD160 is considered to be safe", "a7d78608c7af8a8e728778e81576870734122b66");
+        TestRIPEMD160("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                      "12a053384a9c0c88e405a06c27dcf49ada62eb2b");
+        TestRIPEMD160("For this sample, this 63-byte string will be used as input data",
+                      "de90dbfee14b63fb5abf27c2ad4a82aaa5f27a11");
+        TestRIPEMD160("This is exactly 64 bytes long, not counting the terminating byte",
+                      "eda31d51d3a623b81e19eb02e24ff65d27d67b37");
+        TestRIPEMD160("a".repeat(1000000), "52783243c1697bdbe16d37f97f68f08325dc1528");
+        TestRIPEMD160(LongTestString(), "464243587bd146ea835cdf57bdae582f25ec45f1");
+    }
+}
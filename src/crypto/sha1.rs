@@ -0,0 +1,186 @@
+// Copyright (c) 2014-2019 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use crate::crypto::common::{ReadBE32, WriteBE32, WriteBE64};
+use crate::crypto::Hasher;
+
+/** A hasher class for SHA1. */
+#[derive(Clone)]
+pub struct CSHA1
+{
+    s: [u32; 5],
+    buf: [u8; 64],
+    bytes: u64,
+}
+
+impl CSHA1 {
+    pub fn new() -> Self {
+        let mut s = [0 as u32; 5];
+        Initialize(&mut s);
+        Self {s, buf: [0; 64], bytes: 0}
+    }
+}
+
+impl Default for CSHA1 {
+    fn default() -> Self {
+        CSHA1::new()
+    }
+}
+
+impl Hasher for CSHA1 {
+    const OUTPUT_SIZE: usize = 20;
+
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
+    {
+        let mut data = &data[..len];
+        let mut bufsize: usize = (self.bytes % 64) as usize;
+        //if (bufsize && bufsize + len >= 64) {
+        if bufsize > 0 && bufsize + len >= 64 {
+            // Fill the buffer, and process it.
+            self.buf[bufsize..].copy_from_slice(&data[0..(64 - bufsize)]);
+            self.bytes += 64 - bufsize as u64;
+            data = &data[(64 - bufsize)..];
+            Transform(&mut self.s, &self.buf);
+            bufsize = 0;
+        }
+        //while (end - data >= 64) {
+        while data.len() >= 64 {
+            // Process full chunks directly from the source.
+            Transform(&mut self.s, &data[..64]);
+            self.bytes += 64;
+            data = &data[64..];
+        }
+        //if (end > data) {
+        if !data.is_empty() {
+            // Fill the buffer with what remains.
+            self.buf[bufsize..(bufsize + data.len())].copy_from_slice(data);
+            self.bytes += data.len() as u64;
+        }
+        self
+    }
+
+    fn Finalize(&mut self, hash: &mut [u8])
+    {
+        assert!(hash.len() == Self::OUTPUT_SIZE);
+        //static const unsigned char pad[64] = {0x80};
+        let mut pad: [u8; 64] = [0; 64];
+        pad[0] = 0x80;
+        let mut sizedesc: [u8; 8] = [0; 8];
+        WriteBE64(&mut sizedesc, self.bytes << 3);
+        self.Write(&pad, 1 + ((119 - (self.bytes % 64)) % 64) as usize);
+        self.Write(&sizedesc, 8);
+        WriteBE32(&mut hash[0..4], self.s[0]);
+        WriteBE32(&mut hash[4..8], self.s[1]);
+        WriteBE32(&mut hash[8..12], self.s[2]);
+        WriteBE32(&mut hash[12..16], self.s[3]);
+        WriteBE32(&mut hash[16..20], self.s[4]);
+    }
+
+    fn Reset(&mut self) -> &mut Self
+    {
+        self.bytes = 0;
+        Initialize(&mut self.s);
+        self
+    }
+
+    fn Size(&self) -> usize
+    {
+        self.bytes as usize
+    }
+}
+
+const k1: u32 = 0x5A827999;
+const k2: u32 = 0x6ED9EBA1;
+const k3: u32 = 0x8F1BBCDC;
+const k4: u32 = 0xCA62C1D6;
+
+//uint32_t inline f1(uint32_t b, uint32_t c, uint32_t d) { return d ^ (b & (c ^ d)); }
+fn f1(b: u32, c: u32, d: u32) -> u32 { d ^ (b & (c ^ d)) }
+//uint32_t inline f2(uint32_t b, uint32_t c, uint32_t d) { return b ^ c ^ d; }
+fn f2(b: u32, c: u32, d: u32) -> u32 { b ^ c ^ d }
+//uint32_t inline f3(uint32_t b, uint32_t c, uint32_t d) { return (b & c) | (d & (b | c)); }
+fn f3(b: u32, c: u32, d: u32) -> u32 { (b & c) | (d & (b | c)) }
+
+/** Initialize SHA-1 state. */
+fn Initialize(s: &mut [u32])
+{
+    s[0] = 0x67452301;
+    s[1] = 0xEFCDAB89;
+    s[2] = 0x98BADCFE;
+    s[3] = 0x10325476;
+    s[4] = 0xC3D2E1F0;
+}
+
+/** One round of SHA-1. */
+//void inline Round(uint32_t a, uint32_t& b, uint32_t c, uint32_t d, uint32_t& e, uint32_t f, uint32_t k, uint32_t w)
+fn Round(a: u32, b: &mut u32, e: &mut u32, f: u32, k: u32, w: u32)
+{
+    *e = e.wrapping_add(a.rotate_left(5)).wrapping_add(f).wrapping_add(k).wrapping_add(w);
+    *b = b.rotate_left(30);
+}
+
+/** Perform a SHA-1 transformation, processing a 64-byte chunk. */
+//void Transform(uint32_t* s, const unsigned char* chunk)
+fn Transform(s: &mut [u32], chunk: &[u8])
+{
+    let mut w = [0u32; 80];
+    for i in 0..16 {
+        w[i] = ReadBE32(&chunk[4 * i..]);
+    }
+    for i in 16..80 {
+        //w0 = left(w0 ^ w13 ^ w8 ^ w2)
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    //uint32_t a = s[0], b = s[1], c = s[2], d = s[3], e = s[4];
+    let mut v = [s[0], s[1], s[2], s[3], s[4]];
+
+    for (i, wi) in w.iter().enumerate() {
+        /* Each round rotates the roles of a..e one step to the right. */
+        let (a, b, c, d, e) = ((80 - i) % 5, (81 - i) % 5, (82 - i) % 5, (83 - i) % 5, (84 - i) % 5);
+        let (f, k) = match i / 20 {
+            0 => (f1(v[b], v[c], v[d]), k1),
+            1 => (f2(v[b], v[c], v[d]), k2),
+            2 => (f3(v[b], v[c], v[d]), k3),
+            _ => (f2(v[b], v[c], v[d]), k4),
+        };
+        let (mut vb, mut ve) = (v[b], v[e]);
+        Round(v[a], &mut vb, &mut ve, f, k, *wi);
+        v[b] = vb;
+        v[e] = ve;
+    }
+
+    s[0] = s[0].wrapping_add(v[0]);
+    s[1] = s[1].wrapping_add(v[1]);
+    s[2] = s[2].wrapping_add(v[2]);
+    s[3] = s[3].wrapping_add(v[3]);
+    s[4] = s[4].wrapping_add(v[4]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{LongTestString, TestVector};
+
+    fn TestSHA1(inStr: impl AsRef<[u8]>, hexout: &str) {
+        TestVector(&CSHA1::new(), inStr.as_ref(), &hex::decode(hexout).unwrap());
+    }
+
+    #[test]
+    fn test_sha1_testvectors() {
+        TestSHA1("", "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        TestSHA1("abc", "a9993e364706816aba3e25717850c26c9cd0d89d");
+        TestSHA1("message digest", "c12252ceda8be8994d5fa0290a47231c1d16aae3");
+        TestSHA1("secure hash algorithm", "d4d6d2f0ebe317513bbd8d967d89bac5819c2f60");
+        TestSHA1("SHA1 is considered to be safe", "f2b6650569ad3a8720348dd6ea6c497dee3a842a");
+        TestSHA1("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                 "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+        TestSHA1("For this sample, this 63-byte string will be used as input data",
+                 "4f0ea5cd0585a23d028abdc1a6684e5a8094dc49");
+        TestSHA1("This is exactly 64 bytes long, not counting the terminating byte",
+                 "fb679f23e7d1ce053313e66e127ab1b444397057");
+        TestSHA1("a".repeat(1000000), "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+        TestSHA1(LongTestString(), "b7755760681cbfd971451668f32af5774f4656b5");
+    }
+}
@@ -3,8 +3,11 @@
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
 
 use crate::crypto::common::{WriteBE64, WriteBE32, ReadBE32};
+use crate::crypto::Hasher;
+use std::sync::atomic::{AtomicU8, Ordering};
 use wrapping_arithmetic::wrappit;
 
+#[derive(Clone)]
 pub struct CSHA256
 {
     s: [u32; 8],
@@ -22,8 +25,10 @@ impl CSHA256 {
         Self {s, buf,  bytes: 0}
     }
 
-    pub fn Write(&mut self, mut data: &[u8], len: usize) -> &mut Self
+    pub fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
     {
+        let Transform = Implementation().Transform;
+        let mut data = &data[..len];
         let mut bufsize: usize = (self.bytes % 64) as usize;
         //if (bufsize && bufsize + len >= 64) {
         if bufsize > 0 && bufsize + len >= 64 {
@@ -62,7 +67,8 @@ impl CSHA256 {
     pub fn Finalize(&mut self, hash: &mut [u8; CSHA256::OUTPUT_SIZE])
     {
         //static const unsigned char pad[64] = {0x80};
-        let mut pad: [u8; 64] = [0x80; 64];
+        let mut pad: [u8; 64] = [0; 64];
+        pad[0] = 0x80;
         //unsigned char sizedesc[8];
         let mut sizedesc: [u8; 8] = [0; 8];
         WriteBE64(&mut sizedesc, self.bytes << 3);
@@ -83,10 +89,52 @@ impl CSHA256 {
         self.bytes = 0;
         Initialize(&mut self.s);
         self
-    }    
+    }
+
+    //uint64_t Size() const { return bytes; }
+    pub fn Size(&self) -> usize
+    {
+        self.bytes as usize
+    }
+
+}
+
+impl Hasher for CSHA256 {
+    const OUTPUT_SIZE: usize = CSHA256::OUTPUT_SIZE;
+
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
+    {
+        CSHA256::Write(self, data, len)
+    }
+
+    fn Finalize(&mut self, hash: &mut [u8])
+    {
+        CSHA256::Finalize(self, hash.try_into().expect("SHA256 output is 32 bytes"))
+    }
 
+    fn Reset(&mut self) -> &mut Self
+    {
+        CSHA256::Reset(self)
+    }
+
+    fn Size(&self) -> usize
+    {
+        CSHA256::Size(self)
+    }
 }
 
+/** SHA-256 round constants, shared with the vectorized implementations. */
+pub(crate) const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
 //uint32_t inline Ch(uint32_t x, uint32_t y, uint32_t z) { return z ^ (x & (y ^ z)); }
 fn Ch(x: u32, y: u32, z: u32) -> u32 { z ^ (x & (y ^ z)) }
 
@@ -202,7 +250,7 @@ fn Transform(s: &mut [u32], mut chunk: &[u8], mut blocks: usize)
         w13 += sigma1(w11) + w6 + sigma0(w14);
         Round(d, e, f, &mut g, h, a, b, &mut c, 0xd5a79147_u32 + w13);
         w14 += sigma1(w12) + w7 + sigma0(w15);
-        (c, d, e, &mut f, g, h, a, &mut b, 0x06ca6351_u32 + w14);
+        Round(c, d, e, &mut f, g, h, a, &mut b, 0x06ca6351_u32 + w14);
         w15 += sigma1(w13) + w8 + sigma0(w0);
         Round(b, c, d, &mut e, f, g, h, &mut a, 0x14292967_u32 + w15);
         
@@ -287,11 +335,306 @@ fn Transform(s: &mut [u32], mut chunk: &[u8], mut blocks: usize)
     }
 }
 
+type TransformType = fn(&mut [u32], &[u8], usize);
+type TransformD64Type = fn(&mut [u8], &[u8]);
+
+//template<TransformType tr>
+//void TransformD64Wrapper(unsigned char* out, const unsigned char* in)
+/** Double SHA-256 of one 64-byte input, built out of a block transform. */
+fn TransformD64Wrapper(tr: TransformType, out: &mut [u8], input: &[u8])
+{
+    let mut s: [u32; 8] = [0; 8];
+    let mut padding1: [u8; 64] = [0; 64];
+    padding1[0] = 0x80;
+    padding1[62] = 0x02;
+    let mut buffer2: [u8; 64] = [0; 64];
+    buffer2[32] = 0x80;
+    buffer2[62] = 0x01;
+    Initialize(&mut s);
+    tr(&mut s, input, 1);
+    tr(&mut s, &padding1, 1);
+    for i in 0..8 {
+        WriteBE32(&mut buffer2[4 * i..], s[i]);
+    }
+    Initialize(&mut s);
+    tr(&mut s, &buffer2, 1);
+    for i in 0..8 {
+        WriteBE32(&mut out[4 * i..], s[i]);
+    }
+}
+
+fn TransformD64(out: &mut [u8], input: &[u8])
+{
+    TransformD64Wrapper(Transform, out, input);
+}
+
+/* The vectorized implementations are only ever installed by SHA256AutoDetect
+ * after it has checked that the CPU supports the instructions they use. */
+#[cfg(target_arch = "x86_64")]
+fn TransformShani(s: &mut [u32], chunk: &[u8], blocks: usize)
+{
+    assert!(chunk.len() >= 64 * blocks);
+    unsafe { crate::crypto::sha256_x86_shani::Transform(s, chunk, blocks) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn TransformD64Shani(out: &mut [u8], input: &[u8])
+{
+    TransformD64Wrapper(TransformShani, out, input);
+}
+
+#[cfg(target_arch = "x86_64")]
+fn TransformD64_4way(out: &mut [u8], input: &[u8])
+{
+    assert!(out.len() >= 128 && input.len() >= 256);
+    unsafe { crate::crypto::sha256_sse41::Transform_4way(out, input) }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn TransformD64_8way(out: &mut [u8], input: &[u8])
+{
+    assert!(out.len() >= 256 && input.len() >= 512);
+    unsafe { crate::crypto::sha256_avx2::Transform_8way(out, input) }
+}
+
+/** Which hardware accelerated implementations SHA256AutoDetect may use. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UseImplementation {
+    STANDARD = 0,
+    USE_SSE4 = 1 << 0,
+    USE_AVX2 = 1 << 1,
+    USE_SHANI = 1 << 2,
+    USE_SSE4_AND_AVX2 = 1 << 0 | 1 << 1,
+    USE_SSE4_AND_SHANI = 1 << 0 | 1 << 2,
+    USE_ALL = 1 << 0 | 1 << 1 | 1 << 2,
+}
+
+/** The set of functions CSHA256 and SHA256D64 dispatch to. */
+#[derive(Clone, Copy)]
+struct Dispatch {
+    Transform: TransformType,
+    TransformD64: TransformD64Type,
+    TransformD64_4way: Option<TransformD64Type>,
+    TransformD64_8way: Option<TransformD64Type>,
+}
+
+/** Bitmask of the UseImplementation flags in effect, or UNSELECTED before the first use. */
+static IMPLEMENTATION: AtomicU8 = AtomicU8::new(UNSELECTED);
+const UNSELECTED: u8 = 0xff;
+
+fn DispatchFor(enabled: u8) -> Dispatch
+{
+    #[allow(unused_mut)]
+    let mut dispatch = Dispatch { Transform, TransformD64, TransformD64_4way: None, TransformD64_8way: None };
+    #[cfg(target_arch = "x86_64")]
+    {
+        if enabled & UseImplementation::USE_SHANI as u8 != 0 {
+            dispatch.Transform = TransformShani;
+            dispatch.TransformD64 = TransformD64Shani;
+        }
+        if enabled & UseImplementation::USE_SSE4 as u8 != 0 {
+            dispatch.TransformD64_4way = Some(TransformD64_4way);
+        }
+        if enabled & UseImplementation::USE_AVX2 as u8 != 0 {
+            dispatch.TransformD64_8way = Some(TransformD64_8way);
+        }
+    }
+    dispatch
+}
+
+/** The implementation in use, autodetecting it (and running the self-test) on first use. */
+fn Implementation() -> Dispatch
+{
+    let mut enabled = IMPLEMENTATION.load(Ordering::Relaxed);
+    if enabled == UNSELECTED {
+        SHA256AutoDetect(UseImplementation::USE_ALL);
+        enabled = IMPLEMENTATION.load(Ordering::Relaxed);
+    }
+    DispatchFor(enabled)
+}
+
+/** Sanity check an implementation against known double-SHA256 results, and its
+ * block transform against the portable one. */
+fn SelfTest(dispatch: &Dispatch) -> bool
+{
+    // Input: bytes i % 251 for i in 0..512, i.e. eight 64-byte blocks.
+    let mut data: [u8; 512] = [0; 512];
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = (i % 251) as u8;
+    }
+    // Double-SHA256 of each of the eight blocks.
+    const D64: [&str; 8] = [
+        "01c9f464780a1b6af4eb400fe2f2896cfb2169f5a65701439e4c2c4e213903ef",
+        "f5ee57325dd069b79c096736566f676131ed3c7c8bf60310d17eb0dd6a723557",
+        "4f704c96e3e052e951e45029c0067b39160605c282a0a0baab6b6a53067519ee",
+        "318ab938d3118b9e7f0d88aa7649f6e6a9fedf6a71067e4fa6765a50d5ebdb05",
+        "2276f18fa18bff59f911c75563b692b478330bd34a04e61a81ff8781f3a37bd8",
+        "da3e1abde5d8328fdd747a074c6b0f134ae2b3e1dbda8dcb0098855172e516be",
+        "d6ee71f9e4c92362ed965f1976c0e204f84bbbc60425715f514b87d69946ebfa",
+        "f098b0d11f0b4ef486896492651703d9d27d38fd0ccce9c8b92459c0cbf82e97",
+    ];
+    let mut expected: [u8; 256] = [0; 256];
+    for (i, h) in D64.iter().enumerate() {
+        expected[32 * i..32 * (i + 1)].copy_from_slice(&hex::decode(h).unwrap());
+    }
+
+    // Test Transform() for 0 through 8 transformations.
+    for blocks in 0..=8 {
+        let (mut state, mut reference) = ([0u32; 8], [0u32; 8]);
+        Initialize(&mut state);
+        Initialize(&mut reference);
+        (dispatch.Transform)(&mut state, &data, blocks);
+        Transform(&mut reference, &data, blocks);
+        if state != reference {
+            return false;
+        }
+    }
+
+    let mut out: [u8; 256] = [0; 256];
+
+    // Test TransformD64
+    for i in 0..8 {
+        (dispatch.TransformD64)(&mut out[32 * i..], &data[64 * i..]);
+    }
+    if out != expected {
+        return false;
+    }
+
+    // Test TransformD64_4way, if available.
+    if let Some(TransformD64_4way) = dispatch.TransformD64_4way {
+        out = [0; 256];
+        TransformD64_4way(&mut out[..128], &data[..256]);
+        TransformD64_4way(&mut out[128..], &data[256..]);
+        if out != expected {
+            return false;
+        }
+    }
+
+    // Test TransformD64_8way, if available.
+    if let Some(TransformD64_8way) = dispatch.TransformD64_8way {
+        out = [0; 256];
+        TransformD64_8way(&mut out, &data);
+        if out != expected {
+            return false;
+        }
+    }
+
+    true
+}
+
+/** Autodetect the best available SHA256 implementation.
+ *  Returns the name of the implementation.
+ */
+//std::string SHA256AutoDetect(sha256_implementation::UseImplementation use_implementation)
+pub fn SHA256AutoDetect(use_implementation: UseImplementation) -> String
+{
+    let use_implementation = use_implementation as u8;
+    #[allow(unused_mut)]
+    let mut enabled: u8 = UseImplementation::STANDARD as u8;
+    let mut ret = "standard".to_string();
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        let have_sse4 = use_implementation & UseImplementation::USE_SSE4 as u8 != 0 && is_x86_feature_detected!("sse4.1");
+        let have_avx2 = use_implementation & UseImplementation::USE_AVX2 as u8 != 0 && is_x86_feature_detected!("avx2");
+        let have_x86_shani = use_implementation & UseImplementation::USE_SHANI as u8 != 0
+            && is_x86_feature_detected!("sha") && is_x86_feature_detected!("sse4.1");
+
+        if have_x86_shani {
+            // The SHA-NI transform beats the multi-way ones, so disable SSE4/AVX2.
+            enabled = UseImplementation::USE_SHANI as u8;
+            ret = "x86_shani(1way)".to_string();
+        } else {
+            if have_sse4 {
+                enabled |= UseImplementation::USE_SSE4 as u8;
+                ret += ",sse41(4way)";
+            }
+            if have_avx2 {
+                enabled |= UseImplementation::USE_AVX2 as u8;
+                ret += ",avx2(8way)";
+            }
+        }
+    }
+
+    assert!(SelfTest(&DispatchFor(enabled)));
+    IMPLEMENTATION.store(enabled, Ordering::Relaxed);
+    ret
+}
+
+/** Compute multiple double-SHA256's of 64-byte blobs.
+ *  output:  pointer to a blocks*32 byte output buffer
+ *  input:   pointer to a blocks*64 byte input buffer
+ *  blocks:  the number of hashes to compute.
+ */
+//void SHA256D64(unsigned char* output, const unsigned char* input, size_t blocks)
+pub fn SHA256D64(mut output: &mut [u8], mut input: &[u8], mut blocks: usize)
+{
+    assert!(output.len() >= 32 * blocks && input.len() >= 64 * blocks);
+    let dispatch = Implementation();
+    if let Some(TransformD64_8way) = dispatch.TransformD64_8way {
+        while blocks >= 8 {
+            TransformD64_8way(output, input);
+            output = &mut output[256..];
+            input = &input[512..];
+            blocks -= 8;
+        }
+    }
+    if let Some(TransformD64_4way) = dispatch.TransformD64_4way {
+        while blocks >= 4 {
+            TransformD64_4way(output, input);
+            output = &mut output[128..];
+            input = &input[256..];
+            blocks -= 4;
+        }
+    }
+    while blocks > 0 {
+        (dispatch.TransformD64)(output, input);
+        output = &mut output[32..];
+        input = &input[64..];
+        blocks -= 1;
+    }
+}
+
+#[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::crypto::{LongTestString, TestVector};
+
+    //static void TestSHA256(const std::string &in, const std::string &hexout) { TestVector(CSHA256(), in, ParseHex(hexout));}
+    fn TestSHA256(inStr: impl AsRef<[u8]>, hexout: &str) {
+        TestVector(&CSHA256::new(), inStr.as_ref(), &hex::decode(hexout).unwrap());
+    }
 
-    fn TestSHA256(inStr: &str, hexout: &str) {
-        todo!();
-        //TestVector(&CSHA256::new(), inStr, hexout);
+    #[test]
+    fn test_sha256d64() {
+        let mut data = vec![0u8; 64 * 13];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i * 31 + i / 64) as u8;
+        }
+        // Reference: the portable double-SHA256, one block at a time
+        let mut expected = vec![0u8; 32 * 13];
+        for i in 0..13 {
+            TransformD64(&mut expected[32 * i..], &data[64 * i..]);
+        }
+
+        for use_implementation in [UseImplementation::STANDARD, UseImplementation::USE_SSE4, UseImplementation::USE_AVX2,
+                                   UseImplementation::USE_SSE4_AND_AVX2, UseImplementation::USE_SHANI, UseImplementation::USE_ALL] {
+            let name = SHA256AutoDetect(use_implementation);
+            assert!(!name.is_empty());
+            for blocks in 0..=13 {
+                let mut out = vec![0u8; 32 * blocks];
+                SHA256D64(&mut out, &data, blocks);
+                assert_eq!(out[..], expected[..32 * blocks]);
+            }
+
+            // CSHA256 goes through the selected block transform too
+            let mut d64 = [0u8; 32];
+            SHA256D64(&mut d64, &data[..64], 1);
+            let mut first = [0u8; CSHA256::OUTPUT_SIZE];
+            CSHA256::new().Write(&data, 64).Finalize(&mut first);
+            CSHA256::new().Write(&first, 32).Finalize(&mut first);
+            assert_eq!(d64, first);
+        }
     }
 
     #[test]
@@ -312,9 +655,8 @@ mod tests {
                 "ab64eff7e88e2e46165e29f2bce41826bd4c7b3552f6b382a9e7d3af47c245f8");
         TestSHA256("As Bitcoin relies on 80 byte header hashes, we want to have an example for that.",
                 "7406e8de7d6e4fffc573daef05aefb8806e7790f55eab5576f31349743cca743");
-        /* TestSHA256(std::string(1000000, 'a'),
+        TestSHA256("a".repeat(1000000),
                 "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
-        TestSHA256(test1, "a316d55510b49662420f49d145d42fb83f31ef8dc016aa4e32df049991a91e26");
-        */
+        TestSHA256(LongTestString(), "a316d55510b49662420f49d145d42fb83f31ef8dc016aa4e32df049991a91e26");
     }
 }
\ No newline at end of file
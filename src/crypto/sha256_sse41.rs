@@ -0,0 +1,121 @@
+// Copyright (c) 2018-2019 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+// 4-way parallel double SHA-256 of 64-byte inputs, using SSE4.1.
+
+use std::arch::x86_64::*;
+use crate::crypto::common::{ReadBE32, WriteBE32};
+use crate::crypto::sha256::K;
+
+//__m128i inline K(uint32_t x) { return _mm_set1_epi32(x); }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Kx(x: u32) -> __m128i { _mm_set1_epi32(x as i32) }
+
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Add(x: __m128i, y: __m128i) -> __m128i { _mm_add_epi32(x, y) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Xor(x: __m128i, y: __m128i) -> __m128i { _mm_xor_si128(x, y) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Or(x: __m128i, y: __m128i) -> __m128i { _mm_or_si128(x, y) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn And(x: __m128i, y: __m128i) -> __m128i { _mm_and_si128(x, y) }
+
+macro_rules! Ror {
+    ($x:expr, $n:literal) => { Or(_mm_srli_epi32::<$n>($x), _mm_slli_epi32::<{32 - $n}>($x)) };
+}
+
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Ch(x: __m128i, y: __m128i, z: __m128i) -> __m128i { Xor(z, And(x, Xor(y, z))) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Maj(x: __m128i, y: __m128i, z: __m128i) -> __m128i { Or(And(x, y), And(z, Or(x, y))) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Sigma0(x: __m128i) -> __m128i { Xor(Xor(Ror!(x, 2), Ror!(x, 13)), Ror!(x, 22)) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Sigma1(x: __m128i) -> __m128i { Xor(Xor(Ror!(x, 6), Ror!(x, 11)), Ror!(x, 25)) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn sigma0(x: __m128i) -> __m128i { Xor(Xor(Ror!(x, 7), Ror!(x, 18)), _mm_srli_epi32::<3>(x)) }
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn sigma1(x: __m128i) -> __m128i { Xor(Xor(Ror!(x, 17), Ror!(x, 19)), _mm_srli_epi32::<10>(x)) }
+
+/** Run the 64 SHA-256 rounds over one block per lane and add the result into s. */
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Compress(s: &mut [__m128i; 8], w: &mut [__m128i; 16])
+{
+    let mut v = *s;
+    for i in 0..64 {
+        if i >= 16 {
+            w[i & 15] = Add(Add(sigma1(w[(i + 14) & 15]), w[(i + 9) & 15]), Add(sigma0(w[(i + 1) & 15]), w[i & 15]));
+        }
+        let t1 = Add(Add(v[7], Sigma1(v[4])), Add(Ch(v[4], v[5], v[6]), Add(Kx(K[i]), w[i & 15])));
+        let t2 = Add(Sigma0(v[0]), Maj(v[0], v[1], v[2]));
+        v = [Add(t1, t2), v[0], v[1], v[2], Add(v[3], t1), v[4], v[5], v[6]];
+    }
+    for j in 0..8 {
+        s[j] = Add(s[j], v[j]);
+    }
+}
+
+#[inline]
+#[target_feature(enable = "sse4.1")]
+unsafe fn Initial() -> [__m128i; 8]
+{
+    [Kx(0x6a09e667), Kx(0xbb67ae85), Kx(0x3c6ef372), Kx(0xa54ff53a),
+     Kx(0x510e527f), Kx(0x9b05688c), Kx(0x1f83d9ab), Kx(0x5be0cd19)]
+}
+
+//void Transform_4way(unsigned char* out, const unsigned char* in)
+/** Compute the double SHA-256 of four 64-byte inputs into four 32-byte outputs.
+ *
+ * The caller must make sure the CPU supports SSE4.1.
+ */
+#[target_feature(enable = "sse4.1")]
+pub unsafe fn Transform_4way(out: &mut [u8], input: &[u8])
+{
+    // Transform 1
+    let mut s = Initial();
+    let mut w = [Kx(0); 16];
+    for (i, wi) in w.iter_mut().enumerate() {
+        let mut lanes = [0u32; 4];
+        for (l, lane) in lanes.iter_mut().enumerate() {
+            *lane = ReadBE32(&input[64 * l + 4 * i..]);
+        }
+        *wi = _mm_loadu_si128(lanes.as_ptr() as *const __m128i);
+    }
+    Compress(&mut s, &mut w);
+
+    // Transform 2: the padding block of a 64-byte message
+    let mut w = [Kx(0); 16];
+    w[0] = Kx(0x80000000);
+    w[15] = Kx(0x200);
+    Compress(&mut s, &mut w);
+
+    // Transform 3: hash the 32-byte result
+    let mut w = [Kx(0); 16];
+    w[..8].copy_from_slice(&s);
+    w[8] = Kx(0x80000000);
+    w[15] = Kx(0x100);
+    let mut t = Initial();
+    Compress(&mut t, &mut w);
+
+    // Output
+    for (j, tj) in t.iter().enumerate() {
+        let mut lanes = [0u32; 4];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, *tj);
+        for (l, lane) in lanes.iter().enumerate() {
+            WriteBE32(&mut out[32 * l + 4 * j..], *lane);
+        }
+    }
+}
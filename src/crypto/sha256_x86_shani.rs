@@ -0,0 +1,125 @@
+// Copyright (c) 2018-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+//
+// Based on https://github.com/noloader/SHA-Intrinsics/blob/master/sha256-x86.c,
+// Written and placed in public domain by Jeffrey Walton.
+// Based on code from Intel, and by Sean Gulley for the miTLS project.
+
+// SHA-256 block transform using the x86 SHA extensions.
+
+use std::arch::x86_64::*;
+use crate::crypto::sha256::K;
+
+//alignas(__m128i) const uint8_t MASK[16] = {0x03, 0x02, 0x01, 0x00, 0x07, 0x06, 0x05, 0x04, 0x0b, 0x0a, 0x09, 0x08, 0x0f, 0x0e, 0x0d, 0x0c};
+const MASK: [u8; 16] = [0x03, 0x02, 0x01, 0x00, 0x07, 0x06, 0x05, 0x04, 0x0b, 0x0a, 0x09, 0x08, 0x0f, 0x0e, 0x0d, 0x0c];
+
+//void inline QuadRound(__m128i& state0, __m128i& state1, __m128i m, uint64_t k1, uint64_t k0)
+#[inline]
+#[target_feature(enable = "sha,sse4.1")]
+unsafe fn QuadRound(state0: &mut __m128i, state1: &mut __m128i, m: __m128i, q: usize)
+{
+    let msg = _mm_add_epi32(m, _mm_loadu_si128(K[4 * q..].as_ptr() as *const __m128i));
+    *state1 = _mm_sha256rnds2_epu32(*state1, *state0, msg);
+    *state0 = _mm_sha256rnds2_epu32(*state0, *state1, _mm_shuffle_epi32::<0x0e>(msg));
+}
+
+//void inline ShiftMessageA(__m128i& m0, __m128i m1)
+#[inline]
+#[target_feature(enable = "sha,sse4.1")]
+unsafe fn ShiftMessageA(m0: &mut __m128i, m1: __m128i)
+{
+    *m0 = _mm_sha256msg1_epu32(*m0, m1);
+}
+
+//void inline ShiftMessageC(__m128i& m0, __m128i m1, __m128i& m2)
+#[inline]
+#[target_feature(enable = "sha,sse4.1")]
+unsafe fn ShiftMessageC(m0: __m128i, m1: __m128i, m2: &mut __m128i)
+{
+    *m2 = _mm_sha256msg2_epu32(_mm_add_epi32(*m2, _mm_alignr_epi8::<4>(m1, m0)), m1);
+}
+
+//void inline Shuffle(__m128i& s0, __m128i& s1)
+#[inline]
+#[target_feature(enable = "sha,sse4.1")]
+unsafe fn Shuffle(s0: &mut __m128i, s1: &mut __m128i)
+{
+    let t1 = _mm_shuffle_epi32::<0xB1>(*s0);
+    let t2 = _mm_shuffle_epi32::<0x1B>(*s1);
+    *s0 = _mm_alignr_epi8::<0x08>(t1, t2);
+    *s1 = _mm_blend_epi16::<0xF0>(t2, t1);
+}
+
+//void inline Unshuffle(__m128i& s0, __m128i& s1)
+#[inline]
+#[target_feature(enable = "sha,sse4.1")]
+unsafe fn Unshuffle(s0: &mut __m128i, s1: &mut __m128i)
+{
+    let t1 = _mm_shuffle_epi32::<0x1B>(*s0);
+    let t2 = _mm_shuffle_epi32::<0xB1>(*s1);
+    *s0 = _mm_blend_epi16::<0xF0>(t1, t2);
+    *s1 = _mm_alignr_epi8::<0x08>(t2, t1);
+}
+
+//__m128i inline Load(const unsigned char* in)
+#[inline]
+#[target_feature(enable = "sha,sse4.1")]
+unsafe fn Load(input: &[u8]) -> __m128i
+{
+    assert!(input.len() >= 16);
+    _mm_shuffle_epi8(_mm_loadu_si128(input.as_ptr() as *const __m128i), _mm_loadu_si128(MASK.as_ptr() as *const __m128i))
+}
+
+//void Transform(uint32_t* s, const unsigned char* chunk, size_t blocks)
+/** Perform a number of SHA-256 transformations, processing 64-byte chunks.
+ *
+ * The caller must make sure the CPU supports the SHA and SSE4.1 extensions.
+ */
+#[target_feature(enable = "sha,sse4.1")]
+pub unsafe fn Transform(s: &mut [u32], mut chunk: &[u8], mut blocks: usize)
+{
+    assert!(s.len() >= 8);
+
+    /* Load state */
+    let mut s0 = _mm_loadu_si128(s.as_ptr() as *const __m128i);
+    let mut s1 = _mm_loadu_si128(s[4..].as_ptr() as *const __m128i);
+    Shuffle(&mut s0, &mut s1);
+
+    while blocks > 0 {
+        /* Remember old state */
+        let (so0, so1) = (s0, s1);
+
+        /* Load data and transform */
+        let mut m = [Load(&chunk[0..]), Load(&chunk[16..]), Load(&chunk[32..]), Load(&chunk[48..])];
+        for q in 0..16 {
+            QuadRound(&mut s0, &mut s1, m[q & 3], q);
+            match q {
+                1 | 2 => {
+                    let m1 = m[q & 3];
+                    ShiftMessageA(&mut m[(q + 3) & 3], m1);
+                }
+                3..=14 => {
+                    let (m0, m1) = (m[(q + 3) & 3], m[q & 3]);
+                    ShiftMessageC(m0, m1, &mut m[(q + 1) & 3]);
+                    if q <= 12 {
+                        ShiftMessageA(&mut m[(q + 3) & 3], m1);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        /* Combine with old state */
+        s0 = _mm_add_epi32(s0, so0);
+        s1 = _mm_add_epi32(s1, so1);
+
+        /* Advance */
+        chunk = &chunk[64..];
+        blocks -= 1;
+    }
+
+    Unshuffle(&mut s0, &mut s1);
+    _mm_storeu_si128(s.as_mut_ptr() as *mut __m128i, s0);
+    _mm_storeu_si128(s[4..].as_mut_ptr() as *mut __m128i, s1);
+}
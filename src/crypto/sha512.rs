@@ -6,6 +6,7 @@ use crate::crypto::common::{WriteBE64, ReadBE64};
 use wrapping_arithmetic::wrappit;
 use crate::crypto::Hasher;
 
+#[derive(Clone)]
 pub struct CSHA512
 {
     s: [u64; 8],
@@ -26,8 +27,9 @@ impl CSHA512 {
 impl Hasher for CSHA512 {
     const OUTPUT_SIZE: usize = 64;
 
-    fn Write(&mut self, mut data: &mut [u8], len: usize) -> &mut Self
+    fn Write(&mut self, data: &[u8], len: usize) -> &mut Self
     {
+        let mut data = &data[..len];
         let mut bufsize: usize = (self.bytes % 128) as usize;
         //if (bufsize && bufsize + len >= 128) {
         if bufsize > 0 && bufsize + len >= 128 {
@@ -37,7 +39,7 @@ impl Hasher for CSHA512 {
             // bytes += 128 - bufsize;
             self.bytes += 128 - bufsize as u64;
             //data += 128 - bufsize;
-            data = &mut data[(128-bufsize)..];
+            data = &data[(128-bufsize)..];
             Transform(&mut self.s, &mut self.buf);
             bufsize = 0;
         }
@@ -46,9 +48,11 @@ impl Hasher for CSHA512 {
         {
             //size_t blocks = (end - data) / 128;
             let blocks: usize = data.len() / 128;
-            Transform(&mut self.s, data);
+            for block in data[..128 * blocks].chunks_exact(128) {
+                Transform(&mut self.s, block);
+            }
             //data += 128 * blocks;
-            data = &mut data[(128 * blocks)..];
+            data = &data[(128 * blocks)..];
             self.bytes += 128 * blocks as u64;
         }
         //if (end > data) {
@@ -66,8 +70,9 @@ impl Hasher for CSHA512 {
     fn Finalize(&mut self, hash: &mut [u8])
     {
         assert!(hash.len() == Self::OUTPUT_SIZE);
-        //static const unsigned char pad[64] = {0x80};
-        let mut pad: [u8; 128] = [0x80; 128];
+        //static const unsigned char pad[128] = {0x80};
+        let mut pad: [u8; 128] = [0; 128];
+        pad[0] = 0x80;
         //unsigned char sizedesc[8];
         let mut sizedesc: [u8; 16] = [0; 16];
         WriteBE64(&mut sizedesc[8..], self.bytes << 3);
@@ -141,7 +146,7 @@ fn Initialize(s: &mut [u64])
 /** Perform one SHA-512 transformation, processing a 128-byte chunk. */
 // void Transform(uint64_t* s, const unsigned char* chunk)
 #[wrappit]
-fn Transform(s: &mut [u64], chunk: &mut [u8])
+fn Transform(s: &mut [u64], chunk: &[u8])
 {
     //uint64_t a = s[0], b = s[1], c = s[2], d = s[3], e = s[4], f = s[5], g = s[6], h = s[7];
     let (mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h) = (s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]);
@@ -323,56 +328,14 @@ fn Transform(s: &mut [u64], chunk: &mut [u8])
     s[7] += h;
 }
 
+#[cfg(test)]
 mod tests {
-    use crate::crypto::Hasher;
     use super::CSHA512;
-    use hex;
-    /*
-    template<typename Hasher, typename In, typename Out>
-    static void TestVector(const Hasher &h, const In &in, const Out &out) {
-        Out hash;
-        BOOST_CHECK(out.size() == h.OUTPUT_SIZE);
-        hash.resize(out.size());
-        {
-            // Test that writing the whole input string at once works.
-            Hasher(h).Write((const uint8_t*)in.data(), in.size()).Finalize(hash.data());
-            BOOST_CHECK(hash == out);
-        }
-        for (int i=0; i<32; i++) {
-            // Test that writing the string broken up in random pieces works.
-            Hasher hasher(h);
-            size_t pos = 0;
-            while (pos < in.size()) {
-                size_t len = InsecureRandRange((in.size() - pos + 1) / 2 + 1);
-                hasher.Write((const uint8_t*)in.data() + pos, len);
-                pos += len;
-                if (pos > 0 && pos + 2 * out.size() > in.size() && pos < in.size()) {
-                    // Test that writing the rest at once to a copy of a hasher works.
-                    Hasher(hasher).Write((const uint8_t*)in.data() + pos, in.size() - pos).Finalize(hash.data());
-                    BOOST_CHECK(hash == out);
-                }
-            }
-            hasher.Finalize(hash.data());
-            BOOST_CHECK(hash == out);
-        }
-    } */
-    fn TestVector<H: Hasher>(h: &mut H, inStr: &str, outStr: &str) {
-
-        assert!(outStr.len() == H::OUTPUT_SIZE);
-        let mut hash: [u8; CSHA512::OUTPUT_SIZE] = [0; CSHA512::OUTPUT_SIZE];
-
-
-
-        let mut bytes = vec![];
-        bytes.copy_from_slice(inStr.as_bytes());
-        let len = bytes.len();
-        h.Write(&mut bytes[..], len).Finalize(&mut hash[..]);
-        assert!(hex::encode(&hash) == outStr.to_string());
-    }
+    use crate::crypto::{LongTestString, TestVector};
 
     //static void TestSHA512(const std::string &in, const std::string &hexout) { TestVector(CSHA512(), in, ParseHex(hexout));}
-    fn TestSHA512(inStr: &str, hexout: &str) {
-        TestVector(&mut CSHA512::new(), inStr, hexout);
+    fn TestSHA512(inStr: impl AsRef<[u8]>, hexout: &str) {
+        TestVector(&CSHA512::new(), inStr.as_ref(), &hex::decode(hexout).unwrap());
     }
 
     #[test]
@@ -406,12 +369,12 @@ mod tests {
                 ijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
                 "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
                 501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909");
-        //TestSHA512(std::string(1000000, 'a'),
-        //        "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973eb\
-        //        de0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b");
-        //TestSHA512(test1,
-        //        "40cac46c147e6131c5193dd5f34e9d8bb4951395f27b08c558c65ff4ba2de594\
-         //       37de8c3ef5459d76a52cedc02dc499a3c9ed9dedbfb3281afd9653b8a112fafc");
+        TestSHA512("a".repeat(1000000),
+                "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973eb\
+                de0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b");
+        TestSHA512(LongTestString(),
+                "40cac46c147e6131c5193dd5f34e9d8bb4951395f27b08c558c65ff4ba2de594\
+                37de8c3ef5459d76a52cedc02dc499a3c9ed9dedbfb3281afd9653b8a112fafc");
 
     }
 }
\ No newline at end of file
@@ -4,10 +4,17 @@
 
 use primitive_types::{U256, H256, H160};
 use std::cmp::min;
-use std::io::Read;
-use std::ops::{Shl, Shr};
-use crate::serialize::AsBytes;
+use std::io::{self, Read};
+use std::ops::{Shl, ShlAssign, Shr};
+use std::sync::LazyLock;
+use crate::serialize::{AsBytes, ReadStream, Serialize, Unserialize, WriteStream, SER};
+use crate::version::PROTOCOL_VERSION;
+use crate::streams::CDataStream;
+use crate::crypto::ripemd160::CRIPEMD160;
 use crate::crypto::sha256::CSHA256;
+use crate::crypto::Hasher;
+use crate::crypto::common::{ReadLE32, ReadLE64};
+use crate::random::GetRand;
 
 //inline uint32_t ROTL32(uint32_t x, int8_t r)
 fn ROTL32(x: u32, r: i8) -> u32
@@ -35,13 +42,13 @@ pub fn MurmurHash3(nHashSeed: u32, vDataToHash: &[u8]) -> u32
         let mut k1: u32 =  (vDataToHash[i*4 + 3] as u32) << 24 | (vDataToHash[i*4 + 2] as u32) << 16 |
             (vDataToHash[i*4 + 1] as u32) << 8 | vDataToHash[i*4] as u32;
         
-        k1 *= c1;
+        k1 = k1.wrapping_mul(c1);
         k1 = ROTL32(k1, 15);
-        k1 *= c2;
+        k1 = k1.wrapping_mul(c2);
 
         h1 ^= k1;
         h1 = ROTL32(h1, 13);
-        h1 = h1 * 5 + 0xe6546b64;
+        h1 = h1.wrapping_mul(5).wrapping_add(0xe6546b64);
     }
 
     //----------
@@ -51,17 +58,18 @@ pub fn MurmurHash3(nHashSeed: u32, vDataToHash: &[u8]) -> u32
     let tail = &vDataToHash[nblocks * 4 ..];
     let mut k1: u32 = 0;
 
-    if (vDataToHash.len() & 3) == 3 {
+    // The C++ switch falls through from case 3 down to case 1
+    if (vDataToHash.len() & 3) >= 3 {
         k1 ^= (tail[2] as u32) << 16;
     }
-    if (vDataToHash.len() & 3) == 2 {
+    if (vDataToHash.len() & 3) >= 2 {
         k1 ^= (tail[1] as u32 )<< 8;
     }
-    if (vDataToHash.len() & 3) == 1 {
+    if (vDataToHash.len() & 3) >= 1 {
         k1 ^= tail[0] as u32;
-        k1 *= c1;
+        k1 = k1.wrapping_mul(c1);
         k1 = ROTL32(k1, 15);
-        k1 *= c2;
+        k1 = k1.wrapping_mul(c2);
         h1 ^= k1;
     }
 
@@ -69,21 +77,22 @@ pub fn MurmurHash3(nHashSeed: u32, vDataToHash: &[u8]) -> u32
     // finalization
     h1 ^= vDataToHash.len() as u32;
     h1 ^= h1 >> 16;
-    h1 *= 0x85ebca6b;
+    h1 = h1.wrapping_mul(0x85ebca6b);
     h1 ^= h1 >> 13;
-    h1 *= 0xc2b2ae35;
+    h1 = h1.wrapping_mul(0xc2b2ae35);
     h1 ^= h1 >> 16;
 
     return h1;
 }
 
 
-struct CHash256 {
+/** A hasher class for Bitcoin's 256-bit hash (double SHA-256). */
+pub struct CHash256 {
     sha: CSHA256,
 }
 
 impl CHash256 {
-    const OUTPUT_SIZE:usize = CSHA256::OUTPUT_SIZE;
+    pub const OUTPUT_SIZE:usize = CSHA256::OUTPUT_SIZE;
 
     pub fn new() -> Self {
         Self { sha: CSHA256::new() }
@@ -95,7 +104,7 @@ impl CHash256 {
         let mut buf: [u8; CSHA256::OUTPUT_SIZE] = [0; CSHA256::OUTPUT_SIZE];
         self.sha.Finalize(&mut buf);
         self.sha.Reset().Write(&mut buf, CSHA256::OUTPUT_SIZE).Finalize(&mut buf);
-
+        output.copy_from_slice(&buf);
     }
 
     //CHash256& Write(Span<const unsigned char> input) {
@@ -130,7 +139,7 @@ impl CHash160 {
         //CRIPEMD160::Write(buf, CSHA256::OUTPUT_SIZE).Finalize(output.data());
         let mut buf: [u8; CSHA256::OUTPUT_SIZE] = [0; CSHA256::OUTPUT_SIZE];
         self.sha.Finalize(&mut buf);
-        output.copy_from_slice(&buf[0..CHash160::OUTPUT_SIZE])
+        CRIPEMD160::new().Write(&buf, CSHA256::OUTPUT_SIZE).Finalize(output);
     }
 
     //CHash160& Write(Span<const unsigned char> input) {
@@ -182,11 +191,28 @@ pub fn Hash160<T1: AsBytes>(in1: &T1) -> H160
     H160::from_slice(&result)
 }
 
-struct HashWriter {
+/** Compute the 256-bit hash of an object's serialization. */
+//template<typename T>
+//uint256 SerializeHash(const T& obj, int nType=SER_GETHASH, int nVersion=PROTOCOL_VERSION)
+pub fn SerializeHash<T: Serialize + ?Sized>(obj: &T, nType: SER, nVersion: i32) -> H256
+{
+    let mut ss = CHashWriter::new(nType, nVersion);
+    ss.serialize(obj);
+    ss.GetHash()
+}
+
+/** A writer stream (for serialization) that computes a 256-bit hash. */
+#[derive(Clone)]
+pub struct HashWriter {
     ctx: CSHA256,
 }
 
 impl HashWriter {
+    pub fn new() -> Self
+    {
+        Self { ctx: CSHA256::new() }
+    }
+
     //void write(Span<const std::byte> src)
     pub fn write(&mut self, src: &[u8])
     {
@@ -224,125 +250,737 @@ impl HashWriter {
     pub fn GetCheapHash(&mut self) -> u64 {
         let result = self.GetHash();
         //return ReadLE64(result.begin());
-        result.to_low_u64_le()
+        ReadLE64(&result[0..8])
     }
 
-    // TODO: implement
     //template <typename T>
     //HashWriter& operator<<(const T& obj)
-    //{
-    //    ::Serialize(*this, obj);
-    //    return *this;
-    //}
+    pub fn serialize<T: Serialize + ?Sized>(&mut self, obj: &T) -> &mut Self
+    {
+        obj.Serialize(self);
+        self
+    }
+}
+
+impl Default for HashWriter {
+    fn default() -> Self
+    {
+        HashWriter::new()
+    }
+}
 
+impl WriteStream for HashWriter {
+    fn write(&mut self, src: &[u8]) { HashWriter::write(self, src); }
+    fn GetType(&self) -> i32 { SER::GETHASH as i32 }
+    fn GetVersion(&self) -> i32 { PROTOCOL_VERSION }
 }
 
-impl<T> Shl<T> for HashWriter {
+impl<T: Serialize + ?Sized> Shl<&T> for HashWriter {
     type Output = Self;
 
-    fn shl(self, _rhs:T) -> Self::Output
+    fn shl(mut self, rhs: &T) -> Self::Output
     {
-        // TODO: figure out what to do here
-        //Serialize(self.S, rhs);
+        rhs.Serialize(&mut self);
         self
-    } 
+    }
+}
 
+impl<T: Serialize + ?Sized> ShlAssign<&T> for HashWriter {
+    fn shl_assign(&mut self, rhs: &T)
+    {
+        rhs.Serialize(self);
+    }
 }
 
-struct CHashWriter {
+/** A HashWriter that carries the serialization type and version. */
+#[derive(Clone)]
+pub struct CHashWriter {
     hash_writer: HashWriter,
     nType: i32,
     nVersion: i32,
-    //source: S,
 }
 
 impl CHashWriter {
-    pub fn GetType(self) -> i32
+    //CHashWriter(int nTypeIn, int nVersionIn) : nType(nTypeIn), nVersion(nVersionIn) {}
+    pub fn new(nTypeIn: SER, nVersionIn: i32) -> Self
+    {
+        Self { hash_writer: HashWriter::new(), nType: nTypeIn as i32, nVersion: nVersionIn }
+    }
+
+    pub fn GetType(&self) -> i32
     {
         return self.nType;
     }
 
-    pub fn GetVersion(self) -> i32
+    pub fn GetVersion(&self) -> i32
     {
         return self.nVersion;
     }
 
+    pub fn write(&mut self, src: &[u8])
+    {
+        self.hash_writer.write(src);
+    }
+
+    pub fn GetHash(&mut self) -> H256
+    {
+        self.hash_writer.GetHash()
+    }
+
+    pub fn GetSHA256(&mut self) -> H256
+    {
+        self.hash_writer.GetSHA256()
+    }
+
+    pub fn GetCheapHash(&mut self) -> u64
+    {
+        self.hash_writer.GetCheapHash()
+    }
+
     //template<typename T>
     //CHashWriter& operator<<(const T& obj) {
+    pub fn serialize<T: Serialize + ?Sized>(&mut self, obj: &T) -> &mut Self
+    {
         // Serialize to this stream
-    //    ::Serialize(*this, obj);
-    //    return (*this);
-    //}
+        obj.Serialize(self);
+        self
+    }
 }
 
-impl<T> Shl<T> for CHashWriter {
+impl WriteStream for CHashWriter {
+    fn write(&mut self, src: &[u8]) { CHashWriter::write(self, src); }
+    fn GetType(&self) -> i32 { self.nType }
+    fn GetVersion(&self) -> i32 { self.nVersion }
+}
+
+impl<T: Serialize + ?Sized> Shl<&T> for CHashWriter {
     type Output = Self;
 
-    fn shl(self, _rhs:T) -> Self::Output
+    fn shl(mut self, rhs: &T) -> Self::Output
     {
-        // TODO: figure out what to do here
-        //Serialize(self.S, rhs);
+        rhs.Serialize(&mut self);
         self
-    } 
+    }
+}
 
+impl<T: Serialize + ?Sized> ShlAssign<&T> for CHashWriter {
+    fn shl_assign(&mut self, rhs: &T)
+    {
+        rhs.Serialize(self);
+    }
 }
 
-struct CHashVerifier<'a, T> {
-    source:&'a mut T,
-    hashWriter: HashWriter,
+/** Reads data from an underlying stream, while hashing the read data. */
+pub struct CHashVerifier<'a, S> {
+    source: &'a mut S,
+    hashWriter: CHashWriter,
 }
 
-impl <'a, T: Read>CHashVerifier<'a, T>  {
+impl<'a, S: Read> CHashVerifier<'a, S> {
+    //explicit CHashVerifier(Source* source_) : CHashWriter(source_->GetType(), source_->GetVersion()), source(source_) {}
+    pub fn new(source: &'a mut S, nTypeIn: SER, nVersionIn: i32) -> Self
+    {
+        Self { source, hashWriter: CHashWriter::new(nTypeIn, nVersionIn) }
+    }
+
     //void read(Span<std::byte> dst)
-    pub fn read(&mut self, dst: &mut [u8])
+    pub fn read(&mut self, dst: &mut [u8]) -> io::Result<()>
     {
-        self.source.read(dst);
+        self.source.read_exact(dst)?;
         self.hashWriter.write(dst);
+        Ok(())
     }
 
     //void ignore(size_t nSize)
-    fn ignore (&mut self, mut nSize: usize)
+    pub fn ignore(&mut self, mut nSize: usize) -> io::Result<()>
     {
         //std::byte data[1024];
         let mut data: [u8; 1024] = [0; 1024];
         while nSize > 0 {
             let now:usize = min(nSize, 1024);
-            self.read(&mut data[0..now]);
+            self.read(&mut data[0..now])?;
             nSize -= now;
         }
+        Ok(())
     }
 
-    //template<typenameT>
+    pub fn GetHash(&mut self) -> H256
+    {
+        self.hashWriter.GetHash()
+    }
+
+    //template<typename T>
     //CHashVerifier<Source>& operator>>(T&& obj)
-    // {
-    //    // Unserialize from this stream
-    //    ::Unserialize(*this, obj);
-    //    return (*this);
-    //}
+    pub fn unserialize<T: Unserialize + ?Sized>(&mut self, obj: &mut T) -> io::Result<&mut Self>
+    {
+        // Unserialize from this stream
+        obj.Unserialize(self)?;
+        Ok(self)
+    }
+}
+
+impl<S: Read> ReadStream for CHashVerifier<'_, S> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<()> { CHashVerifier::read(self, dst) }
+    fn GetType(&self) -> i32 { self.hashWriter.GetType() }
+    fn GetVersion(&self) -> i32 { self.hashWriter.GetVersion() }
+}
 
+impl<'a, 'b, S: Read, T: Unserialize + ?Sized> Shr<&mut T> for &'b mut CHashVerifier<'a, S> {
+    type Output = io::Result<&'b mut CHashVerifier<'a, S>>;
 
+    fn shr(self, rhs: &mut T) -> Self::Output
+    {
+        rhs.Unserialize(self)?;
+        Ok(self)
+    }
 }
 
-impl<T> Shr<T> for CHashVerifier<'_, T> {
-    type Output = Self;
+/** Return a HashWriter primed for tagged hashes (as specified in BIP 340).
+ *
+ * The returned object will have SHA256(tag) written to it twice (= 64 bytes).
+ * A tagged hash can be computed by feeding the message into this object, and
+ * then calling HashWriter::GetSHA256().
+ */
+//HashWriter TaggedHash(const std::string& tag)
+pub fn TaggedHash(tag: &str) -> HashWriter
+{
+    let mut taghash: [u8; CSHA256::OUTPUT_SIZE] = [0; CSHA256::OUTPUT_SIZE];
+    CSHA256::new().Write(tag.as_bytes(), tag.len()).Finalize(&mut taghash);
+    let taghash = H256::from(taghash);
+    HashWriter::new() << &taghash << &taghash
+}
 
-    fn shr(self, _rhs:T) -> Self::Output
+/* Precomputed midstates for the tags used by BIP 340 and BIP 341. Clone one
+ * instead of calling TaggedHash() on every use. */
+//const HashWriter HASHER_TAPSIGHASH{TaggedHash("TapSighash")};
+pub static HASHER_TAPSIGHASH: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("TapSighash"));
+//const HashWriter HASHER_TAPLEAF{TaggedHash("TapLeaf")};
+pub static HASHER_TAPLEAF: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("TapLeaf"));
+//const HashWriter HASHER_TAPBRANCH{TaggedHash("TapBranch")};
+pub static HASHER_TAPBRANCH: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("TapBranch"));
+//const HashWriter HASHER_TAPTWEAK{TaggedHash("TapTweak")};
+pub static HASHER_TAPTWEAK: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("TapTweak"));
+pub static HASHER_BIP340_CHALLENGE: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("BIP0340/challenge"));
+pub static HASHER_BIP340_AUX: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("BIP0340/aux"));
+pub static HASHER_BIP340_NONCE: LazyLock<HashWriter> = LazyLock::new(|| TaggedHash("BIP0340/nonce"));
+
+/** SipHash-2-4 */
+#[derive(Clone, Debug)]
+pub struct CSipHasher {
+    v: [u64; 4],
+    tmp: u64,
+    count: u8, // Only the low 8 bits of the input size matter.
+}
+
+macro_rules! SIPROUND {
+    ($v0:ident, $v1:ident, $v2:ident, $v3:ident) => {
+        $v0 = $v0.wrapping_add($v1); $v1 = $v1.rotate_left(13); $v1 ^= $v0;
+        $v0 = $v0.rotate_left(32);
+        $v2 = $v2.wrapping_add($v3); $v3 = $v3.rotate_left(16); $v3 ^= $v2;
+        $v0 = $v0.wrapping_add($v3); $v3 = $v3.rotate_left(21); $v3 ^= $v0;
+        $v2 = $v2.wrapping_add($v1); $v1 = $v1.rotate_left(17); $v1 ^= $v2;
+        $v2 = $v2.rotate_left(32);
+    };
+}
+
+impl CSipHasher {
+    /** Construct a SipHash calculator initialized with 128-bit key (k0, k1) */
+    //CSipHasher(uint64_t k0, uint64_t k1);
+    pub fn new(k0: u64, k1: u64) -> Self
     {
-        // TODO: figure out what to do here
-        //Unserialize(self.S, rhs);
+        Self {
+            v: [
+                0x736f6d6570736575 ^ k0,
+                0x646f72616e646f6d ^ k1,
+                0x6c7967656e657261 ^ k0,
+                0x7465646279746573 ^ k1,
+            ],
+            tmp: 0,
+            count: 0,
+        }
+    }
+
+    /** Hash a 64-bit integer worth of data
+     *  It is treated as if this was the little-endian interpretation of 8 bytes.
+     *  This function can only be used when a multiple of 8 bytes have been written so far.
+     */
+    //CSipHasher& Write(uint64_t data);
+    pub fn WriteU64(&mut self, data: u64) -> &mut Self
+    {
+        let [mut v0, mut v1, mut v2, mut v3] = self.v;
+
+        assert!(self.count % 8 == 0);
+
+        v3 ^= data;
+        SIPROUND!(v0, v1, v2, v3);
+        SIPROUND!(v0, v1, v2, v3);
+        v0 ^= data;
+
+        self.v = [v0, v1, v2, v3];
+
+        self.count = self.count.wrapping_add(8);
         self
-    } 
+    }
+
+    /** Hash arbitrary bytes. */
+    //CSipHasher& Write(const unsigned char* data, size_t size);
+    pub fn Write(&mut self, data: &[u8]) -> &mut Self
+    {
+        let [mut v0, mut v1, mut v2, mut v3] = self.v;
+        let mut t = self.tmp;
+        let mut c = self.count;
+
+        for byte in data {
+            t |= (*byte as u64) << (8 * (c % 8));
+            c = c.wrapping_add(1);
+            if (c & 7) == 0 {
+                v3 ^= t;
+                SIPROUND!(v0, v1, v2, v3);
+                SIPROUND!(v0, v1, v2, v3);
+                v0 ^= t;
+                t = 0;
+            }
+        }
+
+        self.v = [v0, v1, v2, v3];
+        self.count = c;
+        self.tmp = t;
 
+        self
+    }
+
+    /** Compute the 64-bit SipHash-2-4 of the data written so far. The object remains untouched. */
+    //uint64_t Finalize() const;
+    pub fn Finalize(&self) -> u64
+    {
+        let [mut v0, mut v1, mut v2, mut v3] = self.v;
+
+        let t = self.tmp | ((self.count as u64) << 56);
+
+        v3 ^= t;
+        SIPROUND!(v0, v1, v2, v3);
+        SIPROUND!(v0, v1, v2, v3);
+        v0 ^= t;
+        v2 ^= 0xFF;
+        SIPROUND!(v0, v1, v2, v3);
+        SIPROUND!(v0, v1, v2, v3);
+        SIPROUND!(v0, v1, v2, v3);
+        SIPROUND!(v0, v1, v2, v3);
+        v0 ^ v1 ^ v2 ^ v3
+    }
+}
+
+/** Optimized SipHash-2-4 implementation for uint256.
+ *
+ *  It is identical to:
+ *    SipHasher(k0, k1)
+ *      .Write(val.GetUint64(0))
+ *      .Write(val.GetUint64(1))
+ *      .Write(val.GetUint64(2))
+ *      .Write(val.GetUint64(3))
+ *      .Finalize()
+ */
+//uint64_t SipHashUint256(uint64_t k0, uint64_t k1, const uint256& val)
+pub fn SipHashUint256(k0: u64, k1: u64, val: &H256) -> u64
+{
+    /* Specialized implementation for efficiency */
+    let bytes = val.as_bytes();
+    let mut d = ReadLE64(&bytes[0..8]);
+
+    let mut v0: u64 = 0x736f6d6570736575 ^ k0;
+    let mut v1: u64 = 0x646f72616e646f6d ^ k1;
+    let mut v2: u64 = 0x6c7967656e657261 ^ k0;
+    let mut v3: u64 = 0x7465646279746573 ^ k1 ^ d;
+
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = ReadLE64(&bytes[8..16]);
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = ReadLE64(&bytes[16..24]);
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = ReadLE64(&bytes[24..32]);
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    v3 ^= 4u64 << 59;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= 4u64 << 59;
+    v2 ^= 0xFF;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^ v1 ^ v2 ^ v3
+}
+
+//uint64_t SipHashUint256Extra(uint64_t k0, uint64_t k1, const uint256& val, uint32_t extra)
+pub fn SipHashUint256Extra(k0: u64, k1: u64, val: &H256, extra: u32) -> u64
+{
+    /* Specialized implementation for efficiency */
+    let bytes = val.as_bytes();
+    let mut d = ReadLE64(&bytes[0..8]);
+
+    let mut v0: u64 = 0x736f6d6570736575 ^ k0;
+    let mut v1: u64 = 0x646f72616e646f6d ^ k1;
+    let mut v2: u64 = 0x6c7967656e657261 ^ k0;
+    let mut v3: u64 = 0x7465646279746573 ^ k1 ^ d;
+
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = ReadLE64(&bytes[8..16]);
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = ReadLE64(&bytes[16..24]);
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = ReadLE64(&bytes[24..32]);
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    d = (36u64 << 56) | extra as u64;
+    v3 ^= d;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^= d;
+    v2 ^= 0xFF;
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    SIPROUND!(v0, v1, v2, v3);
+    v0 ^ v1 ^ v2 ^ v3
+}
+
+/** std::hash::Hasher fed by the salted hashers below.
+ *
+ * A txid hashes as its 32 bytes and an outpoint as its 32 byte hash followed
+ * by the little-endian index, so those are routed to the specialized
+ * SipHashUint256 / SipHashUint256Extra. Anything else falls back to a plain
+ * CSipHasher over the written bytes.
+ */
+#[derive(Clone, Debug)]
+pub struct SaltedSipHasher {
+    k0: u64,
+    k1: u64,
+    buf: [u8; 36],
+    len: usize,
+    overflow: Option<CSipHasher>,
+}
+
+impl SaltedSipHasher {
+    fn new(k0: u64, k1: u64) -> Self
+    {
+        Self { k0, k1, buf: [0; 36], len: 0, overflow: None }
+    }
+}
+
+impl std::hash::Hasher for SaltedSipHasher {
+    fn write(&mut self, bytes: &[u8])
+    {
+        if self.overflow.is_none() && self.len + bytes.len() <= self.buf.len() {
+            self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+            return;
+        }
+        let (k0, k1) = (self.k0, self.k1);
+        let sip = self.overflow.get_or_insert_with(|| CSipHasher::new(k0, k1));
+        sip.Write(&self.buf[..self.len]).Write(bytes);
+        self.len = 0;
+    }
+
+    fn write_u32(&mut self, i: u32)
+    {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64
+    {
+        if let Some(sip) = &self.overflow {
+            return sip.clone().Write(&self.buf[..self.len]).Finalize();
+        }
+        match self.len {
+            32 => SipHashUint256(self.k0, self.k1, &H256::from_slice(&self.buf[..32])),
+            36 => SipHashUint256Extra(self.k0, self.k1, &H256::from_slice(&self.buf[..32]), ReadLE32(&self.buf[32..36])),
+            _ => CSipHasher::new(self.k0, self.k1).Write(&self.buf[..self.len]).Finalize(),
+        }
+    }
+}
+
+/** DoS-resistant hashing of txids for HashMap / HashSet. */
+#[derive(Clone, Debug)]
+pub struct SaltedTxidHasher {
+    /** Salt */
+    k0: u64,
+    k1: u64,
+}
+
+impl SaltedTxidHasher {
+    //SaltedTxidHasher::SaltedTxidHasher() : k0(GetRand<uint64_t>()), k1(GetRand<uint64_t>()) {}
+    pub fn new() -> Self
+    {
+        Self { k0: GetRand(u64::MAX), k1: GetRand(u64::MAX) }
+    }
+}
+
+impl Default for SaltedTxidHasher {
+    fn default() -> Self
+    {
+        SaltedTxidHasher::new()
+    }
+}
+
+impl std::hash::BuildHasher for SaltedTxidHasher {
+    type Hasher = SaltedSipHasher;
+
+    fn build_hasher(&self) -> SaltedSipHasher
+    {
+        SaltedSipHasher::new(self.k0, self.k1)
+    }
+}
+
+/** DoS-resistant hashing of outpoints for HashMap / HashSet. */
+#[derive(Clone, Debug)]
+pub struct SaltedOutpointHasher {
+    /** Salt */
+    k0: u64,
+    k1: u64,
 }
 
+impl SaltedOutpointHasher {
+    //SaltedOutpointHasher::SaltedOutpointHasher(bool deterministic)
+    pub fn new(deterministic: bool) -> Self
+    {
+        Self {
+            k0: if deterministic { 0x8e819f2607a18de6 } else { GetRand(u64::MAX) },
+            k1: if deterministic { 0xf4020d2e3983b0eb } else { GetRand(u64::MAX) },
+        }
+    }
+}
+
+impl Default for SaltedOutpointHasher {
+    fn default() -> Self
+    {
+        SaltedOutpointHasher::new(false)
+    }
+}
+
+impl std::hash::BuildHasher for SaltedOutpointHasher {
+    type Hasher = SaltedSipHasher;
+
+    fn build_hasher(&self) -> SaltedSipHasher
+    {
+        SaltedSipHasher::new(self.k0, self.k1)
+    }
+}
 
 #[cfg(test)]
 
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2+2, 4);
     }
+
+    #[test]
+    fn test_hash160() {
+        let mut result = [0u8; CHash160::OUTPUT_SIZE];
+        CHash160::new().finalize(&mut result);
+        assert_eq!(hex::encode(result), "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb");
+        CHash160::new().write(b"abc").finalize(&mut result);
+        assert_eq!(hex::encode(result), "bb1be98c142444d7a56aa3981c3942a978e4dc33");
+    }
+
+    #[test]
+    fn test_siphash() {
+        let mut hasher = CSipHasher::new(0x0706050403020100, 0x0F0E0D0C0B0A0908);
+        assert_eq!(hasher.Finalize(), 0x726fdb47dd0e0e31);
+        hasher.Write(&[0]);
+        assert_eq!(hasher.Finalize(), 0x74f839c593dc67fd);
+        hasher.Write(&[1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(hasher.Finalize(), 0x93f5f5799a932462);
+        hasher.WriteU64(0x0F0E0D0C0B0A0908);
+        assert_eq!(hasher.Finalize(), 0x3f2acc7f57c29bdb);
+        hasher.Write(&[16, 17]);
+        assert_eq!(hasher.Finalize(), 0x4bc1b3f0968dd39c);
+        hasher.Write(&[18, 19, 20, 21, 22, 23, 24, 25, 26]);
+        assert_eq!(hasher.Finalize(), 0x2f2e6163076bcfad);
+        hasher.Write(&[27, 28, 29, 30, 31]);
+        assert_eq!(hasher.Finalize(), 0x7127512f72f27cce);
+        hasher.WriteU64(0x2726252423222120);
+        assert_eq!(hasher.Finalize(), 0x0e3ea96b5304a7d0);
+        hasher.WriteU64(0x2F2E2D2C2B2A2928);
+        assert_eq!(hasher.Finalize(), 0xe612a3cb9ecba951);
+    }
+
+    #[test]
+    fn test_siphash_uint256() {
+        let val = crate::uint256::uint256S("1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100");
+        assert_eq!(SipHashUint256(0x0706050403020100, 0x0F0E0D0C0B0A0908, &val), 0x7127512f72f27cce);
+
+        // Check consistency between CSipHasher and SipHashUint256[Extra].
+        for i in 0..16u32 {
+            let k0 = 0x0706050403020100u64.wrapping_mul(i as u64 + 1);
+            let k1 = 0x0F0E0D0C0B0A0908u64 ^ i as u64;
+            let mut x = [0u8; 32];
+            for (j, b) in x.iter_mut().enumerate() {
+                *b = (j as u32 * 7 + i * 13) as u8;
+            }
+            let x = H256::from(x);
+            let n = i.wrapping_mul(0x9e3779b9);
+            let nb = n.to_le_bytes();
+
+            let mut sip256 = CSipHasher::new(k0, k1);
+            let mut sip288 = CSipHasher::new(k0, k1);
+            sip256.Write(x.as_bytes());
+            sip288.Write(x.as_bytes()).Write(&nb);
+            assert_eq!(SipHashUint256(k0, k1, &x), sip256.Finalize());
+            assert_eq!(SipHashUint256Extra(k0, k1, &x, n), sip288.Finalize());
+        }
+    }
+
+    #[test]
+    fn test_salted_hashers() {
+        use std::hash::BuildHasher;
+        use std::collections::HashMap;
+        use crate::primitives::transaction::COutPoint;
+
+        let txid = H256::repeat_byte(0xab);
+        let txid_hasher = SaltedTxidHasher::new();
+        assert_eq!(txid_hasher.hash_one(&txid), SipHashUint256(txid_hasher.k0, txid_hasher.k1, &txid));
+
+        let outpoint = COutPoint::new(txid, 7);
+        let outpoint_hasher = SaltedOutpointHasher::new(true);
+        assert_eq!(outpoint_hasher.hash_one(&outpoint), SipHashUint256Extra(0x8e819f2607a18de6, 0xf4020d2e3983b0eb, &txid, 7));
+
+        let mut map: HashMap<COutPoint, i32, SaltedOutpointHasher> = HashMap::with_hasher(SaltedOutpointHasher::default());
+        map.insert(outpoint.clone(), 1);
+        map.insert(COutPoint::new(txid, 8), 2);
+        assert_eq!(map.get(&outpoint), Some(&1));
+        assert_eq!(map.len(), 2);
+
+        // Keys other than txids and outpoints still hash consistently
+        assert_eq!(txid_hasher.hash_one("a longer key that does not fit the buffer"), txid_hasher.hash_one("a longer key that does not fit the buffer"));
+    }
+
+    #[test]
+    fn test_hash_writer() {
+        use crate::primitives::transaction::{CMutableTransaction, CTransaction, CTxIn, CTxOut, COutPoint, SERIALIZE_TRANSACTION_NO_WITNESS};
+        use crate::script::CScript;
+
+        let mut mtx = CMutableTransaction::new();
+        mtx.vin.push(CTxIn::new(COutPoint::new(H256::repeat_byte(1), 3), CScript::default(), CTxIn::SEQUENCE_FINAL));
+        mtx.vin[0].scriptWitness.stack.push(vec![0xaa; 20]);
+        mtx.vout.push(CTxOut::new(5000, CScript::default()));
+        let tx = CTransaction::from(&mtx);
+
+        // HashWriter serializes with witness, CHashWriter honours its version
+        assert_eq!((HashWriter::new() << &tx).GetHash(), tx.GetWitnessHash());
+        assert_eq!((CHashWriter::new(SER::GETHASH, SERIALIZE_TRANSACTION_NO_WITNESS) << &tx).GetHash(), tx.GetHash());
+
+        let mut hw = HashWriter::new();
+        hw <<= &tx;
+        let mut ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION);
+        ss.serialize(&tx);
+        let mut result = [0u8; CHash256::OUTPUT_SIZE];
+        CHash256::new().write(ss.data()).finalize(&mut result);
+        assert_eq!(hw.clone().GetHash(), H256::from(result));
+        assert_eq!(hw.GetCheapHash(), ReadLE64(&result[0..8]));
+
+        // GetCheapHash returns the first 8 bytes of the double-SHA256, read little-endian
+        // (SHA256d("abc") = 4f8b42c22dd3729b519ba6f68d2da7cc5b2d606d05daed5ad5128cc03e6c6358)
+        let mut hw = HashWriter::new();
+        hw.write(b"abc");
+        assert_eq!(hw.GetCheapHash(), 0x9b72d32dc2428b4f);
+        let mut chw = CHashWriter::new(SER::GETHASH, 0);
+        chw.write(b"abc");
+        assert_eq!(chw.GetCheapHash(), 0x9b72d32dc2428b4f);
+
+        // CHashVerifier hashes exactly what it deserializes
+        let bytes = ss.to_vec();
+        let mut source = &bytes[..];
+        let mut verifier = CHashVerifier::new(&mut source, SER::NETWORK, PROTOCOL_VERSION);
+        let mut mtx2 = CMutableTransaction::new();
+        (&mut verifier >> &mut mtx2).unwrap();
+        assert_eq!(mtx2.GetHash(), tx.GetHash());
+        assert_eq!(verifier.GetHash(), H256::from(result));
+
+        let mut source = &bytes[..];
+        let mut verifier = CHashVerifier::new(&mut source, SER::NETWORK, PROTOCOL_VERSION);
+        verifier.ignore(bytes.len()).unwrap();
+        assert_eq!(verifier.GetHash(), H256::from(result));
+        assert!(verifier.ignore(1).is_err());
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        let vectors = [
+            (&HASHER_TAPLEAF, "TapLeaf", "83a56308a9c56f467e8df293da5ae5fdbc85b871952a83c4bf0575ee948ec230"),
+            (&HASHER_TAPBRANCH, "TapBranch", "d695f69898df4e3f1dbdb34be238218be5ee53c7deb2493a5345aa9154660b70"),
+            (&HASHER_TAPTWEAK, "TapTweak", "b4db0a539110ab84dac5af069f081eee7e3becbf970e6705d20f59ea9b4eb1f8"),
+        ];
+        for (cached, tag, expected) in vectors {
+            let expected = H256::from_slice(&hex::decode(expected).unwrap());
+            let mut hw = TaggedHash(tag);
+            hw.write(b"abc");
+            assert_eq!(hw.GetSHA256(), expected);
+            let mut hw = (*cached).clone();
+            hw.write(b"abc");
+            assert_eq!(hw.GetSHA256(), expected);
+        }
+
+        // The cached midstates match a fresh TaggedHash
+        for (cached, tag) in [(&HASHER_TAPSIGHASH, "TapSighash"), (&HASHER_BIP340_CHALLENGE, "BIP0340/challenge"),
+                              (&HASHER_BIP340_AUX, "BIP0340/aux"), (&HASHER_BIP340_NONCE, "BIP0340/nonce")] {
+            assert_eq!((*cached).clone().GetSHA256(), TaggedHash(tag).GetSHA256());
+        }
+    }
+
+    #[test]
+    fn test_murmurhash3() {
+        let T = |expected: u32, seed: u32, data: &str| {
+            assert_eq!(MurmurHash3(seed, &hex::decode(data).unwrap()), expected);
+        };
+
+        // Test MurmurHash3 with various inputs. Of course this is retested in the
+        // bloom filter tests - they would fail if MurmurHash3() had any problems -
+        // but is useful for those trying to implement Bitcoin libraries as a
+        // source of test data for their MurmurHash3() primitive during
+        // development.
+        //
+        // The magic number 0xFBA4C795 comes from CBloomFilter::Hash()
+        T(0x00000000, 0x00000000, "");
+        T(0x6a396f08, 0xFBA4C795, "");
+        T(0x81f16f39, 0xffffffff, "");
+
+        T(0x514e28b7, 0x00000000, "00");
+        T(0xea3f0b17, 0xFBA4C795, "00");
+        T(0xfd6cf10d, 0x00000000, "ff");
+
+        T(0x16c6b7ab, 0x00000000, "0011");
+        T(0x8eb51c3d, 0x00000000, "001122");
+        T(0xb4471bf8, 0x00000000, "00112233");
+        T(0xe2301fa8, 0x00000000, "0011223344");
+        T(0xfc2e4a15, 0x00000000, "001122334455");
+        T(0xb074502c, 0x00000000, "00112233445566");
+        T(0x8034d2a0, 0x00000000, "0011223344556677");
+        T(0xb4698def, 0x00000000, "001122334455667788");
+    }
 }
 
 
@@ -3,10 +3,22 @@
 // Distributed under the MIT software license, see the accompanying
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
 
+use primitive_types::H256;
+use crate::crypto::common::WriteLE32;
 use crate::random::GetStrongRandBytes;
 use crate::secp256k1::{secp256k1_ec_seckey_verify, secp256k1_ec_seckey_negate, secp256k1_ec_pubkey_create, secp256k1_ec_pubkey_serialize,
-                       secp256k1_context, secp256k1_pubkey, SECP256K1_EC_COMPRESSED, SECP256K1_EC_UNCOMPRESSED,};
-use crate::pubkey::CPubKey;
+                       secp256k1_context, secp256k1_pubkey, SECP256K1_EC_COMPRESSED, SECP256K1_EC_UNCOMPRESSED,
+                       secp256k1_ecdsa_signature, secp256k1_ecdsa_sign, secp256k1_ecdsa_verify, secp256k1_ecdsa_signature_serialize_der,
+                       secp256k1_ecdsa_signature_serialize_compact, secp256k1_nonce_function_rfc6979, secp256k1_ec_pubkey_cmp,
+                       secp256k1_ec_pubkey_parse};
+use crate::secp256k1::recovery::{secp256k1_ecdsa_recoverable_signature, secp256k1_ecdsa_sign_recoverable,
+                                 secp256k1_ecdsa_recoverable_signature_serialize_compact, secp256k1_ecdsa_recover};
+use crate::secp256k1::extrakeys::{secp256k1_keypair, secp256k1_keypair_create, secp256k1_keypair_xonly_pub,
+                                  secp256k1_keypair_xonly_tweak_add, secp256k1_xonly_pubkey, secp256k1_xonly_pubkey_serialize};
+use crate::secp256k1::ecdh::secp256k1_ecdh;
+use crate::secp256k1::ellswift::{secp256k1_ellswift_create, secp256k1_ellswift_xdh, secp256k1_ellswift_xdh_hash_function_bip324};
+use crate::secp256k1::schnorrsig::{secp256k1_schnorrsig_sign32, secp256k1_schnorrsig_verify};
+use crate::pubkey::{CPubKey, XOnlyPubKey, EllSwiftPubKey};
 
 /** These functions are taken from the libsecp256k1 distribution and are very ugly. */
 
@@ -172,8 +184,28 @@ fn ec_seckey_export_der(ctx: &mut secp256k1_context, seckey: &mut [u8], key32: &
     return true;
 }
 
+//bool SigHasLowR(const secp256k1_ecdsa_signature* sig)
+fn SigHasLowR(ctx: &secp256k1_context, sig: &secp256k1_ecdsa_signature) -> bool
+{
+    let mut compact_sig = [0u8; 64];
+    secp256k1_ecdsa_signature_serialize_compact(ctx, &mut compact_sig, sig);
+
+    // In DER serialization, all values are interpreted as big-endian, signed integers. The highest bit in the integer indicates
+    // its signed-ness; 0 is positive, 1 is negative. When the value is interpreted as a negative integer, it must be converted
+    // to a positive value by prepending a 0x00 byte so that the highest bit is 0. We can avoid this prepending by ensuring that
+    // our highest bit is always 0, and thus we must check that the first byte is less than 0x80.
+    return compact_sig[0] < 0x80;
+}
+
 type CPrivKey = [u8; 279];
 
+/** Size of ECDH shared secrets. */
+pub const ECDH_SECRET_SIZE: usize = 32;
+
+// Used to represent ECDH shared secret (ECDH_SECRET_SIZE bytes)
+//using ECDHSecret = std::array<std::byte, ECDH_SECRET_SIZE>;
+pub type ECDHSecret = [u8; ECDH_SECRET_SIZE];
+
 pub struct CKey {
     fValid: bool,
     fCompressed: bool,
@@ -213,7 +245,7 @@ impl CKey {
     pub fn MakeNewKey(&mut self, fCompressedIn: bool) {
         loop  {
             GetStrongRandBytes(&mut self.keydata[..]);
-            if !self.Check(&self.keydata) {
+            if self.Check(&self.keydata) {
                 break;
             }
         };
@@ -252,5 +284,393 @@ impl CKey {
         //assert(result.IsValid());
         return result;
     }
-    
+
+    /**
+     * Create a DER-serialized signature.
+     * The test_case parameter tweaks the deterministic nonce.
+     */
+    //bool CKey::Sign(const uint256 &hash, std::vector<unsigned char>& vchSig, bool grind, uint32_t test_case) const {
+    pub fn Sign(&mut self, hash: &H256, vchSig: &mut Vec<u8>, grind: bool, test_case: u32) -> bool {
+        if !self.fValid {
+            return false;
+        }
+        vchSig.resize(CPubKey::SIGNATURE_SIZE, 0);
+        let mut nSigLen: usize = CPubKey::SIGNATURE_SIZE;
+        let mut extra_entropy = [0u8; 32];
+        WriteLE32(&mut extra_entropy, test_case);
+        let mut sig = secp256k1_ecdsa_signature { data: [0u8; 64] };
+        let mut counter: u32 = 0;
+        let noncedata = if !grind && test_case != 0 { Some(&extra_entropy[..]) } else { None };
+        let mut ret = secp256k1_ecdsa_sign(&mut self.secp256k1_context_sign, &mut sig, hash.as_fixed_bytes(), &self.keydata, Some(secp256k1_nonce_function_rfc6979), noncedata);
+
+        // Grind for low R
+        while ret != 0 && !SigHasLowR(&self.secp256k1_context_sign, &sig) && grind {
+            counter += 1;
+            WriteLE32(&mut extra_entropy, counter);
+            ret = secp256k1_ecdsa_sign(&mut self.secp256k1_context_sign, &mut sig, hash.as_fixed_bytes(), &self.keydata, Some(secp256k1_nonce_function_rfc6979), Some(&extra_entropy[..]));
+        }
+        assert!(ret != 0);
+        secp256k1_ecdsa_signature_serialize_der(&self.secp256k1_context_sign, &mut vchSig[..], &mut nSigLen, &sig);
+        vchSig.truncate(nSigLen);
+        // Additional verification step to prevent using a potentially corrupted signature
+        let mut pk = secp256k1_pubkey { data: [0u8; 64] };
+        let ok = secp256k1_ec_pubkey_create(&mut self.secp256k1_context_sign, &mut pk, &self.keydata);
+        assert!(ok);
+        ret = secp256k1_ecdsa_verify(&self.secp256k1_context_sign, &sig, hash.as_fixed_bytes(), &pk);
+        assert!(ret != 0);
+        return true;
+    }
+
+    /**
+     * Create a compact signature (65 bytes), which allows reconstructing the used public key.
+     * The format is one header byte, followed by two times 32 bytes for the serialized r and s values.
+     * The header byte: 0x1B = first key with even y, 0x1C = first key with odd y,
+     *                  0x1D = second key with even y, 0x1E = second key with odd y,
+     *                  add 0x04 for compressed keys.
+     */
+    //bool CKey::SignCompact(const uint256 &hash, std::vector<unsigned char>& vchSig) const {
+    pub fn SignCompact(&mut self, hash: &H256, vchSig: &mut Vec<u8>) -> bool {
+        if !self.fValid {
+            return false;
+        }
+        vchSig.resize(CPubKey::COMPACT_SIGNATURE_SIZE, 0);
+        let mut rec: i32 = -1;
+        let mut rsig = secp256k1_ecdsa_recoverable_signature::new();
+        let mut ret = secp256k1_ecdsa_sign_recoverable(&mut self.secp256k1_context_sign, &mut rsig, hash.as_fixed_bytes(), &self.keydata, Some(secp256k1_nonce_function_rfc6979), None);
+        assert!(ret != 0);
+        ret = secp256k1_ecdsa_recoverable_signature_serialize_compact(&self.secp256k1_context_sign, (&mut vchSig[1..]).try_into().unwrap(), &mut rec, &rsig);
+        assert!(ret != 0);
+        assert!(rec != -1);
+        vchSig[0] = (27 + rec + if self.fCompressed { 4 } else { 0 }) as u8;
+        // Additional verification step to prevent using a potentially corrupted signature
+        let mut epk = secp256k1_pubkey { data: [0u8; 64] };
+        let mut rpk = secp256k1_pubkey { data: [0u8; 64] };
+        let ok = secp256k1_ec_pubkey_create(&mut self.secp256k1_context_sign, &mut epk, &self.keydata);
+        assert!(ok);
+        ret = secp256k1_ecdsa_recover(&self.secp256k1_context_sign, &mut rpk, &rsig, hash.as_fixed_bytes());
+        assert!(ret != 0);
+        ret = secp256k1_ec_pubkey_cmp(&self.secp256k1_context_sign, &epk, &rpk);
+        assert!(ret == 0);
+        return true;
+    }
+
+    /**
+     * Create a BIP-340 Schnorr signature, for the xonly-pubkey corresponding to *this,
+     * optionally tweaked by *merkle_root. Additional nonce entropy is provided through
+     * aux.
+     *
+     * merkle_root is used to optionally perform tweaking of the private key, as specified
+     * in BIP341:
+     * - If merkle_root is None: no tweaking is done, sign with key directly (this is
+     *                           used for signatures in BIP342 script).
+     * - If merkle_root points to a null H256: sign with key + H_TapTweak(pubkey) (this
+     *                                         is used for key path spending, with no
+     *                                         script tree).
+     * - Otherwise:              sign with key + H_TapTweak(pubkey || *merkle_root)
+     *                           (this is used for key path spending, with specific
+     *                           Merkle root of the script tree).
+     */
+    //bool CKey::SignSchnorr(const uint256& hash, Span<unsigned char> sig, const uint256* merkle_root, const uint256& aux) const
+    pub fn SignSchnorr(&mut self, hash: &H256, sig: &mut [u8], merkle_root: Option<&H256>, aux: &H256) -> bool {
+        assert!(sig.len() == 64);
+        let sig: &mut [u8; 64] = sig.try_into().unwrap();
+        let mut keypair = secp256k1_keypair::new();
+        if secp256k1_keypair_create(&mut self.secp256k1_context_sign, &mut keypair, &self.keydata) == 0 {
+            return false;
+        }
+        if let Some(merkle_root) = merkle_root {
+            let mut pubkey = secp256k1_xonly_pubkey::new();
+            if secp256k1_keypair_xonly_pub(&self.secp256k1_context_sign, &mut pubkey, None, &keypair) == 0 {
+                return false;
+            }
+            let mut pubkey_bytes = [0u8; 32];
+            if secp256k1_xonly_pubkey_serialize(&self.secp256k1_context_sign, &mut pubkey_bytes, &pubkey) == 0 {
+                return false;
+            }
+            let tweak = XOnlyPubKey::new(&pubkey_bytes).ComputeTapTweakHash(if merkle_root.is_zero() { None } else { Some(merkle_root) });
+            if secp256k1_keypair_xonly_tweak_add(&self.secp256k1_context_sign, &mut keypair, tweak.as_fixed_bytes()) == 0 {
+                return false;
+            }
+        }
+        let mut ret = secp256k1_schnorrsig_sign32(&mut self.secp256k1_context_sign, sig, hash.as_fixed_bytes(), &keypair, Some(aux.as_fixed_bytes())) != 0;
+        if ret {
+            // Additional verification step to prevent using a potentially corrupted signature
+            let mut pubkey_verify = secp256k1_xonly_pubkey::new();
+            ret = secp256k1_keypair_xonly_pub(&self.secp256k1_context_sign, &mut pubkey_verify, None, &keypair) != 0;
+            ret &= secp256k1_schnorrsig_verify(&self.secp256k1_context_sign, sig, hash.as_fixed_bytes(), &pubkey_verify) != 0;
+        }
+        if !ret {
+            sig.fill(0);
+        }
+        keypair.data.fill(0);
+        ret
+    }
+
+    /**
+     * Compute an EC Diffie-Hellman shared secret with another party's public key,
+     * hashed with secp256k1_ecdh_hash_function_default (SHA256 of the compressed
+     * shared point). Returns None if this key or the given public key is invalid.
+     */
+    pub fn ComputeECDH(&self, pubkey: &CPubKey) -> Option<ECDHSecret> {
+        if !self.fValid || !pubkey.IsValid() {
+            return None;
+        }
+        let mut point = secp256k1_pubkey { data: [0u8; 64] };
+        if secp256k1_ec_pubkey_parse(&self.secp256k1_context_sign, &mut point, pubkey.data()) == 0 {
+            return None;
+        }
+        let mut output: ECDHSecret = [0u8; 32];
+        let ret = secp256k1_ecdh(&self.secp256k1_context_sign, &mut output, &point, &self.keydata, None, None);
+        assert!(ret != 0);
+        Some(output)
+    }
+
+    /** Create an ellswift-encoded public key for this key, with specified entropy.
+     *
+     *  ent32 must be 32 bytes of additional entropy to use in the encoding. Every
+     *  public key has ~2^256 different encodings, and this function will deterministically pick
+     *  one of them, based on entropy. Note that even without truly random entropy, the
+     *  resulting encoding will be indistinguishable from uniform to any adversary who does not
+     *  know the private key (because the private key itself is always used as entropy as well).
+     */
+    //EllSwiftPubKey CKey::EllSwiftCreate(Span<const std::byte> ent32) const
+    pub fn EllSwiftCreate(&mut self, ent32: &[u8]) -> EllSwiftPubKey {
+        assert!(self.fValid);
+        assert!(ent32.len() == 32);
+        let mut encoded_pubkey = [0u8; EllSwiftPubKey::SIZE];
+
+        let success = secp256k1_ellswift_create(&mut self.secp256k1_context_sign, &mut encoded_pubkey, &self.keydata, Some(ent32.try_into().unwrap()));
+
+        // Should always succeed for valid keys (asserted above).
+        assert!(success != 0);
+        EllSwiftPubKey::new(&encoded_pubkey)
+    }
+
+    /** Compute a BIP324-style ECDH shared secret.
+     *
+     *  - their_ellswift: EllSwiftPubKey that was received from the other side.
+     *  - our_ellswift: EllSwiftPubKey that was sent to the other side (must have been generated
+     *                  from self using EllSwiftCreate()).
+     *  - initiating: whether we are the initiating party (true) or responding party (false).
+     */
+    //ECDHSecret CKey::ComputeBIP324ECDHSecret(const EllSwiftPubKey& their_ellswift, const EllSwiftPubKey& our_ellswift, bool initiating) const
+    pub fn ComputeBIP324ECDHSecret(&self, their_ellswift: &EllSwiftPubKey, our_ellswift: &EllSwiftPubKey, initiating: bool) -> ECDHSecret {
+        assert!(self.fValid);
+
+        let mut output: ECDHSecret = [0u8; ECDH_SECRET_SIZE];
+        // BIP324 uses the initiator as party A, and the responder as party B. Remap the inputs
+        // accordingly:
+        let success = secp256k1_ellswift_xdh(&self.secp256k1_context_sign,
+                                             &mut output,
+                                             if initiating { our_ellswift.data() } else { their_ellswift.data() },
+                                             if initiating { their_ellswift.data() } else { our_ellswift.data() },
+                                             &self.keydata,
+                                             if initiating { 0 } else { 1 },
+                                             secp256k1_ellswift_xdh_hash_function_bip324,
+                                             None);
+        // Should always succeed for valid keys (assert above).
+        assert!(success != 0);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::CHash256;
+
+    //strSecret1 = "5HxWvvfubhXpYYpS3tJkw6fq9jE9j18THftkZjHHfmFiWtmAbrj"
+    const SECRET1: &str = "12b004fff7f4b69ef8650e767f18f11ede158148b425660723b9f9a66e61f747";
+    const PUBKEY1: &str = "040b4c866585dd868a9d62348a9cd008d6a312937048fff31670e7e920cfc7a7447b5f0bba9e01e6fe4735c8383e6e7a3347a0fd72381b8f797a19f694054e5a69";
+
+    fn Hash(msg: &[u8]) -> H256 {
+        let mut result = [0u8; 32];
+        CHash256::new().write(msg).finalize(&mut result);
+        H256::from(result)
+    }
+
+    fn key(secret: &str, fCompressed: bool) -> CKey {
+        let mut k = CKey::new();
+        k.set(&hex::decode(secret).unwrap().try_into().unwrap(), fCompressed);
+        k
+    }
+
+    #[test]
+    fn test_key_sign_verify() {
+        let mut key1 = key(SECRET1, false);
+        let mut key1C = key(SECRET1, true);
+        let pubkey1 = key1.GetPubKey();
+        let pubkey1C = key1C.GetPubKey();
+        assert_eq!(hex::encode(&pubkey1.vch[..]), PUBKEY1);
+        assert!(pubkey1.IsFullyValid());
+        assert!(pubkey1C.IsFullyValid());
+
+        for n in 0..16 {
+            let hashMsg = Hash(format!("Very secret message {}: 11", n).as_bytes());
+            let mut sign1 = vec![];
+            let mut sign1C = vec![];
+            assert!(key1.Sign(&hashMsg, &mut sign1, true, 0));
+            assert!(key1C.Sign(&hashMsg, &mut sign1C, true, 0));
+            // grinding guarantees a low R, which never needs a 0x00 pad byte
+            assert!(sign1.len() <= 70);
+            assert!(CPubKey::CheckLowS(&sign1));
+            assert!(pubkey1.Verify(&hashMsg, &sign1));
+            assert!(pubkey1.Verify(&hashMsg, &sign1C));
+            assert!(pubkey1C.Verify(&hashMsg, &sign1));
+
+            let otherMsg = Hash(format!("Very secret message {}: 12", n).as_bytes());
+            assert!(!pubkey1.Verify(&otherMsg, &sign1));
+        }
+
+        // test deterministic signing
+        let hashMsg = Hash(b"Very deterministic message");
+        let mut detsig = vec![];
+        let mut detsigc = vec![];
+        assert!(key1.Sign(&hashMsg, &mut detsig, false, 0));
+        assert!(key1C.Sign(&hashMsg, &mut detsigc, false, 0));
+        assert_eq!(detsig, detsigc);
+        assert_eq!(hex::encode(&detsig), "304402205dbbddda71772d95ce91cd2d14b592cfbc1dd0aabd6a394b6c2d377bbe59d31d022014ddda21494a4e221f0824f0b8b924c43fa43c0ad57dccdaa11f81a6bd4582f6");
+    }
+
+    #[test]
+    fn test_check_low_s() {
+        let hashMsg = Hash(b"Very deterministic message");
+        let mut pubkey1 = CPubKey { vch: [0u8; 65] };
+        pubkey1.vch.copy_from_slice(&hex::decode(PUBKEY1).unwrap());
+        // same signature as above, with S replaced by N - S
+        let highS = hex::decode("304502205dbbddda71772d95ce91cd2d14b592cfbc1dd0aabd6a394b6c2d377bbe59d31d022100eb2225deb6b5b1dde0f7db0f4746db3a7b0aa0dbd9cad3611eb2dce612f0be4b").unwrap();
+        assert!(!CPubKey::CheckLowS(&highS));
+        // Verify normalizes before checking, so a high S is still accepted
+        assert!(pubkey1.Verify(&hashMsg, &highS));
+    }
+
+    #[test]
+    fn test_sign_compact() {
+        let mut key1 = key(SECRET1, false);
+        let mut key1C = key(SECRET1, true);
+        let pubkey1 = key1.GetPubKey();
+        let pubkey1C = key1C.GetPubKey();
+        // addr1 = "1QFqqMUD55ZV3PJEJZtaKCsQmjLT6JkjvJ"
+        assert_eq!(hex::encode(pubkey1.GetID()), "ff197b14e502ab41f3bc8ccb48c4abac9eab35bc");
+
+        for n in 0..16 {
+            let hashMsg = Hash(format!("Very secret message {}: 11", n).as_bytes());
+            let mut csign1 = vec![];
+            let mut csign1C = vec![];
+            assert!(key1.SignCompact(&hashMsg, &mut csign1));
+            assert!(key1C.SignCompact(&hashMsg, &mut csign1C));
+            assert_eq!(csign1.len(), CPubKey::COMPACT_SIGNATURE_SIZE);
+            assert!((27..31).contains(&csign1[0]));
+            assert!((31..35).contains(&csign1C[0]));
+
+            let mut rkey1 = CPubKey { vch: [0u8; 65] };
+            let mut rkey1C = CPubKey { vch: [0u8; 65] };
+            assert!(rkey1.RecoverCompact(&hashMsg, &csign1));
+            assert!(rkey1C.RecoverCompact(&hashMsg, &csign1C));
+            assert_eq!(rkey1.data(), pubkey1.data());
+            assert_eq!(rkey1C.data(), pubkey1C.data());
+
+            let otherMsg = Hash(format!("Very secret message {}: 12", n).as_bytes());
+            assert!(rkey1.RecoverCompact(&otherMsg, &csign1));
+            assert!(rkey1.data() != pubkey1.data());
+        }
+        let mut rkey = CPubKey { vch: [0u8; 65] };
+        assert!(!rkey.RecoverCompact(&Hash(b"short"), &[27u8; 64]));
+    }
+
+    #[test]
+    fn test_compute_ecdh() {
+        let mut key1 = key(SECRET1, false);
+        let mut key2 = key("d97f5108f11cda6eeebaaa420fef0726b1f898060b98489fa3098463c0032866", true);
+        let pubkey1 = key1.GetPubKey();
+        let pubkey2 = key2.GetPubKey();
+
+        // SHA256 of the compressed shared point, independent of the pubkey encoding
+        let secret12 = key1.ComputeECDH(&pubkey2).unwrap();
+        let secret21 = key2.ComputeECDH(&pubkey1).unwrap();
+        assert_eq!(secret12, secret21);
+        assert_eq!(hex::encode(secret12), "a778e645961e1ab40269cfd5551acf1a0d5461675f0a21283eef034348bdfd2b");
+
+        let mut key3 = CKey::new();
+        key3.MakeNewKey(true);
+        let pubkey3 = key3.GetPubKey();
+        assert_eq!(key3.ComputeECDH(&pubkey1), key1.ComputeECDH(&pubkey3));
+        assert!(key3.ComputeECDH(&pubkey1) != key3.ComputeECDH(&pubkey2));
+
+        // invalid keys
+        assert!(CKey::new().ComputeECDH(&pubkey1).is_none());
+        // x = 5 is not on the curve
+        let mut offcurve = CPubKey { vch: [0u8; 65] };
+        offcurve.vch[0] = 2;
+        offcurve.vch[32] = 5;
+        assert!(key1.ComputeECDH(&offcurve).is_none());
+        assert!(key1.ComputeECDH(&CPubKey { vch: [0u8; 65] }).is_none());
+    }
+
+    #[test]
+    fn test_bip324_ecdh() {
+        // BIP324 packet encoding test vector 1
+        let mut key1 = key("61062ea5071d800bbfd59e2e8b53d47d194b095ae5a4df04936b49772ef0d4d7", true);
+        let ours = EllSwiftPubKey::new(&hex::decode("ec0adff257bbfe500c188c80b4fdd640f6b45a482bbc15fc7cef5931deff0aa186f6eb9bba7b85dc4dcc28b28722de1e3d9108b985e2967045668f66098e475b").unwrap());
+        let theirs = EllSwiftPubKey::new(&hex::decode("a4a94dfce69b4a2a0a099313d10f9f7e7d649d60501c9e1d274c300e0d89aafaffffffffffffffffffffffffffffffffffffffffffffffffffffffff8faf88d5").unwrap());
+        assert_eq!(ours.Decode().data()[1..], key1.GetPubKey().data()[1..]);
+        assert_eq!(hex::encode(key1.ComputeBIP324ECDHSecret(&theirs, &ours, true)),
+                   "c6992a117f5edbea70c3f511d32d26b9798be4b81a62eaee1a5acaa8459a3592");
+
+        // Both sides of a handshake agree on the secret, and the encodings decode to the keys
+        let mut key2 = key(SECRET1, false);
+        let mut key3 = CKey::new();
+        key3.MakeNewKey(true);
+        let ell2 = key2.EllSwiftCreate(&[1u8; 32]);
+        let ell3 = key3.EllSwiftCreate(&[2u8; 32]);
+        assert_eq!(ell2.Decode().data()[1..33], key2.GetPubKey().data()[1..33]);
+        assert_eq!(ell3.Decode().data(), key3.GetPubKey().data());
+        assert_eq!(key2.EllSwiftCreate(&[1u8; 32]), ell2);
+        assert!(key2.EllSwiftCreate(&[3u8; 32]) != ell2);
+        let initiator = key2.ComputeBIP324ECDHSecret(&ell3, &ell2, true);
+        let responder = key3.ComputeBIP324ECDHSecret(&ell2, &ell3, false);
+        assert_eq!(initiator, responder);
+        assert!(key2.ComputeBIP324ECDHSecret(&ell3, &ell2, false) != initiator);
+    }
+
+    #[test]
+    fn test_sign_schnorr() {
+        // BIP340 test vectors 1 and 2: (secret key, aux_rand, message, signature)
+        let vectors = [
+            ("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+             "0000000000000000000000000000000000000000000000000000000000000001",
+             "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+             "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a"),
+            ("c90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b14e5c9",
+             "c87aa53824b4d7ae2eb035a2b5bbbccc080e76cdc6d1692c4b0b62d798e6d906",
+             "7e2d58d8b3bcdf1abadec7829054f90dda9805aab56c77333024b9d0a508b75c",
+             "5831aaeed7b44bb74e5eab94ba9d4294c49bcf2a60728d8b4c200f50dd313c1bab745879a5ad954a72c45a91c3a51d3c7adea98d82f8481e0e1e03674a6f3fb7"),
+        ];
+        let h256 = |s: &str| H256::from_slice(&hex::decode(s).unwrap());
+        for (secret, aux, msg, expected) in vectors {
+            let mut k = key(secret, true);
+            let pubkey = XOnlyPubKey::from_pubkey(&k.GetPubKey());
+            let (aux, msg) = (h256(aux), h256(msg));
+            let mut sig = [0u8; 64];
+
+            // no tweak: plain BIP340 signing
+            assert!(k.SignSchnorr(&msg, &mut sig, None, &aux));
+            assert_eq!(hex::encode(sig), expected);
+            assert!(pubkey.VerifySchnorr(&msg, &sig));
+
+            // key path spend without a script tree
+            let (output, _) = pubkey.CreateTapTweak(None).unwrap();
+            assert!(k.SignSchnorr(&msg, &mut sig, Some(&H256::zero()), &aux));
+            assert!(output.VerifySchnorr(&msg, &sig));
+            assert!(!pubkey.VerifySchnorr(&msg, &sig));
+
+            // key path spend committing to a script tree
+            let merkle_root = Hash(b"script tree");
+            let (output, parity) = pubkey.CreateTapTweak(Some(&merkle_root)).unwrap();
+            assert!(output.CheckTapTweak(&pubkey, &merkle_root, parity));
+            assert!(k.SignSchnorr(&msg, &mut sig, Some(&merkle_root), &aux));
+            assert!(output.VerifySchnorr(&msg, &sig));
+            assert!(!pubkey.VerifySchnorr(&msg, &sig));
+        }
+    }
 }
@@ -48,4 +48,12 @@ macro_rules! LogPrint {
             }
         }
     };
-}
\ No newline at end of file
+}
+
+/** Unconditionally log a message (the messages carry their own trailing newline). */
+#[macro_export]
+macro_rules! LogPrintf {
+    ($($arg:tt)*) => {
+        print!($($arg)*)
+    };
+}
@@ -1,6 +1,8 @@
 #![allow (nonstandard_style)]
 #![allow(warnings)]
+pub mod blockfilter;
 pub mod bloom;
+pub mod compat;
 pub mod script;
 pub mod hash;
 pub mod logging;
@@ -12,9 +14,13 @@ pub mod consensus;
 pub mod pubkey;
 pub mod crypto;
 pub mod key;
+pub mod merkleblock;
 pub mod random;
+pub mod randomenv;
 pub mod secp256k1;
 pub mod time;
+pub mod uint256;
+pub mod util;
 
 fn main() {
     println!("Hello, world!");
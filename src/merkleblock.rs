@@ -0,0 +1,677 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2021 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use primitive_types::H256;
+use super::bloom::CBloomFilter;
+use super::consensus::consensus::{MAX_BLOCK_WEIGHT, MIN_TRANSACTION_WEIGHT};
+use super::hash::CHash256;
+use super::primitives::block::{CBlock, CBlockHeader};
+use super::serialize::{Serialize, Unserialize, WriteStream, ReadStream};
+use std::collections::HashSet;
+use std::io;
+
+// Helper functions for serialization.
+//std::vector<unsigned char> BitsToBytes(const std::vector<bool>& bits)
+pub fn BitsToBytes(bits: &[bool]) -> Vec<u8>
+{
+    let mut ret: Vec<u8> = vec![0; (bits.len() + 7) / 8];
+    for (p, bit) in bits.iter().enumerate() {
+        ret[p / 8] |= (*bit as u8) << (p % 8);
+    }
+    ret
+}
+
+//std::vector<bool> BytesToBits(const std::vector<unsigned char>& bytes)
+pub fn BytesToBits(bytes: &[u8]) -> Vec<bool>
+{
+    let mut ret: Vec<bool> = vec![false; bytes.len() * 8];
+    for (p, bit) in ret.iter_mut().enumerate() {
+        *bit = (bytes[p / 8] & (1 << (p % 8))) != 0;
+    }
+    ret
+}
+
+fn Hash(left: &H256, right: &H256) -> H256
+{
+    let mut result: [u8; CHash256::OUTPUT_SIZE] = [0; CHash256::OUTPUT_SIZE];
+    CHash256::new().write(left.as_bytes()).write(right.as_bytes()).finalize(&mut result);
+    H256::from(result)
+}
+
+/** Data structure that represents a partial merkle tree.
+ *
+ * It represents a subset of the txid's of a known block, in a way that
+ * allows recovery of the list of txid's and the merkle root, in an
+ * authenticated way.
+ *
+ * The encoding works as follows: we traverse the tree in depth-first order,
+ * storing a bit for each traversed node, signifying whether the node is the
+ * parent of at least one matched leaf txid (or a matched txid itself). In
+ * case we are at the leaf level, or this bit is 0, its merkle node hash is
+ * stored, and its children are not explored further. Otherwise, no hash is
+ * stored, but we recurse into both (or the only) child branch. During
+ * decoding, the same depth-first traversal is performed, consuming bits and
+ * hashes as they written during encoding.
+ *
+ * The serialization is fixed and provides a hard guarantee about the
+ * encoded size:
+ *
+ *   SIZE <= 10 + ceil(32.25*N)
+ *
+ * Where N represents the number of leaf nodes of the partial tree. N itself
+ * is bounded by:
+ *
+ *   N <= total_transactions
+ *   N <= 1 + matched_transactions*tree_height
+ *
+ * The serialization format:
+ *  - uint32     total_transactions (4 bytes)
+ *  - varint     number of hashes   (1-3 bytes)
+ *  - uint256[]  hashes in depth-first order (<= 32*N bytes)
+ *  - varint     number of bytes of flag bits (1-3 bytes)
+ *  - byte[]     flag bits, packed per 8 in a byte, least significant bit first (<= 2*N-1 bits)
+ * The size constraints follow from this.
+ */
+#[derive(Clone, Debug)]
+pub struct CPartialMerkleTree {
+    /** the total number of transactions in the block */
+    nTransactions: u32,
+
+    /** node-is-parent-of-matched-txid bits */
+    vBits: Vec<bool>,
+
+    /** txids and internal hashes */
+    vHash: Vec<H256>,
+
+    /** flag set when encountering invalid data */
+    fBad: bool,
+}
+
+impl CPartialMerkleTree {
+    /** helper function to efficiently calculate the number of nodes at given height in the merkle tree */
+    fn CalcTreeWidth(&self, height: i32) -> u32
+    {
+        ((self.nTransactions as u64 + (1u64 << height) - 1) >> height) as u32
+    }
+
+    /** calculate the hash of a node in the merkle tree (at leaf level: the txid's themselves) */
+    //uint256 CPartialMerkleTree::CalcHash(int height, unsigned int pos, const std::vector<uint256> &vTxid)
+    fn CalcHash(&self, height: i32, pos: u32, vTxid: &[H256]) -> H256
+    {
+        //we can never have zero txs in a merkle block, we always need the coinbase tx
+        //if we do not have this assert, we can hit a memory access violation when indexing into vTxid
+        assert!(!vTxid.is_empty());
+        if height == 0 {
+            // hash at height 0 is the txids themselves
+            vTxid[pos as usize]
+        } else {
+            // calculate left hash
+            let left = self.CalcHash(height - 1, pos * 2, vTxid);
+            // calculate right hash if not beyond the end of the array - copy left hash otherwise
+            let right = if pos * 2 + 1 < self.CalcTreeWidth(height - 1) {
+                self.CalcHash(height - 1, pos * 2 + 1, vTxid)
+            } else {
+                left
+            };
+            // combine subhashes
+            Hash(&left, &right)
+        }
+    }
+
+    /** recursive function that traverses tree nodes, storing the data as bits and hashes */
+    //void CPartialMerkleTree::TraverseAndBuild(int height, unsigned int pos, const std::vector<uint256> &vTxid, const std::vector<bool> &vMatch)
+    fn TraverseAndBuild(&mut self, height: i32, pos: u32, vTxid: &[H256], vMatch: &[bool])
+    {
+        // determine whether this node is the parent of at least one matched txid
+        let mut fParentOfMatch = false;
+        let mut p = (pos as u64) << height;
+        while p < ((pos as u64 + 1) << height) && p < self.nTransactions as u64 {
+            fParentOfMatch |= vMatch[p as usize];
+            p += 1;
+        }
+        // store as flag bit
+        self.vBits.push(fParentOfMatch);
+        if height == 0 || !fParentOfMatch {
+            // if at height 0, or nothing interesting below, store hash and stop
+            let hash = self.CalcHash(height, pos, vTxid);
+            self.vHash.push(hash);
+        } else {
+            // otherwise, don't store any hash, but descend into the subtrees
+            self.TraverseAndBuild(height - 1, pos * 2, vTxid, vMatch);
+            if pos * 2 + 1 < self.CalcTreeWidth(height - 1) {
+                self.TraverseAndBuild(height - 1, pos * 2 + 1, vTxid, vMatch);
+            }
+        }
+    }
+
+    /** recursive function that traverses tree nodes, consuming the bits and hashes produced by TraverseAndBuild.
+     * it returns the hash of the respective node and its respective index.
+     */
+    //uint256 CPartialMerkleTree::TraverseAndExtract(int height, unsigned int pos, unsigned int &nBitsUsed, unsigned int &nHashUsed, std::vector<uint256> &vMatch, std::vector<unsigned int> &vnIndex)
+    fn TraverseAndExtract(&mut self, height: i32, pos: u32, nBitsUsed: &mut usize, nHashUsed: &mut usize, vMatch: &mut Vec<H256>, vnIndex: &mut Vec<u32>) -> H256
+    {
+        if *nBitsUsed >= self.vBits.len() {
+            // overflowed the bits array - failure
+            self.fBad = true;
+            return H256::zero();
+        }
+        let fParentOfMatch = self.vBits[*nBitsUsed];
+        *nBitsUsed += 1;
+        if height == 0 || !fParentOfMatch {
+            // if at height 0, or nothing interesting below, use stored hash and do not descend
+            if *nHashUsed >= self.vHash.len() {
+                // overflowed the hash array - failure
+                self.fBad = true;
+                return H256::zero();
+            }
+            let hash = self.vHash[*nHashUsed];
+            *nHashUsed += 1;
+            if height == 0 && fParentOfMatch { // in case of height 0, we have a matched txid
+                vMatch.push(hash);
+                vnIndex.push(pos);
+            }
+            hash
+        } else {
+            // otherwise, descend into the subtrees to extract matched txids and hashes
+            let left = self.TraverseAndExtract(height - 1, pos * 2, nBitsUsed, nHashUsed, vMatch, vnIndex);
+            let right = if pos * 2 + 1 < self.CalcTreeWidth(height - 1) {
+                let right = self.TraverseAndExtract(height - 1, pos * 2 + 1, nBitsUsed, nHashUsed, vMatch, vnIndex);
+                if right == left {
+                    // The left and right branches should never be identical, as the transaction
+                    // hashes covered by them must each be unique.
+                    self.fBad = true;
+                }
+                right
+            } else {
+                left
+            };
+            // and combine them before returning
+            Hash(&left, &right)
+        }
+    }
+
+    /** Construct a partial merkle tree from a list of transaction ids, and a mask that selects a subset of them */
+    //CPartialMerkleTree::CPartialMerkleTree(const std::vector<uint256> &vTxid, const std::vector<bool> &vMatch)
+    pub fn new(vTxid: &[H256], vMatch: &[bool]) -> CPartialMerkleTree
+    {
+        let mut tree = CPartialMerkleTree {
+            nTransactions: vTxid.len() as u32,
+            vBits: vec![],
+            vHash: vec![],
+            fBad: false,
+        };
+
+        // calculate height of tree
+        let mut nHeight = 0;
+        while tree.CalcTreeWidth(nHeight) > 1 {
+            nHeight += 1;
+        }
+
+        // traverse the partial tree
+        tree.TraverseAndBuild(nHeight, 0, vTxid, vMatch);
+        tree
+    }
+
+    /**
+     * extract the matching txid's represented by this partial merkle tree
+     * and their respective indices within the partial tree.
+     * returns the merkle root, or 0 in case of failure
+     */
+    //uint256 CPartialMerkleTree::ExtractMatches(std::vector<uint256> &vMatch, std::vector<unsigned int> &vnIndex)
+    pub fn ExtractMatches(&mut self, vMatch: &mut Vec<H256>, vnIndex: &mut Vec<u32>) -> H256
+    {
+        vMatch.clear();
+        // An empty set will not work
+        if self.nTransactions == 0 {
+            return H256::zero();
+        }
+        // check for excessively high numbers of transactions
+        if self.nTransactions as usize > MAX_BLOCK_WEIGHT as usize / MIN_TRANSACTION_WEIGHT {
+            return H256::zero();
+        }
+        // there can never be more hashes provided than one for every txid
+        if self.vHash.len() > self.nTransactions as usize {
+            return H256::zero();
+        }
+        // there must be at least one bit per node in the partial tree, and at least one node per hash
+        if self.vBits.len() < self.vHash.len() {
+            return H256::zero();
+        }
+        // calculate height of tree
+        let mut nHeight = 0;
+        while self.CalcTreeWidth(nHeight) > 1 {
+            nHeight += 1;
+        }
+        // traverse the partial tree
+        let mut nBitsUsed: usize = 0;
+        let mut nHashUsed: usize = 0;
+        let hashMerkleRoot = self.TraverseAndExtract(nHeight, 0, &mut nBitsUsed, &mut nHashUsed, vMatch, vnIndex);
+        // verify that no problems occurred during the tree traversal
+        if self.fBad {
+            return H256::zero();
+        }
+        // verify that all bits were consumed (except for the padding caused by serializing it as a byte sequence)
+        if (nBitsUsed + 7) / 8 != (self.vBits.len() + 7) / 8 {
+            return H256::zero();
+        }
+        // verify that all hashes were consumed
+        if nHashUsed != self.vHash.len() {
+            return H256::zero();
+        }
+        hashMerkleRoot
+    }
+
+    /** Get number of transactions the merkle proof is indicating for cross-reference with
+     * local blockchain knowledge.
+     */
+    pub fn GetNumTransactions(&self) -> u32 { self.nTransactions }
+}
+
+//CPartialMerkleTree::CPartialMerkleTree() : nTransactions(0), fBad(true) {}
+impl Default for CPartialMerkleTree {
+    fn default() -> Self
+    {
+        CPartialMerkleTree { nTransactions: 0, vBits: vec![], vHash: vec![], fBad: true }
+    }
+}
+
+//SERIALIZE_METHODS(CPartialMerkleTree, obj)
+impl Serialize for CPartialMerkleTree {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.nTransactions.Serialize(s);
+        self.vHash.Serialize(s);
+        BitsToBytes(&self.vBits).Serialize(s);
+    }
+}
+
+impl Unserialize for CPartialMerkleTree {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.nTransactions.Unserialize(s)?;
+        self.vHash.Unserialize(s)?;
+        let mut bytes: Vec<u8> = vec![];
+        bytes.Unserialize(s)?;
+        self.vBits = BytesToBits(&bytes);
+        self.fBad = false;
+        Ok(())
+    }
+}
+
+/**
+ * Used to relay blocks as header + vector<merkle branch>
+ * to filtered nodes.
+ *
+ * NOTE: The class assumes that the given CBlock has *at least* 1 transaction. If the CBlock has 0 txs, it will hit an assertion.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct CMerkleBlock {
+    /** Public only for unit testing */
+    pub header: CBlockHeader,
+    pub txn: CPartialMerkleTree,
+
+    /**
+     * Public only for unit testing and relay testing (not relayed).
+     *
+     * Used only when a bloom filter is specified to allow
+     * testing the transactions which matched the bloom filter.
+     */
+    pub vMatchedTxn: Vec<(u32, H256)>,
+}
+
+impl CMerkleBlock {
+    /**
+     * Create from a CBlock, filtering transactions according to filter
+     * Note that this will call IsRelevantAndUpdate on the filter for each transaction,
+     * thus the filter will likely be modified.
+     */
+    //CMerkleBlock(const CBlock& block, CBloomFilter& filter)
+    pub fn new(block: &CBlock, filter: &mut CBloomFilter) -> CMerkleBlock
+    {
+        CMerkleBlock::build(block, Some(filter), None)
+    }
+
+    // Create from a CBlock, matching the txids in the set
+    //CMerkleBlock(const CBlock& block, const std::set<uint256>& txids)
+    pub fn from_txids(block: &CBlock, txids: &HashSet<H256>) -> CMerkleBlock
+    {
+        CMerkleBlock::build(block, None, Some(txids))
+    }
+
+    // Combined constructor to consolidate code
+    //CMerkleBlock::CMerkleBlock(const CBlock& block, CBloomFilter* filter, const std::set<uint256>* txids)
+    fn build(block: &CBlock, mut filter: Option<&mut CBloomFilter>, txids: Option<&HashSet<H256>>) -> CMerkleBlock
+    {
+        let mut vMatchedTxn: Vec<(u32, H256)> = vec![];
+        let mut vMatch: Vec<bool> = Vec::with_capacity(block.vtx.len());
+        let mut vHashes: Vec<H256> = Vec::with_capacity(block.vtx.len());
+
+        for (i, tx) in block.vtx.iter().enumerate() {
+            let hash = tx.GetHash();
+            if txids.is_some_and(|txids| txids.contains(&hash)) {
+                vMatch.push(true);
+            } else if filter.as_mut().is_some_and(|filter| filter.IsRelevantAndUpdate(tx)) {
+                vMatch.push(true);
+                vMatchedTxn.push((i as u32, hash));
+            } else {
+                vMatch.push(false);
+            }
+            vHashes.push(hash);
+        }
+
+        CMerkleBlock {
+            header: block.GetBlockHeader(),
+            txn: CPartialMerkleTree::new(&vHashes, &vMatch),
+            vMatchedTxn,
+        }
+    }
+}
+
+//SERIALIZE_METHODS(CMerkleBlock, obj) { READWRITE(obj.header, obj.txn); }
+impl Serialize for CMerkleBlock {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.header.Serialize(s);
+        self.txn.Serialize(s);
+    }
+}
+
+impl Unserialize for CMerkleBlock {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.header.Unserialize(s)?;
+        self.txn.Unserialize(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::merkle::{BlockMerkleRoot, ComputeMerkleRoot};
+    use crate::bloom::bloomflags;
+    use crate::primitives::transaction::{COutPoint, CMutableTransaction, CTxIn, CTxOut, CTransactionRef, MakeTransactionRef};
+    use crate::script::{CScript, opcodetype::*};
+    use crate::serialize::SER;
+    use crate::streams::CDataStream;
+    use crate::version::PROTOCOL_VERSION;
+
+    fn make_txids(n: u32) -> Vec<H256> {
+        (0..n).map(|i| {
+            let mut mtx = CMutableTransaction::new();
+            mtx.nLockTime = i;
+            mtx.vin.push(CTxIn::default());
+            mtx.GetHash()
+        }).collect()
+    }
+
+    // The blocks in bloom_tests.cpp are real mainnet blocks; these are built to the same shape
+    // (transaction count, which outputs pay to the watched keys, which inputs spend them).
+    const PUBKEY: &str = "046d11fee51b0e60666d5049a9101a72741df480b96ee26488a4d3466b95c9a40ac5eeef87e10a5cd336c19a84565f80fa6c547957b7700ff4dfbdefe76036c339";
+    const PUBKEY_HASH: &str = "04943fdd508053c75000106d3bc6e2754dbcff19";
+
+    fn p2pk() -> CScript {
+        CScript::push_data(&hex::decode(PUBKEY).unwrap()) << OP_CHECKSIG.cs()
+    }
+
+    fn p2pkh() -> CScript {
+        OP_DUP.cs() << OP_HASH160.cs() << CScript::push_data(&hex::decode(PUBKEY_HASH).unwrap()) << OP_EQUALVERIFY.cs() << OP_CHECKSIG.cs()
+    }
+
+    fn bare_multisig() -> CScript {
+        OP_1.cs() << CScript::push_data(&hex::decode(PUBKEY).unwrap()) << OP_1.cs() << OP_CHECKMULTISIG.cs()
+    }
+
+    fn make_tx(prevout: COutPoint, scriptPubKey: CScript, nLockTime: u32) -> CTransactionRef {
+        let mut mtx = CMutableTransaction::new();
+        mtx.nLockTime = nLockTime;
+        mtx.vin.push(CTxIn::new(prevout, CScript::default(), u32::MAX));
+        mtx.vout.push(CTxOut::new(50_0000_0000, scriptPubKey));
+        MakeTransactionRef(mtx)
+    }
+
+    // A transaction unrelated to anything the filters watch
+    fn make_unrelated_tx(i: u32) -> CTransactionRef {
+        make_tx(COutPoint::new(H256::repeat_byte(0xa0 + i as u8), i), CScript::push_data(&[i as u8; 20]), i)
+    }
+
+    fn make_block(vtx: Vec<CTransactionRef>) -> CBlock {
+        let mut block = CBlock::new();
+        block.vtx = vtx;
+        block.header.hashMerkleRoot = BlockMerkleRoot(&block, None);
+        block
+    }
+
+    // Check that merkleBlock matched exactly the transactions at vExpected, and that its
+    // partial merkle tree commits to the block's merkle root and yields the same matches.
+    fn check_merkle_block(merkleBlock: &CMerkleBlock, block: &CBlock, vExpected: &[u32]) {
+        assert_eq!(merkleBlock.header.GetHash(), block.GetHash());
+
+        let expected: Vec<(u32, H256)> = vExpected.iter().map(|i| (*i, block.vtx[*i as usize].GetHash())).collect();
+        assert_eq!(merkleBlock.vMatchedTxn, expected);
+
+        let mut vMatched: Vec<H256> = vec![];
+        let mut vIndex: Vec<u32> = vec![];
+        assert_eq!(merkleBlock.txn.clone().ExtractMatches(&mut vMatched, &mut vIndex), block.header.hashMerkleRoot);
+        assert_eq!(vMatched.len(), merkleBlock.vMatchedTxn.len());
+        for (i, (index, hash)) in merkleBlock.vMatchedTxn.iter().enumerate() {
+            assert_eq!(vMatched[i], *hash);
+            assert_eq!(vIndex[i], *index);
+        }
+    }
+
+    #[test]
+    fn merkle_block_1() {
+        let block = make_block((0..9).map(make_unrelated_tx).collect());
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_ALL as u8);
+        // Match the last transaction
+        filter.insert_span(block.vtx[8].GetHash().as_bytes());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[8]);
+
+        // Also match the 8th transaction
+        filter.insert_span(block.vtx[7].GetHash().as_bytes());
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[7, 8]);
+    }
+
+    fn make_block_2() -> CBlock {
+        let coinbase = make_tx(COutPoint::default(), p2pk(), 0);
+        let spend = make_tx(COutPoint::new(coinbase.GetHash(), 0), CScript::push_data(&[0x22; 20]), 2);
+        let repay = make_tx(COutPoint::new(H256::repeat_byte(0x33), 0), p2pk(), 3);
+        make_block(vec![coinbase, make_unrelated_tx(1), spend, repay])
+    }
+
+    #[test]
+    fn merkle_block_2() {
+        let block = make_block_2();
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_ALL as u8);
+        // Match the first transaction
+        filter.insert_span(block.vtx[0].GetHash().as_bytes());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[0]);
+
+        // Match an output from the first transaction (the pubkey of its P2PK output).
+        // This should match the third transaction because it spends the output matched.
+        // It also matches the fourth transaction, which pays to the pubkey again.
+        filter.insert_span(&hex::decode(PUBKEY).unwrap());
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[0, 2, 3]);
+    }
+
+    #[test]
+    fn merkle_block_2_with_update_none() {
+        let block = make_block_2();
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_NONE as u8);
+        filter.insert_span(block.vtx[0].GetHash().as_bytes());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[0]);
+
+        // Without updates the spend of the matched output is not found
+        filter.insert_span(&hex::decode(PUBKEY).unwrap());
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[0, 3]);
+    }
+
+    #[test]
+    fn merkle_block_3_and_serialize() {
+        let block = make_block(vec![make_unrelated_tx(0)]);
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_ALL as u8);
+        filter.insert_span(block.vtx[0].GetHash().as_bytes());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[0]);
+
+        // header, 1 transaction, 1 hash (the txid itself, which is the root), 1 flag byte
+        let merkleStream = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &merkleBlock;
+        let mut expected = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &block.GetBlockHeader();
+        expected.write(&1u32.to_le_bytes());
+        expected.write(&[1]);
+        expected.write(block.vtx[0].GetHash().as_bytes());
+        expected.write(&[1, 1]);
+        assert_eq!(merkleStream.data(), expected.data());
+    }
+
+    #[test]
+    fn merkle_block_4() {
+        let block = make_block((0..7).map(make_unrelated_tx).collect());
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_ALL as u8);
+        // Match the last transaction
+        filter.insert_span(block.vtx[6].GetHash().as_bytes());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[6]);
+
+        // Also match the 4th transaction
+        filter.insert_span(block.vtx[3].GetHash().as_bytes());
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[3, 6]);
+    }
+
+    fn make_block_4_outputs() -> CBlock {
+        let mut vtx: Vec<CTransactionRef> = (0..7).map(make_unrelated_tx).collect();
+        // One transaction pays to a pay-to-pubkey, one to a bare multisig, one to a pay-to-pubkey-hash
+        vtx[2] = make_tx(COutPoint::new(H256::repeat_byte(0x12), 0), p2pk(), 2);
+        vtx[4] = make_tx(COutPoint::new(H256::repeat_byte(0x14), 0), bare_multisig(), 4);
+        vtx[5] = make_tx(COutPoint::new(H256::repeat_byte(0x15), 0), p2pkh(), 5);
+        make_block(vtx)
+    }
+
+    #[test]
+    fn merkle_block_4_test_p2pubkey_only() {
+        let block = make_block_4_outputs();
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_P2PUBKEY_ONLY as u8);
+        // Match the generation pubkey
+        filter.insert_span(&hex::decode(PUBKEY).unwrap());
+        // ...and the output address of the pay-to-pubkey-hash transaction
+        filter.insert_span(&hex::decode(PUBKEY_HASH).unwrap());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[2, 4, 5]);
+
+        // We should match the generation outpoint and the multisig one
+        assert!(filter.contains(&COutPoint::new(block.vtx[2].GetHash(), 0)));
+        assert!(filter.contains(&COutPoint::new(block.vtx[4].GetHash(), 0)));
+        // ... but not the pay-to-pubkey-hash output
+        assert!(!filter.contains(&COutPoint::new(block.vtx[5].GetHash(), 0)));
+    }
+
+    #[test]
+    fn merkle_block_4_test_update_none() {
+        let block = make_block_4_outputs();
+        let mut filter = CBloomFilter::new(10, 0.000001, 0, bloomflags::BLOOM_UPDATE_NONE as u8);
+        filter.insert_span(&hex::decode(PUBKEY).unwrap());
+        filter.insert_span(&hex::decode(PUBKEY_HASH).unwrap());
+
+        let merkleBlock = CMerkleBlock::new(&block, &mut filter);
+        check_merkle_block(&merkleBlock, &block, &[2, 4, 5]);
+
+        // We shouldn't match any outpoints (UPDATE_NONE)
+        assert!(!filter.contains(&COutPoint::new(block.vtx[2].GetHash(), 0)));
+        assert!(!filter.contains(&COutPoint::new(block.vtx[4].GetHash(), 0)));
+        assert!(!filter.contains(&COutPoint::new(block.vtx[5].GetHash(), 0)));
+    }
+
+    #[test]
+    fn test_partial_merkle_tree() {
+        for &nTx in &[1u32, 4, 7, 17, 56, 100, 127, 256, 312, 513, 1000] {
+            let vTxid = make_txids(nTx);
+            let merkleRoot1 = ComputeMerkleRoot(vTxid.clone(), None);
+
+            // try with various match patterns: every n'th transaction
+            for &nth in &[1u32, 2, 3, 7, 31, 1000, 100000] {
+                let vMatch: Vec<bool> = (0..nTx).map(|i| i % nth == 0).collect();
+                let vMatchTxid1: Vec<H256> = vTxid.iter().zip(&vMatch).filter(|(_, m)| **m).map(|(h, _)| *h).collect();
+
+                // build the partial merkle tree and serialize it
+                let pmt1 = CPartialMerkleTree::new(&vTxid, &vMatch);
+                let mut ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &pmt1;
+
+                // verify CPartialMerkleTree's size guarantees
+                let n = std::cmp::min(nTx, 1 + vMatchTxid1.len() as u32 * 32);
+                assert!(ss.size() <= 10 + (258 * n as usize + 7) / 8);
+
+                // deserialize into a tester copy
+                let mut pmt2 = CPartialMerkleTree::default();
+                ss.unserialize(&mut pmt2).unwrap();
+
+                // extract merkle root and matched txids from copy
+                let mut vMatchTxid2: Vec<H256> = vec![];
+                let mut vIndex: Vec<u32> = vec![];
+                let merkleRoot2 = pmt2.ExtractMatches(&mut vMatchTxid2, &mut vIndex);
+
+                // check that it has the same merkle root as the original, and a valid one
+                assert_eq!(merkleRoot1, merkleRoot2);
+                assert!(!merkleRoot2.is_zero());
+
+                // check that it contains the matched transactions (in the same order!)
+                assert_eq!(vMatchTxid1, vMatchTxid2);
+                for (j, index) in vIndex.iter().enumerate() {
+                    assert_eq!(vTxid[*index as usize], vMatchTxid2[j]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_merkle_tree_malleability() {
+        let vTxid: Vec<H256> = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 9, 10].iter().map(|b| H256::repeat_byte(*b)).collect();
+        let vMatch = vec![false, false, false, false, false, false, false, false, false, true, true, false];
+
+        let mut tree = CPartialMerkleTree::new(&vTxid, &vMatch);
+        let mut vTxid2: Vec<H256> = vec![];
+        let mut vIndex: Vec<u32> = vec![];
+        assert!(tree.ExtractMatches(&mut vTxid2, &mut vIndex).is_zero());
+    }
+
+    #[test]
+    fn test_merkle_block_from_txids() {
+        let mut block = CBlock::new();
+        for i in 0..5 {
+            let mut mtx = CMutableTransaction::new();
+            mtx.nLockTime = i;
+            mtx.vin.push(CTxIn::default());
+            block.vtx.push(MakeTransactionRef(mtx));
+        }
+        block.header.hashMerkleRoot = BlockMerkleRoot(&block, None);
+
+        let txids: HashSet<H256> = [block.vtx[1].GetHash(), block.vtx[4].GetHash()].into_iter().collect();
+        let merkleBlock = CMerkleBlock::from_txids(&block, &txids);
+        assert_eq!(merkleBlock.header.GetHash(), block.GetHash());
+        // vMatchedTxn is only used when bloom filter is specified
+        assert!(merkleBlock.vMatchedTxn.is_empty());
+
+        let ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &merkleBlock;
+        let mut ss = ss;
+        let mut merkleBlock2 = CMerkleBlock::default();
+        ss.unserialize(&mut merkleBlock2).unwrap();
+        assert!(ss.empty());
+
+        let mut vMatched: Vec<H256> = vec![];
+        let mut vIndex: Vec<u32> = vec![];
+        assert_eq!(merkleBlock2.txn.ExtractMatches(&mut vMatched, &mut vIndex), block.header.hashMerkleRoot);
+        assert_eq!(merkleBlock2.txn.GetNumTransactions(), 5);
+        assert_eq!(vMatched, vec![block.vtx[1].GetHash(), block.vtx[4].GetHash()]);
+        assert_eq!(vIndex, vec![1, 4]);
+    }
+}
@@ -8,42 +8,34 @@
 
 use std::fs::File;
 use std::io::{self, Write};
-// Assuming these types exist in Rust
 
 use crate::secp256k1::group::*;
 use crate::secp256k1::ecmult_gen::ECMULT_WINDOW_SIZE;
-//use crate::SECP256K1_G;
-
-// Placeholder types and constants for the Rust version
-const ECMULT_TABLE_SIZE: fn(usize) -> usize = |i| 1 << (i - 2);
-//const ECMULT_WINDOW_SIZE: usize = 15;
-
-
 
 // Function to compute a single table
-fn secp256k1_ecmult_compute_table(table: &mut [secp256k1_ge_storage], window_g: i32, gen: &secp256k1_gej) {
-    let mut gj = gen.clone();  // Assuming Gej implements Copy or we need a clone method
-    let mut ge = secp256k1_ge::new();  // Assuming default constructor or appropriate initialization
+//static void secp256k1_ecmult_compute_table(secp256k1_ge_storage* table, int window_g, const secp256k1_gej* gen) {
+fn secp256k1_ecmult_compute_table(table: &mut [secp256k1_ge_storage], window_g: usize, gen: &secp256k1_gej) {
+    let mut gj = gen.clone();
+    let mut ge = secp256k1_ge::new();
     let mut dgen = secp256k1_ge::new();
-    let mut r: secp256k1_ge_storage = secp256k1_ge_storage::new();
 
     secp256k1_ge_set_gej_var(&mut ge, &mut gj);
-    secp256k1_ge_to_storage(&mut r, &ge);
+    secp256k1_ge_to_storage(&mut table[0], &ge);
 
     secp256k1_gej_double_var(&mut gj, gen, None);
     secp256k1_ge_set_gej_var(&mut dgen, &mut gj);
 
-    // Using Rust's range syntax instead of C++ increment
-    for j in 1..ecmult_table_size(window_g) {
+    for entry in table[1..ECMULT_TABLE_SIZE!(window_g)].iter_mut() {
         secp256k1_gej_set_ge(&mut gj, &ge);
         let gj2 = gj.clone();
         secp256k1_gej_add_ge_var(&mut gj, &gj2, &dgen, None);
         secp256k1_ge_set_gej_var(&mut ge, &mut gj);
-        secp256k1_ge_to_storage(&mut table[j as usize], &ge);
+        secp256k1_ge_to_storage(entry, &ge);
     }
 }
 
 // Function to compute two tables
+//static void secp256k1_ecmult_compute_two_tables(secp256k1_ge_storage* table, secp256k1_ge_storage* table_128, int window_g, const secp256k1_ge* gen) {
 fn secp256k1_ecmult_compute_two_tables(
     table: &mut [secp256k1_ge_storage],
     table_128: &mut [secp256k1_ge_storage],
@@ -52,73 +44,65 @@ fn secp256k1_ecmult_compute_two_tables(
 ) {
     let mut gj = secp256k1_gej::new();
     secp256k1_gej_set_ge(&mut gj, gen);
-    
-    secp256k1_ecmult_compute_table(table, window_g as i32, &gj);
-    
-    // Rust range for the loop
+
+    secp256k1_ecmult_compute_table(table, window_g, &gj);
+
     for _ in 0..128 {
         let gj2 = gj.clone();
         secp256k1_gej_double_var(&mut gj, &gj2, None);
     }
-    
-    secp256k1_ecmult_compute_table(table_128, window_g as i32, &gj);
-}
 
-// Assuming this helper function exists or needs to be defined
-fn ecmult_table_size(window_g: i32) -> usize {
-    // Implementation would depend on the original macro/constant definition
-    // This is just a placeholder
-    (1 << window_g) as usize
+    secp256k1_ecmult_compute_table(table_128, window_g, &gj);
 }
 
-
+/* Unlike the C generator, which emits every window size up to 15 behind #if WINDOW_G guards,
+ * only the ECMULT_WINDOW_SIZE entries are written, as limbs of the storage representation. */
+//static void print_table(FILE* fp, const char *name, int window_g, const secp256k1_ge_storage* table) {
 fn print_table(fp: &mut File, name: &str, window_g: usize, table: &[secp256k1_ge_storage]) -> io::Result<()> {
-    writeln!(fp, "pub const {}: [secp256k1_ge_storage; {}] = [", name, ECMULT_TABLE_SIZE(window_g))?;
-    todo!();
-    // writeln!(
-    //     fp,
-    //     "    secp256k1_ge_storage([0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}])",
-    //     table[0][0], table[0][1], table[0][2], table[0][3],
-    //     table[0][4], table[0][5], table[0][6], table[0][7],
-    //     table[0][8], table[0][9], table[0][10], table[0][11],
-    //     table[0][12], table[0][13], table[0][14], table[0][15],
-    // )?;
-
-    // for j in 1..ECMULT_TABLE_SIZE(window_g) {
-    //     writeln!(
-    //         fp,
-    //         "    ,secp256k1_ge_storage([0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}, 0x{:08x}])",
-    //         table[j][0], table[j][1], table[j][2], table[j][3],
-    //         table[j][4], table[j][5], table[j][6], table[j][7],
-    //         table[j][8], table[j][9], table[j][10], table[j][11],
-    //         table[j][12], table[j][13], table[j][14], table[j][15],
-    //     )?;
-    // }
+    writeln!(fp, "pub static {}: [secp256k1_ge_storage; ECMULT_TABLE_SIZE!(WINDOW_G)] = [", name)?;
+    for entry in &table[..ECMULT_TABLE_SIZE!(window_g)] {
+        let x = &entry.x.n;
+        let y = &entry.y.n;
+        writeln!(
+            fp,
+            "S([0x{:016x}, 0x{:016x}, 0x{:016x}, 0x{:016x}], [0x{:016x}, 0x{:016x}, 0x{:016x}, 0x{:016x}]),",
+            x[0], x[1], x[2], x[3], y[0], y[1], y[2], y[3]
+        )?;
+    }
     writeln!(fp, "];")?;
     Ok(())
 }
 
+//static void print_two_tables(FILE *fp, int window_g) {
 fn print_two_tables(fp: &mut File, window_g: usize) -> io::Result<()> {
-    let mut table = vec![secp256k1_ge_storage::new(); ECMULT_TABLE_SIZE(window_g as usize)];
-    let mut table_128 = vec![secp256k1_ge_storage::new(); ECMULT_TABLE_SIZE(window_g as usize)];
+    let mut table = vec![secp256k1_ge_storage::new(); ECMULT_TABLE_SIZE!(window_g)];
+    let mut table_128 = vec![secp256k1_ge_storage::new(); ECMULT_TABLE_SIZE!(window_g)];
 
     secp256k1_ecmult_compute_two_tables(&mut table, &mut table_128, window_g, &secp256k1_ge_const_g);
 
-    print_table(fp, "SECP256K1_PRE_G", window_g, &table)?;
-    print_table(fp, "SECP256K1_PRE_G_128", window_g, &table_128)?;
+    print_table(fp, "secp256k1_pre_g", window_g, &table)?;
+    writeln!(fp)?;
+    print_table(fp, "secp256k1_pre_g_128", window_g, &table_128)?;
 
     Ok(())
 }
 
 fn main() -> io::Result<()> {
-    let window_g:usize = if ECMULT_WINDOW_SIZE < 15 { 15 } else { ECMULT_WINDOW_SIZE as usize};
+    let window_g = ECMULT_WINDOW_SIZE as usize;
     let mut fp = File::create("src/secp256k1/precomputed_ecmult.rs")?;
 
     writeln!(fp, "// This file was automatically generated by precompute_ecmult.")?;
-    writeln!(fp, "// This file contains an array SECP256K1_PRE_G with odd multiples of the base point G and")?;
-    writeln!(fp, "// an array SECP256K1_PRE_G_128 with odd multiples of 2^128*G for accelerating the computation of a*P + b*G.")?;
+    writeln!(fp, "// This file contains an array secp256k1_pre_g with odd multiples of the base point G and")?;
+    writeln!(fp, "// an array secp256k1_pre_g_128 with odd multiples of 2^128*G for accelerating the computation of a*P + b*G.")?;
+    writeln!(fp, "#![allow(warnings)]")?;
+    writeln!(fp, "use crate::secp256k1::group::secp256k1_ge_storage;")?;
+    writeln!(fp, "use crate::secp256k1::field_5x52::secp256k1_fe_storage;")?;
+    writeln!(fp, "use crate::secp256k1::ecmult_impl::WINDOW_G;")?;
+    writeln!(fp, "use crate::ECMULT_TABLE_SIZE;")?;
     writeln!(fp)?;
-    writeln!(fp, "use super::secp256k1_ge_storage;")?;
+    writeln!(fp, "const fn S(x: [u64; 4], y: [u64; 4]) -> secp256k1_ge_storage {{")?;
+    writeln!(fp, "    secp256k1_ge_storage {{ x: secp256k1_fe_storage {{ n: x }}, y: secp256k1_fe_storage {{ n: y }} }}")?;
+    writeln!(fp, "}}")?;
     writeln!(fp)?;
 
     print_two_tables(&mut fp, window_g)?;
@@ -0,0 +1,242 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2020 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use primitive_types::H256;
+use super::transaction::CTransactionRef;
+use crate::hash::SerializeHash;
+use crate::serialize::{Serialize, Unserialize, WriteStream, ReadStream, SER};
+use crate::uint256::GetHex;
+use crate::version::PROTOCOL_VERSION;
+use std::io;
+
+/** Nodes collect new transactions into a block, hash them into a hash tree,
+ * and scan through nonce values to make the block's hash satisfy proof-of-work
+ * requirements.  When they solve the proof-of-work, they broadcast the block
+ * to everyone and the block is added to the block chain.  The first transaction
+ * in the block is a special one that creates a new coin owned by the creator
+ * of the block.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CBlockHeader {
+    // header
+    pub nVersion: i32,
+    pub hashPrevBlock: H256,
+    pub hashMerkleRoot: H256,
+    pub nTime: u32,
+    pub nBits: u32,
+    pub nNonce: u32,
+}
+
+impl CBlockHeader {
+    pub fn new() -> CBlockHeader
+    {
+        CBlockHeader::default()
+    }
+
+    pub fn SetNull(&mut self)
+    {
+        *self = CBlockHeader::default();
+    }
+
+    pub fn IsNull(&self) -> bool
+    {
+        self.nBits == 0
+    }
+
+    //uint256 CBlockHeader::GetHash() const
+    pub fn GetHash(&self) -> H256
+    {
+        SerializeHash(self, SER::GETHASH, PROTOCOL_VERSION)
+    }
+
+    pub fn GetBlockTime(&self) -> i64
+    {
+        self.nTime as i64
+    }
+}
+
+//SERIALIZE_METHODS(CBlockHeader, obj) { READWRITE(obj.nVersion, obj.hashPrevBlock, obj.hashMerkleRoot, obj.nTime, obj.nBits, obj.nNonce); }
+impl Serialize for CBlockHeader {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.nVersion.Serialize(s);
+        self.hashPrevBlock.Serialize(s);
+        self.hashMerkleRoot.Serialize(s);
+        self.nTime.Serialize(s);
+        self.nBits.Serialize(s);
+        self.nNonce.Serialize(s);
+    }
+}
+
+impl Unserialize for CBlockHeader {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.nVersion.Unserialize(s)?;
+        self.hashPrevBlock.Unserialize(s)?;
+        self.hashMerkleRoot.Unserialize(s)?;
+        self.nTime.Unserialize(s)?;
+        self.nBits.Unserialize(s)?;
+        self.nNonce.Unserialize(s)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CBlock {
+    pub header: CBlockHeader,
+
+    // network and disk
+    pub vtx: Vec<CTransactionRef>,
+
+    // memory only
+    pub fChecked: bool,
+    pub m_checked_witness_commitment: bool,
+    pub m_checked_merkle_root: bool,
+}
+
+impl CBlock {
+    pub fn new() -> CBlock
+    {
+        CBlock::default()
+    }
+
+    //CBlock(const CBlockHeader &header)
+    pub fn from_header(header: &CBlockHeader) -> CBlock
+    {
+        CBlock { header: header.clone(), ..CBlock::default() }
+    }
+
+    pub fn SetNull(&mut self)
+    {
+        *self = CBlock::default();
+    }
+
+    pub fn GetHash(&self) -> H256
+    {
+        self.header.GetHash()
+    }
+
+    pub fn GetBlockHeader(&self) -> CBlockHeader
+    {
+        self.header.clone()
+    }
+
+    //std::string CBlock::ToString() const
+    pub fn ToString(&self) -> String
+    {
+        let mut s = format!("CBlock(hash={}, ver=0x{:08x}, hashPrevBlock={}, hashMerkleRoot={}, nTime={}, nBits={:08x}, nNonce={}, vtx={})\n",
+            GetHex(&self.GetHash()),
+            self.header.nVersion,
+            GetHex(&self.header.hashPrevBlock),
+            GetHex(&self.header.hashMerkleRoot),
+            self.header.nTime, self.header.nBits, self.header.nNonce,
+            self.vtx.len());
+        for tx in &self.vtx {
+            s += &format!("  CTransaction(hash={})\n", &GetHex(&tx.GetHash())[..10]);
+        }
+        s
+    }
+}
+
+//SERIALIZE_METHODS(CBlock, obj) { READWRITEAS(CBlockHeader, obj); READWRITE(obj.vtx); }
+impl Serialize for CBlock {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.header.Serialize(s);
+        self.vtx.Serialize(s);
+    }
+}
+
+impl Unserialize for CBlock {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.header.Unserialize(s)?;
+        self.vtx.Unserialize(s)
+    }
+}
+
+/** Describes a place in the block chain to another node such that if the
+ * other node doesn't have the same branch, it can find a recent common trunk.
+ * The further back it is, the further before the fork it may be.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CBlockLocator {
+    pub vHave: Vec<H256>,
+}
+
+impl CBlockLocator {
+    pub fn new(vHaveIn: Vec<H256>) -> CBlockLocator
+    {
+        CBlockLocator { vHave: vHaveIn }
+    }
+
+    pub fn SetNull(&mut self)
+    {
+        self.vHave.clear();
+    }
+
+    pub fn IsNull(&self) -> bool
+    {
+        self.vHave.is_empty()
+    }
+}
+
+//SERIALIZE_METHODS(CBlockLocator, obj)
+impl Serialize for CBlockLocator {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        if (s.GetType() & SER::GETHASH as i32) == 0 {
+            s.GetVersion().Serialize(s);
+        }
+        self.vHave.Serialize(s);
+    }
+}
+
+impl Unserialize for CBlockLocator {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        if (s.GetType() & SER::GETHASH as i32) == 0 {
+            let mut nVersion: i32 = 0;
+            nVersion.Unserialize(s)?;
+        }
+        self.vHave.Unserialize(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::CDataStream;
+    use crate::uint256::uint256S;
+
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    #[test]
+    fn test_genesis_block() {
+        let raw = format!("{}01{}", GENESIS_HEADER, GENESIS_COINBASE);
+        let mut ss = CDataStream::from_bytes(&hex::decode(&raw).unwrap(), SER::DISK, PROTOCOL_VERSION);
+        let mut block = CBlock::new();
+        ss.unserialize(&mut block).unwrap();
+        assert!(ss.empty());
+
+        assert_eq!(block.GetHash(), uint256S("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"));
+        assert_eq!(block.header.nVersion, 1);
+        assert!(block.header.hashPrevBlock.is_zero());
+        assert_eq!(block.header.nTime, 1231006505);
+        assert_eq!(block.header.nBits, 0x1d00ffff);
+        assert_eq!(block.header.nNonce, 2083236893);
+        assert_eq!(block.vtx.len(), 1);
+        assert_eq!(block.vtx[0].GetHash(), block.header.hashMerkleRoot);
+
+        let ss = CDataStream::new(SER::DISK, PROTOCOL_VERSION) << &block;
+        assert_eq!(hex::encode(ss.data()), raw);
+    }
+
+    #[test]
+    fn test_block_locator() {
+        let locator = CBlockLocator::new(vec![uint256S("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f")]);
+        let ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &locator;
+        assert_eq!(ss.size(), 4 + 1 + 32);
+
+        let mut ss = ss;
+        let mut locator2 = CBlockLocator::default();
+        ss.unserialize(&mut locator2).unwrap();
+        assert_eq!(locator, locator2);
+    }
+}
@@ -1 +1,2 @@
-pub mod transaction;
\ No newline at end of file
+pub mod block;
+pub mod transaction;
@@ -5,18 +5,32 @@
 
 use primitive_types::H256;
 use super::super::consensus::amount::CAmount;
-use crate::script::CScript;
+use crate::script::{CScript, CScriptWitness};
+use crate::serialize::{Serialize, Unserialize, WriteStream, ReadStream, SER, GetSerializeSize};
+use crate::hash::SerializeHash;
+use crate::version::PROTOCOL_VERSION;
+use std::io;
+use std::sync::Arc;
+
+/**
+ * A flag that is ORed into the protocol version to designate that a transaction
+ * should be (un)serialized without witness data.
+ * Make sure that this does not collide with any of the values in `version.h`
+ * or with `ADDRV2_FORMAT`.
+ */
+pub const SERIALIZE_TRANSACTION_NO_WITNESS: i32 = 0x40000000;
 
 /** An outpoint - a combination of a transaction hash and an index n into its vout */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct COutPoint
 {
-    hash: H256,
-    n: u32,
+    pub hash: H256,
+    pub n: u32,
 }
 
 impl COutPoint
 {
-    const NULL_INDEX: u32 = u32::MAX;
+    pub const NULL_INDEX: u32 = u32::MAX;
 
     // COutPoint(const uint256& hashIn, uint32_t nIn): hash(hashIn), n(nIn) { }
     pub fn new(hashIn: H256, nIn: u32) -> Self
@@ -24,8 +38,6 @@ impl COutPoint
         Self { hash: hashIn, n: nIn}
     }
 
-    // TODO: SERIALIZE_METHODS(COutPoint, obj) { READWRITE(obj.hash, obj.n); }
-
     // void SetNull() { hash.SetNull(); n = NULL_INDEX; }
     pub fn SetNull(&mut self)
     {
@@ -35,16 +47,46 @@ impl COutPoint
     }
 
     // bool IsNull() const { return (hash.IsNull() && n == NULL_INDEX); }
-    pub fn IsNull(self) -> bool {
+    pub fn IsNull(&self) -> bool {
         self.hash.is_zero() && self.n == COutPoint::NULL_INDEX
     }
 }
 
+// COutPoint(): n(NULL_INDEX) { }
+impl Default for COutPoint
+{
+    fn default() -> Self
+    {
+        Self { hash: H256::zero(), n: COutPoint::NULL_INDEX }
+    }
+}
+
+//SERIALIZE_METHODS(COutPoint, obj) { READWRITE(obj.hash, obj.n); }
+impl Serialize for COutPoint {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.hash.Serialize(s);
+        self.n.Serialize(s);
+    }
+}
+
+impl Unserialize for COutPoint {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.hash.Unserialize(s)?;
+        self.n.Unserialize(s)
+    }
+}
+
+/** An input of a transaction.  It contains the location of the previous
+ * transaction's output that it claims and a signature that matches the
+ * output's public key.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CTxIn {
     pub prevout: COutPoint,
     pub scriptSig: CScript,
     pub nSequence: u32,
-    //pub scriptWitness: CScriptWitness,
+    /// Only serialized through CTransaction
+    pub scriptWitness: CScriptWitness,
 }
 
 impl CTxIn {
@@ -95,36 +137,484 @@ impl CTxIn {
      * 9 bits. */
     pub const SEQUENCE_LOCKTIME_GRANULARITY:i32 = 9;
 
+    //explicit CTxIn(COutPoint prevoutIn, CScript scriptSigIn=CScript(), uint32_t nSequenceIn=SEQUENCE_FINAL);
+    pub fn new(prevoutIn: COutPoint, scriptSigIn: CScript, nSequenceIn: u32) -> Self
+    {
+        Self { prevout: prevoutIn, scriptSig: scriptSigIn, nSequence: nSequenceIn, scriptWitness: CScriptWitness::default() }
+    }
+}
 
+// CTxIn() { nSequence = SEQUENCE_FINAL; }
+impl Default for CTxIn {
+    fn default() -> Self
+    {
+        CTxIn::new(COutPoint::default(), CScript::default(), CTxIn::SEQUENCE_FINAL)
+    }
 }
 
-#[derive(Clone)]
+//SERIALIZE_METHODS(CTxIn, obj) { READWRITE(obj.prevout, obj.scriptSig, obj.nSequence); }
+impl Serialize for CTxIn {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.prevout.Serialize(s);
+        self.scriptSig.Serialize(s);
+        self.nSequence.Serialize(s);
+    }
+}
+
+impl Unserialize for CTxIn {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.prevout.Unserialize(s)?;
+        self.scriptSig.Unserialize(s)?;
+        self.nSequence.Unserialize(s)
+    }
+}
+
+/** An output of a transaction.  It contains the public key that the next input
+ * must be able to sign with to claim it.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CTxOut {
-    nValue: CAmount,
+    pub nValue: CAmount,
     pub scriptPubKey: CScript,
 }
 
 impl CTxOut {
-    pub fn SetNull(mut self)
+    //CTxOut(const CAmount& nValueIn, CScript scriptPubKeyIn);
+    pub fn new(nValueIn: CAmount, scriptPubKeyIn: CScript) -> Self
+    {
+        Self { nValue: nValueIn, scriptPubKey: scriptPubKeyIn }
+    }
+
+    pub fn SetNull(&mut self)
     {
         self.nValue = -1;
         self.scriptPubKey.clear();
     }
+
+    pub fn IsNull(&self) -> bool
+    {
+        self.nValue == -1
+    }
+}
+
+// CTxOut() { SetNull(); }
+impl Default for CTxOut {
+    fn default() -> Self
+    {
+        CTxOut::new(-1, CScript::default())
+    }
+}
+
+//SERIALIZE_METHODS(CTxOut, obj) { READWRITE(obj.nValue, obj.scriptPubKey); }
+impl Serialize for CTxOut {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.nValue.Serialize(s);
+        self.scriptPubKey.Serialize(s);
+    }
+}
+
+impl Unserialize for CTxOut {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.nValue.Unserialize(s)?;
+        self.scriptPubKey.Unserialize(s)
+    }
 }
 
+/**
+ * Basic transaction serialization format:
+ * - int32_t nVersion
+ * - std::vector<CTxIn> vin
+ * - std::vector<CTxOut> vout
+ * - uint32_t nLockTime
+ *
+ * Extended transaction serialization format:
+ * - int32_t nVersion
+ * - unsigned char dummy = 0x00
+ * - unsigned char flags (!= 0)
+ * - std::vector<CTxIn> vin
+ * - std::vector<CTxOut> vout
+ * - if (flags & 1):
+ *   - CScriptWitness scriptWitness; (deserialized into CTxIn)
+ * - uint32_t nLockTime
+ */
+//template<typename Stream, typename TxType>
+//inline void UnserializeTransaction(TxType& tx, Stream& s) {
+pub fn UnserializeTransaction<S: ReadStream>(vin: &mut Vec<CTxIn>, vout: &mut Vec<CTxOut>, nVersion: &mut i32, nLockTime: &mut u32, s: &mut S) -> io::Result<()>
+{
+    let fAllowWitness: bool = (s.GetVersion() & SERIALIZE_TRANSACTION_NO_WITNESS) == 0;
+
+    nVersion.Unserialize(s)?;
+    let mut flags: u8 = 0;
+    vin.clear();
+    vout.clear();
+    /* Try to read the vin. In case the dummy is there, this will be read as an empty vector. */
+    vin.Unserialize(s)?;
+    if vin.is_empty() && fAllowWitness {
+        /* We read a dummy or an empty vin. */
+        flags.Unserialize(s)?;
+        if flags != 0 {
+            vin.Unserialize(s)?;
+            vout.Unserialize(s)?;
+        }
+    } else {
+        /* We read a non-empty vin. Assume a normal vout follows. */
+        vout.Unserialize(s)?;
+    }
+    if (flags & 1) != 0 && fAllowWitness {
+        /* The witness flag is present, and we support witnesses. */
+        flags ^= 1;
+        for txin in vin.iter_mut() {
+            txin.scriptWitness.stack.Unserialize(s)?;
+        }
+        if !vin.iter().any(|txin| !txin.scriptWitness.IsNull()) {
+            /* It's illegal to encode witnesses when all witness stacks are empty. */
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Superfluous witness record"));
+        }
+    }
+    if flags != 0 {
+        /* Unknown flag in the serialization */
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown transaction optional data"));
+    }
+    nLockTime.Unserialize(s)
+}
+
+//template<typename Stream, typename TxType>
+//inline void SerializeTransaction(const TxType& tx, Stream& s) {
+pub fn SerializeTransaction<S: WriteStream>(vin: &[CTxIn], vout: &[CTxOut], nVersion: i32, nLockTime: u32, s: &mut S)
+{
+    let fAllowWitness: bool = (s.GetVersion() & SERIALIZE_TRANSACTION_NO_WITNESS) == 0;
+
+    nVersion.Serialize(s);
+    let mut flags: u8 = 0;
+    // Consistency check
+    if fAllowWitness {
+        /* Check whether witnesses need to be serialized. */
+        if vin.iter().any(|txin| !txin.scriptWitness.IsNull()) {
+            flags |= 1;
+        }
+    }
+    if flags != 0 {
+        /* Use extended format in case witnesses are to be serialized. */
+        let vinDummy: Vec<CTxIn> = vec![];
+        vinDummy.Serialize(s);
+        flags.Serialize(s);
+    }
+    WriteVec(vin, s);
+    WriteVec(vout, s);
+    if (flags & 1) != 0 {
+        for txin in vin {
+            txin.scriptWitness.stack.Serialize(s);
+        }
+    }
+    nLockTime.Serialize(s);
+}
+
+fn WriteVec<T: Serialize, S: WriteStream>(v: &[T], s: &mut S)
+{
+    crate::serialize::WriteCompactSize(s, v.len() as u64);
+    T::SerializeMany(v, s);
+}
+
+/** The basic transaction that is broadcasted on the network and contained in
+ * blocks.  A transaction can contain multiple inputs and outputs.
+ */
+#[derive(Clone, Debug)]
 pub struct CTransaction {
     pub vin: Vec<CTxIn>,
     pub vout: Vec<CTxOut>,
     pub nVersion: i32,
     pub nLockTime: u32,
-    hash: H256
+    /** Memory only. */
+    hash: H256,
+    m_witness_hash: H256,
 }
 
 impl CTransaction {
-    const CURRENT_VERSION: i32 = 2;
+    // Default transaction version.
+    pub const CURRENT_VERSION: i32 = 2;
+
+    pub fn IsNull(&self) -> bool
+    {
+        self.vin.is_empty() && self.vout.is_empty()
+    }
 
     pub fn GetHash(&self) -> H256
     {
         self.hash.clone()
     }
-}
\ No newline at end of file
+
+    pub fn GetWitnessHash(&self) -> H256
+    {
+        self.m_witness_hash.clone()
+    }
+
+    //uint256 CTransaction::ComputeHash() const
+    pub fn ComputeHash(&self) -> H256
+    {
+        SerializeHash(self, SER::GETHASH, SERIALIZE_TRANSACTION_NO_WITNESS)
+    }
+
+    //uint256 CTransaction::ComputeWitnessHash() const
+    pub fn ComputeWitnessHash(&self) -> H256
+    {
+        if !self.HasWitness() {
+            return self.hash.clone();
+        }
+        SerializeHash(self, SER::GETHASH, 0)
+    }
+
+    /**
+     * Get the total transaction size in bytes, including witness data.
+     * "Total Size" defined in BIP141 and BIP144.
+     * @return Total transaction size in bytes
+     */
+    pub fn GetTotalSize(&self) -> usize
+    {
+        GetSerializeSize(self, PROTOCOL_VERSION)
+    }
+
+    pub fn IsCoinBase(&self) -> bool
+    {
+        self.vin.len() == 1 && self.vin[0].prevout.IsNull()
+    }
+
+    pub fn HasWitness(&self) -> bool
+    {
+        self.vin.iter().any(|txin| !txin.scriptWitness.IsNull())
+    }
+}
+
+//CTransaction::CTransaction(const CMutableTransaction& tx)
+impl From<&CMutableTransaction> for CTransaction {
+    fn from(tx: &CMutableTransaction) -> Self
+    {
+        CTransaction::from(tx.clone())
+    }
+}
+
+//CTransaction::CTransaction(CMutableTransaction&& tx)
+impl From<CMutableTransaction> for CTransaction {
+    fn from(tx: CMutableTransaction) -> Self
+    {
+        let mut result = CTransaction {
+            vin: tx.vin,
+            vout: tx.vout,
+            nVersion: tx.nVersion,
+            nLockTime: tx.nLockTime,
+            hash: H256::zero(),
+            m_witness_hash: H256::zero(),
+        };
+        result.hash = result.ComputeHash();
+        result.m_witness_hash = result.ComputeWitnessHash();
+        result
+    }
+}
+
+impl Default for CTransaction {
+    fn default() -> Self
+    {
+        Self {
+            vin: vec![],
+            vout: vec![],
+            nVersion: CTransaction::CURRENT_VERSION,
+            nLockTime: 0,
+            hash: H256::zero(),
+            m_witness_hash: H256::zero(),
+        }
+    }
+}
+
+impl PartialEq for CTransaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl Eq for CTransaction {}
+
+impl Serialize for CTransaction {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        SerializeTransaction(&self.vin, &self.vout, self.nVersion, self.nLockTime, s);
+    }
+}
+
+impl Unserialize for CTransaction {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        UnserializeTransaction(&mut self.vin, &mut self.vout, &mut self.nVersion, &mut self.nLockTime, s)?;
+        self.hash = self.ComputeHash();
+        self.m_witness_hash = self.ComputeWitnessHash();
+        Ok(())
+    }
+}
+
+/** A mutable version of CTransaction. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CMutableTransaction {
+    pub vin: Vec<CTxIn>,
+    pub vout: Vec<CTxOut>,
+    pub nVersion: i32,
+    pub nLockTime: u32,
+}
+
+impl CMutableTransaction {
+    pub fn new() -> CMutableTransaction
+    {
+        CMutableTransaction::default()
+    }
+
+    /** Compute the hash of this CMutableTransaction. This is computed on the
+     * fly, as opposed to GetHash() in CTransaction, which uses a cached result.
+     */
+    //uint256 CMutableTransaction::GetHash() const
+    pub fn GetHash(&self) -> H256
+    {
+        SerializeHash(self, SER::GETHASH, SERIALIZE_TRANSACTION_NO_WITNESS)
+    }
+
+    pub fn HasWitness(&self) -> bool
+    {
+        self.vin.iter().any(|txin| !txin.scriptWitness.IsNull())
+    }
+}
+
+impl Default for CMutableTransaction {
+    fn default() -> Self
+    {
+        Self {
+            vin: vec![],
+            vout: vec![],
+            nVersion: CTransaction::CURRENT_VERSION,
+            nLockTime: 0,
+        }
+    }
+}
+
+//CMutableTransaction::CMutableTransaction(const CTransaction& tx)
+impl From<&CTransaction> for CMutableTransaction {
+    fn from(tx: &CTransaction) -> Self
+    {
+        CMutableTransaction {
+            vin: tx.vin.clone(),
+            vout: tx.vout.clone(),
+            nVersion: tx.nVersion,
+            nLockTime: tx.nLockTime,
+        }
+    }
+}
+
+impl Serialize for CMutableTransaction {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        SerializeTransaction(&self.vin, &self.vout, self.nVersion, self.nLockTime, s);
+    }
+}
+
+impl Unserialize for CMutableTransaction {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        UnserializeTransaction(&mut self.vin, &mut self.vout, &mut self.nVersion, &mut self.nLockTime, s)
+    }
+}
+
+pub type CTransactionRef = Arc<CTransaction>;
+
+//template <typename Tx> static inline CTransactionRef MakeTransactionRef(Tx&& txIn)
+pub fn MakeTransactionRef<T: Into<CTransaction>>(txIn: T) -> CTransactionRef
+{
+    Arc::new(txIn.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::CDataStream;
+    use crate::uint256::{GetHex, uint256S};
+
+    // The genesis block coinbase transaction
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    // BIP143 native P2WPKH example (signed)
+    const SEGWIT_TX: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+
+    fn parse(hex_tx: &str, nVersion: i32) -> std::io::Result<CTransaction> {
+        let mut ss = CDataStream::from_bytes(&hex::decode(hex_tx).unwrap(), SER::NETWORK, nVersion);
+        let mut tx = CTransaction::default();
+        ss.unserialize(&mut tx)?;
+        assert!(ss.empty());
+        Ok(tx)
+    }
+
+    #[test]
+    fn test_genesis_coinbase() {
+        let tx = parse(GENESIS_COINBASE, PROTOCOL_VERSION).unwrap();
+        assert!(tx.IsCoinBase());
+        assert!(!tx.HasWitness());
+        assert_eq!(tx.nVersion, 1);
+        assert_eq!(tx.vout[0].nValue, 50 * crate::consensus::amount::COIN);
+        assert_eq!(GetHex(&tx.GetHash()), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+        assert_eq!(tx.GetHash(), tx.GetWitnessHash());
+        assert_eq!(tx.GetTotalSize(), GENESIS_COINBASE.len() / 2);
+
+        let ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &tx;
+        assert_eq!(hex::encode(ss.data()), GENESIS_COINBASE);
+    }
+
+    #[test]
+    fn test_witness_roundtrip() {
+        let tx = parse(SEGWIT_TX, PROTOCOL_VERSION).unwrap();
+        assert!(tx.HasWitness());
+        assert_eq!(tx.vin.len(), 2);
+        assert_eq!(tx.vout.len(), 2);
+        assert!(tx.vin[0].scriptWitness.IsNull());
+        assert_eq!(tx.vin[1].scriptWitness.stack.len(), 2);
+        assert_eq!(tx.nLockTime, 0x11);
+        assert_eq!(tx.GetHash(), uint256S("e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609"));
+        assert_eq!(tx.GetWitnessHash(), uint256S("c36c38370907df2324d9ce9d149d191192f338b37665a82e78e76a12c909b762"));
+
+        let ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION) << &tx;
+        assert_eq!(hex::encode(ss.data()), SEGWIT_TX);
+
+        // Without witness the serialization must hash to the txid
+        let ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION | SERIALIZE_TRANSACTION_NO_WITNESS) << &tx;
+        let stripped = hex::encode(ss.data());
+        let tx2 = parse(&stripped, PROTOCOL_VERSION).unwrap();
+        assert!(!tx2.HasWitness());
+        assert_eq!(tx2.GetHash(), tx.GetHash());
+        assert_eq!(tx2.GetWitnessHash(), tx.GetHash());
+    }
+
+    #[test]
+    fn test_mutable_transaction() {
+        let tx = parse(SEGWIT_TX, PROTOCOL_VERSION).unwrap();
+        let mut mtx = CMutableTransaction::from(&tx);
+        assert_eq!(mtx.GetHash(), tx.GetHash());
+        assert!(mtx.HasWitness());
+
+        // Converting back caches the same txid and wtxid
+        let tx2 = CTransaction::from(&mtx);
+        assert_eq!(tx2.GetHash(), tx.GetHash());
+        assert_eq!(tx2.GetWitnessHash(), tx.GetWitnessHash());
+
+        // Edits only show up once the transaction is frozen again
+        mtx.nLockTime = 0;
+        assert_ne!(mtx.GetHash(), tx.GetHash());
+        let tx3 = CTransaction::from(mtx);
+        assert_eq!(tx3.nLockTime, 0);
+        assert_ne!(tx3.GetHash(), tx.GetHash());
+        assert_ne!(tx3.GetWitnessHash(), tx.GetWitnessHash());
+    }
+
+    #[test]
+    fn test_bad_witness_encoding() {
+        // A segwit tx read without witness support is not the same transaction
+        let mut ss = CDataStream::from_bytes(&hex::decode(SEGWIT_TX).unwrap(), SER::NETWORK, PROTOCOL_VERSION | SERIALIZE_TRANSACTION_NO_WITNESS);
+        let mut tx = CTransaction::default();
+        assert!(ss.unserialize(&mut tx).is_err() || !ss.empty());
+
+        // Marker and flag present, but all witness stacks empty
+        let superfluous = "0100000000010100000000000000000000000000000000000000000000000000000000000000000000000000ffffffff010000000000000000000000000000";
+        assert_eq!(parse(superfluous, PROTOCOL_VERSION).unwrap_err().to_string(), "Superfluous witness record");
+
+        // Unknown flag bit
+        let unknown = "0100000000020100000000000000000000000000000000000000000000000000000000000000000000000000ffffffff01000000000000000000000000";
+        assert_eq!(parse(unknown, PROTOCOL_VERSION).unwrap_err().to_string(), "Unknown transaction optional data");
+    }
+}
@@ -2,8 +2,20 @@
 // Copyright (c) 2017 The Zcash developers
 // Distributed under the MIT software license, see the accompanying
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
-use primitive_types::U256;
+use primitive_types::{H160, H256, U256};
+use crate::hash::{Hash160, HASHER_TAPTWEAK};
+use crate::serialize::AsBytes;
 use crate::script::CScript;
+use crate::secp256k1::{secp256k1_context_static, secp256k1_ec_pubkey_parse, secp256k1_ec_pubkey_serialize, ecdsa_signature_parse_der_lax,
+                       SECP256K1_EC_COMPRESSED, SECP256K1_EC_UNCOMPRESSED,
+                       secp256k1_ecdsa_signature_normalize, secp256k1_ecdsa_verify, secp256k1_pubkey, secp256k1_ecdsa_signature};
+use crate::secp256k1::extrakeys::{secp256k1_xonly_pubkey, secp256k1_xonly_pubkey_parse, secp256k1_xonly_pubkey_serialize,
+                                  secp256k1_xonly_pubkey_from_pubkey, secp256k1_xonly_pubkey_tweak_add,
+                                  secp256k1_xonly_pubkey_tweak_add_check};
+use crate::secp256k1::schnorrsig::secp256k1_schnorrsig_verify;
+use crate::secp256k1::ellswift::secp256k1_ellswift_decode;
+use crate::secp256k1::recovery::{secp256k1_ecdsa_recoverable_signature, secp256k1_ecdsa_recoverable_signature_parse_compact,
+                                 secp256k1_ecdsa_recover};
 
 //const unsigned int BIP32_EXTKEY_SIZE = 74;
 pub const BIP32_EXTKEY_SIZE: u32 = 74;
@@ -29,6 +41,10 @@ pub struct CPubKey {
     pub vch: [u8; 65],
 }
 
+impl AsBytes for CPubKey {
+    fn as_bytes(&self) -> &[u8] { self.data() }
+}
+
 impl CPubKey {
     pub const SIZE: usize = 65;
     pub const COMPRESSED_SIZE: usize = 33;
@@ -59,8 +75,103 @@ impl CPubKey {
 
     /// Simple read-only vector-like interface to the pubkey data.
     pub fn size(&self) -> usize { Self::GetLen(self.vch[0]) }
-    pub fn data(&self) -> &[u8] { &self.vch[0..] }
+    pub fn data(&self) -> &[u8] { &self.vch[0..self.size()] }
 
+    /** Get the KeyID of this public key (hash of its serialization) */
+    //CKeyID GetID() const
+    pub fn GetID(&self) -> H160
+    {
+        Hash160(self)
+    }
+
+    /*
+     * Check syntactic correctness.
+     *
+     * When setting a pubkey (Set()) or deserializing fails (its header byte
+     * is invalid), the size will be set to 0, so this returns false.
+     *
+     * Note that this is consensus critical as CheckECDSASignature() calls it!
+     */
+    //bool IsValid() const
+    pub fn IsValid(&self) -> bool
+    {
+        self.size() > 0
+    }
+
+    /** fully validate whether this is a valid public key (more expensive than IsValid()) */
+    //bool CPubKey::IsFullyValid() const {
+    pub fn IsFullyValid(&self) -> bool
+    {
+        if !self.IsValid() {
+            return false;
+        }
+        let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+        secp256k1_ec_pubkey_parse(&secp256k1_context_static(), &mut pubkey, &self.vch[..self.size()]) != 0
+    }
+
+    /**
+     * Verify a DER signature (~72 bytes).
+     * If this public key is not fully valid, the return value will be false.
+     */
+    //bool CPubKey::Verify(const uint256 &hash, const std::vector<unsigned char>& vchSig) const {
+    pub fn Verify(&self, hash: &H256, vchSig: &[u8]) -> bool
+    {
+        if !self.IsValid() {
+            return false;
+        }
+        let ctx = secp256k1_context_static();
+        let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+        let mut sig = secp256k1_ecdsa_signature { data: [0u8; 64] };
+        if secp256k1_ec_pubkey_parse(&ctx, &mut pubkey, &self.vch[..self.size()]) == 0 {
+            return false;
+        }
+        if ecdsa_signature_parse_der_lax(&mut sig, vchSig) == 0 {
+            return false;
+        }
+        /* libsecp256k1's ECDSA verification requires lower-S signatures, which have
+         * not historically been enforced in Bitcoin, so normalize them first. */
+        let sigin = secp256k1_ecdsa_signature { data: sig.data };
+        secp256k1_ecdsa_signature_normalize(&ctx, Some(&mut sig), &sigin);
+        secp256k1_ecdsa_verify(&ctx, &sig, hash.as_fixed_bytes(), &pubkey) != 0
+    }
+
+    /** Recover a public key from a compact signature. */
+    //bool CPubKey::RecoverCompact(const uint256 &hash, const std::vector<unsigned char>& vchSig) {
+    pub fn RecoverCompact(&mut self, hash: &H256, vchSig: &[u8]) -> bool
+    {
+        if vchSig.len() != Self::COMPACT_SIGNATURE_SIZE {
+            return false;
+        }
+        let recid = (vchSig[0].wrapping_sub(27) & 3) as i32;
+        let fComp = (vchSig[0].wrapping_sub(27) & 4) != 0;
+        let ctx = secp256k1_context_static();
+        let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+        let mut sig = secp256k1_ecdsa_recoverable_signature::new();
+        if secp256k1_ecdsa_recoverable_signature_parse_compact(&ctx, &mut sig, vchSig[1..].try_into().unwrap(), recid) == 0 {
+            return false;
+        }
+        if secp256k1_ecdsa_recover(&ctx, &mut pubkey, &sig, hash.as_fixed_bytes()) == 0 {
+            return false;
+        }
+        let mut publen: usize = Self::SIZE;
+        self.vch = [0u8; 65];
+        secp256k1_ec_pubkey_serialize(&ctx, &mut self.vch, &mut publen, &pubkey, if fComp { SECP256K1_EC_COMPRESSED } else { SECP256K1_EC_UNCOMPRESSED });
+        true
+    }
+
+    /**
+     * Check whether a signature is normalized (lower-S).
+     */
+    //bool CPubKey::CheckLowS(const std::vector<unsigned char>& vchSig) {
+    pub fn CheckLowS(vchSig: &[u8]) -> bool
+    {
+        let ctx = secp256k1_context_static();
+        let mut sig = secp256k1_ecdsa_signature { data: [0u8; 64] };
+        if ecdsa_signature_parse_der_lax(&mut sig, vchSig) == 0 {
+            return false;
+        }
+        secp256k1_ecdsa_signature_normalize(&ctx, None, &sig) == 0
+    }
 }
 
 pub struct XOnlyPubKey {
@@ -68,9 +179,374 @@ pub struct XOnlyPubKey {
 }
 
 impl XOnlyPubKey {
-    pub fn cs(&self) -> CScript {
+    pub const SIZE: usize = 32;
+
+    /** Construct an x-only pubkey from exactly 32 bytes. */
+    //explicit XOnlyPubKey(Span<const unsigned char> bytes);
+    pub fn new(bytes: &[u8]) -> Self
+    {
+        assert!(bytes.len() == Self::SIZE);
+        XOnlyPubKey { m_keydata: U256::from_little_endian(bytes) }
+    }
+
+    /** Construct an x-only pubkey from a normal pubkey. */
+    //explicit XOnlyPubKey(const CPubKey& pubkey) : XOnlyPubKey(Span{pubkey}.subspan(1, 32)) {}
+    pub fn from_pubkey(pubkey: &CPubKey) -> Self
+    {
+        Self::new(&pubkey.vch[1..33])
+    }
+
+    pub fn data(&self) -> [u8; 32]
+    {
         let mut buf = [0u8; 32];
         self.m_keydata.to_little_endian(&mut buf);
-        CScript::new(buf.to_vec())
+        buf
+    }
+
+    /** Determine if this pubkey is fully valid. This is true for approximately 50% of all
+     *  possible 32-byte arrays. If false, VerifySchnorr will always return false. */
+    //bool XOnlyPubKey::IsFullyValid() const
+    pub fn IsFullyValid(&self) -> bool
+    {
+        let mut pubkey = secp256k1_xonly_pubkey::new();
+        secp256k1_xonly_pubkey_parse(&secp256k1_context_static(), &mut pubkey, &self.data()) != 0
+    }
+
+    /** Verify a Schnorr signature against this public key.
+     *
+     *  sigbytes must be exactly 64 bytes.
+     */
+    //bool XOnlyPubKey::VerifySchnorr(const uint256& msg, Span<const unsigned char> sigbytes) const
+    pub fn VerifySchnorr(&self, msg: &H256, sigbytes: &[u8]) -> bool
+    {
+        assert!(sigbytes.len() == 64);
+        let ctx = secp256k1_context_static();
+        let mut pubkey = secp256k1_xonly_pubkey::new();
+        if secp256k1_xonly_pubkey_parse(&ctx, &mut pubkey, &self.data()) == 0 {
+            return false;
+        }
+        secp256k1_schnorrsig_verify(&ctx, sigbytes.try_into().unwrap(), msg.as_fixed_bytes(), &pubkey) != 0
+    }
+
+    /** Compute the Taproot tweak as specified in BIP341, with *this as internal
+     * key:
+     *  - if merkle_root is None: H_TapTweak(xonly_pubkey)
+     *  - otherwise:              H_TapTweak(xonly_pubkey || *merkle_root)
+     *
+     * Note that the behavior of this function with merkle_root None is
+     * consensus critical.
+     */
+    //uint256 XOnlyPubKey::ComputeTapTweakHash(const uint256* merkle_root) const
+    pub fn ComputeTapTweakHash(&self, merkle_root: Option<&H256>) -> H256
+    {
+        let mut hasher = HASHER_TAPTWEAK.clone();
+        hasher.write(&self.data());
+        // With no scripts the actual tweak does not matter, but follow BIP341
+        // here to allow for reproducible tweaking.
+        if let Some(merkle_root) = merkle_root {
+            hasher.write(merkle_root.as_bytes());
+        }
+        hasher.GetSHA256()
+    }
+
+    /** Verify that this is a Taproot tweaked output point, against a specified internal key,
+     *  Merkle root, and parity. */
+    //bool XOnlyPubKey::CheckTapTweak(const XOnlyPubKey& internal, const uint256& merkle_root, bool parity) const
+    pub fn CheckTapTweak(&self, internal: &XOnlyPubKey, merkle_root: &H256, parity: bool) -> bool
+    {
+        let ctx = secp256k1_context_static();
+        let mut internal_key = secp256k1_xonly_pubkey::new();
+        if secp256k1_xonly_pubkey_parse(&ctx, &mut internal_key, &internal.data()) == 0 {
+            return false;
+        }
+        let tweak = internal.ComputeTapTweakHash(Some(merkle_root));
+        secp256k1_xonly_pubkey_tweak_add_check(&ctx, &self.data(), parity as i32, &internal_key, tweak.as_fixed_bytes()) != 0
     }
-}
\ No newline at end of file
+
+    /** Construct a Taproot tweaked output point with this point as internal key. */
+    //std::optional<std::pair<XOnlyPubKey, bool>> XOnlyPubKey::CreateTapTweak(const uint256* merkle_root) const
+    pub fn CreateTapTweak(&self, merkle_root: Option<&H256>) -> Option<(XOnlyPubKey, bool)>
+    {
+        let ctx = secp256k1_context_static();
+        let mut base_point = secp256k1_xonly_pubkey::new();
+        if secp256k1_xonly_pubkey_parse(&ctx, &mut base_point, &self.data()) == 0 {
+            return None;
+        }
+        let mut out = secp256k1_pubkey { data: [0u8; 64] };
+        let tweak = self.ComputeTapTweakHash(merkle_root);
+        if secp256k1_xonly_pubkey_tweak_add(&ctx, &mut out, &base_point, tweak.as_fixed_bytes()) == 0 {
+            return None;
+        }
+        let mut parity: i32 = -1;
+        let mut out_xonly = secp256k1_xonly_pubkey::new();
+        if secp256k1_xonly_pubkey_from_pubkey(&ctx, &mut out_xonly, Some(&mut parity), &out) == 0 {
+            return None;
+        }
+        let mut out_bytes = [0u8; 32];
+        secp256k1_xonly_pubkey_serialize(&ctx, &mut out_bytes, &out_xonly);
+        assert!(parity == 0 || parity == 1);
+        Some((XOnlyPubKey::new(&out_bytes), parity == 1))
+    }
+
+    pub fn cs(&self) -> CScript {
+        CScript::new(self.data().to_vec())
+    }
+}
+
+/** An ElligatorSwift-encoded public key. */
+#[derive(Clone, Debug, PartialEq)]
+pub struct EllSwiftPubKey {
+    m_pubkey: [u8; 64],
+}
+
+impl EllSwiftPubKey {
+    pub const SIZE: usize = 64;
+
+    /** Construct a new ellswift public key from a given serialization. */
+    //EllSwiftPubKey(Span<const std::byte> ellswift) noexcept;
+    pub fn new(ellswift: &[u8]) -> Self
+    {
+        assert!(ellswift.len() == Self::SIZE);
+        EllSwiftPubKey { m_pubkey: ellswift.try_into().unwrap() }
+    }
+
+    /** Decode to normal compressed CPubKey (for debugging purposes). */
+    //CPubKey EllSwiftPubKey::Decode() const
+    pub fn Decode(&self) -> CPubKey
+    {
+        let ctx = secp256k1_context_static();
+        let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+        secp256k1_ellswift_decode(&ctx, &mut pubkey, &self.m_pubkey);
+
+        let mut sz: usize = CPubKey::COMPRESSED_SIZE;
+        let mut result = CPubKey { vch: [0u8; 65] };
+        secp256k1_ec_pubkey_serialize(&ctx, &mut result.vch, &mut sz, &pubkey, SECP256K1_EC_COMPRESSED);
+        assert!(sz == CPubKey::COMPRESSED_SIZE);
+        result
+    }
+
+    pub fn size(&self) -> usize { Self::SIZE }
+    pub fn data(&self) -> &[u8; 64] { &self.m_pubkey }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::CHash256;
+    use crate::secp256k1::{secp256k1_context, secp256k1_context_static, secp256k1_ecdsa_signature_parse_der};
+    use crate::secp256k1::extrakeys::{secp256k1_keypair, secp256k1_keypair_create, secp256k1_keypair_xonly_pub,
+                                      secp256k1_xonly_pubkey_serialize};
+    use crate::secp256k1::schnorrsig::{secp256k1_schnorrsig_sign32, secp256k1_schnorrsig_sign_custom, secp256k1_schnorrsig_extraparams};
+
+    const PUBKEY1: &str = "040b4c866585dd868a9d62348a9cd008d6a312937048fff31670e7e920cfc7a7447b5f0bba9e01e6fe4735c8383e6e7a3347a0fd72381b8f797a19f694054e5a69";
+    // signature of Hash("Very deterministic message") by PUBKEY1's secret key
+    const R: &str = "5dbbddda71772d95ce91cd2d14b592cfbc1dd0aabd6a394b6c2d377bbe59d31d";
+    const S: &str = "14ddda21494a4e221f0824f0b8b924c43fa43c0ad57dccdaa11f81a6bd4582f6";
+    // N - S
+    const S_HIGH: &str = "eb2225deb6b5b1dde0f7db0f4746db3a7b0aa0dbd9cad3611eb2dce612f0be4b";
+
+    fn sig(parts: &[&str]) -> Vec<u8> {
+        hex::decode(parts.concat()).unwrap()
+    }
+
+    fn strict(vchSig: &[u8]) -> bool {
+        let mut s = secp256k1_ecdsa_signature { data: [0u8; 64] };
+        secp256k1_ecdsa_signature_parse_der(&secp256k1_context_static(), &mut s, vchSig) != 0
+    }
+
+    #[test]
+    fn test_verify_lax_der() {
+        let mut result = [0u8; 32];
+        CHash256::new().write(b"Very deterministic message").finalize(&mut result);
+        let hash = H256::from(result);
+        let mut pubkey = CPubKey { vch: [0u8; 65] };
+        pubkey.vch.copy_from_slice(&hex::decode(PUBKEY1).unwrap());
+
+        let der = sig(&["3044", "0220", R, "0220", S]);
+        assert!(strict(&der));
+        assert!(pubkey.Verify(&hash, &der));
+
+        // Encodings that violate DER but were accepted before BIP66.
+        let lax = [
+            // long form sequence length
+            sig(&["308144", "0220", R, "0220", S]),
+            // long form integer length
+            sig(&["3045", "028120", R, "0220", S]),
+            // long form integer length with leading zero bytes
+            sig(&["3048", "028400000020", R, "0220", S]),
+            // excessive zero padding
+            sig(&["3046", "02220000", R, "0220", S]),
+            // garbage after the sequence
+            sig(&["3044", "0220", R, "0220", S, "0001"]),
+            // wrong sequence length
+            sig(&["3000", "0220", R, "0220", S]),
+        ];
+        for vchSig in lax.iter() {
+            assert!(!strict(vchSig));
+            assert!(pubkey.Verify(&hash, vchSig));
+        }
+
+        // Negative S: the strict parser reads it as zero, the lax one as unsigned, and
+        // Verify then normalizes the resulting high S.
+        let negative = sig(&["3044", "0220", R, "0220", S_HIGH]);
+        assert!(!CPubKey::CheckLowS(&negative));
+        assert!(pubkey.Verify(&hash, &negative));
+
+        // R does not fit in 32 bytes: parses, but as an invalid all-zero signature
+        let overflow = sig(&["3045", "022101", R, "0220", S]);
+        assert!(CPubKey::CheckLowS(&overflow));
+        assert!(!pubkey.Verify(&hash, &overflow));
+
+        let invalid = [
+            vec![],
+            sig(&["3144", "0220", R, "0220", S]),
+            sig(&["3044", "0320", R, "0220", S]),
+            sig(&["3044", "0220", R, "0320", S]),
+            // integer length needs four significant bytes
+            sig(&["3048", "028401000020", R, "0220", S]),
+            // truncated
+            sig(&["3044", "0220", R, "0220", &S[..62]]),
+            sig(&["3044", "0220", R]),
+        ];
+        for vchSig in invalid.iter() {
+            assert!(!CPubKey::CheckLowS(vchSig));
+            assert!(!pubkey.Verify(&hash, vchSig));
+        }
+    }
+
+    // BIP340 test-vectors.csv: (secret key, public key, aux_rand, message, signature, result)
+    const BIP340_VECTORS: [(&str, &str, &str, &str, &str, bool); 19] = [
+        ("0000000000000000000000000000000000000000000000000000000000000003", "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9", "0000000000000000000000000000000000000000000000000000000000000000",
+         "0000000000000000000000000000000000000000000000000000000000000000",
+         "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0", true),
+        ("B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "0000000000000000000000000000000000000000000000000000000000000001",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A", true),
+        ("C90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B14E5C9", "DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8", "C87AA53824B4D7AE2EB035A2B5BBBCCC080E76CDC6D1692C4B0B62D798E6D906",
+         "7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C",
+         "5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7", true),
+        // test fails if msg is reduced modulo p or n
+        ("0B432B2677937381AEF05BB02A66ECD012773062CF3FA2549E44F58ED2401710", "25D1DFF95105F5253C4022F628A996AD3A0D95FBF21D468A1B33F8C160D8F517", "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+         "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+         "7EB0509757E246F19449885651611CB965ECC1A187DD51B64FDA1EDC9637D5EC97582B9CB13DB3933705B32BA982AF5AF25FD78881EBB32771FC5922EFC66EA3", true),
+        ("", "D69C3509BB99E412E68B0FE8544E72837DFA30746D8BE2AA65975F29D22DC7B9", "",
+         "4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703",
+         "00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C6376AFB1548AF603B3EB45C9F8207DEE1060CB71C04E80F593060B07D28308D7F4", true),
+        // public key not on the curve
+        ("", "EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false),
+        // has_even_y(R) is false
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2", false),
+        // negated message
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "1FA62E331EDBC21C394792D2AB1100A7B432B013DF3F6FF4F99FCB33E0E1515F28890B3EDB6E7189B630448B515CE4F8622A954CFE545735AAEA5134FCCDB2BD", false),
+        // negated s value
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769961764B3AA9B2FFCB6EF947B6887A226E8D7C93E00C5ED0C1834FF0D0C2E6DA6", false),
+        // sG - eP is infinite. Test fails in single verification if has_even_y(inf) is defined as true and x(inf) as 0
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "0000000000000000000000000000000000000000000000000000000000000000123DDA8328AF9C23A94C1FEECFD123BA4FB73476F0D594DCB65C6425BD186051", false),
+        // sG - eP is infinite. Test fails in single verification if has_even_y(inf) is defined as true and x(inf) as 1
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "00000000000000000000000000000000000000000000000000000000000000017615FBAF5AE28864013C099742DEADB4DBA87F11AC6754F93780D5A1837CF197", false),
+        // sig[0:32] is not an X coordinate on the curve
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false),
+        // sig[0:32] is equal to field size
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false),
+        // sig[32:64] is equal to curve order
+        ("", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141", false),
+        // public key is not a valid X coordinate because it exceeds the field size
+        ("", "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30", "",
+         "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+         "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false),
+        // message of size 0 (added 2022-12)
+        ("0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+         "",
+         "71535DB165ECD9FBBC046E5FFAEA61186BB6AD436732FCCC25291A55895464CF6069CE26BF03466228F19A3A62DB8A649F2D560FAC652827D1AF0574E427AB63", true),
+        // message of size 1 (added 2022-12)
+        ("0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+         "11",
+         "08A20A0AFEF64124649232E0693C583AB1B9934AE63B4C3511F3AE1134C6A303EA3173BFEA6683BD101FA5AA5DBC1996FE7CACFC5A577D33EC14564CEC2BACBF", true),
+        // message of size 17 (added 2022-12)
+        ("0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+         "0102030405060708090A0B0C0D0E0F1011",
+         "5130F39A4059B43BC7CAC09A19ECE52B5D8699D1A71E3C52DA9AFDB6B50AC370C4A482B77BF960F8681540E25B6771ECE1E5A37FD80E5A51897C5566A97EA5A5", true),
+        // message of size 100 (added 2022-12)
+        ("0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+         "99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999",
+         "403B12B0D8555A344175EA7EC746566303321E5DBFA8BE6F091635163ECA79A8585ED3E3170807E7C03B720FC54C7B23897FCBA0E9D0B4A06894CFD249F22367", true),
+    ];
+
+    #[test]
+    fn test_bip340_vectors() {
+        let mut ctx = secp256k1_context::new();
+        for (sec, pubhex, aux, msghex, sighex, result) in BIP340_VECTORS.iter() {
+            let pubkey = XOnlyPubKey::new(&hex::decode(pubhex).unwrap());
+            let msg = hex::decode(msghex).unwrap();
+            let sigbytes = hex::decode(sighex).unwrap();
+            let sig64: [u8; 64] = sigbytes.clone().try_into().unwrap();
+            if msg.len() == 32 {
+                assert_eq!(pubkey.VerifySchnorr(&H256::from_slice(&msg), &sigbytes), *result);
+            }
+            let mut xonly = secp256k1_xonly_pubkey::new();
+            if secp256k1_xonly_pubkey_parse(&ctx, &mut xonly, &pubkey.data()) != 0 {
+                assert_eq!(secp256k1_schnorrsig_verify(&ctx, &sig64, &msg, &xonly) != 0, *result);
+            } else {
+                assert!(!*result);
+            }
+
+            if sec.is_empty() {
+                continue;
+            }
+            let seckey: [u8; 32] = hex::decode(sec).unwrap().try_into().unwrap();
+            let aux_rand: [u8; 32] = hex::decode(aux).unwrap().try_into().unwrap();
+            let mut keypair = secp256k1_keypair::new();
+            assert_eq!(secp256k1_keypair_create(&mut ctx, &mut keypair, &seckey), 1);
+            let mut xonly = secp256k1_xonly_pubkey::new();
+            let mut ser = [0u8; 32];
+            assert_eq!(secp256k1_keypair_xonly_pub(&ctx, &mut xonly, None, &keypair), 1);
+            assert_eq!(secp256k1_xonly_pubkey_serialize(&ctx, &mut ser, &xonly), 1);
+            assert_eq!(ser, pubkey.data());
+            assert!(pubkey.IsFullyValid());
+
+            let mut sig64 = [0u8; 64];
+            if msg.len() == 32 {
+                assert_eq!(secp256k1_schnorrsig_sign32(&mut ctx, &mut sig64, msg[..].try_into().unwrap(), &keypair, Some(&aux_rand)), 1);
+                assert_eq!(sig64.to_vec(), sigbytes);
+            }
+            let extraparams = secp256k1_schnorrsig_extraparams { noncefp: None, ndata: Some(&aux_rand) };
+            sig64 = [0u8; 64];
+            assert_eq!(secp256k1_schnorrsig_sign_custom(&mut ctx, &mut sig64, &msg, &keypair, Some(&extraparams)), 1);
+            assert_eq!(sig64.to_vec(), sigbytes);
+        }
+
+        // public keys that are not on the curve or exceed the field size
+        assert!(!XOnlyPubKey::new(&hex::decode(BIP340_VECTORS[5].1).unwrap()).IsFullyValid());
+        assert!(!XOnlyPubKey::new(&hex::decode(BIP340_VECTORS[14].1).unwrap()).IsFullyValid());
+    }
+
+    #[test]
+    fn test_ellswift_decode() {
+        // BIP324 xswiftec test vector: u = 0, t = 0 decodes to this X coordinate
+        let ell = EllSwiftPubKey::new(&[0u8; 64]);
+        let pubkey = ell.Decode();
+        assert!(pubkey.IsFullyValid());
+        assert_eq!(pubkey.size(), CPubKey::COMPRESSED_SIZE);
+        assert_eq!(hex::encode(&pubkey.data()[1..]), "edd1fd3e327ce90cc7a3542614289aee9682003e9cf7dcc9cf2ca9743be5aa0c");
+        // the sign of t selects the parity of Y
+        assert_eq!(pubkey.data()[0], 2);
+    }
+}
@@ -3,74 +3,124 @@
 // Distributed under the MIT software license, see the accompanying
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
 
+use crate::crypto::chacha20::ChaCha20;
 use crate::crypto::sha512::CSHA512;
 use crate::crypto::sha256::CSHA256;
 use crate::time::GetTimeMicros;
 use crate::crypto::Hasher;
-use crate::crypto::common::ReadLE64;
+use crate::crypto::common::{CountBits, ReadLE64};
 use crate::logging::BCLog;
+use crate::randomenv::{RandAddDynamicEnv, RandAddStaticEnv};
 use crate::LogPrint;
-use rand::Rng;
-use std::sync::Mutex;
+use primitive_types::H256;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::sync::{LazyLock, Mutex};
 use std::mem::size_of_val;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
 use std::arch::asm;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_arch = "x86_64")]
+use crate::compat::cpuid::GetCPUID;
+use crate::LogPrintf;
 
+//static inline int64_t GetPerformanceCounter() noexcept
 fn GetPerformanceCounter() -> u64 {
-    let (low, high): (u32, u32);
-    #[cfg(target_arch = "x86_64")] unsafe {
-        asm!(
-            "rdtsc",
-            out("eax") low,
-            out("edx") high,
-        );
+    // Read the hardware time stamp counter when available.
+    // See https://en.wikipedia.org/wiki/Time_Stamp_Counter for more information.
+    #[cfg(target_arch = "x86_64")] {
+        let (low, high): (u32, u32);
+        unsafe {
+            asm!(
+                "rdtsc",
+                out("eax") low,
+                out("edx") high,
+                options(nomem, nostack),
+            );
+        }
+        ((high as u64) << 32) | (low as u64)
+    }
+    // On aarch64 the virtual counter register is readable from userspace.
+    #[cfg(target_arch = "aarch64")] {
+        let r1: u64;
+        unsafe {
+            asm!(
+                "mrs {r1}, cntvct_el0",
+                r1 = out(reg) r1,
+                options(nomem, nostack),
+            );
+        }
+        r1
+    }
+    // Fall back to using standard library clock (usually microsecond or nanosecond precision)
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))] {
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
     }
-    #[cfg(target_arch = "aarch64")] todo!();
-    ((high as u64) << 32) | (low as u64)
 }
 
-
-//#[cfg(feature = "getcpuid")]
-static g_rdrand_supported: bool = false;
-static g_rdseed_supported: bool = false;
-static CPUID_F1_ECX_RDRAND: u32 = 0x40000000;
-static CPUID_F7_EBX_RDSEED: u32 = 0x00040000;
-
-/* 
+#[cfg(target_arch = "x86_64")]
+static g_rdrand_supported: AtomicBool = AtomicBool::new(false);
+#[cfg(target_arch = "x86_64")]
+static g_rdseed_supported: AtomicBool = AtomicBool::new(false);
+#[cfg(target_arch = "x86_64")]
+const CPUID_F1_ECX_RDRAND: u32 = 0x40000000;
+#[cfg(target_arch = "x86_64")]
+const CPUID_F7_EBX_RDSEED: u32 = 0x00040000;
+
+#[cfg(target_arch = "x86_64")]
+//static void InitHardwareRand()
 fn InitHardwareRand()
 {
-    // TODO: implement GetCPUID
- /*    let (eax, ebx, ecx, edx) = (0u32, 0u32, 0u32, 0u32);
-    GetCPUID(1, 0, eax, ebx, ecx, edx);
-    if (ecx & CPUID_F1_ECX_RDRAND) {
-        g_rdrand_supported = true;
-    }
-    GetCPUID(7, 0, eax, ebx, ecx, edx);
-    if (ebx & CPUID_F7_EBX_RDSEED) {
-        g_rdseed_supported = true;
-    } */
-} */
-
-///
-///  Read 64 bits of entropy using rdrand.
-///
-/// Must only be called when RdRand is supported.
-///
+    let (mut eax, mut ebx, mut ecx, mut edx) = (0u32, 0u32, 0u32, 0u32);
+    GetCPUID(1, 0, &mut eax, &mut ebx, &mut ecx, &mut edx);
+    if ecx & CPUID_F1_ECX_RDRAND != 0 {
+        g_rdrand_supported.store(true, Ordering::Relaxed);
+    }
+    // RdSeed is reported in the structured extended feature leaf, which may not exist.
+    GetCPUID(0, 0, &mut eax, &mut ebx, &mut ecx, &mut edx);
+    if eax >= 7 {
+        GetCPUID(7, 0, &mut eax, &mut ebx, &mut ecx, &mut edx);
+        if ebx & CPUID_F7_EBX_RDSEED != 0 {
+            g_rdseed_supported.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+//static void ReportHardwareRand()
+fn ReportHardwareRand()
+{
+    // This must be done in a separate function, as InitHardwareRand() may be indirectly called
+    // from global constructors, before logging is initialized.
+    if g_rdseed_supported.load(Ordering::Relaxed) {
+        LogPrintf!("Using RdSeed as an additional entropy source\n");
+    }
+    if g_rdrand_supported.load(Ordering::Relaxed) {
+        LogPrintf!("Using RdRand as an additional entropy source\n");
+    }
+}
+
+/** Read 64 bits of entropy using rdrand.
+ *
+ * Must only be called when RdRand is supported.
+ */
+#[cfg(target_arch = "x86_64")]
+//static uint64_t GetRdRand() noexcept
 fn GetRdRand() -> u64 {
-    let mut ok: u8;
+    let mut ok: u8 = 0;
     let mut r1: u64 = 0;
 
     // RdRand may very rarely fail. Invoke it up to 10 times in a loop to reduce this risk.
-    for i in 0..10 {
-        #[cfg(target_arch = "x86_64")] unsafe {
+    for _ in 0..10 {
+        unsafe {
             asm!(
-            "rdrand rax",
-            "setc cl",
-            out("rax") r1,
-            out("cl") ok,
-            options(nostack, preserves_flags),
-        ); // rdseed %rax
+                "rdrand rax",
+                "setc cl",
+                out("rax") r1,
+                out("cl") ok,
+                options(nomem, nostack),
+            );
         }
-        #[cfg(target_arch = "aarch64")] todo!();
         if ok != 0 {
             break;
         }
@@ -78,34 +128,130 @@ fn GetRdRand() -> u64 {
     r1
 }
 
-/// Read 64 bits of entropy using rdseed.
-///
-/// Must only be called when RdSeed is supported.
-///
+/** Read 64 bits of entropy using rdseed.
+ *
+ * Must only be called when RdSeed is supported.
+ */
+#[cfg(target_arch = "x86_64")]
+//static uint64_t GetRdSeed() noexcept
 fn GetRdSeed() -> u64 {
     let mut ok: u8;
-    let mut r1: u64 = 0;
+    let mut r1: u64;
 
+    // RdSeed may fail when the HW RNG is overloaded. Loop indefinitely until enough entropy is gathered,
+    // but pause after every failure.
     loop {
-        #[cfg(target_arch = "x86_64")] unsafe {
+        unsafe {
             asm!(
-            "rdseed rax",
-            "setc cl",
-            out("rax") r1,
-            out("cl") ok,
-            options(nostack, preserves_flags),
-        ); // rdseed %rax
+                "rdseed rax",
+                "setc cl",
+                out("rax") r1,
+                out("cl") ok,
+                options(nomem, nostack),
+            );
         }
-        #[cfg(target_arch = "aarch64")] todo!();
         if ok != 0 {
             break;
         }
+        std::hint::spin_loop(); // pause
     }
     r1
 }
 
-struct RNGState {
-    m_mutex: Mutex<()>,
+#[cfg(target_arch = "aarch64")]
+static g_rndr_supported: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_arch = "aarch64")]
+//static void InitHardwareRand()
+fn InitHardwareRand()
+{
+    // Equivalent to checking getauxval(AT_HWCAP2) & HWCAP2_RNG.
+    if std::arch::is_aarch64_feature_detected!("rand") {
+        g_rndr_supported.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+//static void ReportHardwareRand()
+fn ReportHardwareRand()
+{
+    // This must be done in a separate function, as InitHardwareRand() may be indirectly called
+    // from global constructors, before logging is initialized.
+    if g_rndr_supported.load(Ordering::Relaxed) {
+        LogPrintf!("Using RNDR and RNDRRS as additional entropy sources\n");
+    }
+}
+
+/** Read 64 bits of entropy using rndr.
+ *
+ * Must only be called when RNDR is supported.
+ */
+#[cfg(target_arch = "aarch64")]
+//static uint64_t GetRNDR() noexcept
+fn GetRNDR() -> u64 {
+    let mut ok: u32;
+    let mut r1: u64;
+
+    loop {
+        // https://developer.arm.com/documentation/ddi0601/2022-12/AArch64-Registers/RNDR--Random-Number
+        unsafe {
+            asm!(
+                "mrs {r1}, s3_3_c2_c4_0",
+                "cset {ok:w}, ne",
+                r1 = out(reg) r1,
+                ok = out(reg) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 {
+            break;
+        }
+        std::hint::spin_loop(); // yield
+    }
+    r1
+}
+
+/** Read 64 bits of entropy using rndrrs.
+ *
+ * Must only be called when RNDRRS is supported.
+ */
+#[cfg(target_arch = "aarch64")]
+//static uint64_t GetRNDRRS() noexcept
+fn GetRNDRRS() -> u64 {
+    let mut ok: u32;
+    let mut r1: u64;
+
+    loop {
+        // https://developer.arm.com/documentation/ddi0601/2022-12/AArch64-Registers/RNDRRS--Reseeded-Random-Number
+        unsafe {
+            asm!(
+                "mrs {r1}, s3_3_c2_c4_1",
+                "cset {ok:w}, ne",
+                r1 = out(reg) r1,
+                ok = out(reg) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 {
+            break;
+        }
+        std::hint::spin_loop(); // yield
+    }
+    r1
+}
+
+/* Access to other hardware random number generators could be added here later,
+ * assuming it is sufficiently fast (in the order of a few hundred CPU cycles).
+ * Slower sources should probably be invoked separately, and/or only from
+ * RandAddPeriodic (which is called once a minute).
+ */
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn InitHardwareRand() {}
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn ReportHardwareRand() {}
+
+/** The part of RNGState that is guarded by m_mutex. */
+struct RNGStateInner {
     /* The RNG state consists of 256 bits of entropy, taken from the output of
      * one operation's SHA512 output, and fed as input to the next one.
      * Carrying 256 bits of entropy should be sufficient to guarantee
@@ -117,59 +263,54 @@ struct RNGState {
     m_state: [u8; 32],
     m_counter: u64,
     m_strongly_seeded: bool,
-    m_events_mutex: Mutex<()>,
-    m_events_hasher: CSHA256,
+}
+
+struct RNGState {
+    m_mutex: Mutex<RNGStateInner>,
+    /* The events hasher is guarded by its own mutex, m_events_mutex. */
+    m_events_mutex: Mutex<CSHA256>,
 }
 
 impl RNGState {
 
     pub fn new() -> Self
     {
-        //InitHardwareRand();
+        InitHardwareRand();
         Self {
-            m_counter: 0,
-            m_mutex: Mutex::new(()),
-            m_strongly_seeded: false,
-            m_state: [0; 32],
-            m_events_mutex: Mutex::new(()),
-            m_events_hasher: CSHA256::new()
+            m_mutex: Mutex::new(RNGStateInner { m_state: [0; 32], m_counter: 0, m_strongly_seeded: false }),
+            m_events_mutex: Mutex::new(CSHA256::new()),
         }
     }
 
-
     //void AddEvent(uint32_t event_info) noexcept EXCLUSIVE_LOCKS_REQUIRED(!m_events_mutex)
-    pub fn AddEvent(&mut self, event_info: u32)
+    pub fn AddEvent(&self, event_info: u32)
     {
-        let _m = self.m_events_mutex.lock().unwrap();
+        let mut m_events_hasher = self.m_events_mutex.lock().unwrap();
 
-        self.m_events_hasher.Write(
-            &event_info.to_le_bytes()[..],
-            size_of_val(&event_info)
-        );
+        m_events_hasher.Write(&event_info.to_le_bytes()[..], size_of_val(&event_info));
         // Get the low four bytes of the performance counter. This translates to roughly the
         // subsecond part.
-        let perfcounter = GetPerformanceCounter() & 0xffffffff;
-        self.m_events_hasher.Write(&perfcounter.to_le_bytes()[..], size_of_val(&perfcounter));
+        let perfcounter = (GetPerformanceCounter() & 0xffffffff) as u32;
+        m_events_hasher.Write(&perfcounter.to_le_bytes()[..], size_of_val(&perfcounter));
     }
 
     /**
      * Feed (the hash of) all events added through AddEvent() to hasher.
      */
     //void SeedEvents(CSHA512& hasher) noexcept EXCLUSIVE_LOCKS_REQUIRED(!m_events_mutex)
-    pub fn SeedEvents(&mut self, hasher: &mut CSHA512)
+    pub fn SeedEvents(&self, hasher: &mut CSHA512)
     {
         // We use only SHA256 for the events hashing to get the ASM speedups we have for SHA256,
         // since we want it to be fast as network peers may be able to trigger it repeatedly.
-        let _m = self.m_events_mutex.lock().unwrap();
+        let mut m_events_hasher = self.m_events_mutex.lock().unwrap();
 
         let mut events_hash: [u8; 32] = [0; 32];
-        self.m_events_hasher.Finalize(&mut events_hash);
-        //hasher.Write(events_hash, 32);
-        hasher.Write(&mut events_hash[..], 32);
+        m_events_hasher.Finalize(&mut events_hash);
+        hasher.Write(&events_hash[..], 32);
 
         // Re-initialize the hasher with the finalized state to use later.
-        self.m_events_hasher.Reset();
-        self.m_events_hasher.Write(&events_hash[..], 32);
+        m_events_hasher.Reset();
+        m_events_hasher.Write(&events_hash[..], 32);
     }
 
     /** Extract up to 32 bytes of entropy from the RNG state, mixing in new entropy from hasher.
@@ -177,148 +318,167 @@ impl RNGState {
      * If this function has never been called with strong_seed = true, false is returned.
      */
     //bool MixExtract(unsigned char* out, size_t num, CSHA512&& hasher, bool strong_seed) noexcept EXCLUSIVE_LOCKS_REQUIRED(!m_mutex)
-    pub fn MixExtract(&mut self, out: &mut [u8], num: usize, hasher: &mut CSHA512, strong_seed: bool) -> bool
+    pub fn MixExtract(&self, out: &mut [u8], num: usize, mut hasher: CSHA512, strong_seed: bool) -> bool
     {
         assert!(num <= 32);
         let mut buf: [u8; 64] = [0; 64];
         let ret: bool;
 
         {
-            let _m = self.m_events_mutex.lock().unwrap();
-            self.m_strongly_seeded |= strong_seed;
-            ret = self.m_strongly_seeded;
+            let mut inner = self.m_mutex.lock().unwrap();
+            inner.m_strongly_seeded |= strong_seed;
+            ret = inner.m_strongly_seeded;
             // Write the current state of the RNG into the hasher
-            hasher.Write(&mut self.m_state, 32);
+            hasher.Write(&inner.m_state, 32);
             // Write a new counter number into the state
-            let mut counter_bytes = self.m_counter.to_le_bytes();
-            hasher.Write(&mut counter_bytes, size_of_val(&self.m_counter));
-            self.m_counter = ReadLE64(&counter_bytes[..]);
-            self.m_counter += 1;
+            hasher.Write(&inner.m_counter.to_le_bytes(), size_of_val(&inner.m_counter));
+            inner.m_counter += 1;
             // Finalize the hasher
             hasher.Finalize(&mut buf[..]);
             // Store the last 32 bytes of the hash output as new RNG state.
             //memcpy(m_state, buf + 32, 32);
-            self.m_state.copy_from_slice(&buf[32..64]);
+            inner.m_state.copy_from_slice(&buf[32..64]);
         }
         // If desired, copy (up to) the first 32 bytes of the hash output as output.
         if num > 0 {
             //memcpy(out, buf, num);
-            out.copy_from_slice(&buf[..num]);
+            out[..num].copy_from_slice(&buf[..num]);
         }
         // Best effort cleanup of internal state
         hasher.Reset();
         //memory_cleanse(buf, 64);
-        buf = [0; 64];
+        buf.fill(0);
 
-        return ret;
+        ret
     }
 }
 
+//RNGState& GetRNGState() noexcept
+fn GetRNGState() -> &'static RNGState
+{
+    // This idiom relies on the guarantee that static variable are initialized
+    // on first call, even when multiple parallel calls are permitted.
+    static g_rng: LazyLock<RNGState> = LazyLock::new(RNGState::new);
+    &g_rng
+}
+
 enum RNGLevel {
     FAST, // Automatically called by GetRandBytes
     SLOW, // Automatically called by GetStrongRandBytes
     PERIODIC, // Called by RandAddPeriodic()
 }
 
-fn GetOSRand(buffer: &mut [u8])
+/** Number of random bytes returned by GetOSRand.
+ * When changing this constant make sure to change all call sites, and make
+ * sure that the underlying OS APIs for all platforms support the number.
+ * (many cap out at 256 bytes).
+ */
+pub const NUM_OS_RANDOM_BYTES: usize = 32;
+
+/** Get 32 bytes of system entropy. Do not use this in application code: use
+ * GetStrongRandBytes instead.
+ */
+//void GetOSRand(unsigned char* ent32);
+fn GetOSRand(ent32: &mut [u8])
 {
-    let mut rng = rand::thread_rng();
-    rng.fill(buffer);
+    OsRng.fill_bytes(&mut ent32[..NUM_OS_RANDOM_BYTES]);
 }
 
 //static void SeedTimestamp(CSHA512& hasher) noexcept
 fn SeedTimestamp(hasher: &mut CSHA512)
 {
     let perfcounter = GetPerformanceCounter();
-    hasher.Write(&mut perfcounter.to_le_bytes(), size_of_val(&perfcounter));
+    hasher.Write(&perfcounter.to_le_bytes(), size_of_val(&perfcounter));
 }
 
 /** Add 64 bits of entropy gathered from hardware to hasher. Do nothing if not supported. */
-/* static void SeedHardwareFast(CSHA512& hasher) noexcept {
-    #if defined(__x86_64__) || defined(__amd64__) || defined(__i386__)
-        if (g_rdrand_supported) {
-            uint64_t out = GetRdRand();
-            hasher.Write((const unsigned char*)&out, sizeof(out));
-            return;
-        }
-    #endif
-} */
-
+//static void SeedHardwareFast(CSHA512& hasher) noexcept
 fn SeedHardwareFast(hasher: &mut CSHA512)
 {
-    let mut buffer:[u8; 8] = [0; 8];
-    let len = buffer.len();
-
-    hasher.Write(&mut buffer[..], len);
+    #[cfg(target_arch = "x86_64")]
+    if g_rdrand_supported.load(Ordering::Relaxed) {
+        let out = GetRdRand();
+        hasher.Write(&out.to_le_bytes(), size_of_val(&out));
+    }
+    #[cfg(target_arch = "aarch64")]
+    if g_rndr_supported.load(Ordering::Relaxed) {
+        let out = GetRNDR();
+        hasher.Write(&out.to_le_bytes(), size_of_val(&out));
+    }
 }
 
 /** Add 256 bits of entropy gathered from hardware to hasher. Do nothing if not supported. */
 //static void SeedHardwareSlow(CSHA512& hasher) noexcept {
 fn SeedHardwareSlow(hasher: &mut CSHA512) {
-    // When we want 256 bits of entropy, prefer RdSeed over RdRand, as it's
-    // guaranteed to produce independent randomness on every call.
-    if g_rdseed_supported {
-        for i in [0..4] {
-            let mut out = GetRdSeed();
-            hasher.Write(&mut out.to_le_bytes(), size_of_val(&out));
+    #[cfg(target_arch = "x86_64")] {
+        // When we want 256 bits of entropy, prefer RdSeed over RdRand, as it's
+        // guaranteed to produce independent randomness on every call.
+        if g_rdseed_supported.load(Ordering::Relaxed) {
+            for _ in 0..4 {
+                let out = GetRdSeed();
+                hasher.Write(&out.to_le_bytes(), size_of_val(&out));
+            }
+            return;
+        }
+        // When falling back to RdRand, XOR the result of 1024 results.
+        // This guarantees a reseeding occurs between each.
+        if g_rdrand_supported.load(Ordering::Relaxed) {
+            for _ in 0..4 {
+                let mut out: u64 = 0;
+                for _ in 0..1024 { out ^= GetRdRand(); }
+                hasher.Write(&out.to_le_bytes(), size_of_val(&out));
+            }
         }
-        return;
-    }
-    // When falling back to RdRand, XOR the result of 1024 results.
-    // This guarantees a reseeding occurs between each.
-    if g_rdrand_supported {
-        for i in [0..4] {
-            let mut out: u64 = 0;
-            for j in [0..1024] { out ^= GetRdRand(); }
-            hasher.Write(&mut out.to_le_bytes(), size_of_val(&out));
+    }
+    #[cfg(target_arch = "aarch64")]
+    if g_rndr_supported.load(Ordering::Relaxed) {
+        for _ in 0..4 {
+            let out = GetRNDRRS();
+            hasher.Write(&out.to_le_bytes(), size_of_val(&out));
         }
-        return;
     }
 }
-    
-    /** Use repeated SHA512 to strengthen the randomness in seed32, and feed into hasher. */
-    //static void Strengthen(const unsigned char (&seed)[32], int microseconds, CSHA512& hasher) noexcept
-    fn Strengthen(seed: &mut [u8; 32], microseconds: i32, hasher: &mut CSHA512)
-    {
-        let mut inner_hasher = CSHA512::new();
-        let len = seed.len();
-        inner_hasher.Write(&mut seed[..], len);
-    
-        // Hash loop
-        let mut buffer:[u8; 64] = [0; 64];
-        let stop: i64 = GetTimeMicros() + microseconds as i64;
-        loop {
-            for _i in [0..1000] {
-                inner_hasher.Finalize(&mut buffer[..]);
-                inner_hasher.Reset();
-                let len = buffer.len();
-                inner_hasher.Write(&mut buffer[..], len);
-            }
-            // Benchmark operation and feed it into outer hasher.
-            let mut perf = GetPerformanceCounter();
-            hasher.Write(&mut perf.to_le_bytes(), size_of_val(&perf));
-            if GetTimeMicros() < stop { break; }
+
+/** Use repeated SHA512 to strengthen the randomness in seed32, and feed into hasher. */
+//static void Strengthen(const unsigned char (&seed)[32], int microseconds, CSHA512& hasher) noexcept
+fn Strengthen(seed: &[u8; 32], microseconds: i32, hasher: &mut CSHA512)
+{
+    let mut inner_hasher = CSHA512::new();
+    inner_hasher.Write(&seed[..], seed.len());
+
+    // Hash loop
+    let mut buffer:[u8; 64] = [0; 64];
+    let stop: i64 = GetTimeMicros() + microseconds as i64;
+    loop {
+        for _ in 0..1000 {
+            inner_hasher.Finalize(&mut buffer[..]);
+            inner_hasher.Reset();
+            inner_hasher.Write(&buffer[..], buffer.len());
         }
-    
-        // Produce output from inner state and feed it to outer hasher.
-        inner_hasher.Finalize(&mut buffer[..]);
-        let len = buffer.len();
-        hasher.Write(&mut buffer[..], len);
-        // Try to clean up.
-        inner_hasher.Reset();
-        //memory_cleanse(buffer, sizeof(buffer));
-        buffer = [0; 64];
+        // Benchmark operation and feed it into outer hasher.
+        let perf = GetPerformanceCounter();
+        hasher.Write(&perf.to_le_bytes(), size_of_val(&perf));
+        if GetTimeMicros() >= stop { break; }
     }
 
+    // Produce output from inner state and feed it to outer hasher.
+    inner_hasher.Finalize(&mut buffer[..]);
+    hasher.Write(&buffer[..], buffer.len());
+    // Try to clean up.
+    inner_hasher.Reset();
+    //memory_cleanse(buffer, sizeof(buffer));
+    buffer.fill(0);
+}
 
+//static void SeedFast(CSHA512& hasher) noexcept
 fn SeedFast(hasher: &mut CSHA512)
 {
-    let mut buffer:[u8; 32] = [0; 32];
+    let buffer:[u8; 32] = [0; 32];
 
     // Stack pointer to indirectly commit to thread/callstack
     //const unsigned char* ptr = buffer;
-    let len = buffer.len();
-    hasher.Write(&mut buffer[..], len);
+    let ptr = buffer.as_ptr() as usize;
+    hasher.Write(&ptr.to_le_bytes(), size_of_val(&ptr));
 
     // Hardware randomness is very fast when available; use it always.
     SeedHardwareFast(hasher);
@@ -327,7 +487,8 @@ fn SeedFast(hasher: &mut CSHA512)
     SeedTimestamp(hasher);
 }
 
-fn SeedSlow(hasher: &mut CSHA512, rng: &mut RNGState)
+//static void SeedSlow(CSHA512& hasher, RNGState& rng) noexcept
+fn SeedSlow(hasher: &mut CSHA512, rng: &RNGState)
 {
     let mut buffer:[u8; 32] = [0; 32];
 
@@ -336,9 +497,7 @@ fn SeedSlow(hasher: &mut CSHA512, rng: &mut RNGState)
 
     // OS randomness
     GetOSRand(&mut buffer[..]);
-
-    let len = buffer.len();
-    hasher.Write(&mut buffer[..], len);
+    hasher.Write(&buffer[..], buffer.len());
 
     // Add the events hasher into the mix
     rng.SeedEvents(hasher);
@@ -352,19 +511,17 @@ fn SeedSlow(hasher: &mut CSHA512, rng: &mut RNGState)
 
 /** Extract entropy from rng, strengthen it, and feed it into hasher. */
 //static void SeedStrengthen(CSHA512& hasher, RNGState& rng, int microseconds) noexcept
-fn SeedStrengthen(hasher: &mut CSHA512, rng: &mut RNGState, microseconds: i32)
+fn SeedStrengthen(hasher: &mut CSHA512, rng: &RNGState, microseconds: i32)
 {
     // Generate 32 bytes of entropy from the RNG, and a copy of the entropy already in hasher.
     let mut strengthen_seed:[u8; 32] = [0; 32];
-    let len = strengthen_seed.len();
-    rng.MixExtract(&mut strengthen_seed, len, hasher, false);
-    
+    rng.MixExtract(&mut strengthen_seed, 32, hasher.clone(), false);
     // Strengthen the seed, and feed it into hasher.
-    Strengthen(&mut strengthen_seed, microseconds, hasher);
+    Strengthen(&strengthen_seed, microseconds, hasher);
 }
 
 //static void SeedPeriodic(CSHA512& hasher, RNGState& rng) noexcept
-fn SeedPeriodic(hasher: &mut CSHA512, rng: &mut RNGState)
+fn SeedPeriodic(hasher: &mut CSHA512, rng: &RNGState)
 {
     // Everything that the 'fast' seeder includes
     SeedFast(hasher);
@@ -377,8 +534,7 @@ fn SeedPeriodic(hasher: &mut CSHA512, rng: &mut RNGState)
 
     // Dynamic environment data (performance monitoring, ...)
     let old_size = hasher.Size();
-    // TODO:
-    //RandAddDynamicEnv(hasher);
+    RandAddDynamicEnv(hasher);
     LogPrint!(BCLog::RAND, "Feeding {} bytes of dynamic environment data into RNG\n", hasher.Size() - old_size);
 
     // Strengthen for 10 ms
@@ -386,7 +542,7 @@ fn SeedPeriodic(hasher: &mut CSHA512, rng: &mut RNGState)
 }
 
 //static void SeedStartup(CSHA512& hasher, RNGState& rng) noexcept
-fn SeedStartup(hasher: &mut CSHA512, rng: &mut RNGState)
+fn SeedStartup(hasher: &mut CSHA512, rng: &RNGState)
 {
     // Gather 256 bits of hardware randomness, if available
     SeedHardwareSlow(hasher);
@@ -396,40 +552,458 @@ fn SeedStartup(hasher: &mut CSHA512, rng: &mut RNGState)
 
     // Dynamic environment data (performance monitoring, ...)
     let old_size = hasher.Size();
-    // TODO:
-    //RandAddDynamicEnv(hasher);
+    RandAddDynamicEnv(hasher);
 
     // Static environment data
-    // TODO:
-    //RandAddStaticEnv(hasher);
+    RandAddStaticEnv(hasher);
     LogPrint!(BCLog::RAND, "Feeding {} bytes of environment data into RNG\n", hasher.Size() - old_size);
 
     // Strengthen for 100 ms
     SeedStrengthen(hasher, rng, 100000);
 }
 
+//static void ProcRand(unsigned char* out, int num, RNGLevel level) noexcept
 fn ProcRand(out: &mut [u8], num: usize, level: RNGLevel)
 {
     // Make sure the RNG is initialized first (as all Seed* function possibly need hwrand to be available).
-    let mut rng = RNGState::new();
+    let rng = GetRNGState();
 
-    assert!(!num <= 32);
+    assert!(num <= 32);
 
     let mut hasher: CSHA512 = CSHA512::new();
     match level {
         RNGLevel::FAST => SeedFast(&mut hasher),
-        RNGLevel::SLOW => SeedSlow(&mut hasher, &mut rng),
-        RNGLevel::PERIODIC => SeedPeriodic(&mut hasher, &mut rng),
+        RNGLevel::SLOW => SeedSlow(&mut hasher, rng),
+        RNGLevel::PERIODIC => SeedPeriodic(&mut hasher, rng),
     }
 
     // Combine with and update state
-    if !rng.MixExtract(out, num, &mut hasher, false) {
+    if !rng.MixExtract(out, num, hasher, false) {
         // On the first invocation, also seed with SeedStartup().
         let mut startup_hasher: CSHA512 = CSHA512::new();
-        SeedStartup(&mut startup_hasher, &mut rng);
-        rng.MixExtract(out, num, &mut startup_hasher, true);
+        SeedStartup(&mut startup_hasher, rng);
+        rng.MixExtract(out, num, startup_hasher, true);
     }
 }
 
+/**
+ * Generate random data via the internal PRNG.
+ *
+ * These functions are designed to be fast (sub microsecond), but do not necessarily
+ * meaningfully add entropy to the PRNG state.
+ *
+ * Thread-safe.
+ */
+//void GetRandBytes(Span<unsigned char> bytes) noexcept { ProcRand(bytes.data(), bytes.size(), RNGLevel::FAST); }
+pub fn GetRandBytes(bytes: &mut [u8]) { ProcRand(bytes, bytes.len(), RNGLevel::FAST); }
+
+/**
+ * Gather entropy from various sources, feed it into the internal PRNG, and
+ * generate random data using it.
+ *
+ * This function will cause failure whenever the OS RNG fails.
+ *
+ * Thread-safe.
+ */
 // void GetStrongRandBytes(Span<unsigned char> bytes) noexcept { ProcRand(bytes.data(), bytes.size(), RNGLevel::SLOW); }
-pub fn GetStrongRandBytes(bytes: &mut [u8]) { ProcRand(bytes, bytes.len(), RNGLevel::SLOW); }
\ No newline at end of file
+pub fn GetStrongRandBytes(bytes: &mut [u8]) { ProcRand(bytes, bytes.len(), RNGLevel::SLOW); }
+
+/**
+ * Gather entropy from various expensive sources, and feed them to the PRNG state.
+ *
+ * Thread-safe.
+ */
+//void RandAddPeriodic() noexcept { ProcRand(nullptr, 0, RNGLevel::PERIODIC); }
+pub fn RandAddPeriodic() { ProcRand(&mut [], 0, RNGLevel::PERIODIC); }
+
+/**
+ * Gathers entropy from the low bits of the time at which events occur. Should
+ * be called with a uint32_t describing the event at the time an event occurs.
+ *
+ * Thread-safe.
+ */
+//void RandAddEvent(const uint32_t event_info) noexcept { GetRNGState().AddEvent(event_info); }
+pub fn RandAddEvent(event_info: u32) { GetRNGState().AddEvent(event_info); }
+
+/** Generate a uniform random integer in the range [0..range). Precondition: range > 0 */
+//uint64_t GetRandInternal(uint64_t nMax) noexcept
+fn GetRandInternal(nMax: u64) -> u64
+{
+    FastRandomContext::new(false).randrange(nMax)
+}
+
+/** Generate a uniform random integer of type T in the range [0..nMax)
+ *  nMax defaults to std::numeric_limits<T>::max()
+ *  Precondition: nMax > 0, T is an unsigned integer type of at most 64 bits
+ */
+//template<typename T> T GetRand(T nMax = std::numeric_limits<T>::max()) noexcept
+pub fn GetRand<T>(nMax: T) -> T
+where
+    T: Into<u64> + TryFrom<u64>,
+    <T as TryFrom<u64>>::Error: std::fmt::Debug,
+{
+    T::try_from(GetRandInternal(nMax.into())).unwrap()
+}
+
+//uint256 GetRandHash() noexcept
+pub fn GetRandHash() -> H256
+{
+    let mut hash = H256::zero();
+    GetRandBytes(hash.as_bytes_mut());
+    hash
+}
+
+/**
+ * Fast randomness source. This is seeded once with secure random data, but
+ * is completely deterministic and does not gather more entropy after that.
+ *
+ * This class is not thread-safe.
+ */
+pub struct FastRandomContext {
+    requires_seed: bool,
+    rng: ChaCha20,
+
+    bitbuf: u64,
+    bitbuf_size: u32,
+}
+
+impl FastRandomContext {
+    /** Construct a FastRandomContext. If fDeterministic, it is seeded with an all-zero key. */
+    //explicit FastRandomContext(bool fDeterministic = false) noexcept;
+    pub fn new(fDeterministic: bool) -> Self {
+        // Note that despite always initializing with ZERO_KEY, requires_seed is set to true if not
+        // fDeterministic. That means the rng will be reinitialized with a secure random key upon first
+        // use.
+        Self { requires_seed: !fDeterministic, rng: ChaCha20::new(&[0; ChaCha20::KEYLEN]), bitbuf: 0, bitbuf_size: 0 }
+    }
+
+    /** Initialize with explicit seed (only for testing) */
+    //explicit FastRandomContext(const uint256& seed) noexcept;
+    pub fn from_seed(seed: &H256) -> Self {
+        Self { requires_seed: false, rng: ChaCha20::new(seed.as_bytes()), bitbuf: 0, bitbuf_size: 0 }
+    }
+
+    //void RandomSeed();
+    fn RandomSeed(&mut self)
+    {
+        let seed = GetRandHash();
+        self.rng.SetKey(seed.as_bytes());
+        self.requires_seed = false;
+    }
+
+    //void FillBitBuffer()
+    fn FillBitBuffer(&mut self)
+    {
+        self.bitbuf = self.rand64();
+        self.bitbuf_size = 64;
+    }
+
+    /** Generate a random 64-bit integer. */
+    //uint64_t rand64() noexcept
+    pub fn rand64(&mut self) -> u64
+    {
+        if self.requires_seed {
+            self.RandomSeed();
+        }
+        let mut buf = [0u8; 8];
+        self.rng.Keystream(&mut buf);
+        ReadLE64(&buf)
+    }
+
+    /** Generate a random (bits)-bit integer. */
+    //uint64_t randbits(int bits) noexcept
+    pub fn randbits(&mut self, bits: u32) -> u64
+    {
+        if bits == 0 {
+            0
+        } else if bits > 32 {
+            self.rand64() >> (64 - bits)
+        } else {
+            if self.bitbuf_size < bits {
+                self.FillBitBuffer();
+            }
+            let ret = self.bitbuf & (!0u64 >> (64 - bits));
+            self.bitbuf >>= bits;
+            self.bitbuf_size -= bits;
+            ret
+        }
+    }
+
+    /** Generate a random integer in the range [0..range).
+     * Precondition: range > 0.
+     */
+    //uint64_t randrange(uint64_t range) noexcept
+    pub fn randrange(&mut self, mut range: u64) -> u64
+    {
+        assert!(range > 0);
+        range -= 1;
+        let bits = CountBits(range);
+        loop {
+            let ret = self.randbits(bits);
+            if ret <= range {
+                return ret;
+            }
+        }
+    }
+
+    /** Generate random bytes. */
+    //std::vector<B> randbytes(size_t len);
+    pub fn randbytes(&mut self, len: usize) -> Vec<u8>
+    {
+        let mut ret = vec![0u8; len];
+        self.fillrand(&mut ret);
+        ret
+    }
+
+    /** Fill a byte Span with random bytes. */
+    //void fillrand(Span<std::byte> output);
+    pub fn fillrand(&mut self, output: &mut [u8])
+    {
+        if self.requires_seed {
+            self.RandomSeed();
+        }
+        self.rng.Keystream(output);
+    }
+
+    /** Generate a random 32-bit integer. */
+    //uint32_t rand32() noexcept { return randbits(32); }
+    pub fn rand32(&mut self) -> u32 { self.randbits(32) as u32 }
+
+    /** generate a random uint256. */
+    //uint256 rand256() noexcept;
+    pub fn rand256(&mut self) -> H256
+    {
+        let mut ret = H256::zero();
+        self.fillrand(ret.as_bytes_mut());
+        ret
+    }
+
+    /** Generate a random boolean. */
+    //bool randbool() noexcept { return randbits(1); }
+    pub fn randbool(&mut self) -> bool { self.randbits(1) != 0 }
+}
+
+/** More efficient than using std::shuffle on a FastRandomContext.
+ *
+ * This is more efficient as std::shuffle will consume entropy in groups of
+ * 64 bits at the time and throw away most.
+ *
+ * This also works around a bug in libstdc++ std::shuffle that may cause
+ * type::operator=(type&&) to be invoked on itself, which the library's
+ * debug mode detects and panics on. This is a known issue, see
+ * https://stackoverflow.com/questions/22915325/avoiding-self-assignment-in-stdshuffle
+ */
+//template <typename I, typename R> void Shuffle(I first, I last, R&& rng)
+pub fn Shuffle<T>(v: &mut [T], rng: &mut FastRandomContext)
+{
+    for first in 0..v.len() {
+        let j = rng.randrange((v.len() - first) as u64) as usize;
+        if j > 0 {
+            v.swap(first, first + j);
+        }
+    }
+}
+
+/** Check that OS randomness is available and returning the requested number
+ * of bytes.
+ */
+//bool Random_SanityCheck()
+pub fn Random_SanityCheck() -> bool
+{
+    let start = GetPerformanceCounter();
+
+    /* This does not measure the quality of randomness, but it does test that
+     * GetOSRand() overwrites all 32 bytes of the output given a maximum
+     * number of tries.
+     */
+    const MAX_TRIES: i32 = 1024;
+    let mut data = [0u8; NUM_OS_RANDOM_BYTES];
+    let mut overwritten = [false; NUM_OS_RANDOM_BYTES]; /* Tracks which bytes have been overwritten at least once */
+    let mut num_overwritten;
+    let mut tries = 0;
+    /* Loop until all bytes have been overwritten at least once, or max number tries reached */
+    loop {
+        data.fill(0);
+        GetOSRand(&mut data);
+        for x in 0..NUM_OS_RANDOM_BYTES {
+            overwritten[x] |= data[x] != 0;
+        }
+
+        num_overwritten = overwritten.iter().filter(|&&o| o).count();
+        tries += 1;
+        if num_overwritten >= NUM_OS_RANDOM_BYTES || tries >= MAX_TRIES {
+            break;
+        }
+    }
+    if num_overwritten != NUM_OS_RANDOM_BYTES {
+        return false; /* If this failed, bailed out after too many tries */
+    }
+
+    // Check that GetPerformanceCounter increases at least during a GetOSRand() call + 1ms sleep.
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    let stop = GetPerformanceCounter();
+    if stop == start {
+        return false;
+    }
+
+    // We called GetPerformanceCounter. Use it as entropy.
+    let mut to_add = CSHA512::new();
+    to_add.Write(&start.to_le_bytes(), size_of_val(&start));
+    to_add.Write(&stop.to_le_bytes(), size_of_val(&stop));
+    GetRNGState().MixExtract(&mut [], 0, to_add, false);
+
+    true
+}
+
+/**
+ * Initialize global RNG state, log any CPU features that are used and run
+ * Random_SanityCheck, panicking if the OS RNG or the clock is broken.
+ *
+ * Calling this function is optional. RNG state will be initialized when first
+ * needed if it is not called.
+ */
+//void RandomInit()
+pub fn RandomInit()
+{
+    RandomInitWithCheck(Random_SanityCheck);
+}
+
+fn RandomInitWithCheck(sanity_check: fn() -> bool)
+{
+    // Invoke RNG code to trigger initialization (if not already performed)
+    ProcRand(&mut [], 0, RNGLevel::FAST);
+
+    ReportHardwareRand();
+
+    // Core runs this from AppInitSanityChecks; do it here so startup fails loudly.
+    assert!(sanity_check(), "OS cryptographic RNG sanity check failure. Aborting.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osrandom() {
+        assert!(Random_SanityCheck());
+    }
+
+    #[test]
+    fn test_random_init() {
+        static CHECKED: AtomicBool = AtomicBool::new(false);
+        fn check() -> bool {
+            CHECKED.store(true, Ordering::Relaxed);
+            Random_SanityCheck()
+        }
+        RandomInitWithCheck(check);
+        assert!(CHECKED.load(Ordering::Relaxed));
+        RandomInit();
+    }
+
+    #[test]
+    #[should_panic(expected = "OS cryptographic RNG sanity check failure")]
+    fn test_random_init_failing_check() {
+        RandomInitWithCheck(|| false);
+    }
+
+    #[test]
+    fn test_hardware_rand() {
+        InitHardwareRand();
+        #[cfg(target_arch = "x86_64")]
+        {
+            assert_eq!(g_rdrand_supported.load(Ordering::Relaxed), std::is_x86_feature_detected!("rdrand"));
+            assert_eq!(g_rdseed_supported.load(Ordering::Relaxed), std::is_x86_feature_detected!("rdseed"));
+            if g_rdrand_supported.load(Ordering::Relaxed) {
+                assert_ne!(GetRdRand(), GetRdRand());
+            }
+            if g_rdseed_supported.load(Ordering::Relaxed) {
+                assert_ne!(GetRdSeed(), GetRdSeed());
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            assert_eq!(g_rndr_supported.load(Ordering::Relaxed), std::arch::is_aarch64_feature_detected!("rand"));
+            if g_rndr_supported.load(Ordering::Relaxed) {
+                assert_ne!(GetRNDR(), GetRNDR());
+                assert_ne!(GetRNDRRS(), GetRNDRRS());
+            }
+        }
+    }
+
+    #[test]
+    fn test_fastrandom_deterministic() {
+        // Check that deterministic FastRandomContexts are deterministic
+        let mut ctx1 = FastRandomContext::new(true);
+        let mut ctx2 = FastRandomContext::new(true);
+        // The first output is the all-zero key ChaCha20 keystream of RFC 8439 A.1.
+        assert_eq!(ctx1.rand64(), 0x903df1a0ade0b876);
+        assert_eq!(ctx2.rand64(), 0x903df1a0ade0b876);
+
+        let mut ctx1 = FastRandomContext::new(true);
+        let mut ctx2 = FastRandomContext::new(true);
+        assert_eq!(ctx1.rand32(), ctx2.rand32());
+        assert_eq!(ctx1.rand32(), ctx2.rand32());
+        assert_eq!(ctx1.rand64(), ctx2.rand64());
+        assert_eq!(ctx1.randbits(3), ctx2.randbits(3));
+        assert_eq!(ctx1.randbytes(17), ctx2.randbytes(17));
+        assert_eq!(ctx1.rand256(), ctx2.rand256());
+        assert_eq!(ctx1.randbits(7), ctx2.randbits(7));
+        assert_eq!(ctx1.randbytes(128), ctx2.randbytes(128));
+        assert_eq!(ctx1.rand32(), ctx2.rand32());
+        assert_eq!(ctx1.randbits(3), ctx2.randbits(3));
+        assert_eq!(ctx1.rand256(), ctx2.rand256());
+        assert_eq!(ctx1.randbytes(50), ctx2.randbytes(50));
+
+        // Check that a nondeterministic ones are not
+        let mut ctx3 = FastRandomContext::new(false);
+        let mut ctx4 = FastRandomContext::new(false);
+        assert_ne!(ctx3.rand64(), ctx4.rand64()); // extremely unlikely to be equal
+        assert_ne!(ctx3.rand256(), ctx4.rand256());
+        assert_ne!(ctx3.randbytes(7), ctx4.randbytes(7));
+
+        // Explicit seeds behave like the deterministic mode with a different key
+        let seed = GetRandHash();
+        assert_eq!(FastRandomContext::from_seed(&seed).rand256(), FastRandomContext::from_seed(&seed).rand256());
+    }
+
+    #[test]
+    fn test_fastrandom_randbits() {
+        let mut ctx1 = FastRandomContext::new(false);
+        let mut ctx2 = FastRandomContext::new(false);
+        for bits in 0..63 {
+            for _ in 0..1000 {
+                let rangebits = ctx1.randbits(bits);
+                assert_eq!(rangebits >> bits, 0);
+                let range = (1u64 << bits) + 1;
+                let rand = ctx2.randrange(range);
+                assert!(rand < range);
+            }
+        }
+    }
+
+    #[test]
+    fn test_getrand() {
+        for _ in 0..1000 {
+            assert!(GetRand(10u32) < 10);
+            assert!(GetRand(7u8) < 7);
+            assert_eq!(GetRand(1u64), 0);
+        }
+        assert_ne!(GetRandHash(), GetRandHash());
+        RandAddEvent(42);
+        RandAddPeriodic();
+        let mut buf = [0u8; 32];
+        GetStrongRandBytes(&mut buf);
+        assert_ne!(buf, [0u8; 32]);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut ctx = FastRandomContext::new(true);
+        for n in 0..40 {
+            let mut v: Vec<usize> = (0..n).collect();
+            Shuffle(&mut v, &mut ctx);
+            v.sort();
+            assert_eq!(v, (0..n).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+#[cfg(target_arch = "x86_64")]
+use crate::compat::cpuid::GetCPUID;
+use crate::crypto::sha512::CSHA512;
+use crate::crypto::Hasher;
+use std::fs::{self, File};
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/** Helper to easily feed data into a CSHA512.
+ *
+ * Note that this does not serialize the passed object (like stream.h's << operators do).
+ * Its raw memory representation is used directly.
+ */
+//template<typename T> CSHA512& operator<<(CSHA512& hasher, const T& data)
+fn HashU64(hasher: &mut CSHA512, data: u64)
+{
+    hasher.Write(&data.to_ne_bytes(), 8);
+}
+
+fn HashBytes(hasher: &mut CSHA512, data: &[u8])
+{
+    hasher.Write(data, data.len());
+}
+
+/** Feed the metadata of a filesystem object into hasher. */
+//hasher << sb;
+fn HashMetadata(hasher: &mut CSHA512, sb: &fs::Metadata)
+{
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        for x in [sb.dev(), sb.ino(), sb.mode() as u64, sb.nlink(), sb.uid() as u64, sb.gid() as u64, sb.rdev(),
+                  sb.size(), sb.atime() as u64, sb.atime_nsec() as u64, sb.mtime() as u64, sb.mtime_nsec() as u64,
+                  sb.ctime() as u64, sb.ctime_nsec() as u64, sb.blksize(), sb.blocks()] {
+            HashU64(hasher, x);
+        }
+    }
+    HashU64(hasher, sb.len());
+    for time in [sb.modified(), sb.accessed(), sb.created()].into_iter().flatten() {
+        if let Ok(d) = time.duration_since(UNIX_EPOCH) {
+            HashU64(hasher, d.as_nanos() as u64);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+//void static inline AddCPUID(CSHA512& hasher, uint32_t leaf, uint32_t subleaf, uint32_t& ax, uint32_t& bx, uint32_t& cx, uint32_t& dx)
+fn AddCPUID(hasher: &mut CSHA512, leaf: u32, subleaf: u32, ax: &mut u32, bx: &mut u32, cx: &mut u32, dx: &mut u32)
+{
+    GetCPUID(leaf, subleaf, ax, bx, cx, dx);
+    for x in [leaf, subleaf, *ax, *bx, *cx, *dx] {
+        hasher.Write(&x.to_ne_bytes(), 4);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+//void AddAllCPUID(CSHA512& hasher)
+fn AddAllCPUID(hasher: &mut CSHA512)
+{
+    let (mut ax, mut bx, mut cx, mut dx) = (0u32, 0u32, 0u32, 0u32);
+    // Iterate over all standard leaves
+    AddCPUID(hasher, 0, 0, &mut ax, &mut bx, &mut cx, &mut dx); // Returns max leaf in ax
+    let max = ax;
+    for leaf in 1..=std::cmp::min(max, 0xFF) {
+        let mut maxsub = 0;
+        for subleaf in 0..=0xFF {
+            AddCPUID(hasher, leaf, subleaf, &mut ax, &mut bx, &mut cx, &mut dx);
+            // Iterate subleafs for leaf values 4, 7, 11, 13
+            if leaf == 4 {
+                if (ax & 0x1f) == 0 { break; }
+            } else if leaf == 7 {
+                if subleaf == 0 { maxsub = ax; }
+                if subleaf == maxsub { break; }
+            } else if leaf == 11 {
+                if (cx & 0xff00) == 0 { break; }
+            } else if leaf == 13 {
+                if ax == 0 && bx == 0 && cx == 0 && dx == 0 { break; }
+            } else {
+                // For any other leaf, stop after subleaf 0.
+                break;
+            }
+        }
+    }
+    // Iterate over all extended leaves
+    AddCPUID(hasher, 0x80000000, 0, &mut ax, &mut bx, &mut cx, &mut dx); // Returns max extended leaf in ax
+    let ext_max = ax;
+    for leaf in 0x80000001..=std::cmp::min(ext_max, 0x800000FF) {
+        AddCPUID(hasher, leaf, 0, &mut ax, &mut bx, &mut cx, &mut dx);
+    }
+}
+
+//void AddFile(CSHA512& hasher, const char *path)
+fn AddFile(hasher: &mut CSHA512, path: &str)
+{
+    if let Ok(mut f) = File::open(path) {
+        let mut fbuf = [0u8; 4096];
+        let mut total: usize = 0;
+        if let Ok(sb) = f.metadata() {
+            HashMetadata(hasher, &sb);
+        }
+        loop {
+            let n = match f.read(&mut fbuf) {
+                Ok(n) => n,
+                Err(_) => break,
+            };
+            HashBytes(hasher, &fbuf[..n]);
+            total += n;
+            /* not bothering with EINTR handling. */
+            if n != fbuf.len() || total >= 1048576 { // Read only the first 1 Mbyte
+                break;
+            }
+        }
+    }
+}
+
+//void AddPath(CSHA512& hasher, const char *path)
+fn AddPath(hasher: &mut CSHA512, path: &str)
+{
+    if let Ok(sb) = fs::metadata(path) {
+        HashBytes(hasher, path.as_bytes());
+        HashBytes(hasher, &[0]);
+        HashMetadata(hasher, &sb);
+    }
+}
+
+/** Gather non-cryptographic environment data that changes over time. */
+//void RandAddDynamicEnv(CSHA512& hasher)
+pub fn RandAddDynamicEnv(hasher: &mut CSHA512)
+{
+    // Various clocks
+    if let Ok(d) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        HashU64(hasher, d.as_secs());
+        HashU64(hasher, d.subsec_nanos() as u64);
+    }
+    // Instant is opaque, but its debug representation contains the monotonic clock value.
+    HashBytes(hasher, format!("{:?}", std::time::Instant::now()).as_bytes());
+
+    // Current resource usage.
+    #[cfg(target_os = "linux")]
+    {
+        AddFile(hasher, "/proc/self/stat");
+        AddFile(hasher, "/proc/self/io");
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        AddFile(hasher, "/proc/diskstats");
+        AddFile(hasher, "/proc/vmstat");
+        AddFile(hasher, "/proc/schedstat");
+        AddFile(hasher, "/proc/zoneinfo");
+        AddFile(hasher, "/proc/meminfo");
+        AddFile(hasher, "/proc/softirqs");
+        AddFile(hasher, "/proc/stat");
+        AddFile(hasher, "/proc/self/schedstat");
+        AddFile(hasher, "/proc/self/status");
+    }
+
+    // Stack and heap location
+    let addr = vec![0u8; 4097];
+    HashU64(hasher, &addr as *const Vec<u8> as u64);
+    HashU64(hasher, addr.as_ptr() as u64);
+}
+
+/** Gather non-cryptographic environment data that does not change over time. */
+//void RandAddStaticEnv(CSHA512& hasher)
+pub fn RandAddStaticEnv(hasher: &mut CSHA512)
+{
+    // Some compile-time static properties
+    HashU64(hasher, std::mem::size_of::<usize>() as u64);
+    HashBytes(hasher, std::env::consts::ARCH.as_bytes());
+    HashBytes(hasher, std::env::consts::OS.as_bytes());
+    HashBytes(hasher, std::env::consts::FAMILY.as_bytes());
+
+    // CPUID
+    #[cfg(target_arch = "x86_64")]
+    AddAllCPUID(hasher);
+
+    // Memory locations
+    HashU64(hasher, hasher as *const CSHA512 as u64);
+    HashU64(hasher, RandAddStaticEnv as *const () as u64);
+
+    // Hostname and UNIX kernel information
+    #[cfg(target_os = "linux")]
+    {
+        AddFile(hasher, "/proc/sys/kernel/hostname");
+        AddFile(hasher, "/proc/sys/kernel/ostype");
+        AddFile(hasher, "/proc/sys/kernel/osrelease");
+        AddFile(hasher, "/proc/sys/kernel/version");
+        AddFile(hasher, "/proc/net/dev");
+    }
+
+    // Path and filesystem provided data
+    AddPath(hasher, "/");
+    AddPath(hasher, ".");
+    AddPath(hasher, "/tmp");
+    AddPath(hasher, "/home");
+    AddPath(hasher, "/proc");
+    #[cfg(target_os = "linux")]
+    {
+        AddFile(hasher, "/proc/cmdline");
+        AddFile(hasher, "/proc/cpuinfo");
+        AddFile(hasher, "/proc/version");
+    }
+    AddFile(hasher, "/etc/passwd");
+    AddFile(hasher, "/etc/group");
+    AddFile(hasher, "/etc/hosts");
+    AddFile(hasher, "/etc/resolv.conf");
+    AddFile(hasher, "/etc/timezone");
+    AddFile(hasher, "/etc/localtime");
+
+    if let Ok(exe) = std::env::current_exe() {
+        HashBytes(hasher, exe.as_os_str().as_encoded_bytes());
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        HashBytes(hasher, cwd.as_os_str().as_encoded_bytes());
+    }
+
+    // Env variables
+    for (key, value) in std::env::vars_os() {
+        HashBytes(hasher, key.as_encoded_bytes());
+        HashBytes(hasher, value.as_encoded_bytes());
+    }
+
+    // Process, thread, user, session, group, ... ids.
+    HashU64(hasher, std::process::id() as u64);
+    #[cfg(unix)]
+    HashU64(hasher, std::os::unix::process::parent_id() as u64);
+    #[cfg(target_os = "linux")]
+    if let Ok(sb) = fs::metadata("/proc/self") {
+        // The owner of /proc/self is the effective user and group of this process.
+        HashMetadata(hasher, &sb);
+    }
+    HashBytes(hasher, format!("{:?}", std::thread::current().id()).as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_randomenv() {
+        let mut hasher = CSHA512::new();
+        RandAddStaticEnv(&mut hasher);
+        let static_size = hasher.Size();
+        assert!(static_size > 0);
+        RandAddDynamicEnv(&mut hasher);
+        assert!(hasher.Size() > static_size);
+    }
+}
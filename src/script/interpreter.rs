@@ -3,6 +3,11 @@
 // Distributed under the MIT software license, see the accompanying
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
 
+use primitive_types::H256;
+use crate::hash::{HASHER_TAPLEAF, HASHER_TAPBRANCH};
+use crate::pubkey::XOnlyPubKey;
+use crate::serialize::{Serialize, WriteCompactSize};
+
 pub type valtype = Vec<u8>;
 
 /* Signature hash sizes */
@@ -16,3 +21,131 @@ pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
 pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
 pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
 pub const TAPROOT_CONTROL_MAX_SIZE: usize = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+
+/** Compute the BIP341 tapleaf hash from leaf version & script. */
+//uint256 ComputeTapleafHash(uint8_t leaf_version, Span<const unsigned char> script)
+pub fn ComputeTapleafHash(leaf_version: u8, script: &[u8]) -> H256
+{
+    let mut hasher = HASHER_TAPLEAF.clone();
+    leaf_version.Serialize(&mut hasher);
+    WriteCompactSize(&mut hasher, script.len() as u64);
+    hasher.write(script);
+    hasher.GetSHA256()
+}
+
+/** Compute the BIP341 tapbranch hash from two branches.
+ *  Spans must be 32 bytes each. */
+//uint256 ComputeTapbranchHash(Span<const unsigned char> a, Span<const unsigned char> b)
+pub fn ComputeTapbranchHash(a: &[u8], b: &[u8]) -> H256
+{
+    let mut ss_branch = HASHER_TAPBRANCH.clone();
+    if a < b {
+        ss_branch.write(a);
+        ss_branch.write(b);
+    } else {
+        ss_branch.write(b);
+        ss_branch.write(a);
+    }
+    ss_branch.GetSHA256()
+}
+
+/** Compute the BIP341 taproot script tree Merkle root from control block and leaf hash.
+ *  Requires control block to have valid length (33 + k*32, with k in {0,1,..,128}). */
+//uint256 ComputeTaprootMerkleRoot(Span<const unsigned char> control, const uint256& tapleaf_hash)
+pub fn ComputeTaprootMerkleRoot(control: &[u8], tapleaf_hash: &H256) -> H256
+{
+    assert!(control.len() >= TAPROOT_CONTROL_BASE_SIZE);
+    assert!(control.len() <= TAPROOT_CONTROL_MAX_SIZE);
+    assert!((control.len() - TAPROOT_CONTROL_BASE_SIZE) % TAPROOT_CONTROL_NODE_SIZE == 0);
+
+    let mut k = *tapleaf_hash;
+    for node in control[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE) {
+        k = ComputeTapbranchHash(k.as_bytes(), node);
+    }
+    k
+}
+
+/** Verify that the output key `program` commits to the internal key and script path
+ *  given by a control block, for a leaf with hash tapleaf_hash. */
+//static bool VerifyTaprootCommitment(const std::vector<unsigned char>& control, const std::vector<unsigned char>& program, const uint256& tapleaf_hash)
+pub fn VerifyTaprootCommitment(control: &[u8], program: &[u8], tapleaf_hash: &H256) -> bool
+{
+    assert!(control.len() >= TAPROOT_CONTROL_BASE_SIZE);
+    assert!(program.len() >= WITNESS_V1_TAPROOT_SIZE);
+    // The internal pubkey (x-only, so no Y coordinate parity).
+    let p = XOnlyPubKey::new(&control[1..TAPROOT_CONTROL_BASE_SIZE]);
+    // The output pubkey (taken from the scriptPubKey).
+    let q = XOnlyPubKey::new(&program[..WITNESS_V1_TAPROOT_SIZE]);
+    // Compute the Merkle root from the leaf and the provided path.
+    let merkle_root = ComputeTaprootMerkleRoot(control, tapleaf_hash);
+    // Verify that the output pubkey matches the tweaked internal pubkey, after correcting for parity.
+    q.CheckTapTweak(&p, &merkle_root, (control[0] & 1) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h256(s: &str) -> H256 {
+        H256::from_slice(&hex::decode(s).unwrap())
+    }
+
+    fn xonly(s: &str) -> XOnlyPubKey {
+        XOnlyPubKey::new(&hex::decode(s).unwrap())
+    }
+
+    #[test]
+    fn test_taproot_tweak() {
+        // BIP341 wallet test vectors, scriptPubKey section
+        let internal = xonly("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+        assert_eq!(internal.ComputeTapTweakHash(None), h256("b86e7be8f39bab32a6f2c0443abbc210f0edac0e2c53d501b36b64437d9c6c70"));
+        let (output, parity) = internal.CreateTapTweak(None).unwrap();
+        assert_eq!(output.data().to_vec(), hex::decode("53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343").unwrap());
+        assert!(parity);
+
+        let internal = xonly("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+        let script = hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac").unwrap();
+        let leaf = ComputeTapleafHash(TAPROOT_LEAF_TAPSCRIPT, &script);
+        assert_eq!(leaf, h256("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"));
+        assert_eq!(internal.ComputeTapTweakHash(Some(&leaf)), h256("cbd8679ba636c1110ea247542cfbd964131a6be84f873f7f3b62a777528ed001"));
+        let (output, parity) = internal.CreateTapTweak(Some(&leaf)).unwrap();
+        assert_eq!(output.data().to_vec(), hex::decode("147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3").unwrap());
+        assert!(output.CheckTapTweak(&internal, &leaf, parity));
+        assert!(!output.CheckTapTweak(&internal, &leaf, !parity));
+
+        // A single leaf tree: the control block has no path and the leaf is the root
+        let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | parity as u8];
+        control.extend_from_slice(&internal.data());
+        assert_eq!(control.len(), TAPROOT_CONTROL_BASE_SIZE);
+        assert_eq!(ComputeTaprootMerkleRoot(&control, &leaf), leaf);
+        assert!(VerifyTaprootCommitment(&control, &output.data(), &leaf));
+        control[0] ^= 1;
+        assert!(!VerifyTaprootCommitment(&control, &output.data(), &leaf));
+    }
+
+    #[test]
+    fn test_taproot_merkle_root() {
+        let internal = xonly("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+        let script_a = hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac").unwrap();
+        let script_b = hex::decode("2079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ac").unwrap();
+        let leaf_a = ComputeTapleafHash(TAPROOT_LEAF_TAPSCRIPT, &script_a);
+        let leaf_b = ComputeTapleafHash(TAPROOT_LEAF_TAPSCRIPT, &script_b);
+        assert_eq!(leaf_b, h256("763e9da064b9dc0471fb0f3c8fa2c84b4b84d2ca992497c12d2274386795aa8e"));
+        let root = h256("7825144e06303da2cf98c012929747edbdb28a3ac53b84fb58ce439690df232c");
+        assert_eq!(ComputeTapbranchHash(leaf_a.as_bytes(), leaf_b.as_bytes()), root);
+        assert_eq!(ComputeTapbranchHash(leaf_b.as_bytes(), leaf_a.as_bytes()), root);
+
+        let program = hex::decode("09c9783418eda3b87cef8a0f2719675ab9986370a823aa05c4b53553353bff3f").unwrap();
+        // Spend leaf A, revealing leaf B as the path, and vice versa
+        for (leaf, sibling) in [(&leaf_a, &leaf_b), (&leaf_b, &leaf_a)] {
+            let mut control = vec![TAPROOT_LEAF_TAPSCRIPT];
+            control.extend_from_slice(&internal.data());
+            control.extend_from_slice(sibling.as_bytes());
+            assert_eq!(control.len(), TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE);
+            assert_eq!(ComputeTaprootMerkleRoot(&control, leaf), root);
+            assert!(VerifyTaprootCommitment(&control, &program, leaf));
+            // committing to the wrong leaf fails
+            assert!(!VerifyTaprootCommitment(&control, &program, sibling));
+        }
+    }
+}
@@ -5,7 +5,8 @@
 
 pub mod standard;
 pub mod interpreter;
-use crate::serialize::AsBytes;
+use crate::serialize::{AsBytes, Serialize, Unserialize, WriteStream, ReadStream};
+use std::io;
 use std::ops::{Shl, ShlAssign};
 
 // Maximum number of bytes pushable to the stack
@@ -460,6 +461,7 @@ fn GetScriptOp<'a>(pc: &mut &'a [u8], opcodeRet: &mut u8, pvchRet: &mut &'a [u8]
 
     //if (pvchRet)
     //    pvchRet->clear();
+    *pvchRet = &[];
     if pc.is_empty() {
         return false;
     }
@@ -506,9 +508,9 @@ fn GetScriptOp<'a>(pc: &mut &'a [u8], opcodeRet: &mut u8, pvchRet: &mut &'a [u8]
                 return false;
             }
             //nSize = ReadLE32(&pc[0]);
-            nSize = u32::from_le_bytes(pc[0..2].try_into().unwrap());
+            nSize = u32::from_le_bytes(pc[0..4].try_into().unwrap());
             //pc += 4;
-            *pc = &pc[2..];
+            *pc = &pc[4..];
         }
         //if (end - pc < 0 || (unsigned int)(end - pc) < nSize)
         //    return false;
@@ -539,7 +541,7 @@ fn GetScriptOp<'a>(pc: &mut &'a [u8], opcodeRet: &mut u8, pvchRet: &mut &'a [u8]
 
 /// Serialized script, used inside transaction inputs and outputs
 //class CScript : public CScriptBase
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CScript {
 
     pub v: Vec<u8>,
@@ -876,6 +878,19 @@ impl AsBytes for CScript {
     }
 }
 
+//SERIALIZE_METHODS(CScript, obj) { READWRITEAS(CScriptBase, obj); }
+impl Serialize for CScript {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        self.v.Serialize(s);
+    }
+}
+
+impl Unserialize for CScript {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        self.v.Unserialize(s)
+    }
+}
+
 impl Shl for CScript {
     type Output = CScript;
     fn shl(self, s: CScript) -> CScript
@@ -1007,6 +1022,25 @@ impl ShlAssign<Vec<u8>> for CScript {
 }
 
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CScriptWitness
+{
+    // Note that this encodes the data elements being pushed, rather than
+    // encoding them as a CScript that pushes them.
+    pub stack: Vec<Vec<u8>>,
+}
+
+impl CScriptWitness
+{
+    pub fn IsNull(&self) -> bool { self.stack.is_empty() }
+
+    pub fn SetNull(&mut self)
+    {
+        self.stack.clear();
+        self.stack.shrink_to_fit();
+    }
+}
+
 // bool CheckMinimalPush(const std::vector<unsigned char>& data, opcodetype opcode) {
 pub fn CheckMinimalPush(data: &[u8], opcode: u8) -> bool
 {
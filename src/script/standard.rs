@@ -130,6 +130,14 @@ pub fn GetScriptForDestination(dest: &CTxDestination) -> CScript {
     }
 }
 
+/**
+ * Check whether a CTxDestination is a CNoDestination.
+ */
+//bool IsValidDestination(const CTxDestination& dest) {
+pub fn IsValidDestination(dest: &CTxDestination) -> bool {
+    !matches!(dest, CTxDestination::CNoDestination)
+}
+
 
 fn IsPushdataOp(opcode: u8) -> bool
 {
@@ -145,13 +153,13 @@ fn MatchPayToPubkey(script: &CScript, pubkey: &mut valtype) -> bool
     if script.v.len() == CPubKey::SIZE + 2 && script.v[0] == CPubKey::SIZE as u8 && *script.v.last().unwrap() == opcodetype::OP_CHECKSIG as u8
     {
         //pubkey = valtype(script.begin() + 1, script.begin() + pubkey::SIZE + 1);
-        pubkey.copy_from_slice(&script.v[0..CPubKey::SIZE + 1]);
+        *pubkey = script.v[1..CPubKey::SIZE + 1].to_vec();
         return CPubKey::ValidSize(pubkey);
     }
     if script.v.len() == CPubKey::COMPRESSED_SIZE + 2 && script.v[0] == CPubKey::COMPRESSED_SIZE as u8 && *script.v.last().unwrap() == opcodetype::OP_CHECKSIG as u8
     {
         //pubkey = valtype(script.begin() + 1, script.begin() + pubkey::COMPRESSED_SIZE + 1);
-        pubkey.copy_from_slice(&script.v[1..CPubKey::COMPRESSED_SIZE + 1]);
+        *pubkey = script.v[1..CPubKey::COMPRESSED_SIZE + 1].to_vec();
         return CPubKey::ValidSize(pubkey);
     }
     return false;
@@ -219,17 +227,18 @@ fn MatchMultisig(script: &mut CScript, required_sigs: &mut i32, pubkeys: &mut Ve
     if !CScript::GetOp(&mut it, &mut opcode, &mut out) {
         return false;
     }
-    data.copy_from_slice(out);
+    data = out.to_vec();
 
     let req_sigs = GetScriptNumber(&opcode, &data, 1, super::MAX_PUBKEYS_PER_MULTISIG);
     if req_sigs == None {
         return false;
     }
     *required_sigs = req_sigs.unwrap();
-    while CScript::GetOp(&mut it, &mut opcode, &mut &data[0..]) && CPubKey::ValidSize(&data)
+    while CScript::GetOp(&mut it, &mut opcode, &mut out) && CPubKey::ValidSize(&out.to_vec())
     {
-        pubkeys.push(data.clone());
+        pubkeys.push(out.to_vec());
     }
+    data = out.to_vec();
     let num_keys = GetScriptNumber(&opcode, &data, *required_sigs, super::MAX_PUBKEYS_PER_MULTISIG);
     if num_keys == None
     {
@@ -239,7 +248,8 @@ fn MatchMultisig(script: &mut CScript, required_sigs: &mut i32, pubkeys: &mut Ve
         return false;
     }
 
-    return it.len() == 0;
+    //return (it + 1 == script.end());
+    return it.len() == 1;
 }
 
 //TxoutType Solver(const CScript& scriptPubKey, std::vector<std::vector<unsigned char>>& vSolutionsRet)
@@ -0,0 +1,176 @@
+/***********************************************************************
+ * Copyright (c) 2015 Pieter Wuille, Andrew Poelstra                   *
+ * Distributed under the MIT software license, see the accompanying    *
+ * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
+ ***********************************************************************/
+
+use super::{secp256k1_context, secp256k1_pubkey, secp256k1_pubkey_load};
+use super::field_5x52::{secp256k1_fe_normalize, secp256k1_fe_get_b32};
+use super::group::{secp256k1_ge, secp256k1_gej, secp256k1_ge_set_gej};
+use super::scalar_4x64::{secp256k1_scalar, secp256k1_scalar_set_b32, secp256k1_scalar_is_zero, secp256k1_scalar_cmov,
+                         secp256k1_scalar_clear};
+use super::scalar_impl::secp256k1_scalar_one;
+use super::ecmult_const::secp256k1_ecmult_const;
+use super::hash::{secp256k1_sha256, secp256k1_sha256_initialize, secp256k1_sha256_write, secp256k1_sha256_finalize};
+
+/** A pointer to a function that hashes an EC point to obtain an ECDH secret
+ *
+ *  Returns: 1 if the point was successfully hashed.
+ *           0 will cause secp256k1_ecdh to fail and return 0.
+ *           Other return values are not allowed, and the behaviour of
+ *           secp256k1_ecdh is undefined for other return values.
+ *  Out:     output:     the 32-byte array to be filled by the function
+ *  In:      x32:        the 32-byte x coordinate of the shared point
+ *           y32:        the 32-byte y coordinate of the shared point
+ *           data:       arbitrary data passed through
+ */
+//typedef int (*secp256k1_ecdh_hash_function)(unsigned char *output, const unsigned char *x32, const unsigned char *y32, void *data);
+pub type secp256k1_ecdh_hash_function = fn(output: &mut [u8; 32], x32: &[u8; 32], y32: &[u8; 32], data: Option<&[u8]>) -> i32;
+
+//static int ecdh_hash_function_sha256(unsigned char *output, const unsigned char *x32, const unsigned char *y32, void *data) {
+fn ecdh_hash_function_sha256(output: &mut [u8; 32], x32: &[u8; 32], y32: &[u8; 32], _data: Option<&[u8]>) -> i32 {
+    let version: u8 = (y32[31] & 0x01) | 0x02;
+    let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+
+    secp256k1_sha256_initialize(&mut sha);
+    secp256k1_sha256_write(&mut sha, &[version]);
+    secp256k1_sha256_write(&mut sha, x32);
+    secp256k1_sha256_finalize(&mut sha, output);
+
+    return 1;
+}
+
+/** An implementation of SHA256 hash function that applies to compressed public key.
+ * Populates the output parameter with 32 bytes. */
+//SECP256K1_API const secp256k1_ecdh_hash_function secp256k1_ecdh_hash_function_sha256;
+pub const secp256k1_ecdh_hash_function_sha256: secp256k1_ecdh_hash_function = ecdh_hash_function_sha256;
+
+/** A default ECDH hash function (currently equal to secp256k1_ecdh_hash_function_sha256).
+ * Populates the output parameter with 32 bytes. */
+//SECP256K1_API const secp256k1_ecdh_hash_function secp256k1_ecdh_hash_function_default;
+pub const secp256k1_ecdh_hash_function_default: secp256k1_ecdh_hash_function = ecdh_hash_function_sha256;
+
+/** Compute an EC Diffie-Hellman secret in constant time
+ *
+ *  Returns: 1: exponentiation was successful
+ *           0: scalar was invalid (zero or overflow) or hashfp returned 0
+ *  Args:    ctx:        pointer to a context object.
+ *  Out:     output:     pointer to an array to be filled by hashfp.
+ *  In:      pubkey:     a pointer to a secp256k1_pubkey containing an initialized public key.
+ *           seckey:     a 32-byte scalar with which to multiply the point.
+ *           hashfp:     pointer to a hash function. If None,
+ *                       secp256k1_ecdh_hash_function_sha256 is used
+ *                       (in which case, 32 bytes will be written to output).
+ *           data:       arbitrary data pointer that is passed through to hashfp
+ *                       (can be None for secp256k1_ecdh_hash_function_sha256).
+ */
+//int secp256k1_ecdh(const secp256k1_context* ctx, unsigned char *output, const secp256k1_pubkey *point, const unsigned char *scalar, secp256k1_ecdh_hash_function hashfp, void *data) {
+pub fn secp256k1_ecdh(ctx: &secp256k1_context, output: &mut [u8; 32], point: &secp256k1_pubkey, scalar: &[u8; 32], hashfp: Option<secp256k1_ecdh_hash_function>, data: Option<&[u8]>) -> i32 {
+    let ret: i32;
+    let mut overflow: i32 = 0;
+    let mut res = secp256k1_gej::new();
+    let mut pt = secp256k1_ge::new();
+    let mut s = secp256k1_scalar::new();
+    let mut x = [0u8; 32];
+    let mut y = [0u8; 32];
+
+    let hashfp = hashfp.unwrap_or(secp256k1_ecdh_hash_function_default);
+
+    secp256k1_pubkey_load(ctx, &mut pt, point);
+    secp256k1_scalar_set_b32(&mut s, scalar, &mut overflow);
+
+    overflow |= secp256k1_scalar_is_zero(&s);
+    secp256k1_scalar_cmov(&mut s, &secp256k1_scalar_one, overflow);
+
+    secp256k1_ecmult_const(&mut res, &pt, &s);
+    secp256k1_ge_set_gej(&mut pt, &mut res);
+
+    /* Compute a hash of the point */
+    secp256k1_fe_normalize(&mut pt.x);
+    secp256k1_fe_normalize(&mut pt.y);
+    secp256k1_fe_get_b32(&mut x, &pt.x);
+    secp256k1_fe_get_b32(&mut y, &pt.y);
+
+    ret = hashfp(output, &x, &y, data);
+
+    x = [0u8; 32];
+    y = [0u8; 32];
+    secp256k1_scalar_clear(&mut s);
+
+    ((ret != 0) as i32) & ((overflow == 0) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{secp256k1_ec_pubkey_create, secp256k1_ec_pubkey_serialize, secp256k1_ec_seckey_tweak_mul,
+                       SECP256K1_EC_COMPRESSED, SECP256K1_EC_UNCOMPRESSED};
+
+    /* Checks that it is called with the x and y coordinates passed as data (x32 || y32) */
+    fn ecdh_hash_function_test_xy(output: &mut [u8; 32], x32: &[u8; 32], y32: &[u8; 32], data: Option<&[u8]>) -> i32 {
+        let data = data.expect("data is passed through");
+        assert_eq!(&data[..32], x32);
+        assert_eq!(&data[32..64], y32);
+        output.copy_from_slice(x32);
+        return 1;
+    }
+
+    fn ecdh_hash_function_test_fail(_output: &mut [u8; 32], _x32: &[u8; 32], _y32: &[u8; 32], _data: Option<&[u8]>) -> i32 {
+        return 0;
+    }
+
+    fn serialize(ctx: &secp256k1_context, pubkey: &secp256k1_pubkey, flags: u32) -> Vec<u8> {
+        let mut out = [0u8; 65];
+        let mut len = if flags == SECP256K1_EC_COMPRESSED { 33 } else { 65 };
+        assert!(secp256k1_ec_pubkey_serialize(ctx, &mut out, &mut len, pubkey, flags));
+        out[..len].to_vec()
+    }
+
+    #[test]
+    fn test_ecdh_custom_hash_function() {
+        let mut ctx = secp256k1_context::new();
+        for i in 1..=8u8 {
+            let s1 = [i; 32];
+            let s2 = [i.wrapping_mul(37).wrapping_add(5); 32];
+            let mut point = secp256k1_pubkey { data: [0u8; 64] };
+            assert!(secp256k1_ec_pubkey_create(&mut ctx, &mut point, &s1));
+
+            /* the shared point is (s1*s2)*G */
+            let mut s12 = s1;
+            assert_eq!(secp256k1_ec_seckey_tweak_mul(&ctx, &mut s12, &s2), 1);
+            let mut shared = secp256k1_pubkey { data: [0u8; 64] };
+            assert!(secp256k1_ec_pubkey_create(&mut ctx, &mut shared, &s12));
+            let uncompressed = serialize(&ctx, &shared, SECP256K1_EC_UNCOMPRESSED);
+
+            let mut output = [0u8; 32];
+            assert_eq!(secp256k1_ecdh(&ctx, &mut output, &point, &s2, Some(ecdh_hash_function_test_xy), Some(&uncompressed[1..])), 1);
+            assert_eq!(&output[..], &uncompressed[1..33]);
+
+            /* the default hash function is SHA256 of the compressed point */
+            let compressed = serialize(&ctx, &shared, SECP256K1_EC_COMPRESSED);
+            let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+            let mut expected = [0u8; 32];
+            secp256k1_sha256_initialize(&mut sha);
+            secp256k1_sha256_write(&mut sha, &compressed);
+            secp256k1_sha256_finalize(&mut sha, &mut expected);
+            assert_eq!(secp256k1_ecdh(&ctx, &mut output, &point, &s2, None, None), 1);
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn test_ecdh_failing_hash_function() {
+        let mut ctx = secp256k1_context::new();
+        let s_one = [1u8; 32];
+        let mut point = secp256k1_pubkey { data: [0u8; 64] };
+        assert!(secp256k1_ec_pubkey_create(&mut ctx, &mut point, &s_one));
+
+        let mut output = [0u8; 32];
+        assert_eq!(secp256k1_ecdh(&ctx, &mut output, &point, &s_one, Some(ecdh_hash_function_test_fail), None), 0);
+        assert_eq!(secp256k1_ecdh(&ctx, &mut output, &point, &s_one, None, None), 1);
+
+        /* a zero or overflowing scalar is rejected */
+        assert_eq!(secp256k1_ecdh(&ctx, &mut output, &point, &[0u8; 32], None, None), 0);
+        assert_eq!(secp256k1_ecdh(&ctx, &mut output, &point, &[0xffu8; 32], None, None), 0);
+    }
+}
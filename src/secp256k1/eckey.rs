@@ -134,7 +134,7 @@ pub fn secp256k1_eckey_pubkey_tweak_add(key: &mut secp256k1_ge, tweak: &[secp256
 // }
 
 pub fn secp256k1_eckey_privkey_tweak_mul(key: &mut secp256k1_scalar, tweak: &secp256k1_scalar) -> i32 {
-    let ret = !secp256k1_scalar_is_zero(tweak);
+    let ret = (secp256k1_scalar_is_zero(tweak) == 0) as i32;
     let key2 = key.clone();
 
     secp256k1_scalar_mul(key, &key2, tweak);
@@ -0,0 +1,400 @@
+/***********************************************************************
+ * Copyright (c) 2015 Pieter Wuille, Andrew Poelstra                   *
+ * Distributed under the MIT software license, see the accompanying    *
+ * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
+ ***********************************************************************/
+
+use super::group::{secp256k1_ge, secp256k1_gej, secp256k1_gej_set_ge, secp256k1_gej_set_infinity, secp256k1_gej_double,
+                   secp256k1_gej_add_ge, sec256k1_gej_cmov, secp256k1_ge_neg, secp256k1_ge_is_infinity,
+                   secp256k1_ge_mul_lambda, secp256k1_ge_table_set_globalz};
+use super::field_5x52::{secp256k1_fe, secp256k1_fe_cmov, secp256k1_fe_negate, secp256k1_fe_normalize_weak, secp256k1_fe_mul};
+use super::scalar_4x64::{secp256k1_scalar, secp256k1_scalar_is_high, secp256k1_scalar_is_even, secp256k1_scalar_cadd_bit,
+                         secp256k1_scalar_cond_negate, secp256k1_scalar_shr_int, secp256k1_scalar_is_zero};
+use super::scalar_impl::secp256k1_scalar_split_lambda;
+use super::ecmult_impl::{secp256k1_ecmult_odd_multiples_table, WINDOW_A, WNAF_BITS};
+use crate::{VERIFY_CHECK, ECMULT_TABLE_SIZE, WNAF_SIZE_BITS, WNAF_SIZE};
+
+/* This is like `ECMULT_TABLE_GET_GE` but is constant time */
+//#define ECMULT_CONST_TABLE_GET_GE(r,pre,n,w) do {
+fn ECMULT_CONST_TABLE_GET_GE(r: &mut secp256k1_ge, pre: &[secp256k1_ge], n: i32, w: i32) {
+    /* Extract the sign-bit for a constant time absolute-value. */
+    let mask: i32 = n >> (i32::BITS - 1);
+    let abs_n: i32 = (n + mask) ^ mask;
+    let idx_n: i32 = abs_n >> 1;
+    let mut neg_y = secp256k1_fe::new();
+    VERIFY_CHECK!((n & 1) == 1);
+    VERIFY_CHECK!(n >= -((1 << (w - 1)) - 1));
+    VERIFY_CHECK!(n <= ((1 << (w - 1)) - 1));
+    /* Unconditionally set r->x = (pre)[m].x. r->y = (pre)[m].y. because it's either the correct one
+     * or will get replaced in the later iterations, this is needed to make sure `r` is initialized. */
+    r.x = pre[0].x.clone();
+    r.y = pre[0].y.clone();
+    for m in 1..ECMULT_TABLE_SIZE!(w) {
+        /* This loop is used to avoid secret data in array indices. See
+         * the comment in ecmult_gen_impl.h for rationale. */
+        secp256k1_fe_cmov(&mut r.x, &pre[m as usize].x, (m == idx_n) as i32);
+        secp256k1_fe_cmov(&mut r.y, &pre[m as usize].y, (m == idx_n) as i32);
+    }
+    r.infinity = 0;
+    secp256k1_fe_negate(&mut neg_y, &r.y, 1);
+    secp256k1_fe_cmov(&mut r.y, &neg_y, (n != abs_n) as i32);
+}
+
+/** Convert a number to WNAF notation.
+ *  The number becomes represented by sum(2^{wi} * wnaf[i], i=0..WNAF_SIZE(w)+1) - return_val.
+ *  It has the following guarantees:
+ *  - each wnaf[i] an odd integer between -(1 << w) and (1 << w)
+ *  - each wnaf[i] is nonzero
+ *  - the number of words set is always WNAF_SIZE(w) + 1
+ *
+ *  Adapted from `The Width-w NAF Method Provides Small Memory and Fast Elliptic Scalar
+ *  Multiplications Secure against Side Channel Attacks`, Okeya and Tagaki. M. Joye (Ed.)
+ *  CT-RSA 2003, LNCS 2612, pp. 328-443, 2003. Springer-Verlag Berlin Heidelberg 2003
+ *
+ *  Numbers reference steps of `Algorithm SPA-resistant Width-w NAF with Odd Scalar` on pp. 335
+ */
+//static int secp256k1_wnaf_const(int *wnaf, const secp256k1_scalar *scalar, int w, int size) {
+fn secp256k1_wnaf_const(wnaf: &mut [i32], scalar: &secp256k1_scalar, w: i32, size: i32) -> i32 {
+    let global_sign: i32;
+    let skew: i32;
+    let mut word: usize = 0;
+
+    /* 1 2 3 */
+    let mut u_last: i32;
+    let mut u: i32;
+
+    let flip: i32;
+    let mut s = scalar.clone();
+
+    VERIFY_CHECK!(w > 0);
+    VERIFY_CHECK!(size > 0);
+
+    /* Note that we cannot handle even numbers by negating them to be odd, as is
+     * done in other implementations, since if our scalars were specified to have
+     * width < 256 for performance reasons, their negations would have width 256
+     * and we'd lose any performance benefit. Instead, we use a variation of a
+     * technique from Section 4.2 of the Okeya/Tagaki paper, which is to add 1 to the
+     * number we are encoding when it is even, returning a skew value indicating
+     * this, and having the caller compensate after doing the multiplication.
+     *
+     * In fact, we _do_ want to negate numbers to minimize their bit-lengths (and in
+     * particular, to ensure that the outputs from the endomorphism-split fit into
+     * 128 bits). If we negate, the parity of our number flips, affecting whether
+     * we want to add to the scalar to ensure that it's odd. */
+    flip = secp256k1_scalar_is_high(&s);
+    skew = flip ^ secp256k1_scalar_is_even(&s);
+    secp256k1_scalar_cadd_bit(&mut s, 0, skew);
+    global_sign = secp256k1_scalar_cond_negate(&mut s, flip);
+
+    /* 4 */
+    u_last = secp256k1_scalar_shr_int(&mut s, w);
+    loop {
+        /* 4.1 4.4 */
+        u = secp256k1_scalar_shr_int(&mut s, w);
+        /* 4.2 */
+        let even = ((u & 1) == 0) as i32;
+        /* In contrast to the original algorithm, u_last is always > 0 and
+         * therefore we do not need to check its sign. In particular, it's easy
+         * to see that u_last is never < 0 because u is never < 0. Moreover,
+         * u_last is never = 0 because u is never even after a loop
+         * iteration. The same holds analogously for the initial value of
+         * u_last (in the first loop iteration). */
+        VERIFY_CHECK!(u_last > 0);
+        VERIFY_CHECK!((u_last & 1) == 1);
+        u += even;
+        u_last -= even * (1 << w);
+
+        /* 4.3, adapted for global sign change */
+        wnaf[word] = u_last * global_sign;
+        word += 1;
+
+        u_last = u;
+        if word as i32 * w >= size {
+            break;
+        }
+    }
+    wnaf[word] = u * global_sign;
+
+    VERIFY_CHECK!(secp256k1_scalar_is_zero(&s) != 0);
+    VERIFY_CHECK!(word as i32 == WNAF_SIZE_BITS!(size, w));
+    skew
+}
+
+//static void secp256k1_ecmult_const_odd_multiples_table_globalz(secp256k1_ge *pre, secp256k1_fe *globalz, const secp256k1_gej *a) {
+fn secp256k1_ecmult_const_odd_multiples_table_globalz(pre: &mut [secp256k1_ge], globalz: &mut secp256k1_fe, a: &secp256k1_gej) {
+    let mut zr: Vec<secp256k1_fe> = vec![secp256k1_fe::new(); ECMULT_TABLE_SIZE!(WINDOW_A)];
+
+    secp256k1_ecmult_odd_multiples_table(ECMULT_TABLE_SIZE!(WINDOW_A), pre, &mut zr, globalz, a);
+    secp256k1_ge_table_set_globalz(ECMULT_TABLE_SIZE!(WINDOW_A), pre, &zr);
+}
+
+/**
+ * Multiply: R = q*A (in constant-time for q)
+ */
+//static void secp256k1_ecmult_const(secp256k1_gej *r, const secp256k1_ge *a, const secp256k1_scalar *scalar) {
+pub fn secp256k1_ecmult_const(r: &mut secp256k1_gej, a: &secp256k1_ge, scalar: &secp256k1_scalar) {
+    let mut pre_a: Vec<secp256k1_ge> = vec![secp256k1_ge::new(); ECMULT_TABLE_SIZE!(WINDOW_A)];
+    let mut tmpa = secp256k1_ge::new();
+    let mut Z = secp256k1_fe::new();
+
+    let skew_1: i32;
+    let mut pre_a_lam: Vec<secp256k1_ge> = vec![secp256k1_ge::new(); ECMULT_TABLE_SIZE!(WINDOW_A)];
+    let mut wnaf_lam = [0i32; 1 + WNAF_SIZE!(WINDOW_A as usize - 1)];
+    let skew_lam: i32;
+    let mut q_1 = secp256k1_scalar::new();
+    let mut q_lam = secp256k1_scalar::new();
+    let mut wnaf_1 = [0i32; 1 + WNAF_SIZE!(WINDOW_A as usize - 1)];
+
+    if secp256k1_ge_is_infinity(a) {
+        secp256k1_gej_set_infinity(r);
+        return;
+    }
+
+    /* build wnaf representation for q. */
+    /* split q into q_1 and q_lam (where q = q_1 + q_lam*lambda, and q_1 and q_lam are ~128 bit) */
+    secp256k1_scalar_split_lambda(&mut q_1, &mut q_lam, scalar);
+    skew_1   = secp256k1_wnaf_const(&mut wnaf_1,   &q_1,   WINDOW_A - 1, 128);
+    skew_lam = secp256k1_wnaf_const(&mut wnaf_lam, &q_lam, WINDOW_A - 1, 128);
+
+    /* Calculate odd multiples of a.
+     * All multiples are brought to the same Z 'denominator', which is stored
+     * in Z. Due to secp256k1' isomorphism we can do all operations pretending
+     * that the Z coordinate was 1, use affine addition formulae, and correct
+     * the Z coordinate of the result once at the end.
+     */
+    secp256k1_gej_set_ge(r, a);
+    secp256k1_ecmult_const_odd_multiples_table_globalz(&mut pre_a, &mut Z, r);
+    for i in 0..ECMULT_TABLE_SIZE!(WINDOW_A) {
+        secp256k1_fe_normalize_weak(&mut pre_a[i].y);
+    }
+    for i in 0..ECMULT_TABLE_SIZE!(WINDOW_A) {
+        secp256k1_ge_mul_lambda(&mut pre_a_lam[i], &pre_a[i]);
+    }
+
+    /* first loop iteration (separated out so we can directly set r, rather
+     * than having it start at infinity, get doubled several times, then have
+     * its new value added to it) */
+    let top = WNAF_SIZE_BITS!(128, WINDOW_A as usize - 1);
+    let mut n = wnaf_1[top];
+    VERIFY_CHECK!(n != 0);
+    ECMULT_CONST_TABLE_GET_GE(&mut tmpa, &pre_a, n, WINDOW_A);
+    secp256k1_gej_set_ge(r, &tmpa);
+    n = wnaf_lam[top];
+    VERIFY_CHECK!(n != 0);
+    ECMULT_CONST_TABLE_GET_GE(&mut tmpa, &pre_a_lam, n, WINDOW_A);
+    let r1 = r.clone();
+    secp256k1_gej_add_ge(r, &r1, &tmpa);
+    /* remaining loop iterations */
+    for i in (0..top).rev() {
+        for _ in 0..WINDOW_A - 1 {
+            let r1 = r.clone();
+            secp256k1_gej_double(r, &r1);
+        }
+
+        n = wnaf_1[i];
+        ECMULT_CONST_TABLE_GET_GE(&mut tmpa, &pre_a, n, WINDOW_A);
+        VERIFY_CHECK!(n != 0);
+        let r1 = r.clone();
+        secp256k1_gej_add_ge(r, &r1, &tmpa);
+        n = wnaf_lam[i];
+        ECMULT_CONST_TABLE_GET_GE(&mut tmpa, &pre_a_lam, n, WINDOW_A);
+        VERIFY_CHECK!(n != 0);
+        let r1 = r.clone();
+        secp256k1_gej_add_ge(r, &r1, &tmpa);
+    }
+
+    {
+        /* Correct for wNAF skew */
+        let mut tmpj = secp256k1_gej::new();
+
+        secp256k1_ge_neg(&mut tmpa, &pre_a[0]);
+        secp256k1_gej_add_ge(&mut tmpj, r, &tmpa);
+        sec256k1_gej_cmov(r, &tmpj, skew_1);
+
+        secp256k1_ge_neg(&mut tmpa, &pre_a_lam[0]);
+        secp256k1_gej_add_ge(&mut tmpj, r, &tmpa);
+        sec256k1_gej_cmov(r, &tmpj, skew_lam);
+    }
+
+    let z1 = r.z.clone();
+    secp256k1_fe_mul(&mut r.z, &z1, &Z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::group::{secp256k1_ge_const_g, secp256k1_ge_set_gej, secp256k1_ge_set_gej_var, secp256k1_ge_is_valid_var, secp256k1_gej_is_infinity};
+    use super::super::field_5x52::{secp256k1_fe_normalize_var, secp256k1_fe_get_b32};
+    use super::super::scalar_4x64::{secp256k1_scalar_set_b32, secp256k1_scalar_set_int, secp256k1_scalar_negate, secp256k1_scalar_mul};
+    use super::super::scalar_impl::{secp256k1_scalar_one, secp256k1_scalar_zero};
+    use super::super::ecmult_impl::secp256k1_ecmult;
+    use super::super::hash::{secp256k1_sha256, secp256k1_sha256_initialize, secp256k1_sha256_write, secp256k1_sha256_finalize};
+    use crate::{SECP256K1_FE_CONST, SECP256K1_GE_CONST, SECP256K1_SCALAR_CONST};
+
+    /* Deterministic stand-in for testrand: scalars derived from SHA256(counter). */
+    fn random_scalar_order_test(counter: &mut u32) -> secp256k1_scalar {
+        let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+        let mut b32 = [0u8; 32];
+        let mut s = secp256k1_scalar::new();
+        let mut overflow: i32 = 0;
+        *counter += 1;
+        secp256k1_sha256_initialize(&mut sha);
+        secp256k1_sha256_write(&mut sha, b"ecmult_const test");
+        secp256k1_sha256_write(&mut sha, &counter.to_le_bytes());
+        secp256k1_sha256_finalize(&mut sha, &mut b32);
+        secp256k1_scalar_set_b32(&mut s, &b32, &mut overflow);
+        s
+    }
+
+    fn random_group_element_test(counter: &mut u32) -> secp256k1_ge {
+        let s = random_scalar_order_test(counter);
+        let mut gej = secp256k1_gej::new();
+        let mut ge = secp256k1_ge::new();
+        ecmult_gen(&mut gej, &s);
+        secp256k1_ge_set_gej_var(&mut ge, &mut gej);
+        ge
+    }
+
+    /* r = s*G, computed with the variable-time multiplication */
+    fn ecmult_gen(r: &mut secp256k1_gej, s: &secp256k1_scalar) {
+        let mut inf = secp256k1_gej::new();
+        secp256k1_gej_set_infinity(&mut inf);
+        secp256k1_ecmult(r, &mut inf, &secp256k1_scalar_zero, &[s.clone()]);
+    }
+
+    fn ge_to_bytes(a: &secp256k1_ge) -> Option<[u8; 64]> {
+        if secp256k1_ge_is_infinity(a) {
+            return None;
+        }
+        let mut x = a.x.clone();
+        let mut y = a.y.clone();
+        let mut out = [0u8; 64];
+        secp256k1_fe_normalize_var(&mut x);
+        secp256k1_fe_normalize_var(&mut y);
+        secp256k1_fe_get_b32(&mut out[..32], &x);
+        secp256k1_fe_get_b32(&mut out[32..], &y);
+        Some(out)
+    }
+
+    fn gej_to_bytes(a: &secp256k1_gej) -> Option<[u8; 64]> {
+        let mut ge = secp256k1_ge::new();
+        secp256k1_ge_set_gej_var(&mut ge, &mut a.clone());
+        ge_to_bytes(&ge)
+    }
+
+    #[test]
+    fn ecmult_const_random_mult() {
+        /* random starting point A (on the curve) */
+        let a: secp256k1_ge = SECP256K1_GE_CONST!(
+            0x6d986544, 0x57ff52b8, 0xcf1b8126, 0x5b802a5b,
+            0xa97f9263, 0xb1e88044, 0x93351325, 0x91bc450a,
+            0x535c59f7, 0x325e5d2b, 0xc391fbe8, 0x3c12787c,
+            0x337e4a98, 0xe82a9011, 0x0123ba37, 0xdd769c7d
+        );
+        /* random initial factor xn */
+        let xn: secp256k1_scalar = SECP256K1_SCALAR_CONST!(
+            0x649d4f77, 0xc4242df7, 0x7f2079c9, 0x14530327,
+            0xa31b876a, 0xd2d8ce2a, 0x2236d5c6, 0xd7b2029b
+        );
+        /* expected xn * A (from sage) */
+        let expected_b: secp256k1_ge = SECP256K1_GE_CONST!(
+            0x23773684, 0x4d209dc7, 0x098a786f, 0x20d06fcd,
+            0x070a38bf, 0xc11ac651, 0x03004319, 0x1e2a8786,
+            0xed8c3b8e, 0xc06dd57b, 0xd06ea66e, 0x45492b0f,
+            0xb84e4e1b, 0xfb77e21f, 0x96baae2a, 0x63dec956
+        );
+        let mut b = secp256k1_gej::new();
+        secp256k1_ecmult_const(&mut b, &a, &xn);
+
+        assert!(secp256k1_ge_is_valid_var(&a) != 0);
+        assert_eq!(gej_to_bytes(&b), ge_to_bytes(&expected_b));
+
+        /* the variable-time multiplication agrees */
+        let mut aj = secp256k1_gej::new();
+        let mut b2 = secp256k1_gej::new();
+        secp256k1_gej_set_ge(&mut aj, &a);
+        secp256k1_ecmult(&mut b2, &mut aj, &xn, &[secp256k1_scalar_zero]);
+        assert_eq!(gej_to_bytes(&b2), ge_to_bytes(&expected_b));
+    }
+
+    #[test]
+    fn ecmult_const_commutativity() {
+        let mut counter = 0u32;
+        for _ in 0..16 {
+            let a = random_scalar_order_test(&mut counter);
+            let b = random_scalar_order_test(&mut counter);
+            let mut res1 = secp256k1_gej::new();
+            let mut res2 = secp256k1_gej::new();
+            let mut mid1 = secp256k1_ge::new();
+            let mut mid2 = secp256k1_ge::new();
+
+            secp256k1_ecmult_const(&mut res1, &secp256k1_ge_const_g, &a);
+            secp256k1_ecmult_const(&mut res2, &secp256k1_ge_const_g, &b);
+            secp256k1_ge_set_gej(&mut mid1, &mut res1);
+            secp256k1_ge_set_gej(&mut mid2, &mut res2);
+            secp256k1_ecmult_const(&mut res1, &mid1, &b);
+            secp256k1_ecmult_const(&mut res2, &mid2, &a);
+            assert_eq!(gej_to_bytes(&res1), gej_to_bytes(&res2));
+
+            /* both equal (a*b)*G computed with secp256k1_ecmult */
+            let mut ab = secp256k1_scalar::new();
+            let mut expected = secp256k1_gej::new();
+            secp256k1_scalar_mul(&mut ab, &a, &b);
+            ecmult_gen(&mut expected, &ab);
+            assert_eq!(gej_to_bytes(&res1), gej_to_bytes(&expected));
+        }
+    }
+
+    #[test]
+    fn ecmult_const_mult_zero_one() {
+        let mut counter = 0u32;
+        let mut negone = secp256k1_scalar::new();
+        secp256k1_scalar_negate(&mut negone, &secp256k1_scalar_one);
+
+        for _ in 0..16 {
+            let s = random_scalar_order_test(&mut counter);
+            let point = random_group_element_test(&mut counter);
+            let mut res1 = secp256k1_gej::new();
+            let mut res2 = secp256k1_ge::new();
+            let mut expected = secp256k1_gej::new();
+
+            /* s*0 = 0 */
+            let mut inf = point.clone();
+            inf.infinity = 1;
+            secp256k1_ecmult_const(&mut res1, &inf, &s);
+            assert!(secp256k1_gej_is_infinity(&res1) != 0);
+
+            /* 0*point = 0 */
+            secp256k1_ecmult_const(&mut res1, &point, &secp256k1_scalar_zero);
+            assert!(secp256k1_gej_is_infinity(&res1) != 0);
+
+            /* 1*point = point */
+            secp256k1_ecmult_const(&mut res1, &point, &secp256k1_scalar_one);
+            secp256k1_ge_set_gej(&mut res2, &mut res1);
+            assert_eq!(ge_to_bytes(&res2), ge_to_bytes(&point));
+
+            /* -1*point = -point */
+            secp256k1_ecmult_const(&mut res1, &point, &negone);
+            secp256k1_ge_neg(&mut res2, &point);
+            assert_eq!(gej_to_bytes(&res1), ge_to_bytes(&res2));
+
+            /* s*point matches secp256k1_ecmult */
+            let mut pointj = secp256k1_gej::new();
+            secp256k1_gej_set_ge(&mut pointj, &point);
+            secp256k1_ecmult(&mut expected, &mut pointj, &s, &[secp256k1_scalar_zero]);
+            secp256k1_ecmult_const(&mut res1, &point, &s);
+            assert_eq!(gej_to_bytes(&res1), gej_to_bytes(&expected));
+
+            /* small multiples match repeated addition */
+            let mut small = secp256k1_scalar::new();
+            secp256k1_scalar_set_int(&mut small, 3);
+            secp256k1_ecmult_const(&mut res1, &point, &small);
+            secp256k1_gej_set_ge(&mut expected, &point);
+            for _ in 0..2 {
+                let e = expected.clone();
+                secp256k1_gej_add_ge(&mut expected, &e, &point);
+            }
+            assert_eq!(gej_to_bytes(&res1), gej_to_bytes(&expected));
+        }
+    }
+}
@@ -158,12 +158,12 @@ impl secp256k1_ecmult_gen_context {
             //memcpy(keydata + 32, seed32, 32);
             keydata[32..64].copy_from_slice(&seed32[..32]);
         }
-        secp256k1_rfc6979_hmac_sha256_initialize(&mut rng, keydata.as_slice());
+        secp256k1_rfc6979_hmac_sha256_initialize(&mut rng, &keydata[..if seed32.len() > 0 { 64 } else { 32 }]);
         //memset(keydata, 0, sizeof(keydata));
         keydata = [0; 64];
         /* Accept unobservably small non-uniformity. */
         secp256k1_rfc6979_hmac_sha256_generate(&mut rng, nonce32.as_mut_slice());
-        overflow = !secp256k1_fe_set_b32(&mut s, nonce32.as_slice());
+        overflow = (secp256k1_fe_set_b32(&mut s, nonce32.as_slice()) == 0) as i32;
         overflow |= secp256k1_fe_is_zero(&s);
         secp256k1_fe_cmov(&mut s, &secp256k1_fe_one, overflow);
         /* Randomize the projection to defend against multiplier sidechannels. */
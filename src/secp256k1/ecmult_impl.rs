@@ -22,6 +22,7 @@ use super::*;
 use super::util::secp256k1_callback;
 use super::scratch::secp256k1_scratch;
 use super::scalar_impl::secp256k1_scalar_split_lambda;
+use super::precomputed_ecmult::{secp256k1_pre_g, secp256k1_pre_g_128};
 use crate::{VERIFY_CHECK};
 
 pub type secp256k1_ecmult_multi_callback = fn(&secp256k1_scalar, &secp256k1_ge, usize, &[u8]) -> i32;
@@ -52,7 +53,7 @@ pub type secp256k1_ecmult_multi_callback = fn(&secp256k1_scalar, &secp256k1_ge,
   *  optimization).
   */
  // #endif
- pub const WINDOW_A: i32 = 2;
+ pub const WINDOW_A: i32 = 5;
 
 // #define WINDOW_G ECMULT_WINDOW_SIZE
 pub const WINDOW_G: i32 = ECMULT_WINDOW_SIZE;
@@ -61,6 +62,7 @@ pub const WINDOW_G: i32 = ECMULT_WINDOW_SIZE;
  pub const WNAF_BITS: usize = 128;
  
 // #define WNAF_SIZE_BITS(bits, w) (((bits) + (w) - 1) / (w))
+#[macro_export]
 macro_rules! WNAF_SIZE_BITS {
     ($bits:expr, $w:expr) => {
         ($bits + $w - 1) / $w
@@ -68,6 +70,7 @@ macro_rules! WNAF_SIZE_BITS {
 }
 
 //#define WNAF_SIZE!(w) WNAF_SIZE_BITS(WNAF_BITS, w)
+#[macro_export]
 macro_rules! WNAF_SIZE {
     ($w:expr) => {
         WNAF_SIZE_BITS!(WNAF_BITS, $w)
@@ -611,6 +614,7 @@ fn secp256k1_ecmult_strauss_wnaf(state: &mut secp256k1_strauss_state, r: &mut se
             let aux2 = state.aux[no * ECMULT_TABLE_SIZE!(WINDOW_A)].clone();
             secp256k1_fe_mul(&mut state.aux[no * ECMULT_TABLE_SIZE!(WINDOW_A)], &aux2, &mut a[np].z);
         }
+        no += 1;
     }
 
     /* Bring them to the same Z denominator. */
@@ -650,22 +654,28 @@ fn secp256k1_ecmult_strauss_wnaf(state: &mut secp256k1_strauss_state, r: &mut se
             n = state.ps[np].wnaf_na_lam[i as usize];
             if i < state.ps[np].bits_na_lam && n != 0 {
                 secp256k1_ecmult_table_get_ge_lambda(&mut tmpa, &mut state.pre_a[np * ECMULT_TABLE_SIZE!(WINDOW_A)..], &mut state.aux[np * ECMULT_TABLE_SIZE!(WINDOW_A)..], n, WINDOW_A);
+                let r2 = r.clone();
                 secp256k1_gej_add_ge_var(r, &r2, &tmpa, None);
             }
         }
         n = wnaf_ng_1[i as usize];
         if i < bits_ng_1 && n != 0 {
-            todo!();
-            //secp256k1_ecmult_table_get_ge_storage(&mut tmpa, secp256k1_pre_g, n, WINDOW_G);
-            secp256k1_gej_add_zinv_var(r, r, &tmpa, &Z);
+            secp256k1_ecmult_table_get_ge_storage(&mut tmpa, &secp256k1_pre_g, n, WINDOW_G);
+            let r2 = r.clone();
+            secp256k1_gej_add_zinv_var(r, &r2, &tmpa, &Z);
         }
         n = wnaf_ng_128[i as usize];
         if i < bits_ng_128 && n != 0 {
-            todo!();
-            //secp256k1_ecmult_table_get_ge_storage(&mut tmpa, secp256k1_pre_g_128, n, WINDOW_G);
-            secp256k1_gej_add_zinv_var(r, r, &tmpa, &Z);
+            secp256k1_ecmult_table_get_ge_storage(&mut tmpa, &secp256k1_pre_g_128, n, WINDOW_G);
+            let r2 = r.clone();
+            secp256k1_gej_add_zinv_var(r, &r2, &tmpa, &Z);
         }
     }
+
+    if r.infinity == 0 {
+        let z1 = r.z.clone();
+        secp256k1_fe_mul(&mut r.z, &z1, &Z);
+    }
 }
  
 //  static void secp256k1_ecmult(secp256k1_gej *r, const secp256k1_gej *a, const secp256k1_scalar *na, const secp256k1_scalar *ng) {
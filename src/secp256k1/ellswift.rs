@@ -0,0 +1,962 @@
+/***********************************************************************
+ * Copyright (c) 2022 Pieter Wuille                                    *
+ * Distributed under the MIT software license, see the accompanying    *
+ * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
+ ***********************************************************************/
+
+use super::{secp256k1_context, secp256k1_pubkey, secp256k1_pubkey_load, secp256k1_pubkey_save, secp256k1_ec_pubkey_create_helper};
+use super::eckey::secp256k1_eckey_pubkey_serialize;
+use super::field::{secp256k1_fe_sqrt, secp256k1_fe_is_square_var};
+use super::field_5x52::{secp256k1_fe, secp256k1_fe_set_b32, secp256k1_fe_get_b32, secp256k1_fe_normalize, secp256k1_fe_normalize_var,
+                        secp256k1_fe_normalize_weak, secp256k1_fe_normalizes_to_zero_var, secp256k1_fe_is_odd, secp256k1_fe_negate,
+                        secp256k1_fe_mul_int, secp256k1_fe_add, secp256k1_fe_add_int, secp256k1_fe_mul, secp256k1_fe_sqr,
+                        secp256k1_fe_half, secp256k1_fe_inv_var, secp256k1_fe_clear};
+use super::group::{secp256k1_ge, secp256k1_gej, secp256k1_ge_set_gej, secp256k1_ge_set_xo_var, secp256k1_ge_x_on_curve_var,
+                   secp256k1_ge_x_frac_on_curve_var, SECP256K1_B};
+use super::scalar_4x64::{secp256k1_scalar, secp256k1_scalar_set_b32, secp256k1_scalar_is_zero, secp256k1_scalar_cmov,
+                         secp256k1_scalar_clear};
+use super::scalar_impl::secp256k1_scalar_one;
+use super::ecmult_const::secp256k1_ecmult_const;
+use super::ecmult_gen::secp256k1_fe_one;
+use super::hash::{secp256k1_sha256, secp256k1_sha256_initialize, secp256k1_sha256_write, secp256k1_sha256_finalize};
+use super::util::secp256k1_memczero;
+use crate::{SECP256K1_FE_CONST, SECP256K1_FE_CONST_INNER, VERIFY_CHECK};
+
+/** A pointer to a function used for hashing the shared X coordinate along
+ *  with the encoded public keys to a uniform shared secret.
+ *
+ *  Returns: 1 if a shared secret was successfully computed.
+ *           0 will cause secp256k1_ellswift_xdh to fail and return 0.
+ *           Other return values are not allowed, and the behaviour of
+ *           secp256k1_ellswift_xdh is undefined for other return values.
+ *  Out:     output:     the 32-byte array to be filled by the function
+ *  In:      x32:        the 32-byte serialized X coordinate
+ *                       of the resulting shared point
+ *           ell_a64:    the 64-byte encoded public key of party A
+ *           ell_b64:    the 64-byte encoded public key of party B
+ *           data:       arbitrary data passed through
+ */
+//typedef int (*secp256k1_ellswift_xdh_hash_function)(unsigned char *output, const unsigned char *x32, const unsigned char *ell_a64, const unsigned char *ell_b64, void *data);
+pub type secp256k1_ellswift_xdh_hash_function = fn(output: &mut [u8; 32], x32: &[u8; 32], ell_a64: &[u8; 64], ell_b64: &[u8; 64], data: Option<&[u8]>) -> i32;
+
+/* c1 = (sqrt(-3)-1)/2 */
+//static const secp256k1_fe secp256k1_ellswift_c1 = SECP256K1_FE_CONST(0x851695d4, 0x9a83f8ef, 0x919bb861, 0x53cbcb16, 0x630fb68a, 0xed0a766a, 0x3ec693d6, 0x8e6afa40);
+const secp256k1_ellswift_c1: secp256k1_fe = SECP256K1_FE_CONST!(0x851695d4, 0x9a83f8ef, 0x919bb861, 0x53cbcb16, 0x630fb68a, 0xed0a766a, 0x3ec693d6, 0x8e6afa40);
+/* c2 = (-sqrt(-3)-1)/2 = -(c1+1) */
+//static const secp256k1_fe secp256k1_ellswift_c2 = SECP256K1_FE_CONST(0x7ae96a2b, 0x657c0710, 0x6e64479e, 0xac3434e9, 0x9cf04975, 0x12f58995, 0xc1396c28, 0x719501ee);
+const secp256k1_ellswift_c2: secp256k1_fe = SECP256K1_FE_CONST!(0x7ae96a2b, 0x657c0710, 0x6e64479e, 0xac3434e9, 0x9cf04975, 0x12f58995, 0xc1396c28, 0x719501ee);
+/* c3 = (-sqrt(-3)+1)/2 = -c1 = c2+1 */
+//static const secp256k1_fe secp256k1_ellswift_c3 = SECP256K1_FE_CONST(0x7ae96a2b, 0x657c0710, 0x6e64479e, 0xac3434e9, 0x9cf04975, 0x12f58995, 0xc1396c28, 0x719501ef);
+const secp256k1_ellswift_c3: secp256k1_fe = SECP256K1_FE_CONST!(0x7ae96a2b, 0x657c0710, 0x6e64479e, 0xac3434e9, 0x9cf04975, 0x12f58995, 0xc1396c28, 0x719501ef);
+/* c4 = (sqrt(-3)+1)/2 = -c2 = c1+1 */
+//static const secp256k1_fe secp256k1_ellswift_c4 = SECP256K1_FE_CONST(0x851695d4, 0x9a83f8ef, 0x919bb861, 0x53cbcb16, 0x630fb68a, 0xed0a766a, 0x3ec693d6, 0x8e6afa41);
+const secp256k1_ellswift_c4: secp256k1_fe = SECP256K1_FE_CONST!(0x851695d4, 0x9a83f8ef, 0x919bb861, 0x53cbcb16, 0x630fb68a, 0xed0a766a, 0x3ec693d6, 0x8e6afa41);
+
+/** Decode ElligatorSwift encoding (u, t) to a fraction xn/xd representing a curve X coordinate. */
+//static void secp256k1_ellswift_xswiftec_frac_var(secp256k1_fe *xn, secp256k1_fe *xd, const secp256k1_fe *u, const secp256k1_fe *t) {
+fn secp256k1_ellswift_xswiftec_frac_var(xn: &mut secp256k1_fe, xd: &mut secp256k1_fe, u: &secp256k1_fe, t: &secp256k1_fe) {
+    /* The implemented algorithm is the following (all operations in GF(p)):
+     *
+     * - Let c0 = sqrt(-3) = 0xa2d2ba93507f1df233770c2a797962cc61f6d15da14ecd47d8d27ae1cd5f852.
+     * - If u = 0, set u = 1.
+     * - If t = 0, set t = 1.
+     * - If u^3+7+t^2 = 0, set t = 2*t.
+     * - Let X = (u^3+7-t^2)/(2*t).
+     * - Let Y = (X+t)/(c0*u).
+     * - If x3 = u+4*Y^2 is a valid x coordinate, return it.
+     * - If x2 = (-X/Y-u)/2 is a valid x coordinate, return it.
+     * - Return x1 = (X/Y-u)/2 (which is now guaranteed to be a valid x coordinate).
+     *
+     * Introducing s=t^2, g=u^3+7, and simplifying x1=-(x2+u) we get:
+     *
+     * - Let c0 = ...
+     * - If u = 0, set u = 1.
+     * - If t = 0, set t = 1.
+     * - Let s = t^2
+     * - Let g = u^3+7
+     * - If g+s = 0, set t = 2*t, s = 4*s
+     * - Let X = (g-s)/(2*t).
+     * - Let Y = (X+t)/(c0*u) = (g+s)/(2*c0*t*u).
+     * - If x3 = u+4*Y^2 is a valid x coordinate, return it.
+     * - If x2 = (-X/Y-u)/2 is a valid x coordinate, return it.
+     * - Return x1 = -(x2+u).
+     *
+     * Now substitute Y^2 = -(g+s)^2/(12*s*u^2) and X/Y = c0*u*(g-s)/(g+s). This
+     * means X and Y do not need to be evaluated explicitly anymore.
+     *
+     * - ...
+     * - If g+s = 0, set s = 4*s.
+     * - If x3 = u-(g+s)^2/(3*s*u^2) is a valid x coordinate, return it.
+     * - If x2 = (-c0*u*(g-s)/(g+s)-u)/2 is a valid x coordinate, return it.
+     * - Return x1 = -(x2+u).
+     *
+     * Simplifying x2 using 2 additional constants:
+     *
+     * - Let c1 = (c0-1)/2 = 0x851695d49a83f8ef919bb86153cbcb16630fb68aed0a766a3ec693d68e6afa40.
+     * - Let c2 = (-c0-1)/2 = 0x7ae96a2b657c07106e64479eac3434e99cf0497512f58995c1396c28719501ee.
+     * - ...
+     * - If x2 = u*(c1*s+c2*g)/(g+s) is a valid x coordinate, return it.
+     * - ...
+     *
+     * Writing x3 as a fraction:
+     *
+     * - ...
+     * - If x3 = (3*s*u^3-(g+s)^2)/(3*s*u^2) ...
+     * - ...
+     *
+     * Overall, we get:
+     *
+     * - Let c1 = 0x851695d49a83f8ef919bb86153cbcb16630fb68aed0a766a3ec693d68e6afa40.
+     * - Let c2 = 0x7ae96a2b657c07106e64479eac3434e99cf0497512f58995c1396c28719501ee.
+     * - If u = 0, set u = 1.
+     * - If t = 0, set s = 1, else set s = t^2.
+     * - Let g = u^3+7.
+     * - If g+s = 0, set s = 4*s.
+     * - If x3 = (3*s*u^3-(g+s)^2)/(3*s*u^2) is a valid x coordinate, return it.
+     * - If x2 = u*(c1*s+c2*g)/(g+s) is a valid x coordinate, return it.
+     * - Return x1 = -u*(c1*s+c2*g)/(g+s)-u.
+     */
+    let mut u1 = u.clone();
+    let mut s = secp256k1_fe::new();
+    let mut g = secp256k1_fe::new();
+    let mut p: secp256k1_fe;
+    let mut d = secp256k1_fe::new();
+    let mut n = secp256k1_fe::new();
+    let mut l = secp256k1_fe::new();
+
+    if secp256k1_fe_normalizes_to_zero_var(&u1) != 0 {
+        u1 = secp256k1_fe_one;
+    }
+    secp256k1_fe_sqr(&mut s, t);
+    if secp256k1_fe_normalizes_to_zero_var(t) != 0 {
+        s = secp256k1_fe_one;
+    }
+    secp256k1_fe_sqr(&mut l, &u1);                                          /* l = u^2 */
+    secp256k1_fe_mul(&mut g, &l, &u1);                                      /* g = u^3 */
+    secp256k1_fe_add_int(&mut g, SECP256K1_B);                              /* g = u^3 + 7 */
+    p = g.clone();                                                          /* p = g */
+    secp256k1_fe_add(&mut p, &s);                                           /* p = g+s */
+    if secp256k1_fe_normalizes_to_zero_var(&p) != 0 {
+        secp256k1_fe_mul_int(&mut s, 4);
+        /* Recompute p = g+s */
+        p = g.clone();                                                      /* p = g */
+        secp256k1_fe_add(&mut p, &s);                                       /* p = g+s */
+    }
+    secp256k1_fe_mul(&mut d, &s, &l);                                       /* d = s*u^2 */
+    secp256k1_fe_mul_int(&mut d, 3);                                        /* d = 3*s*u^2 */
+    secp256k1_fe_sqr(&mut l, &p);                                           /* l = (g+s)^2 */
+    let l1 = l.clone();
+    secp256k1_fe_negate(&mut l, &l1, 1);                                    /* l = -(g+s)^2 */
+    secp256k1_fe_mul(&mut n, &d, &u1);                                      /* n = 3*s*u^3 */
+    secp256k1_fe_add(&mut n, &l);                                           /* n = 3*s*u^3-(g+s)^2 */
+    if secp256k1_ge_x_frac_on_curve_var(&n, &d) != 0 {
+        /* Return x3 = n/d = (3*s*u^3-(g+s)^2)/(3*s*u^2) */
+        *xn = n;
+        *xd = d;
+        return;
+    }
+    *xd = p.clone();
+    secp256k1_fe_mul(&mut l, &secp256k1_ellswift_c1, &s);                  /* l = c1*s */
+    secp256k1_fe_mul(&mut n, &secp256k1_ellswift_c2, &g);                  /* n = c2*g */
+    secp256k1_fe_add(&mut n, &l);                                           /* n = c1*s+c2*g */
+    let n1 = n.clone();
+    secp256k1_fe_mul(&mut n, &n1, &u1);                                     /* n = u*(c1*s+c2*g) */
+    /* Possible optimization: in the invocation below, p^2 = (g+s)^2 is computed,
+     * which we already have computed above. This could be deduplicated. */
+    if secp256k1_ge_x_frac_on_curve_var(&n, &p) != 0 {
+        /* Return x2 = n/p = u*(c1*s+c2*g)/(g+s) */
+        *xn = n;
+        return;
+    }
+    secp256k1_fe_mul(&mut l, &p, &u1);                                      /* l = u*(g+s) */
+    secp256k1_fe_add(&mut n, &l);                                           /* n = u*(c1*s+c2*g)+u*(g+s) */
+    secp256k1_fe_negate(xn, &n, 2);                                         /* n = -u*(c1*s+c2*g)-u*(g+s) */
+    #[cfg(feature = "verify")] {
+        VERIFY_CHECK!(secp256k1_ge_x_frac_on_curve_var(xn, &p) != 0);
+    }
+    /* Return x3 = n/p = -(u*(c1*s+c2*g)/(g+s)+u) */
+}
+
+/** Decode ElligatorSwift encoding (u, t) to X coordinate. */
+//static void secp256k1_ellswift_xswiftec_var(secp256k1_fe *x, const secp256k1_fe *u, const secp256k1_fe *t) {
+fn secp256k1_ellswift_xswiftec_var(x: &mut secp256k1_fe, u: &secp256k1_fe, t: &secp256k1_fe) {
+    let mut xn = secp256k1_fe::new();
+    let mut xd = secp256k1_fe::new();
+    secp256k1_ellswift_xswiftec_frac_var(&mut xn, &mut xd, u, t);
+    let xd1 = xd.clone();
+    secp256k1_fe_inv_var(&mut xd, &xd1);
+    secp256k1_fe_mul(x, &xn, &xd);
+}
+
+/** Decode ElligatorSwift encoding (u, t) to point P. */
+//static void secp256k1_ellswift_swiftec_var(secp256k1_ge *p, const secp256k1_fe *u, const secp256k1_fe *t) {
+fn secp256k1_ellswift_swiftec_var(p: &mut secp256k1_ge, u: &secp256k1_fe, t: &secp256k1_fe) {
+    let mut x = secp256k1_fe::new();
+    secp256k1_ellswift_xswiftec_var(&mut x, u, t);
+    let ret = secp256k1_ge_set_xo_var(p, &x, secp256k1_fe_is_odd(t));
+    VERIFY_CHECK!(ret != 0);
+}
+
+/* Try to complete an ElligatorSwift encoding (u, t) for X coordinate x, given u and x.
+ *
+ * There may be up to 8 distinct t values such that (u, t) decodes back to x, but also
+ * fewer, or none at all. Each such partial inverse can be accessed individually using a
+ * distinct input argument c (in range 0-7), and some or all of these may return failure.
+ * The following guarantees exist:
+ * - Given (x, u), no two distinct c values give the same successful result t.
+ * - Every successful result maps back to x through secp256k1_ellswift_xswiftec_var.
+ * - Given (x, u), all t values that map back to x can be found by combining the
+ *   successful results from this function over all c values, with the exception of:
+ *   - this function cannot be called with u=0
+ *   - no result with t=0 will be returned
+ *   - no result for which u^3 + t^2 + 7 = 0 will be returned.
+ *
+ * The rather unusual encoding of bits in c (a large "if" based on the middle bit, and then
+ * using the low and high bits to pick signs of square roots) is to match the paper's
+ * encoding more closely: c=0 through c=3 match branches 1..4 in the paper, while c=4
+ * through c=7 are copies of those with an additional negation of sqrt(w).
+ */
+//static int secp256k1_ellswift_xswiftec_inv_var(secp256k1_fe *t, const secp256k1_fe *x_in, const secp256k1_fe *u_in, int c) {
+fn secp256k1_ellswift_xswiftec_inv_var(t: &mut secp256k1_fe, x_in: &secp256k1_fe, u_in: &secp256k1_fe, c: i32) -> i32 {
+    /* The implemented algorithm is this (all arithmetic, except involving c, is mod p):
+     *
+     * - If (c & 2) = 0:
+     *   - If (-x-u) is a valid X coordinate, fail.
+     *   - Let s=-(u^3+7)/(u^2+u*x+x^2).
+     *   - If s is not square, fail.
+     *   - Let v=x.
+     * - If (c & 2) = 2:
+     *   - Let s=x-u.
+     *   - If s is not square, fail.
+     *   - Let r=sqrt(-s*(4*(u^3+7)+3*u^2*s)); fail if it doesn't exist.
+     *   - If (c & 1) = 1 and r = 0, fail.
+     *   - If s=0, fail.
+     *   - Let v=(r/s-u)/2.
+     * - Let w=sqrt(s).
+     * - If (c & 5) = 0: return -w*(c3*u + v).
+     * - If (c & 5) = 1: return  w*(c4*u + v).
+     * - If (c & 5) = 4: return  w*(c3*u + v).
+     * - If (c & 5) = 5: return -w*(c4*u + v).
+     */
+    let mut x = x_in.clone();
+    let mut u = u_in.clone();
+    let mut g = secp256k1_fe::new();
+    let mut v: secp256k1_fe;
+    let mut s = secp256k1_fe::new();
+    let mut m = secp256k1_fe::new();
+    let mut r = secp256k1_fe::new();
+    let mut q = secp256k1_fe::new();
+    let mut ret: i32;
+
+    secp256k1_fe_normalize_weak(&mut x);
+    secp256k1_fe_normalize_weak(&mut u);
+
+    VERIFY_CHECK!(c >= 0 && c < 8);
+    VERIFY_CHECK!(secp256k1_ge_x_on_curve_var(&x) != 0);
+
+    if (c & 2) == 0 {
+        /* c is in {0, 1, 4, 5}. In this case we need to check whether -(x+u) is a valid X coordinate. */
+        m = x.clone();                                                      /* m = x */
+        secp256k1_fe_add(&mut m, &u);                                       /* m = u+x */
+        let m1 = m.clone();
+        secp256k1_fe_negate(&mut m, &m1, 2);                                /* m = -u-x */
+        /* Test if (-u-x) is a valid X coordinate. If so, fail. */
+        if secp256k1_ge_x_on_curve_var(&m) != 0 {
+            return 0;
+        }
+
+        /* Let s = -(u^3 + 7)/(u^2 + u*x + x^2) [first part] */
+        secp256k1_fe_sqr(&mut s, &m);                                       /* s = (u+x)^2 */
+        let s1 = s.clone();
+        secp256k1_fe_negate(&mut s, &s1, 1);                                /* s = -(u+x)^2 */
+        secp256k1_fe_mul(&mut m, &u, &x);                                   /* m = u*x */
+        secp256k1_fe_add(&mut s, &m);                                       /* s = -(u^2 + u*x + x^2) */
+
+        /* Note that at this point, s = 0 is impossible. If it were the case:
+         *             s = -(u^2 + u*x + x^2) = 0
+         * => u^2 + u*x + x^2 = 0
+         * => (u + 2*x) * (u^2 + u*x + x^2) = 0
+         * => 2*x^3 + 3*x^2*u + 3*x*u^2 + u^3 = 0
+         * => (x + u)^3 + x^3 = 0
+         * => x^3 = -(x + u)^3
+         * => x^3 + B = (-u - x)^3 + B
+         *
+         * However, we know x^3 + B is square (because x is on the curve) and
+         * that (-u-x)^3 + B is not square (the secp256k1_ge_x_on_curve_var(&m)
+         * test above would have failed). This is a contradiction, and thus the
+         * assumption s=0 is false. */
+        VERIFY_CHECK!(secp256k1_fe_normalizes_to_zero_var(&s) == 0);
+
+        /* If s is not square, fail. We have not fully computed s yet, but s is square iff
+         * -(u^3+7)*(u^2+u*x+x^2) is square (because a/b is square iff a*b is square and b is
+         * nonzero). */
+        secp256k1_fe_sqr(&mut g, &u);                                       /* g = u^2 */
+        let g1 = g.clone();
+        secp256k1_fe_mul(&mut g, &g1, &u);                                  /* g = u^3 */
+        secp256k1_fe_add_int(&mut g, SECP256K1_B);                          /* g = u^3+7 */
+        secp256k1_fe_mul(&mut m, &s, &g);                                   /* m = -(u^3 + 7)*(u^2 + u*x + x^2) */
+        if secp256k1_fe_is_square_var(&m) == 0 {
+            return 0;
+        }
+
+        /* Let s = -(u^3 + 7)/(u^2 + u*x + x^2) [second part] */
+        let s1 = s.clone();
+        secp256k1_fe_inv_var(&mut s, &s1);                                  /* s = -1/(u^2 + u*x + x^2) [no div by 0] */
+        let s1 = s.clone();
+        secp256k1_fe_mul(&mut s, &s1, &g);                                  /* s = -(u^3 + 7)/(u^2 + u*x + x^2) */
+
+        /* Let v = x. */
+        v = x.clone();
+    } else {
+        /* c is in {2, 3, 6, 7}. In this case we need to use the x1 and x2 formulas. */
+
+        /* Let s = x-u. */
+        secp256k1_fe_negate(&mut m, &u, 1);                                 /* m = -u */
+        s = m.clone();                                                      /* s = -u */
+        secp256k1_fe_add(&mut s, &x);                                       /* s = x-u */
+
+        /* If s is not square, fail. */
+        if secp256k1_fe_is_square_var(&s) == 0 {
+            return 0;
+        }
+
+        /* Let r = sqrt(-s*(4*(u^3+7)+3*u^2*s)); fail if it doesn't exist. */
+        secp256k1_fe_sqr(&mut g, &u);                                       /* g = u^2 */
+        secp256k1_fe_mul(&mut q, &s, &g);                                   /* q = s*u^2 */
+        secp256k1_fe_mul_int(&mut q, 3);                                    /* q = 3*s*u^2 */
+        let g1 = g.clone();
+        secp256k1_fe_mul(&mut g, &g1, &u);                                  /* g = u^3 */
+        secp256k1_fe_mul_int(&mut g, 4);                                    /* g = 4*u^3 */
+        secp256k1_fe_add_int(&mut g, 4 * SECP256K1_B);                      /* g = 4*(u^3+7) */
+        secp256k1_fe_add(&mut q, &g);                                       /* q = 4*(u^3+7)+3*s*u^2 */
+        let q1 = q.clone();
+        secp256k1_fe_mul(&mut q, &q1, &s);                                  /* q = s*(4*(u^3+7)+3*u^2*s) */
+        let q1 = q.clone();
+        secp256k1_fe_negate(&mut q, &q1, 1);                                /* q = -s*(4*(u^3+7)+3*u^2*s) */
+        if secp256k1_fe_is_square_var(&q) == 0 {
+            return 0;
+        }
+        ret = secp256k1_fe_sqrt(&mut r, &q);                                /* r = sqrt(-s*(4*(u^3+7)+3*u^2*s)) */
+        VERIFY_CHECK!(ret != 0);
+
+        /* If (c & 1) = 1 and r = 0, fail. */
+        if (c & 1) != 0 && secp256k1_fe_normalizes_to_zero_var(&r) != 0 {
+            return 0;
+        }
+
+        /* If s = 0, fail. */
+        if secp256k1_fe_normalizes_to_zero_var(&s) != 0 {
+            return 0;
+        }
+
+        /* Let v = (r/s-u)/2. */
+        v = secp256k1_fe::new();
+        secp256k1_fe_inv_var(&mut v, &s);                                   /* v = 1/s [no div by 0] */
+        let v1 = v.clone();
+        secp256k1_fe_mul(&mut v, &v1, &r);                                  /* v = r/s */
+        secp256k1_fe_add(&mut v, &m);                                       /* v = r/s-u */
+        secp256k1_fe_half(&mut v);                                          /* v = (r/s-u)/2 */
+    }
+
+    /* Let w = sqrt(s). */
+    ret = secp256k1_fe_sqrt(&mut m, &s);                                    /* m = sqrt(s) = w */
+    VERIFY_CHECK!(ret != 0);
+
+    /* Return logic. */
+    if (c & 5) == 0 || (c & 5) == 5 {
+        let m1 = m.clone();
+        secp256k1_fe_negate(&mut m, &m1, 1);                                /* m = -w */
+    }
+    /* Now m = {-w if c&5=0 or c&5=5; w otherwise}. */
+    let u1 = u.clone();
+    secp256k1_fe_mul(&mut u, &u1, if (c & 1) != 0 { &secp256k1_ellswift_c4 } else { &secp256k1_ellswift_c3 });
+    /* u = {c4 if c&1=1; c3 otherwise}*u */
+    secp256k1_fe_add(&mut u, &v);                                           /* u = {c4 if c&1=1; c3 otherwise}*u + v */
+    secp256k1_fe_mul(t, &m, &u);
+    return 1;
+}
+
+/** Use SHA256 as a PRNG, returning SHA256(hasher || cnt).
+ *
+ * hasher is a SHA256 object to which an incrementing 4-byte counter is written to generate randomness.
+ * Writing 13 bytes (4 bytes for counter, plus 9 bytes for the SHA256 padding) cannot cross a
+ * 64-byte block size boundary (to make sure it only triggers a single SHA256 compression). */
+//static void secp256k1_ellswift_prng(unsigned char* out32, const secp256k1_sha256 *hasher, uint32_t cnt) {
+fn secp256k1_ellswift_prng(out32: &mut [u8; 32], hasher: &secp256k1_sha256, cnt: u32) {
+    let mut hash = secp256k1_sha256 { s: hasher.s, buf: hasher.buf, bytes: hasher.bytes };
+    secp256k1_sha256_write(&mut hash, &cnt.to_le_bytes());
+    secp256k1_sha256_finalize(&mut hash, out32);
+}
+
+/** Find an ElligatorSwift encoding (u, t) for X coordinate x, and random Y coordinate.
+ *
+ * u32 is the 32-byte big endian encoding of u; t is the output field element t that still
+ * needs encoding.
+ *
+ * hasher is a hasher in the secp256k1_ellswift_prng sense, with the same restrictions. */
+//static void secp256k1_ellswift_xelligatorswift_var(unsigned char *u32, secp256k1_fe *t, const secp256k1_fe *x, const secp256k1_sha256 *hasher) {
+fn secp256k1_ellswift_xelligatorswift_var(u32: &mut [u8; 32], t: &mut secp256k1_fe, x: &secp256k1_fe, hasher: &secp256k1_sha256) {
+    /* Pool of 3-bit branch values. */
+    let mut branch_hash = [0u8; 32];
+    /* Number of 3-bit values in branch_hash left. */
+    let mut branches_left: i32 = 0;
+    /* Field elements u and branch values are extracted from RNG based on hasher for consecutive
+     * values of cnt. cnt==0 is first used to populate a pool of 64 4-bit branch values. The 64
+     * cnt values that follow are used to generate field elements u. cnt==65 (and multiples
+     * thereof) are used to repopulate the pool and start over, if that were ever necessary.
+     * On average, 4 iterations are needed. */
+    let mut cnt: u32 = 0;
+    loop {
+        let branch: i32;
+        let mut u = secp256k1_fe::new();
+        /* If the pool of branch values is empty, populate it. */
+        if branches_left == 0 {
+            secp256k1_ellswift_prng(&mut branch_hash, hasher, cnt);
+            cnt += 1;
+            branches_left = 64;
+        }
+        /* Take a 3-bit branch value from the branch pool (top bit is discarded). */
+        branches_left -= 1;
+        branch = ((branch_hash[((63 - branches_left) >> 1) as usize] >> (((63 - branches_left) & 1) << 2)) & 7) as i32;
+        /* Compute a new u value by hashing. */
+        secp256k1_ellswift_prng(u32, hasher, cnt);
+        cnt += 1;
+        /* overflow is not a problem (we prefer uniform u32 over uniform u). */
+        secp256k1_fe_set_b32(&mut u, u32);
+        /* Since u is the output of a hash, it should practically never be 0. We could apply the
+         * u=0 to u=1 correction here too to deal with that case still, but it's such a low
+         * probability event that we do not bother. */
+        VERIFY_CHECK!(secp256k1_fe_normalizes_to_zero_var(&u) == 0);
+
+        /* Find a remainder t, and return it if found. */
+        if secp256k1_ellswift_xswiftec_inv_var(t, x, &u, branch) != 0 {
+            break;
+        }
+    }
+}
+
+/** Find an ElligatorSwift encoding (u, t) for point P.
+ *
+ * This is similar secp256k1_ellswift_xelligatorswift_var, except it takes a full group element p
+ * as input, and returns an encoding that matches the provided Y coordinate rather than a random
+ * one.
+ */
+//static void secp256k1_ellswift_elligatorswift_var(unsigned char *u32, secp256k1_fe *t, const secp256k1_ge *p, const secp256k1_sha256 *hasher) {
+fn secp256k1_ellswift_elligatorswift_var(u32: &mut [u8; 32], t: &mut secp256k1_fe, p: &secp256k1_ge, hasher: &secp256k1_sha256) {
+    secp256k1_ellswift_xelligatorswift_var(u32, t, &p.x, hasher);
+    secp256k1_fe_normalize_var(t);
+    if secp256k1_fe_is_odd(t) != secp256k1_fe_is_odd(&p.y) {
+        let t1 = t.clone();
+        secp256k1_fe_negate(t, &t1, 1);
+        secp256k1_fe_normalize_var(t);
+    }
+}
+
+/** Set hash state to the BIP340 tagged hash midstate for "secp256k1_ellswift_encode". */
+//static void secp256k1_ellswift_sha256_init_encode(secp256k1_sha256* hash) {
+fn secp256k1_ellswift_sha256_init_encode(hash: &mut secp256k1_sha256) {
+    secp256k1_sha256_initialize(hash);
+    hash.s[0] = 0xd1a6524b_u32;
+    hash.s[1] = 0x028594b3_u32;
+    hash.s[2] = 0x96e42f4e_u32;
+    hash.s[3] = 0x1037a177_u32;
+    hash.s[4] = 0x1b8fcb8b_u32;
+    hash.s[5] = 0x56023885_u32;
+    hash.s[6] = 0x2560ede1_u32;
+    hash.s[7] = 0xd626b715_u32;
+
+    hash.bytes = 64;
+}
+
+/** Compute a 64-byte ElligatorSwift encoding of a given public key.
+ *
+ *  A given public key has 2^256 possible encodings; this function picks one
+ *  uniformly at random from those, based on the provided randomness rnd32.
+ *
+ *  Returns: 1 always.
+ *  Args:    ctx:        pointer to a context object
+ *  Out:     ell64:      pointer to a 64-byte array to be filled
+ *  In:      pubkey:     a pointer to a secp256k1_pubkey containing an
+ *                       initialized public key
+ *           rnd32:      pointer to 32 bytes of randomness
+ */
+//int secp256k1_ellswift_encode(const secp256k1_context *ctx, unsigned char *ell64, const secp256k1_pubkey *pubkey, const unsigned char *rnd32) {
+pub fn secp256k1_ellswift_encode(ctx: &secp256k1_context, ell64: &mut [u8; 64], pubkey: &secp256k1_pubkey, rnd32: &[u8; 32]) -> i32 {
+    let mut p = secp256k1_ge::new();
+
+    if secp256k1_pubkey_load(ctx, &mut p, pubkey) != 0 {
+        let mut t = secp256k1_fe::new();
+        let mut p64 = [0u8; 64];
+        let mut u32 = [0u8; 32];
+        let mut hash = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+
+        /* Set up hasher state; the used RNG is H(pubkey || "\x00"*31 || rnd32 || cnt++), using
+         * BIP340 tagged hash with tag "secp256k1_ellswift_encode". */
+        secp256k1_ellswift_sha256_init_encode(&mut hash);
+        let ser_ret = secp256k1_eckey_pubkey_serialize(&mut p, &mut p64, true);
+        VERIFY_CHECK!(ser_ret != 0);
+        secp256k1_sha256_write(&mut hash, &p64);
+        secp256k1_sha256_write(&mut hash, rnd32);
+
+        /* Compute ElligatorSwift encoding and construct output. */
+        secp256k1_ellswift_elligatorswift_var(&mut u32, &mut t, &p, &hash);
+        ell64[..32].copy_from_slice(&u32);                                  /* puts u in ell64[0..32] */
+        secp256k1_fe_get_b32(&mut ell64[32..], &t);                         /* puts t in ell64[32..64] */
+        return 1;
+    }
+    /* Only returned in case the provided pubkey is invalid. */
+    return 0;
+}
+
+/** Set hash state to the BIP340 tagged hash midstate for "secp256k1_ellswift_create". */
+//static void secp256k1_ellswift_sha256_init_create(secp256k1_sha256* hash) {
+fn secp256k1_ellswift_sha256_init_create(hash: &mut secp256k1_sha256) {
+    secp256k1_sha256_initialize(hash);
+    hash.s[0] = 0xd29e1bf5_u32;
+    hash.s[1] = 0xf7025f42_u32;
+    hash.s[2] = 0x9b024773_u32;
+    hash.s[3] = 0x094cb7d5_u32;
+    hash.s[4] = 0xe59ed789_u32;
+    hash.s[5] = 0x03bc9786_u32;
+    hash.s[6] = 0x68335b35_u32;
+    hash.s[7] = 0x4e363b53_u32;
+
+    hash.bytes = 64;
+}
+
+/** Compute an ElligatorSwift public key for a secret key.
+ *
+ *  Returns: 1: secret was valid, public key was stored.
+ *           0: secret was invalid, try again.
+ *  Args:    ctx:        pointer to a context object
+ *  Out:     ell64:      pointer to a 64-byte array to receive the ElligatorSwift
+ *                       public key
+ *  In:      seckey32:   pointer to a 32-byte secret key
+ *           auxrnd32:   (optional) pointer to 32 bytes of randomness
+ *
+ *  Constant time in seckey and auxrnd32, but not in the resulting public key.
+ *
+ *  It is recommended to pass auxrnd32 randomness, as it provides additional
+ *  protection against side-channel attacks; it does not affect the security
+ *  of the produced encoding.
+ */
+//int secp256k1_ellswift_create(const secp256k1_context *ctx, unsigned char *ell64, const unsigned char *seckey32, const unsigned char *auxrnd32) {
+pub fn secp256k1_ellswift_create(ctx: &mut secp256k1_context, ell64: &mut [u8; 64], seckey32: &[u8; 32], auxrnd32: Option<&[u8; 32]>) -> i32 {
+    let mut p = secp256k1_ge::new();
+    let mut t = secp256k1_fe::new();
+    let mut u32 = [0u8; 32];
+    let mut hash = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+    let mut seckey_scalar = secp256k1_scalar::new();
+    let zero32 = [0u8; 32];
+
+    *ell64 = [0u8; 64];
+
+    /* Compute (affine) public key */
+    let ret = secp256k1_ec_pubkey_create_helper(&mut ctx.ecmult_gen_ctx, &mut seckey_scalar, &mut p, seckey32);
+    secp256k1_fe_normalize_var(&mut p.x);
+    secp256k1_fe_normalize_var(&mut p.y);
+
+    /* Set up hasher state. The used RNG is H(privkey || "\x00"*32 [|| auxrnd32] || cnt++),
+     * using BIP340 tagged hash with tag "secp256k1_ellswift_create". */
+    secp256k1_ellswift_sha256_init_create(&mut hash);
+    secp256k1_sha256_write(&mut hash, seckey32);
+    secp256k1_sha256_write(&mut hash, &zero32);
+    if let Some(auxrnd32) = auxrnd32 {
+        secp256k1_sha256_write(&mut hash, auxrnd32);
+    }
+
+    /* Compute ElligatorSwift encoding and construct output. */
+    secp256k1_ellswift_elligatorswift_var(&mut u32, &mut t, &p, &hash);
+    ell64[..32].copy_from_slice(&u32);                                      /* puts u in ell64[0..32] */
+    secp256k1_fe_get_b32(&mut ell64[32..], &t);                             /* puts t in ell64[32..64] */
+
+    secp256k1_memczero(ell64, !ret);
+    secp256k1_scalar_clear(&mut seckey_scalar);
+
+    return ret as i32;
+}
+
+/** Decode a 64-bytes ElligatorSwift encoded public key.
+ *
+ *  Returns: always 1
+ *  Args:    ctx:        pointer to a context object
+ *  Out:     pubkey:     pointer to a secp256k1_pubkey that will be filled
+ *  In:      ell64:      pointer to a 64-byte array to decode
+ *
+ *  This function runs in variable time.
+ */
+//int secp256k1_ellswift_decode(const secp256k1_context *ctx, secp256k1_pubkey *pubkey, const unsigned char *ell64) {
+pub fn secp256k1_ellswift_decode(_ctx: &secp256k1_context, pubkey: &mut secp256k1_pubkey, ell64: &[u8; 64]) -> i32 {
+    let mut u = secp256k1_fe::new();
+    let mut t = secp256k1_fe::new();
+    let mut p = secp256k1_ge::new();
+
+    secp256k1_fe_set_b32(&mut u, &ell64[..32]);
+    secp256k1_fe_set_b32(&mut t, &ell64[32..]);
+    secp256k1_fe_normalize_var(&mut t);
+    secp256k1_ellswift_swiftec_var(&mut p, &u, &t);
+    secp256k1_pubkey_save(pubkey, &mut p);
+    return 1;
+}
+
+//static int ellswift_xdh_hash_function_prefix(unsigned char *output, const unsigned char *x32, const unsigned char *ell_a64, const unsigned char *ell_b64, void *data) {
+fn ellswift_xdh_hash_function_prefix(output: &mut [u8; 32], x32: &[u8; 32], ell_a64: &[u8; 64], ell_b64: &[u8; 64], data: Option<&[u8]>) -> i32 {
+    let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+    let prefix64 = match data {
+        Some(prefix64) if prefix64.len() >= 64 => &prefix64[..64],
+        _ => return 0,
+    };
+
+    secp256k1_sha256_initialize(&mut sha);
+    secp256k1_sha256_write(&mut sha, prefix64);
+    secp256k1_sha256_write(&mut sha, ell_a64);
+    secp256k1_sha256_write(&mut sha, ell_b64);
+    secp256k1_sha256_write(&mut sha, x32);
+    secp256k1_sha256_finalize(&mut sha, output);
+
+    return 1;
+}
+
+/** An implementation of an secp256k1_ellswift_xdh_hash_function which uses
+ *  SHA256(prefix64 || ell_a64 || ell_b64 || x32), where prefix64 is the 64-byte
+ *  array pointed to by data. */
+//SECP256K1_API const secp256k1_ellswift_xdh_hash_function secp256k1_ellswift_xdh_hash_function_prefix;
+pub const secp256k1_ellswift_xdh_hash_function_prefix: secp256k1_ellswift_xdh_hash_function = ellswift_xdh_hash_function_prefix;
+
+/** Set hash state to the BIP340 tagged hash midstate for "bip324_ellswift_xonly_ecdh". */
+//static void secp256k1_ellswift_sha256_init_bip324(secp256k1_sha256* hash) {
+fn secp256k1_ellswift_sha256_init_bip324(hash: &mut secp256k1_sha256) {
+    secp256k1_sha256_initialize(hash);
+    hash.s[0] = 0x8c12d730_u32;
+    hash.s[1] = 0x827bd392_u32;
+    hash.s[2] = 0x9e4fb2ee_u32;
+    hash.s[3] = 0x207b373e_u32;
+    hash.s[4] = 0x2292bd7a_u32;
+    hash.s[5] = 0xaa5441bc_u32;
+    hash.s[6] = 0x15c3779f_u32;
+    hash.s[7] = 0xcfb52549_u32;
+
+    hash.bytes = 64;
+}
+
+//static int ellswift_xdh_hash_function_bip324(unsigned char* output, const unsigned char *x32, const unsigned char *ell_a64, const unsigned char *ell_b64, void *data) {
+fn ellswift_xdh_hash_function_bip324(output: &mut [u8; 32], x32: &[u8; 32], ell_a64: &[u8; 64], ell_b64: &[u8; 64], _data: Option<&[u8]>) -> i32 {
+    let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+
+    secp256k1_ellswift_sha256_init_bip324(&mut sha);
+    secp256k1_sha256_write(&mut sha, ell_a64);
+    secp256k1_sha256_write(&mut sha, ell_b64);
+    secp256k1_sha256_write(&mut sha, x32);
+    secp256k1_sha256_finalize(&mut sha, output);
+
+    return 1;
+}
+
+/** An implementation of an secp256k1_ellswift_xdh_hash_function compatible with
+ *  BIP324. It returns H_tag(ell_a64 || ell_b64 || x32), where H_tag is the
+ *  BIP340 tagged hash function with tag "bip324_ellswift_xonly_ecdh". Equivalent
+ *  to secp256k1_ellswift_xdh_hash_function_prefix with prefix64 set to
+ *  SHA256("bip324_ellswift_xonly_ecdh")||SHA256("bip324_ellswift_xonly_ecdh").
+ *  The data argument is ignored. */
+//SECP256K1_API const secp256k1_ellswift_xdh_hash_function secp256k1_ellswift_xdh_hash_function_bip324;
+pub const secp256k1_ellswift_xdh_hash_function_bip324: secp256k1_ellswift_xdh_hash_function = ellswift_xdh_hash_function_bip324;
+
+/** Given a private key, and ElligatorSwift public keys sent in both directions,
+ *  compute a shared secret using x-only Elliptic Curve Diffie-Hellman (ECDH).
+ *
+ *  Returns: 1: shared secret was successfully computed
+ *           0: secret was invalid or hashfp returned 0
+ *  Args:    ctx:       pointer to a context object.
+ *  Out:     output:    pointer to an array to be filled by hashfp.
+ *  In:      ell_a64:   pointer to the 64-byte encoded public key of party A
+ *                      (will not be modified)
+ *           ell_b64:   pointer to the 64-byte encoded public key of party B
+ *                      (will not be modified)
+ *           seckey32:  pointer to our 32-byte secret key
+ *           party:     boolean indicating which party we are: zero if we are
+ *                      party A, non-zero if we are party B. seckey32 must be
+ *                      the private key corresponding to that party's ell_?64.
+ *                      This correspondence is not checked.
+ *           hashfp:    hash function to use.
+ *           data:      arbitrary data pointer passed through to hashfp.
+ *
+ *  Constant time in seckey32.
+ *
+ *  This function is more efficient than decoding the public keys, and performing
+ *  ECDH on them.
+ */
+//int secp256k1_ellswift_xdh(const secp256k1_context *ctx, unsigned char *output, const unsigned char *ell_a64, const unsigned char *ell_b64, const unsigned char *seckey32, int party, secp256k1_ellswift_xdh_hash_function hashfp, void *data) {
+pub fn secp256k1_ellswift_xdh(_ctx: &secp256k1_context, output: &mut [u8; 32], ell_a64: &[u8; 64], ell_b64: &[u8; 64], seckey32: &[u8; 32], party: i32, hashfp: secp256k1_ellswift_xdh_hash_function, data: Option<&[u8]>) -> i32 {
+    let ret: i32;
+    let mut overflow: i32 = 0;
+    let mut s = secp256k1_scalar::new();
+    let mut u = secp256k1_fe::new();
+    let mut t = secp256k1_fe::new();
+    let mut p = secp256k1_ge::new();
+    let mut res = secp256k1_gej::new();
+    let mut sx = [0u8; 32];
+
+    /* Load remote public key. This tree has no secp256k1_ecmult_const_xonly, so lift
+     * the decoded X coordinate to a full point instead; only the X coordinate of the
+     * product is used, which is the same for either choice of Y. */
+    let theirs64 = if party != 0 { ell_a64 } else { ell_b64 };
+    secp256k1_fe_set_b32(&mut u, &theirs64[..32]);
+    secp256k1_fe_set_b32(&mut t, &theirs64[32..]);
+    secp256k1_fe_normalize_var(&mut t);
+    secp256k1_ellswift_swiftec_var(&mut p, &u, &t);
+
+    /* Load private key (using one if invalid). */
+    secp256k1_scalar_set_b32(&mut s, seckey32, &mut overflow);
+    overflow = secp256k1_scalar_is_zero(&s);
+    secp256k1_scalar_cmov(&mut s, &secp256k1_scalar_one, overflow);
+
+    /* Compute shared X coordinate. */
+    secp256k1_ecmult_const(&mut res, &p, &s);
+    secp256k1_ge_set_gej(&mut p, &mut res);
+    secp256k1_fe_normalize(&mut p.x);
+    secp256k1_fe_get_b32(&mut sx, &p.x);
+
+    /* Invoke hasher */
+    ret = hashfp(output, &sx, ell_a64, ell_b64, data);
+
+    sx = [0u8; 32];
+    secp256k1_fe_clear(&mut p.x);
+    secp256k1_scalar_clear(&mut s);
+
+    ((ret != 0) as i32) & ((overflow == 0) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{secp256k1_ec_pubkey_create, secp256k1_ec_pubkey_serialize, SECP256K1_EC_COMPRESSED};
+
+    // Rows in the format of BIP324's ellswift_decode_test_vectors.csv, computed with the
+    // BIP324 reference xswiftec and chosen to reach every branch of xswiftec_frac_var:
+    // (ellswift encoding, compressed public key it decodes to)
+    const ELLSWIFT_DECODE_VECTORS: [(&str, &str); 15] = [
+        // u%p == 0 and t%p == 0
+        ("00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+         "02edd1fd3e327ce90cc7a3542614289aee9682003e9cf7dcc9cf2ca9743be5aa0c"),
+        // u >= p and t >= p, both reduce to 0
+        ("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2ffffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+         "02edd1fd3e327ce90cc7a3542614289aee9682003e9cf7dcc9cf2ca9743be5aa0c"),
+        // u%p == 0
+        ("0000000000000000000000000000000000000000000000000000000000000000177743ca78937308b729ed18f795c827dbbfa6dfb76691142b15e2da971029da",
+         "02052401db51a0c883d47563c6f57ee9ec63bd0ad7270bbf54e56f140f47a61d8b"),
+        // t%p == 0
+        ("811991e143be2ddd01dc3471127aad5e57f338c2d44c4b9b6ccd394b88f43c730000000000000000000000000000000000000000000000000000000000000000",
+         "02cb80f09c82d42d77af4e6e91741459b9f3d3c13b11719270bdf228d5de6919b6"),
+        // u >= p
+        ("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc34b32a07a0a1ce1d1724748aee5ae1ff26e07193e75a4b5cbe34ab2ee52fa79691",
+         "03d5e68931a65c27b9735d2b9a9700e8754276d6ea9a71284d4a45181375c1432e"),
+        // t >= p
+        ("32a3da57cbb8fd1b386ccd40928b93244b3b1347dbc17a92c7d3bfc56588ecbafffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc36",
+         "03fc21a1bc429e4ffe99cf7c45d2f65136b9031c7a6787418964107548814efe2c"),
+        // u >= p and t >= p
+        ("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+         "02a9d2410259b9697cce4599ef2f96fbe8b47d53dcdff28ba28810f0607b89a740"),
+        // u^3+t^2+7 == 0
+        ("8d3e42890c677d9d7d48a64aa933ea19295d70e7d912dfab416e666eb7839faf759bb7642dee98b3c7f07f95e17f412d4e49cc4cfa5527a54d269afbacc90faf",
+         "03cd180e9992217befbb54c18d2a1c29f4a22f792444392f8f1e75298029c7fa00"),
+        // u^3+t^2+7 == 0
+        ("61fd5bfbc27c3d9f55c723224c2158944b8aa5cc206b950c41a0f164a3a89a4d204ac8d577976256772dbd6b5a4ff034bafdadbdf5325ba87f2fa05321eddeb1",
+         "03b9f8fae2e13887294b9a870f2726ed7bcdbb21e2c9ef70382955185ad1b0abc1"),
+        // valid_x(x1), !valid_x(x3), !valid_x(x2)
+        ("745ca3680f7cf12752eef6c11636b54b9ccb3775160bcd87e27bb9133c642baf771d79b7a200d9dc37fb794648e14a4458a9fa0fd9983a1ecdaf8cc82279e5c2",
+         "02fce2d39f205e46557b0f34570ff4f3fa16b8e5cc31b5b1b0deecc6d3b264b74f"),
+        // valid_x(x2), !valid_x(x3)
+        ("b8262932dc343df6b83881e80981a42cab2d3d6fdc37e45b9dac4424252abd490ba09079cab7a995eadf0bd3a0e7bd3c6bed9879f5cc5e985a6f952c7cff6e9f",
+         "039c2663d9d95ed0662bc692ff900c6986529e6e05014702f099ec2abe5da1c3fa"),
+        // valid_x(x2), !valid_x(x3)
+        ("f3630ce7cb511527b4eabc99985039f33e46d3eb279b4b2abae66c712aeec02b4c2325bb796eb907eac1b96c0b37fb6b0d0e140220de588195a48c74b748b9c9",
+         "03e8a3dc7081d545702fa6d816270a06a3ce27726898dd87277834d6f40a348748"),
+        // valid_x(x3)
+        ("22bec8f29d99d2e446c3b043efa80d8c5b9acb58a645e90b2d64084fd1378ff912f2080ec66928d4273c4c6bedb13f36e72f363368e6dd1d1eaecf09d717afa2",
+         "0229fa61658dfbf2cadda91650a29d37e18e579a8803103ccf359c7b987120303c"),
+        // valid_x(x1), !valid_x(x3), !valid_x(x2)
+        ("b24e488684767bc7fbe2a23d83008340afb209af6a6a881abd7df142199413fce5a5d57e84413337136a110c6137c33ac846a3e47f3c6f331d5f59295739428e",
+         "0214b7ca59035db4145c8f2178aa4c84bb7a22884d989b4d252dd66f8a4b95d10d"),
+        // valid_x(x3)
+        ("90645ae693715ca63f67b10a39e88c94b922c39a3ad2736c5a4f563ff7d6344094fbede5e95e51dbf34e5e828836d8faf00013d2e28256589e5023b56cfbb495",
+         "0301039db840f98b53c3c330f8604d247900176b6925340b4c95d3c6a513f31c90"),
+    ];
+
+    // Rows in the format of BIP324's xswiftec_inv_test_vectors.csv, computed with the
+    // BIP324 reference xswiftec_inv and chosen to reach every failure case:
+    // (u, x, t for each c in 0..8 or "" if xswiftec_inv fails for that c)
+    const XSWIFTEC_INV_VECTORS: [(&str, &str, [&str; 8]); 9] = [
+        // random; case0 ok, case2 ok, case3 ok
+        ("d7d09043fea905ff32b7486a9414de6727be3ef640303e00386154ce4bb7b604", "a03507e6257570b16e707a1e31d866681fd9feb24e52653adb25bcfa062feb19",
+         ["df94eb346761fda3638126e745b3fd3ecd61bd18c05ccde77810bf1b64b904f4",
+          "c45f2674501a1831d45623fc95f708c5a07a4c06adf41f5e6ede90b7849bdf6b",
+          "de9e876047dfe896e3771903a0f1747c341ae37c1bf9caea890d6a670c979e47",
+          "6533ce948b92cfa7bc70729bc372324d22a52988b42617e7795cea1b2bc2383b",
+          "206b14cb989e025c9c7ed918ba4c02c1329e42e73fa3321887ef40e39b46f73b",
+          "3ba0d98bafe5e7ce2ba9dc036a08f73a5f85b3f9520be0a191216f477b641cc4",
+          "2161789fb82017691c88e6fc5f0e8b83cbe51c83e406351576f29597f3685de8",
+          "9acc316b746d3058438f8d643c8dcdb2dd5ad6774bd9e81886a315e3d43dc3f4"]),
+        // random; case0 ok, case2 fail, case3 fail
+        ("674a0aec554876839a3977703ab47925eb6d4a29d8f7dd24df957e047fcc9286", "8cf35f2901d6c6858a60743514c6bd776ad2a10173ed75806dfe7849135753bb",
+         ["d6c913853c867b2768dda67cdb82b69e59af2918bb1601be78519178f6527140",
+          "5f5e134db2583cfbdabe25faf834e6f525abcf6e4f4236ca31a98e35fe11b023",
+          "",
+          "",
+          "2936ec7ac37984d897225983247d4961a650d6e744e9fe4187ae6e8609ad8aef",
+          "a0a1ecb24da7c3042541da0507cb190ada543091b0bdc935ce5671c901ee4c0c",
+          "",
+          ""]),
+        // random; case0 fail, case2 ok, case3 ok
+        ("5437c2220721a2f5a7ca5c111a8a85e54c29ff48df27ee99abe7b17fecb4ccc7", "a528654f57bf291fb01f556271d507b693ae04574049ca79fc1582080a230cab",
+         ["",
+          "",
+          "39b69c8c90017280e18662f341c07013e9859b9b414df0bc38cf450d3aa65648",
+          "8ca2419c99b6c2278caeeda59fd13969d3fca0dd315f4593766d1aff4dee643d",
+          "",
+          "",
+          "c64963736ffe8d7f1e799d0cbe3f8fec167a6464beb20f43c730baf1c559a5e7",
+          "735dbe6366493dd87351125a602ec6962c035f22cea0ba6c8992e4ffb21197f2"]),
+        // random; case0 fail, case2 fail, case3 fail
+        ("064a751b885f722a89a59fba5103b631aef293ca0bb86814cba19f8e95c1f3bc", "d48c82097fbf395da25c08b9ede341ed4d55917bf4f89073bcee1803a5d0423a",
+         ["",
+          "",
+          "",
+          "",
+          "",
+          "",
+          "",
+          ""]),
+        // valid_x(-x-u): c&2 == 0 fails
+        ("f2bbe6922542f37969083919876b50522cbcd802eedea44dafc927acd9bd4700", "5652f81eae4d720e6074db3dadcd65ad8dff82a61ca1b0e6aae7c5675c655787",
+         ["",
+          "",
+          "3f731caa1a56af0cd08ca52e68201e319cecf26ed4b83e8fe7aa3179f01445f9",
+          "aa7ba531cb7c320754dd80113514ad444dd65e09d8802d1c3aa80c7df371692d",
+          "",
+          "",
+          "c08ce355e5a950f32f735ad197dfe1ce63130d912b47c1701855ce850febb636",
+          "55845ace3483cdf8ab227feecaeb52bbb229a1f6277fd2e3c557f3810c8e9302"]),
+        // u == x: s == 0 fails for c&2 == 2
+        ("7056cdcd45bf0b6b8cb43fa16033fa57542b466181bf59e946a3b74e022f44a6", "7056cdcd45bf0b6b8cb43fa16033fa57542b466181bf59e946a3b74e022f44a6",
+         ["",
+          "",
+          "",
+          "",
+          "",
+          "",
+          "",
+          ""]),
+        // r == 0: c&3 == 3 fails
+        ("358e5b6d0830bc02a2c0eb4b3cde271cc8a8691caac7e0e7e562b1ce3c9bccd6", "e54b6ee828f01153601f703591397e295cdf475efd20890ce02690d652b2a3c0",
+         ["",
+          "",
+          "114eee73c858367eeb7e14eebe0d194cec183146684705a0792392d8bf92b8bb",
+          "",
+          "",
+          "",
+          "eeb1118c37a7c9811481eb1141f2e6b313e7ceb997b8fa5f86dc6d26406d4374",
+          ""]),
+        // x-u not square: c&2 == 2 fails
+        ("0c83220cc53c16079f9648dd113cba497758bb3aef86149b76f65443340bdfbf", "4725d57a9420398bf8ac1d36ca78278f9c0f8194e8fc9d648baf015e16cbb923",
+         ["bccf4c0377ec481b66947af4d2697b0847f8a04380fd2ef622937482f66395e3",
+          "81a8f2b5eeb13744d82f0a1a957ac81c8e68f59b6a35ff3625898edd593a26b7",
+          "",
+          "",
+          "4330b3fc8813b7e4996b850b2d9684f7b8075fbc7f02d109dd6c8b7c099c664c",
+          "7e570d4a114ec8bb27d0f5e56a8537e371970a6495ca00c9da767121a6c5d578",
+          "",
+          ""]),
+        // u == 1
+        ("0000000000000000000000000000000000000000000000000000000000000001", "58c87dc5e59c408c7720daf64d037b665efd76430f4366f4ccbd44b51ef7695c",
+         ["",
+          "",
+          "de405765d0d838bcbcc143693483816ce8a956a7c40c2ffb1dbec97170e3a0da",
+          "572a57c1a4f056d50419d8b08dad858f3645c891a9a3f041dcc9f6315c9adcb9",
+          "",
+          "",
+          "21bfa89a2f27c743433ebc96cb7c7e931756a9583bf3d004e241368d8f1c5b55",
+          "a8d5a83e5b0fa92afbe6274f72527a70c9ba376e565c0fbe233609cda3651f76"]),
+    ];
+
+    fn fe(hex32: &str) -> secp256k1_fe {
+        let mut r = secp256k1_fe::new();
+        secp256k1_fe_set_b32(&mut r, &hex::decode(hex32).unwrap());
+        r
+    }
+
+    fn fe_hex(a: &secp256k1_fe) -> String {
+        let mut a = a.clone();
+        let mut buf = [0u8; 32];
+        secp256k1_fe_normalize_var(&mut a);
+        secp256k1_fe_get_b32(&mut buf, &a);
+        hex::encode(buf)
+    }
+
+    fn serialize(ctx: &secp256k1_context, pubkey: &secp256k1_pubkey) -> String {
+        let mut out = [0u8; 33];
+        let mut outlen: usize = 33;
+        assert!(secp256k1_ec_pubkey_serialize(ctx, &mut out, &mut outlen, pubkey, SECP256K1_EC_COMPRESSED));
+        hex::encode(out)
+    }
+
+    #[test]
+    fn test_ellswift_decode_vectors() {
+        let ctx = secp256k1_context::new();
+        for (ell, expected) in ELLSWIFT_DECODE_VECTORS.iter() {
+            let ell64: [u8; 64] = hex::decode(ell).unwrap().try_into().unwrap();
+            let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+            assert_eq!(secp256k1_ellswift_decode(&ctx, &mut pubkey, &ell64), 1);
+            assert_eq!(serialize(&ctx, &pubkey), *expected);
+        }
+    }
+
+    #[test]
+    fn test_xswiftec_inv_vectors() {
+        for (uhex, xhex, expected) in XSWIFTEC_INV_VECTORS.iter() {
+            let u = fe(uhex);
+            let x = fe(xhex);
+            for c in 0..8 {
+                let mut t = secp256k1_fe::new();
+                let ret = secp256k1_ellswift_xswiftec_inv_var(&mut t, &x, &u, c);
+                assert_eq!(ret != 0, !expected[c as usize].is_empty());
+                if ret == 0 {
+                    continue;
+                }
+                assert_eq!(fe_hex(&t), expected[c as usize]);
+                // the result decodes back to x
+                let mut x2 = secp256k1_fe::new();
+                secp256k1_ellswift_xswiftec_var(&mut x2, &u, &t);
+                assert_eq!(fe_hex(&x2), *xhex);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ellswift_encode_roundtrip() {
+        let mut ctx = secp256k1_context::new();
+        for i in 1..=8u8 {
+            let seckey = [i; 32];
+            let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+            assert!(secp256k1_ec_pubkey_create(&mut ctx, &mut pubkey, &seckey));
+            let mut ell64 = [0u8; 64];
+            let mut prev = [0u8; 64];
+            for j in 0..4u8 {
+                let rnd32 = [j; 32];
+                assert_eq!(secp256k1_ellswift_encode(&ctx, &mut ell64, &pubkey, &rnd32), 1);
+                let mut decoded = secp256k1_pubkey { data: [0u8; 64] };
+                assert_eq!(secp256k1_ellswift_decode(&ctx, &mut decoded, &ell64), 1);
+                assert_eq!(serialize(&ctx, &decoded), serialize(&ctx, &pubkey));
+                // a different rnd32 picks a different encoding, the same one picks the same
+                assert!(ell64 != prev);
+                prev = ell64;
+                let mut again = [0u8; 64];
+                assert_eq!(secp256k1_ellswift_encode(&ctx, &mut again, &pubkey, &rnd32), 1);
+                assert_eq!(again, ell64);
+            }
+        }
+
+        // an invalid public key cannot be encoded
+        let mut ell64 = [0u8; 64];
+        assert_eq!(secp256k1_ellswift_encode(&ctx, &mut ell64, &secp256k1_pubkey { data: [0u8; 64] }, &[0u8; 32]), 0);
+    }
+}
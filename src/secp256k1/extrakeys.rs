@@ -0,0 +1,359 @@
+/***********************************************************************
+ * Copyright (c) 2020 Jonas Nick                                       *
+ * Distributed under the MIT software license, see the accompanying    *
+ * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
+ ***********************************************************************/
+
+use super::{secp256k1_context, secp256k1_pubkey, secp256k1_pubkey_load, secp256k1_pubkey_save,
+            secp256k1_ec_pubkey_create_helper, secp256k1_ec_pubkey_tweak_add_helper, secp256k1_ec_seckey_tweak_add_helper};
+use super::field_5x52::{secp256k1_fe, secp256k1_fe_set_b32, secp256k1_fe_get_b32, secp256k1_fe_is_odd,
+                        secp256k1_fe_negate, secp256k1_fe_normalize_var};
+use super::group::{secp256k1_ge, secp256k1_ge_set_xo_var, secp256k1_ge_is_infinity, secp256k1_ge_const_g,
+                   sec256k1_ge_is_in_correct_subgroup};
+use super::scalar_4x64::{secp256k1_scalar, secp256k1_scalar_get_b32, secp256k1_scalar_negate, secp256k1_scalar_clear};
+use super::scalar_impl::{secp256k1_scalar_set_b32_seckey, secp256k1_scalar_one};
+use super::util::{secp256k1_memcmp_var, secp256k1_memczero};
+
+/** Opaque data structure that holds a parsed and valid "x-only" public key.
+ *  An x-only pubkey encodes a point whose Y coordinate is even. It is
+ *  serialized using only its X coordinate (32 bytes). See BIP-340 for more
+ *  information about x-only pubkeys.
+ *
+ *  The exact representation of data inside is implementation defined and not
+ *  guaranteed to be portable between different platforms or versions. It is
+ *  however guaranteed to be 64 bytes in size, and can be safely copied/moved.
+ *  If you need to convert to a format suitable for storage or transmission,
+ *  use secp256k1_xonly_pubkey_serialize and secp256k1_xonly_pubkey_parse. To
+ *  compare keys, use secp256k1_xonly_pubkey_cmp.
+ */
+#[derive(Clone)]
+pub struct secp256k1_xonly_pubkey {
+    pub data: [u8; 64],
+}
+
+/** Opaque data structure that holds a keypair consisting of a secret and a
+ *  public key.
+ *
+ *  The exact representation of data inside is implementation defined and not
+ *  guaranteed to be portable between different platforms or versions. It is
+ *  however guaranteed to be 96 bytes in size, and can be safely copied/moved.
+ */
+#[derive(Clone)]
+pub struct secp256k1_keypair {
+    pub data: [u8; 96],
+}
+
+impl secp256k1_xonly_pubkey {
+    pub fn new() -> Self {
+        secp256k1_xonly_pubkey { data: [0u8; 64] }
+    }
+}
+
+impl secp256k1_keypair {
+    pub fn new() -> Self {
+        secp256k1_keypair { data: [0u8; 96] }
+    }
+}
+
+//static SECP256K1_INLINE int secp256k1_xonly_pubkey_load(const secp256k1_context* ctx, secp256k1_ge *ge, const secp256k1_xonly_pubkey *pubkey) {
+pub(super) fn secp256k1_xonly_pubkey_load(ctx: &secp256k1_context, ge: &mut secp256k1_ge, pubkey: &secp256k1_xonly_pubkey) -> i32 {
+    return secp256k1_pubkey_load(ctx, ge, &secp256k1_pubkey { data: pubkey.data });
+}
+
+//static SECP256K1_INLINE void secp256k1_xonly_pubkey_save(secp256k1_xonly_pubkey *pubkey, secp256k1_ge *ge) {
+fn secp256k1_xonly_pubkey_save(pubkey: &mut secp256k1_xonly_pubkey, ge: &mut secp256k1_ge) {
+    let mut tmp = secp256k1_pubkey { data: [0u8; 64] };
+    secp256k1_pubkey_save(&mut tmp, ge);
+    pubkey.data = tmp.data;
+}
+
+/** Parse a 32-byte sequence into a xonly_pubkey object.
+ *
+ *  Returns: 1 if the public key was fully valid.
+ *           0 if the public key could not be parsed or is invalid.
+ */
+//int secp256k1_xonly_pubkey_parse(const secp256k1_context* ctx, secp256k1_xonly_pubkey *pubkey, const unsigned char *input32) {
+pub fn secp256k1_xonly_pubkey_parse(ctx: &secp256k1_context, pubkey: &mut secp256k1_xonly_pubkey, input32: &[u8; 32]) -> i32 {
+    let mut pk = secp256k1_ge::new();
+    let mut x = secp256k1_fe::new();
+
+    pubkey.data = [0u8; 64];
+
+    if secp256k1_fe_set_b32(&mut x, input32) == 0 {
+        return 0;
+    }
+    if secp256k1_ge_set_xo_var(&mut pk, &x, 0) == 0 {
+        return 0;
+    }
+    if sec256k1_ge_is_in_correct_subgroup(&pk) == 0 {
+        return 0;
+    }
+    secp256k1_xonly_pubkey_save(pubkey, &mut pk);
+    return 1;
+}
+
+/** Serialize an xonly_pubkey object into a 32-byte sequence.
+ *
+ *  Returns: 1 always.
+ */
+//int secp256k1_xonly_pubkey_serialize(const secp256k1_context* ctx, unsigned char *output32, const secp256k1_xonly_pubkey *pubkey) {
+pub fn secp256k1_xonly_pubkey_serialize(ctx: &secp256k1_context, output32: &mut [u8; 32], pubkey: &secp256k1_xonly_pubkey) -> i32 {
+    let mut pk = secp256k1_ge::new();
+
+    output32.fill(0);
+    if secp256k1_xonly_pubkey_load(ctx, &mut pk, pubkey) == 0 {
+        return 0;
+    }
+    secp256k1_fe_get_b32(output32, &pk.x);
+    return 1;
+}
+
+/** Compare two x-only public keys using lexicographic order
+ *
+ *  Returns: <0 if the first public key is less than the second
+ *           >0 if the first public key is greater than the second
+ *           0 if the two public keys are equal
+ */
+//int secp256k1_xonly_pubkey_cmp(const secp256k1_context* ctx, const secp256k1_xonly_pubkey* pk0, const secp256k1_xonly_pubkey* pk1) {
+pub fn secp256k1_xonly_pubkey_cmp(ctx: &secp256k1_context, pk0: &secp256k1_xonly_pubkey, pk1: &secp256k1_xonly_pubkey) -> i32 {
+    let mut out = [[0u8; 32]; 2];
+    let pk = [pk0, pk1];
+
+    for i in 0..2 {
+        /* If the public key is invalid, xonly_pubkey_serialize will return 0.
+         * In that case we will serialize the key as all zeros which is less
+         * than any valid public key. This results in consistent comparisons
+         * even if invalid pubkeys are involved and prevents edge cases such as
+         * sorting algorithms that use this function and do not terminate as a
+         * result. */
+        if secp256k1_xonly_pubkey_serialize(ctx, &mut out[i], pk[i]) == 0 {
+            out[i] = [0u8; 32];
+        }
+    }
+    return secp256k1_memcmp_var(&out[0], &out[1], 32);
+}
+
+/** Keeps a group element as is if it has an even Y and otherwise negates it.
+ *  y_parity is set to 0 in the former case and to 1 in the latter case.
+ *  Requires that the coordinates of r are normalized. */
+//static int secp256k1_extrakeys_ge_even_y(secp256k1_ge *r) {
+fn secp256k1_extrakeys_ge_even_y(r: &mut secp256k1_ge) -> i32 {
+    let mut y_parity = 0;
+    assert!(!secp256k1_ge_is_infinity(r));
+
+    if secp256k1_fe_is_odd(&r.y) != 0 {
+        let y1 = r.y.clone();
+        secp256k1_fe_negate(&mut r.y, &y1, 1);
+        y_parity = 1;
+    }
+    return y_parity;
+}
+
+/** Converts a secp256k1_pubkey into a secp256k1_xonly_pubkey.
+ *
+ *  Returns: 1 always.
+ *
+ *  Out: xonly_pubkey: the x-only public key object.
+ *       pk_parity:    if provided, set to 1 if the point encoded by xonly_pubkey
+ *                     is the negation of the pubkey and set to 0 otherwise.
+ */
+//int secp256k1_xonly_pubkey_from_pubkey(const secp256k1_context* ctx, secp256k1_xonly_pubkey *xonly_pubkey, int *pk_parity, const secp256k1_pubkey *pubkey) {
+pub fn secp256k1_xonly_pubkey_from_pubkey(ctx: &secp256k1_context, xonly_pubkey: &mut secp256k1_xonly_pubkey, pk_parity: Option<&mut i32>, pubkey: &secp256k1_pubkey) -> i32 {
+    let mut pk = secp256k1_ge::new();
+
+    xonly_pubkey.data = [0u8; 64];
+    if secp256k1_pubkey_load(ctx, &mut pk, pubkey) == 0 {
+        return 0;
+    }
+    let tmp = secp256k1_extrakeys_ge_even_y(&mut pk);
+    if let Some(pk_parity) = pk_parity {
+        *pk_parity = tmp;
+    }
+    secp256k1_xonly_pubkey_save(xonly_pubkey, &mut pk);
+    return 1;
+}
+
+/** Tweak an x-only public key by adding the generator multiplied with tweak32
+ *  to it.
+ *
+ *  Note that the resulting point can not in general be represented by an x-only
+ *  pubkey because it may have an odd Y coordinate. Instead, the output_pubkey
+ *  is a normal secp256k1_pubkey.
+ *
+ *  Returns: 0 if the arguments are invalid or the resulting public key would be
+ *           invalid (only when the tweak is the negation of the corresponding
+ *           secret key). 1 otherwise.
+ */
+//int secp256k1_xonly_pubkey_tweak_add(const secp256k1_context* ctx, secp256k1_pubkey *output_pubkey, const secp256k1_xonly_pubkey *internal_pubkey, const unsigned char *tweak32) {
+pub fn secp256k1_xonly_pubkey_tweak_add(ctx: &secp256k1_context, output_pubkey: &mut secp256k1_pubkey, internal_pubkey: &secp256k1_xonly_pubkey, tweak32: &[u8; 32]) -> i32 {
+    let mut pk = secp256k1_ge::new();
+
+    output_pubkey.data = [0u8; 64];
+    if secp256k1_xonly_pubkey_load(ctx, &mut pk, internal_pubkey) == 0
+        || secp256k1_ec_pubkey_tweak_add_helper(&mut pk, tweak32) == 0 {
+        return 0;
+    }
+    secp256k1_pubkey_save(output_pubkey, &mut pk);
+    return 1;
+}
+
+/** Checks that a tweaked pubkey is the result of calling
+ *  secp256k1_xonly_pubkey_tweak_add with internal_pubkey and tweak32.
+ *
+ *  The tweaked pubkey is represented by its 32-byte x-only serialization and
+ *  its pk_parity, which can both be obtained by converting the result of
+ *  tweak_add to a secp256k1_xonly_pubkey.
+ *
+ *  Returns: 0 if the arguments are invalid or the tweaked pubkey is not the
+ *           result of tweaking the internal_pubkey with tweak32. 1 otherwise.
+ */
+//int secp256k1_xonly_pubkey_tweak_add_check(const secp256k1_context* ctx, const unsigned char *tweaked_pubkey32, int tweaked_pk_parity, const secp256k1_xonly_pubkey *internal_pubkey, const unsigned char *tweak32) {
+pub fn secp256k1_xonly_pubkey_tweak_add_check(ctx: &secp256k1_context, tweaked_pubkey32: &[u8; 32], tweaked_pk_parity: i32, internal_pubkey: &secp256k1_xonly_pubkey, tweak32: &[u8; 32]) -> i32 {
+    let mut pk = secp256k1_ge::new();
+    let mut pk_expected32 = [0u8; 32];
+
+    if secp256k1_xonly_pubkey_load(ctx, &mut pk, internal_pubkey) == 0
+        || secp256k1_ec_pubkey_tweak_add_helper(&mut pk, tweak32) == 0 {
+        return 0;
+    }
+    secp256k1_fe_normalize_var(&mut pk.x);
+    secp256k1_fe_normalize_var(&mut pk.y);
+    secp256k1_fe_get_b32(&mut pk_expected32, &pk.x);
+
+    return (secp256k1_memcmp_var(&pk_expected32, tweaked_pubkey32, 32) == 0
+            && secp256k1_fe_is_odd(&pk.y) == tweaked_pk_parity) as i32;
+}
+
+//static void secp256k1_keypair_save(secp256k1_keypair *keypair, const secp256k1_scalar *sk, secp256k1_ge *pk) {
+fn secp256k1_keypair_save(keypair: &mut secp256k1_keypair, sk: &secp256k1_scalar, pk: &mut secp256k1_ge) {
+    let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+    secp256k1_scalar_get_b32(&mut keypair.data[0..32], &mut sk.clone());
+    secp256k1_pubkey_save(&mut pubkey, pk);
+    keypair.data[32..].copy_from_slice(&pubkey.data);
+}
+
+//static int secp256k1_keypair_seckey_load(const secp256k1_context* ctx, secp256k1_scalar *sk, const secp256k1_keypair *keypair) {
+fn secp256k1_keypair_seckey_load(ctx: &secp256k1_context, sk: &mut secp256k1_scalar, keypair: &secp256k1_keypair) -> i32 {
+    let mut seckey = [0u8; 32];
+    seckey.copy_from_slice(&keypair.data[0..32]);
+    return secp256k1_scalar_set_b32_seckey(sk, &seckey);
+}
+
+/* Load a keypair into pk and sk (if provided). It always initializes sk and pk
+ * with dummy values. */
+//static int secp256k1_keypair_load(const secp256k1_context* ctx, secp256k1_scalar *sk, secp256k1_ge *pk, const secp256k1_keypair *keypair) {
+pub(super) fn secp256k1_keypair_load(ctx: &secp256k1_context, mut sk: Option<&mut secp256k1_scalar>, pk: &mut secp256k1_ge, keypair: &secp256k1_keypair) -> i32 {
+    let mut pubkey = secp256k1_pubkey { data: [0u8; 64] };
+    pubkey.data.copy_from_slice(&keypair.data[32..]);
+
+    let mut ret = secp256k1_pubkey_load(ctx, pk, &pubkey);
+    if let Some(sk) = sk.as_deref_mut() {
+        ret = (ret != 0 && secp256k1_keypair_seckey_load(ctx, sk, keypair) != 0) as i32;
+    }
+    if ret == 0 {
+        *pk = secp256k1_ge_const_g;
+        if let Some(sk) = sk {
+            *sk = secp256k1_scalar_one;
+        }
+    }
+    return ret;
+}
+
+/** Compute the keypair for a secret key.
+ *
+ *  Returns: 1: secret was valid, keypair is ready to use
+ *           0: secret was invalid, try again with a different secret
+ */
+//int secp256k1_keypair_create(const secp256k1_context* ctx, secp256k1_keypair *keypair, const unsigned char *seckey32) {
+pub fn secp256k1_keypair_create(ctx: &mut secp256k1_context, keypair: &mut secp256k1_keypair, seckey32: &[u8; 32]) -> i32 {
+    let mut sk = secp256k1_scalar::new();
+    let mut pk = secp256k1_ge::new();
+
+    keypair.data = [0u8; 96];
+
+    let ret = secp256k1_ec_pubkey_create_helper(&mut ctx.ecmult_gen_ctx, &mut sk, &mut pk, seckey32);
+    secp256k1_keypair_save(keypair, &sk, &mut pk);
+    secp256k1_memczero(&mut keypair.data, !ret);
+
+    secp256k1_scalar_clear(&mut sk);
+    return ret as i32;
+}
+
+/** Get the secret key from a keypair.
+ *
+ *  Returns: 1 always.
+ */
+//int secp256k1_keypair_sec(const secp256k1_context* ctx, unsigned char *seckey, const secp256k1_keypair *keypair) {
+pub fn secp256k1_keypair_sec(ctx: &secp256k1_context, seckey: &mut [u8; 32], keypair: &secp256k1_keypair) -> i32 {
+    seckey.copy_from_slice(&keypair.data[0..32]);
+    return 1;
+}
+
+/** Get the public key from a keypair.
+ *
+ *  Returns: 1 always.
+ */
+//int secp256k1_keypair_pub(const secp256k1_context* ctx, secp256k1_pubkey *pubkey, const secp256k1_keypair *keypair) {
+pub fn secp256k1_keypair_pub(ctx: &secp256k1_context, pubkey: &mut secp256k1_pubkey, keypair: &secp256k1_keypair) -> i32 {
+    pubkey.data.copy_from_slice(&keypair.data[32..]);
+    return 1;
+}
+
+/** Get the x-only public key from a keypair.
+ *
+ *  This is the same as calling secp256k1_keypair_pub and then
+ *  secp256k1_xonly_pubkey_from_pubkey.
+ *
+ *  Returns: 1 always.
+ */
+//int secp256k1_keypair_xonly_pub(const secp256k1_context* ctx, secp256k1_xonly_pubkey *pubkey, int *pk_parity, const secp256k1_keypair *keypair) {
+pub fn secp256k1_keypair_xonly_pub(ctx: &secp256k1_context, pubkey: &mut secp256k1_xonly_pubkey, pk_parity: Option<&mut i32>, keypair: &secp256k1_keypair) -> i32 {
+    let mut pk = secp256k1_ge::new();
+
+    pubkey.data = [0u8; 64];
+    if secp256k1_keypair_load(ctx, None, &mut pk, keypair) == 0 {
+        return 0;
+    }
+    let tmp = secp256k1_extrakeys_ge_even_y(&mut pk);
+    if let Some(pk_parity) = pk_parity {
+        *pk_parity = tmp;
+    }
+    secp256k1_xonly_pubkey_save(pubkey, &mut pk);
+    return 1;
+}
+
+/** Tweak a keypair by adding tweak32 to the secret key and updating the public
+ *  key accordingly.
+ *
+ *  Calling this function and then secp256k1_keypair_pub results in the same
+ *  public key as calling secp256k1_keypair_xonly_pub and then
+ *  secp256k1_xonly_pubkey_tweak_add.
+ *
+ *  Returns: 0 if the arguments are invalid or the resulting keypair would be
+ *           invalid (only when the tweak is the negation of the keypair's
+ *           secret key). 1 otherwise.
+ */
+//int secp256k1_keypair_xonly_tweak_add(const secp256k1_context* ctx, secp256k1_keypair *keypair, const unsigned char *tweak32) {
+pub fn secp256k1_keypair_xonly_tweak_add(ctx: &secp256k1_context, keypair: &mut secp256k1_keypair, tweak32: &[u8; 32]) -> i32 {
+    let mut pk = secp256k1_ge::new();
+    let mut sk = secp256k1_scalar::new();
+
+    let mut ret = secp256k1_keypair_load(ctx, Some(&mut sk), &mut pk, keypair);
+    keypair.data = [0u8; 96];
+
+    let y_parity = secp256k1_extrakeys_ge_even_y(&mut pk);
+    if y_parity == 1 {
+        let sk1 = sk.clone();
+        secp256k1_scalar_negate(&mut sk, &sk1);
+    }
+
+    ret &= secp256k1_ec_seckey_tweak_add_helper(&mut sk, tweak32);
+    ret &= secp256k1_ec_pubkey_tweak_add_helper(&mut pk, tweak32);
+
+    if ret != 0 {
+        secp256k1_keypair_save(keypair, &sk, &mut pk);
+    }
+
+    secp256k1_scalar_clear(&mut sk);
+    return ret;
+}
@@ -43,7 +43,6 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     let mut x223 = secp256k1_fe::new();
     let mut t1 = secp256k1_fe::new();
 
-    VERIFY_CHECK!(r != a);
 
     /** The binary representation of (p + 1)/4 has 3 blocks of 1s, with lengths in
      *  { 2, 22, 223 }. Use an addition chain to calculate 2^n - 1 for each block:
@@ -59,7 +58,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x3, &x3_clone, a);
 
     x6 = x3.clone();
-    for j in [0..3] {
+    for j in 0..3 {
         let x6_clone = x6.clone();
         secp256k1_fe_sqr(&mut x6, &x6_clone);
     }
@@ -67,7 +66,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x6, &x6_clone, &x3);
 
     x9 = x6.clone();
-    for j in [0..3] {
+    for j in 0..3 {
         let x9_clone = x9.clone();
         secp256k1_fe_sqr(&mut x9, &x9_clone);
     }
@@ -75,7 +74,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x9, &x9_clone, &x3);
 
     x11 = x9.clone();
-    for j in [0..2] {
+    for j in 0..2 {
         let x11_clone = x11.clone();
         secp256k1_fe_sqr(&mut x11, &x11_clone);
     }
@@ -83,7 +82,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x11, &x11_clone, &x2);
 
     x22 = x11.clone();
-    for j in [0..11] {
+    for j in 0..11 {
         let x22_clone = x22.clone();
         secp256k1_fe_sqr(&mut x22, &x22_clone);
     }
@@ -91,7 +90,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x22, &x22_clone, &x11);
 
     x44 = x22.clone();
-    for j in [0..22] {
+    for j in 0..22 {
         let x44_clone = x44.clone();
         secp256k1_fe_sqr(&mut x44, &x44_clone);
     }
@@ -99,7 +98,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x44, &x44_clone, &x22);
 
     x88 = x44.clone();
-    for j in [0..44] {
+    for j in 0..44 {
         let x88_clone = x88.clone();
         secp256k1_fe_sqr(&mut x88, &x88_clone);
     }
@@ -107,7 +106,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x88, &x88_clone, &x44);
 
     x176 = x88.clone();
-    for j in [0..88] {
+    for j in 0..88 {
         let x176_clone = x176.clone();
         secp256k1_fe_sqr(&mut x176, &x176_clone);
     }
@@ -115,7 +114,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x176, &x176_clone, &x88);
 
     x220 = x176.clone();
-    for j in [0..44] {
+    for j in 0..44 {
         let x220_clone = x220.clone();
         secp256k1_fe_sqr(&mut x220, &x220_clone);
     }
@@ -123,7 +122,7 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_mul(&mut x220, &x220_clone, &x44);
 
     x223 = x220.clone();
-    for j in [0..3] {
+    for j in 0..3 {
         let x223_clone = x223.clone();
         secp256k1_fe_sqr(&mut x223, &x223_clone);
     }
@@ -133,13 +132,13 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     /* The final result is then assembled using a sliding window over the blocks. */
 
     t1 = x223.clone();
-    for j in [0..23] {
+    for j in 0..23 {
         let t1_clone = t1.clone();
         secp256k1_fe_sqr(&mut t1, &t1_clone);
     }
     let t1_clone = t1.clone();
     secp256k1_fe_mul(&mut t1, &t1_clone, &x22);
-    for j in [0..6] {
+    for j in 0..6 {
         let t1_clone = t1.clone();
         secp256k1_fe_sqr(&mut t1, &t1_clone);
     }
@@ -154,3 +153,10 @@ pub fn secp256k1_fe_sqrt(r: &mut secp256k1_fe, a: &secp256k1_fe) -> i32 {
     secp256k1_fe_sqr(&mut t1, r);
     return secp256k1_fe_equal(&t1, a);
 }
+
+/** Determine whether a is a square (modulo p). */
+//static int secp256k1_fe_is_square_var(const secp256k1_fe *a) {
+pub fn secp256k1_fe_is_square_var(a: &secp256k1_fe) -> i32 {
+    let mut r = secp256k1_fe::new();
+    secp256k1_fe_sqrt(&mut r, a)
+}
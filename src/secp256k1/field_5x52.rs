@@ -91,7 +91,7 @@ macro_rules! SECP256K1_FE_CONST {
             #[cfg(feature = "verify")]
             magnitude: 1,
             #[cfg(feature = "verify")]
-            normalized: 0,
+            normalized: 1,
         }
     };
 }
@@ -503,7 +503,7 @@ pub fn secp256k1_fe_clear(a: &mut secp256k1_fe) {
 }
  
 // static int secp256k1_fe_cmp_var(const secp256k1_fe *a, const secp256k1_fe *b) {
-fn secp256k1_fe_cmp_var(a: &secp256k1_fe, b: &secp256k1_fe) -> i32 {
+pub fn secp256k1_fe_cmp_var(a: &secp256k1_fe, b: &secp256k1_fe) -> i32 {
     // int i;
     let mut i: i32;
     #[cfg(feature = "verify")] {
@@ -666,6 +666,20 @@ pub fn secp256k1_fe_add(r: &mut secp256k1_fe, a: &secp256k1_fe) {
         secp256k1_fe_verify(r);
     }
  }
+
+// SECP256K1_INLINE static void secp256k1_fe_add_int(secp256k1_fe *r, int a) {
+pub fn secp256k1_fe_add_int(r: &mut secp256k1_fe, a: i32) {
+    VERIFY_CHECK!(0 <= a && a <= 0x7FFF);
+    #[cfg(feature = "verify")] {
+        secp256k1_fe_verify(r);
+    }
+    r.n[0] += a as u64;
+    #[cfg(feature = "verify")] {
+        r.magnitude += 1;
+        r.normalized = 0;
+        secp256k1_fe_verify(r);
+    }
+}
  
 // static void secp256k1_fe_mul(secp256k1_fe *r, const secp256k1_fe *a, const secp256k1_fe * SECP256K1_RESTRICT b) {
 pub fn secp256k1_fe_mul(r: &mut secp256k1_fe, a: &secp256k1_fe, b: &secp256k1_fe) {
@@ -674,8 +688,7 @@ pub fn secp256k1_fe_mul(r: &mut secp256k1_fe, a: &secp256k1_fe, b: &secp256k1_fe
         VERIFY_CHECK!(b.magnitude <= 8);
         secp256k1_fe_verify(a);
         secp256k1_fe_verify(b);
-        VERIFY_CHECK!(r != b);
-        VERIFY_CHECK!(a != b);
+        /* r and b can not alias: r is borrowed mutably. */
     }
     secp256k1_fe_mul_inner(r.n.as_mut_slice(), a.n.as_slice(), b.n.as_slice());
     #[cfg(feature = "verify")] {
@@ -705,7 +718,7 @@ pub fn secp256k1_fe_cmov(r: &mut secp256k1_fe, a: &secp256k1_fe, flag: i32) {
     let mut mask1: u64;
     // VG_CHECK_VERIFY(r.n, sizeof(r.n));
 
-    mask0 = flag as u64 + !(0 as u64);
+    mask0 = (flag as u64).wrapping_add(!(0 as u64));
     mask1 = !mask0;
     r.n[0] = (r.n[0] & mask0) | (a.n[0] & mask1);
     r.n[1] = (r.n[1] & mask0) | (a.n[1] & mask1);
@@ -723,7 +736,7 @@ pub fn secp256k1_fe_cmov(r: &mut secp256k1_fe, a: &secp256k1_fe, flag: i32) {
 pub fn secp256k1_fe_half(r: &mut secp256k1_fe) {
     let (mut t0, mut t1, mut t2, mut t3, mut t4) = (r.n[0], r.n[1], r.n[2], r.n[3], r.n[4]);
     let one: u64 = 1;
-    let mask: u64 = -(((t0 & one) >> 12) as i64) as u64;
+    let mask: u64 = (t0 & one).wrapping_neg() >> 12;
  
     #[cfg(feature = "verify")] {
         secp256k1_fe_verify(r);
@@ -790,7 +803,7 @@ pub fn secp256k1_fe_storage_cmov(r: &mut secp256k1_fe_storage, a: &secp256k1_fe_
     let mut mask0: u64;
     let mut mask1: u64;
     //#[cfg(feature = "verify")] VG_CHECK_VERIFY!(r.n, sizeof(r.n));
-    mask0 = flag as u64 + !(0 as u64);
+    mask0 = (flag as u64).wrapping_add(!(0 as u64));
     mask1 = !mask0;
     r.n[0] = (r.n[0] & mask0) | (a.n[0] & mask1);
     r.n[1] = (r.n[1] & mask0) | (a.n[1] & mask1);
@@ -54,8 +54,6 @@ pub fn secp256k1_fe_mul_inner(r: &mut [u64], a: &[u64], b: &[u64]) {
     VERIFY_BITS!(b[2], 56);
     VERIFY_BITS!(b[3], 56);
     VERIFY_BITS!(b[4], 52);
-    VERIFY_CHECK!(r != b);
-    VERIFY_CHECK!(a != b);
 
     /*  [... a b c] is a shorthand for ... + a<<104 + b<<52 + c<<0 mod n.
      *  for 0 <= x <= 4, px is a shorthand for sum(a[i]*b[x-i], i=0..x).
@@ -72,7 +70,7 @@ pub fn secp256k1_fe_mul_inner(r: &mut [u64], a: &[u64], b: &[u64]) {
     c  = a4 as u128 * b[4] as u128;
     VERIFY_BITS!(c, 112);
     /* [c 0 0 0 0 d 0 0 0] = [p8 0 0 0 0 p3 0 0 0] */
-    d += R as u128 * c; c >>= 64;
+    d += R as u128 * (c as u64) as u128; c >>= 64;
     VERIFY_BITS!(d, 115);
     VERIFY_BITS!(c, 48);
     /* [(c<<12) 0 0 0 0 0 d 0 0 0] = [p8 0 0 0 0 p3 0 0 0] */
@@ -229,7 +227,7 @@ pub fn secp256k1_fe_sqr_inner(r: &mut [u64], a: &[u64]) {
     VERIFY_BITS!(t4, 52);
     VERIFY_BITS!(d, 64);
     /* [d t4 t3 0 0 0] = [p8 0 0 0 p4 p3 0 0 0] */
-    tx = 4 >> 48; t4 &= (M >> 4) as i64;
+    tx = t4 >> 48; t4 &= (M >> 4) as i64;
     VERIFY_BITS!(tx, 4);
     VERIFY_BITS!(t4, 48);
     /* [d t4+(tx<<48) t3 0 0 0] = [p8 0 0 0 p4 p3 0 0 0] */
@@ -281,7 +279,7 @@ pub fn secp256k1_fe_sqr_inner(r: &mut [u64], a: &[u64]) {
     d += a3 as u128 * a4 as u128;
     VERIFY_BITS!(d, 114);
     /* [d 0 0 t4 t3 c r1 r0] = [p8 p7 p6 p5 p4 p3 p2 p1 p0] */
-    c += R  as u128 * d as u128; d >>= 64;
+    c += R * (d as u64) as u128; d >>= 64;
     VERIFY_BITS!(c, 115);
     VERIFY_BITS!(d, 50);
     /* [(d<<12) 0 0 0 t4 t3 c r1 r0] = [p8 p7 p6 p5 p4 p3 p2 p1 p0] */
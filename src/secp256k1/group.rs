@@ -218,6 +218,8 @@ const secp256k1_fe_const_b: secp256k1_fe = SECP256K1_FE_CONST(
 pub const secp256k1_ge_const_g: secp256k1_ge = SECP256K1_G!();
 
 const secp256k1_fe_const_b: secp256k1_fe = SECP256K1_FE_CONST!(0, 0, 0, 0, 0, 0, 0, 7);
+//#define SECP256K1_B 7
+pub const SECP256K1_B: i32 = 7;
 
 
 //static void secp256k1_ge_set_gej_zinv(secp256k1_ge *r, const secp256k1_gej *a, const secp256k1_fe *zi) {
@@ -324,7 +326,7 @@ fn secp256k1_ge_set_all_gej_var(r: &mut [secp256k1_ge], a: &[secp256k1_gej], len
             last_i = i;
         }
     }
-    #[cfg(feature = "verify")] VERIFY_CHECK!(!a[last_i].infinity == 1);
+    #[cfg(feature = "verify")] VERIFY_CHECK!(a[last_i].infinity == 0);
     r[last_i].x = u;
 
     for i in 0..len {
@@ -337,10 +339,10 @@ fn secp256k1_ge_set_all_gej_var(r: &mut [secp256k1_ge], a: &[secp256k1_gej], len
 
 //static void secp256k1_ge_table_set_globalz(size_t len, secp256k1_ge *a, const secp256k1_fe *zr) {
 pub fn secp256k1_ge_table_set_globalz(len: usize, a: &mut [secp256k1_ge], zr: &[secp256k1_fe]) {
-    let mut i: usize = len - 1;
     let mut zs: secp256k1_fe;
 
     if (len > 0) {
+        let mut i: usize = len - 1;
         /* Ensure all y values are in weak normal form for fast negation of points */
         secp256k1_fe_normalize_weak(&mut a[i].y);
         zs = zr[i].clone();
@@ -412,6 +414,41 @@ pub fn secp256k1_ge_set_xo_var(r: &mut secp256k1_ge, x: &secp256k1_fe, odd: i32)
     return 1;
 }
 
+/** Determine whether x is a valid X coordinate on the curve. */
+//static int secp256k1_ge_x_on_curve_var(const secp256k1_fe *x) {
+pub fn secp256k1_ge_x_on_curve_var(x: &secp256k1_fe) -> i32 {
+    let mut c = secp256k1_fe::new();
+    secp256k1_fe_sqr(&mut c, x);
+    let c1 = c.clone();
+    secp256k1_fe_mul(&mut c, &c1, x);
+    secp256k1_fe_add_int(&mut c, SECP256K1_B);
+    return secp256k1_fe_is_square_var(&c);
+}
+
+/** Determine whether fraction xn/xd is a valid X coordinate on the curve (xd != 0). */
+//static int secp256k1_ge_x_frac_on_curve_var(const secp256k1_fe *xn, const secp256k1_fe *xd) {
+pub fn secp256k1_ge_x_frac_on_curve_var(xn: &secp256k1_fe, xd: &secp256k1_fe) -> i32 {
+    /* We want to determine whether (xn/xd) is on the curve.
+     *
+     * (xn/xd)^3 + 7 is square <=> xd*xn^3 + 7*xd^4 is square (multiplying by xd^4, a square).
+     */
+    let mut r = secp256k1_fe::new();
+    let mut t = secp256k1_fe::new();
+    VERIFY_CHECK!(secp256k1_fe_normalizes_to_zero_var(xd) == 0);
+
+    secp256k1_fe_mul(&mut r, xd, xn); /* r = xd*xn */
+    secp256k1_fe_sqr(&mut t, xn); /* t = xn^2 */
+    let r1 = r.clone();
+    secp256k1_fe_mul(&mut r, &r1, &t); /* r = xd*xn^3 */
+    secp256k1_fe_sqr(&mut t, xd); /* t = xd^2 */
+    let t1 = t.clone();
+    secp256k1_fe_sqr(&mut t, &t1); /* t = xd^4 */
+    VERIFY_CHECK!(SECP256K1_B <= 31);
+    secp256k1_fe_mul_int(&mut t, SECP256K1_B); /* t = 7*xd^4 */
+    secp256k1_fe_add(&mut r, &t); /* r = xd*xn^3 + 7*xd^4 */
+    return secp256k1_fe_is_square_var(&r);
+}
+
 //static void secp256k1_gej_set_ge(secp256k1_gej *r, const secp256k1_ge *a) {
 pub fn secp256k1_gej_set_ge(r: &mut secp256k1_gej, a: &secp256k1_ge) {
    r.infinity = a.infinity;
@@ -421,7 +458,7 @@ pub fn secp256k1_gej_set_ge(r: &mut secp256k1_gej, a: &secp256k1_ge) {
 }
 
 //static int secp256k1_gej_eq_x_var(const secp256k1_fe *x, const secp256k1_gej *a) {
-fn secp256k1_gej_eq_x_var(x: &secp256k1_fe, a: &secp256k1_gej) -> i32 {
+pub fn secp256k1_gej_eq_x_var(x: &secp256k1_fe, a: &secp256k1_gej) -> i32 {
     let mut r = secp256k1_fe::new();
     let mut r2 = secp256k1_fe::new();
     //VERIFY_CHECK(!a.infinity);
@@ -468,7 +505,7 @@ pub fn secp256k1_ge_is_valid_var(a: &secp256k1_ge) -> i32 {
 }
 
 //static SECP256K1_INLINE void secp256k1_gej_double(secp256k1_gej *r, const secp256k1_gej *a) {
-fn secp256k1_gej_double(r: &mut secp256k1_gej, a: &secp256k1_gej) {
+pub fn secp256k1_gej_double(r: &mut secp256k1_gej, a: &secp256k1_gej) {
     /* Operations: 3 mul, 4 sqr, 8 add/half/mul_int/negate */
     let mut l = secp256k1_fe::new();
     let mut s = secp256k1_fe::new();
@@ -877,8 +914,8 @@ pub fn secp256k1_gej_add_ge(r: &mut secp256k1_gej, a: &secp256k1_gej, b: &secp25
     secp256k1_fe_mul_int(&mut rr_alt, 2);       /* rr = Y1*Z2^3 - Y2*Z1^3 (2) */
     secp256k1_fe_add(&mut m_alt, &u1);          /* Malt = X1*Z2^2 - X2*Z1^2 */
 
-    secp256k1_fe_cmov(&mut rr_alt, &rr, !degenerate);
-    secp256k1_fe_cmov(&mut m_alt, &m, !degenerate);
+    secp256k1_fe_cmov(&mut rr_alt, &rr, (degenerate == 0) as i32);
+    secp256k1_fe_cmov(&mut m_alt, &m, (degenerate == 0) as i32);
     /* Now Ralt / Malt = lambda and is guaranteed not to be 0/0.
      * From here on out Ralt and Malt represent the numerator
      * and denominator of lambda; R and M represent the explicit
@@ -953,7 +990,7 @@ pub fn secp256k1_ge_from_storage(r: &mut secp256k1_ge, a: &secp256k1_ge_storage)
 }
 
 //static SECP256K1_INLINE void secp256k1_gej_cmov(secp256k1_gej *r, const secp256k1_gej *a, int flag) {
-fn sec256k1_gej_cmov(r: &mut secp256k1_gej, a: &secp256k1_gej, flag: i32) {
+pub fn sec256k1_gej_cmov(r: &mut secp256k1_gej, a: &secp256k1_gej, flag: i32) {
     secp256k1_fe_cmov(&mut r.x, &a.x, flag);
     secp256k1_fe_cmov(&mut r.y, &a.y, flag);
     secp256k1_fe_cmov(&mut r.z, &a.z, flag);
@@ -975,7 +1012,7 @@ pub fn secp256k1_ge_mul_lambda(r: &mut secp256k1_ge, a: &secp256k1_ge) {
 }
 
 //static int secp256k1_ge_is_in_correct_subgroup(const secp256k1_ge* ge) {
-fn sec256k1_ge_is_in_correct_subgroup(ge: &secp256k1_ge) -> i32 {
+pub fn sec256k1_ge_is_in_correct_subgroup(ge: &secp256k1_ge) -> i32 {
 
 #[cfg(feature = "EXHAUSTIVE_TEST_ORDER")]
     {
@@ -174,42 +174,43 @@ pub fn secp256k1_sha256_initialize( hash: &mut secp256k1_sha256) {
 
 /** Perform one SHA-256 transformation, processing 16 big endian 32-bit words. */
 //static void secp256k1_sha256_transform(uint32_t* s, const unsigned char* buf) {
+#[wrappit]
 pub fn secp256k1_sha256_transform(s: &mut [u32; 8], buf: &[u8; 64]) {
 
     let mut a = s[0]; let mut b = s[1]; let mut c = s[2]; let mut d = s[3]; let mut e = s[4]; let mut f = s[5]; let mut g = s[6]; let mut h = s[7];
     let (mut w0, mut w1, mut w2, mut w3, mut w4, mut w5, mut w6, mut w7, mut w8, mut w9, mut w10, mut w11, mut w12, mut w13, mut w14, mut w15); (0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32, 0u32,);
 
-    w0 = secp256k1_read_be32(&buf[0..3]);
+    w0 = secp256k1_read_be32(&buf[0..]);
     Round(a, b, c, &mut d, e, f, g, &mut h, 0x428a2f98,  w0);
-    w1 = secp256k1_read_be32(&buf[4..7]);
+    w1 = secp256k1_read_be32(&buf[4..]);
     Round(h, a, b, &mut c, d, e, f, &mut g, 0x71374491,  w1);
-    w2 = secp256k1_read_be32(&buf[8..11]);
+    w2 = secp256k1_read_be32(&buf[8..]);
     Round(g, h, a, &mut b, c, d, e, &mut f, 0xb5c0fbcf,  w2);
-    w3 = secp256k1_read_be32(&buf[12..15]);
+    w3 = secp256k1_read_be32(&buf[12..]);
     Round(f, g, h, &mut a, b, c, d, &mut e, 0xe9b5dba5,  w3);
-    w4 = secp256k1_read_be32(&buf[16..19]);
+    w4 = secp256k1_read_be32(&buf[16..]);
     Round(e, f, g, &mut h, a, b, c, &mut d, 0x3956c25b,  w4);
-    w5 = secp256k1_read_be32(&buf[20..23]);
+    w5 = secp256k1_read_be32(&buf[20..]);
     Round(d, e, f, &mut g, h, a, b, &mut c, 0x59f111f1,  w5);
-    w6 = secp256k1_read_be32(&buf[24..27]);
+    w6 = secp256k1_read_be32(&buf[24..]);
     Round(c, d, e, &mut f, g, h, a, &mut b, 0x923f82a4,  w6);
-    w7 = secp256k1_read_be32(&buf[28..31]);
+    w7 = secp256k1_read_be32(&buf[28..]);
     Round(b, c, d, &mut e, f, g, h, &mut a, 0xab1c5ed5,  w7);
-    w8 = secp256k1_read_be32(&buf[32..35]);
+    w8 = secp256k1_read_be32(&buf[32..]);
     Round(a, b, c, &mut d, e, f, g, &mut h, 0xd807aa98,  w8);
-    w9 = secp256k1_read_be32(&buf[36..39]);
+    w9 = secp256k1_read_be32(&buf[36..]);
     Round(h, a, b, &mut c, d, e, f, &mut g, 0x12835b01,  w9);
-    w10 = secp256k1_read_be32(&buf[40..43]);;
+    w10 = secp256k1_read_be32(&buf[40..]);
     Round(g, h, a, &mut b, c, d, e, &mut f, 0x243185be, w10);
-    w11 = secp256k1_read_be32(&buf[44..47]);
+    w11 = secp256k1_read_be32(&buf[44..]);
     Round(f, g, h, &mut a, b, c, d, &mut e, 0x550c7dc3, w11);
-    w12 = secp256k1_read_be32(&buf[48..51]);
+    w12 = secp256k1_read_be32(&buf[48..]);
     Round(e, f, g, &mut h, a, b, c, &mut d, 0x72be5d74, w12);
-    w13 = secp256k1_read_be32(&buf[52..55]);
+    w13 = secp256k1_read_be32(&buf[52..]);
     Round(d, e, f, &mut g, h, a, b, &mut c, 0x80deb1fe, w13);
-    w14 = secp256k1_read_be32(&buf[56..59]);
+    w14 = secp256k1_read_be32(&buf[56..]);
     Round(c, d, e, &mut f, g, h, a, &mut b, 0x9bdc06a7, w14);
-    w15 = secp256k1_read_be32(&buf[60..63]);
+    w15 = secp256k1_read_be32(&buf[60..]);
     Round(b, c, d, &mut e, f, g, h, &mut a, 0xc19bf174, w15);
 
     w0 += sigma1!(w14) + w9 + sigma0!(w1);
@@ -340,7 +341,8 @@ pub fn secp256k1_sha256_transform(s: &mut [u32; 8], buf: &[u8; 64]) {
 
 pub fn secp256k1_sha256_write(hash: &mut secp256k1_sha256, data: &[u8]) {
     let mut bufsize = hash.bytes as usize & 0x3F;
-    let len = data.len();
+    let mut data = data;
+    let mut len = data.len();
     hash.bytes += len as u64;
     //VERIFY_CHECK(hash->bytes >= len);
     assert!(hash.bytes >= len as u64);
@@ -349,8 +351,8 @@ pub fn secp256k1_sha256_write(hash: &mut secp256k1_sha256, data: &[u8]) {
         let chunk_len = 64 - bufsize;
         hash.buf[bufsize..(bufsize + chunk_len) as usize].copy_from_slice(&data[0..chunk_len as usize]);
         //memcpy(hash->buf + bufsize, data, chunk_len);
-        //data += chunk_len;
-        //len -= chunk_len;
+        data = &data[chunk_len..];
+        len -= chunk_len;
         secp256k1_sha256_transform(&mut hash.s, &hash.buf);
         bufsize = 0;
     }
@@ -378,7 +380,11 @@ pub fn secp256k1_sha256_write(hash: &mut secp256k1_sha256, data: &[u8]) {
 // }
 
 pub fn secp256k1_sha256_finalize(hash: &mut secp256k1_sha256, out32: &mut [u8; 32]) {
-    static PAD: [u8; 64] = [0x80; 64];
+    static PAD: [u8; 64] = {
+        let mut pad = [0u8; 64];
+        pad[0] = 0x80;
+        pad
+    };
     let mut sizedesc = [0u8; 8];
     let mut i: usize;
     let bytes = hash.bytes as usize;
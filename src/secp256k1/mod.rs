@@ -4,7 +4,12 @@
  * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
  ***********************************************************************/
  #![allow(warnings)]
+pub mod ecdsa;
+pub mod ecdh;
 pub mod eckey;
+pub mod ellswift;
+pub mod extrakeys;
+pub mod ecmult_const;
 pub mod ecmult_gen;
 pub mod ecmult_impl;
 pub mod field;
@@ -14,10 +19,13 @@ pub mod scalar_4x64;
 pub mod group;
 pub mod hash;
 pub mod precomputed_ec_mult_gen;
+pub mod precomputed_ecmult;
 pub mod util;
 pub mod modinv64;
+pub mod recovery;
 pub mod scalar_impl;
 pub mod scratch;
+pub mod schnorrsig;
 
 use ecmult_gen::secp256k1_ecmult_gen_context;
 use group::{secp256k1_ge, secp256k1_ge_storage, secp256k1_ge_from_storage, secp256k1_gej, secp256k1_gej_add_ge, secp256k1_gej_is_infinity};
@@ -25,7 +33,8 @@ use field_5x52::{
     secp256k1_fe,
     secp256k1_fe_set_b32,
     secp256k1_fe_normalize_var,
-    secp256k1_fe_get_b32
+    secp256k1_fe_get_b32,
+    secp256k1_fe_is_zero
 };
 use scalar_4x64::{
     secp256k1_scalar,
@@ -34,15 +43,21 @@ use scalar_4x64::{
     secp256k1_scalar_negate,
     secp256k1_scalar_get_b32,
     secp256k1_scalar_set_b32,
+    secp256k1_scalar_set_int,
+    secp256k1_scalar_is_high,
 };
 use group::{
     secp256k1_ge_set_xy,
     secp256k1_ge_to_storage,
     secp256k1_ge_neg,
     secp256k1_ge_set_gej,
-    secp256k1_gej_set_infinity
+    secp256k1_gej_set_infinity,
+    secp256k1_ge_clear,
+    sec256k1_ge_is_in_correct_subgroup
 };
+use ecdsa::{secp256k1_ecdsa_sig_parse, secp256k1_ecdsa_sig_serialize, secp256k1_ecdsa_sig_sign, secp256k1_ecdsa_sig_verify};
 use eckey::{
+    secp256k1_eckey_pubkey_parse,
     secp256k1_eckey_pubkey_serialize,
     secp256k1_eckey_privkey_tweak_add,
     secp256k1_eckey_pubkey_tweak_add,
@@ -50,6 +65,12 @@ use eckey::{
     secp256k1_eckey_pubkey_tweak_mul
 };
 use hash::{secp256k1_sha256, secp256k1_sha256_initialize_tagged, secp256k1_sha256_write, secp256k1_sha256_finalize};
+use hash::{
+    secp256k1_rfc6979_hmac_sha256,
+    secp256k1_rfc6979_hmac_sha256_initialize,
+    secp256k1_rfc6979_hmac_sha256_generate,
+    secp256k1_rfc6979_hmac_sha256_finalize
+};
 use scalar_impl::{secp256k1_scalar_set_b32_seckey, secp256k1_scalar_one, secp256k1_scalar_zero};
 // use hash::*;
 // use field_5x52::*;
@@ -98,6 +119,38 @@ pub struct secp256k1_pubkey {
     pub data: [u8; 64],
 }
 
+/** Opaque data structured that holds a parsed ECDSA signature.
+ *
+ *  The exact representation of data inside is implementation defined and not
+ *  guaranteed to be portable between different platforms or versions. It is
+ *  however guaranteed to be 64 bytes in size, and can be safely copied/moved.
+ *  If you need to convert to a format suitable for storage, transmission, or
+ *  comparison, use the secp256k1_ecdsa_signature_serialize_* and
+ *  secp256k1_ecdsa_signature_parse_* functions.
+ */
+pub struct secp256k1_ecdsa_signature {
+    pub data: [u8; 64],
+}
+
+/** A pointer to a function to deterministically generate a nonce.
+ *
+ * Returns: 1 if a nonce was successfully generated. 0 will cause signing to fail.
+ * Out:     nonce32:   pointer to a 32-byte array to be filled by the function.
+ * In:      msg32:     the 32-byte message hash being verified (will not be NULL)
+ *          key32:     pointer to a 32-byte secret key (will not be NULL)
+ *          algo16:    pointer to a 16-byte array describing the signature
+ *                     algorithm (will be NULL for ECDSA for compatibility).
+ *          data:      Arbitrary data pointer that is passed through.
+ *          attempt:   how many iterations we have tried to find a nonce.
+ *                     This will almost always be 0, but different attempt values
+ *                     are required to result in a different nonce.
+ *
+ * Except for test cases, this function should compute some cryptographic hash of
+ * the message, the algorithm, the key and the attempt.
+ */
+//typedef int (*secp256k1_nonce_function)(unsigned char *nonce32, const unsigned char *msg32, const unsigned char *key32, const unsigned char *algo16, void *data, unsigned int attempt);
+pub type secp256k1_nonce_function = fn(nonce32: &mut [u8; 32], msg32: &[u8; 32], key32: &[u8; 32], algo16: Option<&[u8; 16]>, data: Option<&[u8]>, attempt: u32) -> i32;
+
 pub struct secp256k1_context {
     ecmult_gen_ctx: secp256k1_ecmult_gen_context,
     //secp256k1_callback illegal_callback;
@@ -107,13 +160,29 @@ pub struct secp256k1_context {
 
 impl secp256k1_context {
     pub fn new() -> Self {
-        secp256k1_context {
+        let mut ctx = secp256k1_context {
             ecmult_gen_ctx: secp256k1_ecmult_gen_context::new(),
-            //ecmult_gen_ctx: secp256k1_ecmult_gen_context::new(),
             //illegal_callback: secp256k1_callback::new(),
             //error_callback: secp256k1_callback::new(),
             declassify: 0,
-        }
+        };
+        secp256k1_ecmult_gen_context::secp256k1_ecmult_gen_context_build(&mut ctx.ecmult_gen_ctx);
+        ctx
+    }
+}
+
+/** A built-in constant secp256k1 context object.
+ *
+ *  This context object offers *only limited functionality*, i.e., it cannot be used
+ *  for API functions that perform computations involving secret keys, e.g., signing
+ *  and public key generation. Its ecmult_gen context is left unbuilt, so creating it
+ *  is cheap.
+ */
+//SECP256K1_API const secp256k1_context *secp256k1_context_static;
+pub fn secp256k1_context_static() -> secp256k1_context {
+    secp256k1_context {
+        ecmult_gen_ctx: secp256k1_ecmult_gen_context::new(),
+        declassify: 0,
     }
 }
 
@@ -151,7 +220,8 @@ fn secp256k1_pubkey_load(ctx: &secp256k1_context, ge: &mut secp256k1_ge, pubkey:
          * Note that secp256k1_pubkey_save must use the same representation. */
         let mut s = secp256k1_ge_storage::new();
         //memcpy(&s, &pubkey.data[0], std::mem::size_of::<secp256k1_ge_storage>());
-        s.x.copy_from_u8slice(pubkey.data.as_slice());
+        s.x.copy_from_u8slice(&pubkey.data[..32]);
+        s.y.copy_from_u8slice(&pubkey.data[32..]);
         secp256k1_ge_from_storage(ge, &s);
     } else {
         /* Otherwise, fall back to 32-byte big endian for X and Y. */
@@ -161,6 +231,10 @@ fn secp256k1_pubkey_load(ctx: &secp256k1_context, ge: &mut secp256k1_ge, pubkey:
         secp256k1_fe_set_b32(&mut y, &pubkey.data[32..]);
         secp256k1_ge_set_xy(ge, &x, &y);
     }
+    //ARG_CHECK(!secp256k1_fe_is_zero(&ge->x));
+    if secp256k1_fe_is_zero(&ge.x) != 0 {
+        return 0;
+    }
     return 1;
 }
 
@@ -199,7 +273,7 @@ pub fn secp256k1_ec_pubkey_create_helper(ecmult_gen_ctx: &mut secp256k1_ecmult_g
     let mut pj = secp256k1_gej::new();
 
     let ret = secp256k1_scalar_set_b32_seckey(seckey_scalar, seckey);
-    secp256k1_scalar_cmov(seckey_scalar, &secp256k1_scalar_one, !ret);
+    secp256k1_scalar_cmov(seckey_scalar, &secp256k1_scalar_one, (ret == 0) as i32);
 
     ecmult_gen_ctx.secp256k1_ecmult_gen(&mut pj, seckey_scalar);
     secp256k1_ge_set_gej(p, &mut pj);
@@ -224,6 +298,26 @@ pub fn secp256k1_ec_pubkey_create(ctx: &mut secp256k1_context, pubkey: &mut secp
     return ret;
 }
 
+//int secp256k1_ec_pubkey_parse(const secp256k1_context* ctx, secp256k1_pubkey* pubkey, const unsigned char *input, size_t inputlen) {
+pub fn secp256k1_ec_pubkey_parse(ctx: &secp256k1_context, pubkey: &mut secp256k1_pubkey, input: &[u8]) -> i32 {
+    let mut Q = secp256k1_ge::new();
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(pubkey != NULL);
+    //memset(pubkey, 0, sizeof(*pubkey));
+    pubkey.data.fill(0);
+    //ARG_CHECK(input != NULL);
+    if secp256k1_eckey_pubkey_parse(&mut Q, input) == 0 {
+        return 0;
+    }
+    if sec256k1_ge_is_in_correct_subgroup(&Q) == 0 {
+        return 0;
+    }
+    secp256k1_pubkey_save(pubkey, &mut Q);
+    secp256k1_ge_clear(&mut Q);
+    return 1;
+}
+
 //int secp256k1_ec_pubkey_serialize(const secp256k1_context* ctx, unsigned char *output, size_t *outputlen, const secp256k1_pubkey* pubkey, unsigned int flags) {
 pub fn secp256k1_ec_pubkey_serialize(ctx: &secp256k1_context, output: &mut [u8], outputlen: &mut usize, pubkey: &secp256k1_pubkey, flags: u32) -> bool {
     let mut Q = secp256k1_ge::new();
@@ -250,6 +344,464 @@ pub fn secp256k1_ec_pubkey_serialize(ctx: &secp256k1_context, output: &mut [u8],
     return ret != 0;
 }
 
+//int secp256k1_ec_pubkey_cmp(const secp256k1_context* ctx, const secp256k1_pubkey* pubkey0, const secp256k1_pubkey* pubkey1) {
+pub fn secp256k1_ec_pubkey_cmp(ctx: &secp256k1_context, pubkey0: &secp256k1_pubkey, pubkey1: &secp256k1_pubkey) -> i32 {
+    let mut out = [[0u8; 33]; 2];
+    let pk = [pubkey0, pubkey1];
+
+    for i in 0..2 {
+        let mut out_size: usize = 33;
+        /* If the public key is invalid, ec_pubkey_serialize will return 0. In
+         * that case we will serialize the key as all zeros which is less than
+         * any valid public key. This results in consistent comparisons even if
+         * invalid pubkeys are involved and prevents edge cases such as sorting
+         * algorithms that use this function and do not terminate as a
+         * result. */
+        if !secp256k1_ec_pubkey_serialize(ctx, &mut out[i], &mut out_size, pk[i], SECP256K1_EC_COMPRESSED) {
+            out[i] = [0u8; 33];
+        }
+    }
+    return util::secp256k1_memcmp_var(&out[0], &out[1], 33);
+}
+
+//static void secp256k1_ecdsa_signature_load(const secp256k1_context* ctx, secp256k1_scalar* r, secp256k1_scalar* s, const secp256k1_ecdsa_signature* sig) {
+fn secp256k1_ecdsa_signature_load(ctx: &secp256k1_context, r: &mut secp256k1_scalar, s: &mut secp256k1_scalar, sig: &secp256k1_ecdsa_signature) {
+    if std::mem::size_of::<secp256k1_scalar>() == 32 {
+        /* When the secp256k1_scalar type is exactly 32 byte, use its
+         * representation inside secp256k1_ecdsa_signature, as conversion is very fast.
+         * Note that secp256k1_ecdsa_signature_save must use the same representation. */
+        //memcpy(r, &sig->data[0], 32);
+        //memcpy(s, &sig->data[32], 32);
+        for i in 0..4 {
+            r.d[i] = u64::from_ne_bytes(sig.data[8*i..8*i + 8].try_into().unwrap());
+            s.d[i] = u64::from_ne_bytes(sig.data[32 + 8*i..32 + 8*i + 8].try_into().unwrap());
+        }
+    } else {
+        let mut overflow: i32 = 0;
+        secp256k1_scalar_set_b32(r, &sig.data[0..32], &mut overflow);
+        secp256k1_scalar_set_b32(s, &sig.data[32..64], &mut overflow);
+    }
+}
+
+//static void secp256k1_ecdsa_signature_save(secp256k1_ecdsa_signature* sig, const secp256k1_scalar* r, const secp256k1_scalar* s) {
+fn secp256k1_ecdsa_signature_save(sig: &mut secp256k1_ecdsa_signature, r: &secp256k1_scalar, s: &secp256k1_scalar) {
+    if std::mem::size_of::<secp256k1_scalar>() == 32 {
+        //memcpy(&sig->data[0], r, 32);
+        //memcpy(&sig->data[32], s, 32);
+        for i in 0..4 {
+            sig.data[8*i..8*i + 8].copy_from_slice(&r.d[i].to_ne_bytes());
+            sig.data[32 + 8*i..32 + 8*i + 8].copy_from_slice(&s.d[i].to_ne_bytes());
+        }
+    } else {
+        secp256k1_scalar_get_b32(&mut sig.data[0..32], &mut r.clone());
+        secp256k1_scalar_get_b32(&mut sig.data[32..64], &mut s.clone());
+    }
+}
+
+//int secp256k1_ecdsa_signature_parse_der(const secp256k1_context* ctx, secp256k1_ecdsa_signature* sig, const unsigned char *input, size_t inputlen) {
+pub fn secp256k1_ecdsa_signature_parse_der(ctx: &secp256k1_context, sig: &mut secp256k1_ecdsa_signature, input: &[u8]) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(sig != NULL);
+    //ARG_CHECK(input != NULL);
+
+    if secp256k1_ecdsa_sig_parse(&mut r, &mut s, input) != 0 {
+        secp256k1_ecdsa_signature_save(sig, &r, &s);
+        return 1;
+    } else {
+        //memset(sig, 0, sizeof(*sig));
+        sig.data.fill(0);
+        return 0;
+    }
+}
+
+/** This function is taken from the libsecp256k1 distribution and implements
+ *  DER parsing for ECDSA signatures, while supporting an arbitrary subset of
+ *  format violations.
+ *
+ *  Supported violations include negative integers, excessive padding, garbage
+ *  at the end, and overly long length descriptors. This is safe to use in
+ *  Bitcoin because since the activation of BIP66, signatures are verified to be
+ *  strict DER before being passed to this module, and we know it supports all
+ *  violations present in the blockchain before that point.
+ */
+//int ecdsa_signature_parse_der_lax(secp256k1_ecdsa_signature* sig, const unsigned char *input, size_t inputlen) {
+pub fn ecdsa_signature_parse_der_lax(sig: &mut secp256k1_ecdsa_signature, input: &[u8]) -> i32 {
+    let ctx = secp256k1_context_static();
+    let inputlen = input.len();
+    let (mut rpos, mut rlen, mut spos, mut slen): (usize, usize, usize, usize);
+    let mut pos: usize = 0;
+    let mut lenbyte: usize;
+    let mut tmpsig = [0u8; 64];
+    let mut overflow: i32 = 0;
+
+    /* Hack to initialize sig with a correctly-parsed but invalid signature. */
+    secp256k1_ecdsa_signature_parse_compact(&ctx, sig, &tmpsig);
+
+    /* Sequence tag byte */
+    if pos == inputlen || input[pos] != 0x30 {
+        return 0;
+    }
+    pos += 1;
+
+    /* Sequence length bytes */
+    if pos == inputlen {
+        return 0;
+    }
+    lenbyte = input[pos] as usize;
+    pos += 1;
+    if lenbyte & 0x80 != 0 {
+        lenbyte -= 0x80;
+        if lenbyte > inputlen - pos {
+            return 0;
+        }
+        pos += lenbyte;
+    }
+
+    /* Integer tag byte for R */
+    if pos == inputlen || input[pos] != 0x02 {
+        return 0;
+    }
+    pos += 1;
+
+    /* Integer length for R */
+    if pos == inputlen {
+        return 0;
+    }
+    lenbyte = input[pos] as usize;
+    pos += 1;
+    if lenbyte & 0x80 != 0 {
+        lenbyte -= 0x80;
+        if lenbyte > inputlen - pos {
+            return 0;
+        }
+        while lenbyte > 0 && input[pos] == 0 {
+            pos += 1;
+            lenbyte -= 1;
+        }
+        if lenbyte >= 4 {
+            return 0;
+        }
+        rlen = 0;
+        while lenbyte > 0 {
+            rlen = (rlen << 8) + input[pos] as usize;
+            pos += 1;
+            lenbyte -= 1;
+        }
+    } else {
+        rlen = lenbyte;
+    }
+    if rlen > inputlen - pos {
+        return 0;
+    }
+    rpos = pos;
+    pos += rlen;
+
+    /* Integer tag byte for S */
+    if pos == inputlen || input[pos] != 0x02 {
+        return 0;
+    }
+    pos += 1;
+
+    /* Integer length for S */
+    if pos == inputlen {
+        return 0;
+    }
+    lenbyte = input[pos] as usize;
+    pos += 1;
+    if lenbyte & 0x80 != 0 {
+        lenbyte -= 0x80;
+        if lenbyte > inputlen - pos {
+            return 0;
+        }
+        while lenbyte > 0 && input[pos] == 0 {
+            pos += 1;
+            lenbyte -= 1;
+        }
+        if lenbyte >= 4 {
+            return 0;
+        }
+        slen = 0;
+        while lenbyte > 0 {
+            slen = (slen << 8) + input[pos] as usize;
+            pos += 1;
+            lenbyte -= 1;
+        }
+    } else {
+        slen = lenbyte;
+    }
+    if slen > inputlen - pos {
+        return 0;
+    }
+    spos = pos;
+
+    /* Ignore leading zeroes in R */
+    while rlen > 0 && input[rpos] == 0 {
+        rlen -= 1;
+        rpos += 1;
+    }
+    /* Copy R value */
+    if rlen > 32 {
+        overflow = 1;
+    } else {
+        tmpsig[32 - rlen..32].copy_from_slice(&input[rpos..rpos + rlen]);
+    }
+
+    /* Ignore leading zeroes in S */
+    while slen > 0 && input[spos] == 0 {
+        slen -= 1;
+        spos += 1;
+    }
+    /* Copy S value */
+    if slen > 32 {
+        overflow = 1;
+    } else {
+        tmpsig[64 - slen..64].copy_from_slice(&input[spos..spos + slen]);
+    }
+
+    if overflow == 0 {
+        overflow = (secp256k1_ecdsa_signature_parse_compact(&ctx, sig, &tmpsig) == 0) as i32;
+    }
+    if overflow != 0 {
+        /* Overwrite the result again with a correctly-parsed but invalid
+           signature if parsing failed. */
+        tmpsig.fill(0);
+        secp256k1_ecdsa_signature_parse_compact(&ctx, sig, &tmpsig);
+    }
+    return 1;
+}
+
+//int secp256k1_ecdsa_signature_parse_compact(const secp256k1_context* ctx, secp256k1_ecdsa_signature* sig, const unsigned char *input64) {
+pub fn secp256k1_ecdsa_signature_parse_compact(ctx: &secp256k1_context, sig: &mut secp256k1_ecdsa_signature, input64: &[u8; 64]) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut ret: i32 = 1;
+    let mut overflow: i32 = 0;
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(sig != NULL);
+    //ARG_CHECK(input64 != NULL);
+
+    secp256k1_scalar_set_b32(&mut r, &input64[0..32], &mut overflow);
+    ret &= (overflow == 0) as i32;
+    secp256k1_scalar_set_b32(&mut s, &input64[32..64], &mut overflow);
+    ret &= (overflow == 0) as i32;
+    if ret != 0 {
+        secp256k1_ecdsa_signature_save(sig, &r, &s);
+    } else {
+        //memset(sig, 0, sizeof(*sig));
+        sig.data.fill(0);
+    }
+    return ret;
+}
+
+//int secp256k1_ecdsa_signature_serialize_der(const secp256k1_context* ctx, unsigned char *output, size_t *outputlen, const secp256k1_ecdsa_signature* sig) {
+pub fn secp256k1_ecdsa_signature_serialize_der(ctx: &secp256k1_context, output: &mut [u8], outputlen: &mut usize, sig: &secp256k1_ecdsa_signature) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(output != NULL);
+    //ARG_CHECK(outputlen != NULL);
+    //ARG_CHECK(sig != NULL);
+
+    secp256k1_ecdsa_signature_load(ctx, &mut r, &mut s, sig);
+    return secp256k1_ecdsa_sig_serialize(output, outputlen, &r, &s);
+}
+
+//int secp256k1_ecdsa_signature_serialize_compact(const secp256k1_context* ctx, unsigned char *output64, const secp256k1_ecdsa_signature* sig) {
+pub fn secp256k1_ecdsa_signature_serialize_compact(ctx: &secp256k1_context, output64: &mut [u8; 64], sig: &secp256k1_ecdsa_signature) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(output64 != NULL);
+    //ARG_CHECK(sig != NULL);
+
+    secp256k1_ecdsa_signature_load(ctx, &mut r, &mut s, sig);
+    secp256k1_scalar_get_b32(&mut output64[0..32], &mut r);
+    secp256k1_scalar_get_b32(&mut output64[32..64], &mut s);
+    return 1;
+}
+
+/** Convert a signature to a normalized lower-S form.
+ *
+ *  With sigout = None only the return value is computed: 1 if sigin was not
+ *  normalized (its S value was high), 0 if it already was.
+ */
+//int secp256k1_ecdsa_signature_normalize(const secp256k1_context* ctx, secp256k1_ecdsa_signature *sigout, const secp256k1_ecdsa_signature *sigin) {
+pub fn secp256k1_ecdsa_signature_normalize(ctx: &secp256k1_context, sigout: Option<&mut secp256k1_ecdsa_signature>, sigin: &secp256k1_ecdsa_signature) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut ret: i32 = 0;
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(sigin != NULL);
+
+    secp256k1_ecdsa_signature_load(ctx, &mut r, &mut s, sigin);
+    ret = secp256k1_scalar_is_high(&s);
+    if let Some(sigout) = sigout {
+        if ret != 0 {
+            let s1 = s.clone();
+            secp256k1_scalar_negate(&mut s, &s1);
+        }
+        secp256k1_ecdsa_signature_save(sigout, &r, &s);
+    }
+
+    return ret;
+}
+
+//int secp256k1_ecdsa_verify(const secp256k1_context* ctx, const secp256k1_ecdsa_signature *sig, const unsigned char *msghash32, const secp256k1_pubkey *pubkey) {
+pub fn secp256k1_ecdsa_verify(ctx: &secp256k1_context, sig: &secp256k1_ecdsa_signature, msghash32: &[u8; 32], pubkey: &secp256k1_pubkey) -> i32 {
+    let mut q = secp256k1_ge::new();
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut m = secp256k1_scalar::new();
+    let mut overflow: i32 = 0;
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(msghash32 != NULL);
+    //ARG_CHECK(sig != NULL);
+    //ARG_CHECK(pubkey != NULL);
+
+    secp256k1_scalar_set_b32(&mut m, msghash32, &mut overflow);
+    secp256k1_ecdsa_signature_load(ctx, &mut r, &mut s, sig);
+    return (secp256k1_scalar_is_high(&s) == 0 &&
+            secp256k1_pubkey_load(ctx, &mut q, pubkey) != 0 &&
+            secp256k1_ecdsa_sig_verify(&r, &s, &q, &m) != 0) as i32;
+}
+
+//static SECP256K1_INLINE void buffer_append(unsigned char *buf, unsigned int *offset, const void *data, unsigned int len) {
+fn buffer_append(buf: &mut [u8], offset: &mut usize, data: &[u8]) {
+    //memcpy(buf + *offset, data, len);
+    buf[*offset..*offset + data.len()].copy_from_slice(data);
+    *offset += data.len();
+}
+
+//static int nonce_function_rfc6979(unsigned char *nonce32, const unsigned char *msg32, const unsigned char *key32, const unsigned char *algo16, void *data, unsigned int counter) {
+fn nonce_function_rfc6979(nonce32: &mut [u8; 32], msg32: &[u8; 32], key32: &[u8; 32], algo16: Option<&[u8; 16]>, data: Option<&[u8]>, counter: u32) -> i32 {
+    let mut keydata = [0u8; 112];
+    let mut offset: usize = 0;
+    let mut rng = secp256k1_rfc6979_hmac_sha256::new();
+    let mut msg = secp256k1_scalar::new();
+    let mut msgmod32 = [0u8; 32];
+    let mut overflow: i32 = 0;
+    secp256k1_scalar_set_b32(&mut msg, msg32, &mut overflow);
+    secp256k1_scalar_get_b32(&mut msgmod32, &mut msg);
+    /* We feed a byte array to the PRNG as input, consisting of:
+     * - the private key (32 bytes) and message (32 bytes), see RFC 6979 3.2d.
+     * - optionally 32 extra bytes of data, see RFC 6979 3.6 Additional Data.
+     * - optionally 16 extra bytes with the algorithm name.
+     * Because the arguments have distinct fixed lengths it is not possible for
+     *  different argument mixtures to emulate each other and result in the same
+     *  nonces.
+     */
+    buffer_append(&mut keydata, &mut offset, key32);
+    buffer_append(&mut keydata, &mut offset, &msgmod32);
+    if let Some(data) = data {
+        buffer_append(&mut keydata, &mut offset, &data[..32]);
+    }
+    if let Some(algo16) = algo16 {
+        buffer_append(&mut keydata, &mut offset, algo16);
+    }
+    secp256k1_rfc6979_hmac_sha256_initialize(&mut rng, &keydata[..offset]);
+    keydata.fill(0);
+    for _ in 0..=counter {
+        secp256k1_rfc6979_hmac_sha256_generate(&mut rng, nonce32);
+    }
+    secp256k1_rfc6979_hmac_sha256_finalize(&mut rng);
+    return 1;
+}
+
+/** An implementation of RFC6979 (using HMAC-SHA256) as nonce generation function.
+ * If a data pointer is passed, it is assumed to be a pointer to 32 bytes of
+ * extra entropy.
+ */
+pub const secp256k1_nonce_function_rfc6979: secp256k1_nonce_function = nonce_function_rfc6979;
+
+/** A default safe nonce generation function (currently equal to secp256k1_nonce_function_rfc6979). */
+pub const secp256k1_nonce_function_default: secp256k1_nonce_function = nonce_function_rfc6979;
+
+//static int secp256k1_ecdsa_sign_inner(const secp256k1_context* ctx, secp256k1_scalar* r, secp256k1_scalar* s, int* recid, const unsigned char *msg32, const unsigned char *seckey, secp256k1_nonce_function noncefp, const void* noncedata) {
+fn secp256k1_ecdsa_sign_inner(ctx: &mut secp256k1_context, r: &mut secp256k1_scalar, s: &mut secp256k1_scalar, mut recid: Option<&mut i32>, msg32: &[u8; 32], seckey: &[u8; 32], noncefp: Option<secp256k1_nonce_function>, noncedata: Option<&[u8]>) -> i32 {
+    let mut sec = secp256k1_scalar::new();
+    let mut non = secp256k1_scalar::new();
+    let mut msg = secp256k1_scalar::new();
+    let mut ret: i32 = 0;
+    let is_sec_valid: i32;
+    let mut nonce32 = [0u8; 32];
+    let mut count: u32 = 0;
+    let mut overflow: i32 = 0;
+    /* Default initialization here is important so we won't pass uninit values to the cmov in the end */
+    *r = secp256k1_scalar_zero;
+    *s = secp256k1_scalar_zero;
+    if let Some(recid) = recid.as_deref_mut() {
+        *recid = 0;
+    }
+    let noncefp = noncefp.unwrap_or(secp256k1_nonce_function_default);
+
+    /* Fail if the secret key is invalid. */
+    is_sec_valid = secp256k1_scalar_set_b32_seckey(&mut sec, seckey);
+    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_one, (is_sec_valid == 0) as i32);
+    secp256k1_scalar_set_b32(&mut msg, msg32, &mut overflow);
+    loop {
+        let is_nonce_valid: i32;
+        ret = (noncefp(&mut nonce32, msg32, seckey, None, noncedata, count) != 0) as i32;
+        if ret == 0 {
+            break;
+        }
+        is_nonce_valid = secp256k1_scalar_set_b32_seckey(&mut non, &nonce32);
+        /* The nonce is still secret here, but it being invalid is is less likely than 1:2^255. */
+        //secp256k1_declassify(ctx, &is_nonce_valid, sizeof(is_nonce_valid));
+        if is_nonce_valid != 0 {
+            ret = secp256k1_ecdsa_sig_sign(&mut ctx.ecmult_gen_ctx, r, s, &sec, &msg, &non, recid.as_deref_mut());
+            /* The final signature is no longer a secret, nor is the fact that we were successful or not. */
+            //secp256k1_declassify(ctx, &ret, sizeof(ret));
+            if ret != 0 {
+                break;
+            }
+        }
+        count += 1;
+    }
+    /* We don't want to declassify is_sec_valid and therefore the range of
+     * seckey. As a result is_sec_valid is included in ret only after ret was
+     * used as a branching variable. */
+    ret &= is_sec_valid;
+    nonce32.fill(0);
+    secp256k1_scalar_clear(&mut msg);
+    secp256k1_scalar_clear(&mut non);
+    secp256k1_scalar_clear(&mut sec);
+    secp256k1_scalar_cmov(r, &secp256k1_scalar_zero, (ret == 0) as i32);
+    secp256k1_scalar_cmov(s, &secp256k1_scalar_zero, (ret == 0) as i32);
+    if let Some(recid) = recid {
+        let zero: i32 = 0;
+        //secp256k1_int_cmov(recid, &zero, !ret);
+        if ret == 0 {
+            *recid = zero;
+        }
+    }
+    return ret;
+}
+
+//int secp256k1_ecdsa_sign(const secp256k1_context* ctx, secp256k1_ecdsa_signature *signature, const unsigned char *msghash32, const unsigned char *seckey, secp256k1_nonce_function noncefp, const void* noncedata) {
+pub fn secp256k1_ecdsa_sign(ctx: &mut secp256k1_context, signature: &mut secp256k1_ecdsa_signature, msghash32: &[u8; 32], seckey: &[u8; 32], noncefp: Option<secp256k1_nonce_function>, noncedata: Option<&[u8]>) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut ret: i32 = 0;
+
+    //VERIFY_CHECK(ctx != NULL);
+    //ARG_CHECK(secp256k1_ecmult_gen_context_is_built(&ctx->ecmult_gen_ctx));
+    //ARG_CHECK(msghash32 != NULL);
+    //ARG_CHECK(signature != NULL);
+    //ARG_CHECK(seckey != NULL);
+
+    ret = secp256k1_ecdsa_sign_inner(ctx, &mut r, &mut s, None, msghash32, seckey, noncefp, noncedata);
+    secp256k1_ecdsa_signature_save(signature, &r, &s);
+    return ret;
+}
+
 //int secp256k1_ec_seckey_negate(const secp256k1_context* ctx, unsigned char *seckey) {
 pub fn secp256k1_ec_seckey_negate(ctx: &secp256k1_context, seckey: &mut [u8; 32]) -> i32 {
     let mut sec = secp256k1_scalar::new();
@@ -258,7 +810,7 @@ pub fn secp256k1_ec_seckey_negate(ctx: &secp256k1_context, seckey: &mut [u8; 32]
     //ARG_CHECK(seckey != NULL);
 
     ret = secp256k1_scalar_set_b32_seckey(&mut sec, seckey);
-    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_zero, !ret);
+    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_zero, (ret == 0) as i32);
     let sec1 = sec.clone();
     secp256k1_scalar_negate(&mut sec, &sec1);
     secp256k1_scalar_get_b32(seckey, &mut sec);
@@ -308,7 +860,7 @@ pub fn secp256k1_ec_seckey_tweak_add(ctx: &secp256k1_context, seckey: &mut [u8;
 
     ret = secp256k1_scalar_set_b32_seckey(&mut sec, seckey);
     ret &= secp256k1_ec_seckey_tweak_add_helper(&mut sec, tweak32);
-    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_zero, !ret);
+    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_zero, (ret == 0) as i32);
     secp256k1_scalar_get_b32(seckey, &mut sec);
 
     secp256k1_scalar_clear(&mut sec);
@@ -352,8 +904,8 @@ pub fn secp256k1_ec_seckey_tweak_mul(ctx: &secp256k1_context, seckey: &mut [u8;
 
     secp256k1_scalar_set_b32(&mut factor, tweak32, &mut overflow);
     ret = secp256k1_scalar_set_b32_seckey(&mut sec, seckey);
-    ret &= ((overflow != 0) && secp256k1_eckey_privkey_tweak_mul(&mut sec, &factor) == 0) as i32;
-    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_zero, !ret);
+    ret &= ((overflow == 0) && secp256k1_eckey_privkey_tweak_mul(&mut sec, &factor) != 0) as i32;
+    secp256k1_scalar_cmov(&mut sec, &secp256k1_scalar_zero, (ret == 0) as i32);
     secp256k1_scalar_get_b32(seckey, &mut sec);
 
     secp256k1_scalar_clear(&mut sec);
@@ -374,7 +926,7 @@ pub fn secp256k1_ec_pubkey_tweak_mul(ctx: &secp256k1_context, pubkey: &mut secp2
     let mut overflow: i32 = 0;
 
     secp256k1_scalar_set_b32(&mut factor, tweak32, &mut overflow);
-    ret = (overflow != 0) as i32 & secp256k1_pubkey_load(ctx, &mut p, pubkey);
+    ret = (overflow == 0) as i32 & secp256k1_pubkey_load(ctx, &mut p, pubkey);
     //memset(pubkey, 0, sizeof(*pubkey));
     pubkey.data.fill(0);
     if ret != 0 {
@@ -46,7 +46,7 @@ fn secp256k1_modinv64_abs(v: i64) -> i64 {
 
 //static const secp256k1_modinv64_signed62 SECP256K1_SIGNED62_ONE = {{1}};
 #[cfg(feature = "verify")]
-const SECP256K1_SIGNED62_ONE: secp256k1_modinv64_signed62 = secp256k1_modinv64_signed62 { v: [1; 5]};
+const SECP256K1_SIGNED62_ONE: secp256k1_modinv64_signed62 = secp256k1_modinv64_signed62 { v: [1, 0, 0, 0, 0]};
 
 /* Compute a*factor and put it in r. All but the top limb in r will be in range [0,2^62). */
 // static void secp256k1_modinv64_mul_62(secp256k1_modinv64_signed62 *r, const secp256k1_modinv64_signed62 *a, int alen, int64_t factor) {
@@ -89,7 +89,7 @@ fn secp256k1_modinv64_mul_cmp_62(a: &secp256k1_modinv64_signed62, alen: i32, b:
         VERIFY_CHECK!(am.v[i] >> 62 == 0);
         VERIFY_CHECK!(bm.v[i] >> 62 == 0);        
     }
-    for i in 4..0 {
+    for i in (0..=4).rev() {
         if am.v[i] < bm.v[i] { return -1 };
         if am.v[i] > bm.v[i] { return 1 };
     }
@@ -135,6 +135,11 @@ pub fn secp256k1_modinv64_normalize_62(r: &mut secp256k1_modinv64_signed62, sign
     r2 = (r2 ^ cond_negate) - cond_negate;
     r3 = (r3 ^ cond_negate) - cond_negate;
     r4 = (r4 ^ cond_negate) - cond_negate;
+    // Propagate the top bits, to bring limbs back to range (-2^62,2^62).
+    r1 += r0 >> 62; r0 &= M62;
+    r2 += r1 >> 62; r1 &= M62;
+    r3 += r2 >> 62; r2 &= M62;
+    r4 += r3 >> 62; r3 &= M62;
 
     // In a second step add the modulus again if the result is still negative, bringing
     // r to range [0,modulus).
@@ -174,10 +179,10 @@ pub fn secp256k1_modinv64_normalize_62(r: &mut secp256k1_modinv64_signed62, sign
  */
 
 struct secp256k1_modinv64_trans2x2 {
-    u: u64,
-    v: u64,
-    q: u64,
-    r: u64,
+    u: i64,
+    v: i64,
+    q: i64,
+    r: i64,
 }
 
 /* Compute the transition matrix and eta for 59 divsteps (where zeta=-(delta+1/2)).
@@ -208,28 +213,28 @@ fn secp256k1_modinv64_divsteps_59(mut zeta: i64, f0: u64, g0: u64, t: &mut secp2
     for i in 3..62 {
         #[cfg(feature = "verify")] {
             VERIFY_CHECK!((f & 1) == 1); /* f must always be odd */
-            VERIFY_CHECK!((u * f0 + v * g0) == f << i);
-            VERIFY_CHECK!((q * f0 + r * g0) == g << i);
+            VERIFY_CHECK!(u.wrapping_mul(f0).wrapping_add(v.wrapping_mul(g0)) == f << i);
+            VERIFY_CHECK!(q.wrapping_mul(f0).wrapping_add(r.wrapping_mul(g0)) == g << i);
         }
         /* Compute conditional masks for (zeta < 0) and for (g & 1). */
-        c1 = zeta as u64 >> 63;
-        c2 = -(g as i64 & 1) as u64;
+        c1 = (zeta >> 63) as u64;
+        c2 = (g & 1).wrapping_neg();
         /* Compute x,y,z, conditionally negated versions of f,u,v. */
-        x = (f ^ c1) - c1;
-        y = (u ^ c1) - c1;
-        z = (v ^ c1) - c1;
+        x = (f ^ c1).wrapping_sub(c1);
+        y = (u ^ c1).wrapping_sub(c1);
+        z = (v ^ c1).wrapping_sub(c1);
         /* Conditionally add x,y,z to g,q,r. */
-        g += x & c2;
-        q += y & c2;
-        r += z & c2;
+        g = g.wrapping_add(x & c2);
+        q = q.wrapping_add(y & c2);
+        r = r.wrapping_add(z & c2);
         /* In what follows, c1 is a condition mask for (zeta < 0) and (g & 1). */
         c1 &= c2;
         /* Conditionally change zeta into -zeta-2 or zeta-1. */
         zeta = (zeta ^ c1 as i64) - 1;
         /* Conditionally add g,q,r to f,u,v. */
-        f += g & c1;
-        u += q & c1;
-        v += r & c1;
+        f = f.wrapping_add(g & c1);
+        u = u.wrapping_add(q & c1);
+        v = v.wrapping_add(r & c1);
         /* Shifts */
         g >>= 1;
         u <<= 1;
@@ -240,10 +245,10 @@ fn secp256k1_modinv64_divsteps_59(mut zeta: i64, f0: u64, g0: u64, t: &mut secp2
         }
     }
     /* Return data in t and return value. */
-    t.u = u;
-    t.v = v;
-    t.q = q;
-    t.r = r;
+    t.u = u as i64;
+    t.v = v as i64;
+    t.q = q as i64;
+    t.r = r as i64;
     /* The determinant of t must be a power of two. This guarantees that multiplication with t
      * does not change the gcd of f and g, apart from adding a power-of-2 factor to it (which
      * will be divided out again). As each divstep's individual matrix has determinant 2, the
@@ -294,16 +299,16 @@ fn secp256k1_modinv64_divsteps_62_var(mut eta: i64, f0: u64, g0: u64, t: &mut se
         #[cfg(feature = "verify")] {
             VERIFY_CHECK!((f & 1) == 1);
             VERIFY_CHECK!((g & 1) == 1);
-            VERIFY_CHECK!((u * f0 + v * g0) == f << (62 - i));
-            VERIFY_CHECK!((q * f0 + r * g0) == g << (62 - i));
+            VERIFY_CHECK!(u.wrapping_mul(f0).wrapping_add(v.wrapping_mul(g0)) == f << (62 - i));
+            VERIFY_CHECK!(q.wrapping_mul(f0).wrapping_add(r.wrapping_mul(g0)) == g << (62 - i));
             VERIFY_CHECK!(eta >= -745 && eta <= 745);
         }
         if eta < 0 {
             let mut tmp: u64;
             eta = -eta;
-            tmp = f; f = g; g = -(tmp as i64) as u64;
-            tmp = u; u = q; q = -(tmp as i64) as u64;
-            tmp = v; v = r; r = -(tmp as i64) as u64;
+            tmp = f; f = g; g = tmp.wrapping_neg();
+            tmp = u; u = q; q = tmp.wrapping_neg();
+            tmp = v; v = r; r = tmp.wrapping_neg();
              /* Use a formula to cancel out up to 6 bits of g. Also, no more than i can be cancelled
               * out (as we'd be done before that point), and no more than eta+1 can be done as its
               * will flip again once that happens. */
@@ -320,19 +325,19 @@ fn secp256k1_modinv64_divsteps_62_var(mut eta: i64, f0: u64, g0: u64, t: &mut se
             m = (u64::MAX >> (64 - limit)) & 15;
             /* Find what multiple of f must be added to g to cancel its bottom min(limit, 4)
              * bits. */
-            w = (f + (((f + 1) & 4) << 1)) as u32;
-            w = ((-(w as i64) as u64 * g) & m) as u32;
+            w = f.wrapping_add((f.wrapping_add(1) & 4) << 1) as u32;
+            w = ((w.wrapping_neg() as u64).wrapping_mul(g) & m) as u32;
         }
-        g += f * w as u64;
-        q += u * w as u64;
-        r += v * w as u64;
+        g = g.wrapping_add(f.wrapping_mul(w as u64));
+        q = q.wrapping_add(u.wrapping_mul(w as u64));
+        r = r.wrapping_add(v.wrapping_mul(w as u64));
         #[cfg(feature = "verify")] VERIFY_CHECK!((g & m) == 0);
     }
     /* Return data in t and return value. */
-    t.u = u;
-    t.v = v;
-    t.q = q;
-    t.r = r;
+    t.u = u as i64;
+    t.v = v as i64;
+    t.q = q as i64;
+    t.r = r as i64;
     /* The determinant of t must be a power of two. This guarantees that multiplication with t
      * does not change the gcd of f and g, apart from adding a power-of-2 factor to it (which
      * will be divided out again). As each divstep's individual matrix has determinant 2, the
@@ -386,22 +391,20 @@ fn secp256k1_modinv64_update_de_62(d: &mut secp256k1_modinv64_signed62, e: &mut
     /* [md,me] start as zero; plus [u,q] if d is negative; plus [v,r] if e is negative. */
     sd = d4 >> 63;
     se = e4 >> 63;
-    md = (u as i64 & sd) + (v as i64 & se);
-    me = (q as i64 & sd) + (r as i64 & se);
+    md = (u & sd) + (v & se);
+    me = (q & sd) + (r & se);
     /* Begin computing t*[d,e]. */
     cd = u as i128 * d0 as i128 + v as i128 * e0 as i128;
     ce = q as i128 * d0 as i128 + r as i128 * e0 as i128;
     /* Correct md,me so that t*[d,e]+modulus*[md,me] has 62 zero bottom bits. */
-    md -= (modinfo.modulus_inv62 * cd as u64 + md as u64) as i64 & M62;
-    me -= (modinfo.modulus_inv62 * ce as u64 + me as u64) as i64 & M62;
+    md -= modinfo.modulus_inv62.wrapping_mul(cd as u64).wrapping_add(md as u64) as i64 & M62;
+    me -= modinfo.modulus_inv62.wrapping_mul(ce as u64).wrapping_add(me as u64) as i64 & M62;
     /* Update the beginning of computation for t*[d,e]+modulus*[md,me] now md,me are known. */
     cd += modinfo.modulus.v[0] as i128 * md as i128;
     ce += modinfo.modulus.v[0] as i128 * me as i128;
     /* Verify that the low 62 bits of the computation are indeed zero, and then throw them away. */
-    #[cfg(feature = "verify")] {
-        VERIFY_CHECK!((cd as i64 & M62) == 0); cd >>= 62;
-        VERIFY_CHECK!((ce as i64 & M62) == 0); ce >>= 62;
-    }
+    VERIFY_CHECK!((cd as i64 & M62) == 0); cd >>= 62;
+    VERIFY_CHECK!((ce as i64 & M62) == 0); ce >>= 62;
     /* Compute limb 1 of t*[d,e]+modulus*[md,me], and store it as output limb 0 (= down shift). */
     cd += u as i128 * d1 as i128 + v as i128 * e1 as i128;
     ce += q as i128 * d1 as i128 + r as i128 * e1 as i128;
@@ -457,7 +460,7 @@ fn secp256k1_modinv64_update_fg_62(f: &mut secp256k1_modinv64_signed62, g: &mut
 
     let g0: i64 = g.v[0]; let g1: i64 = g.v[1]; let g2: i64 = g.v[2]; let g3: i64 = g.v[3]; let g4: i64 = g.v[4];
 
-    let u: i64 = t.u as i64; let v: i64 = t.v as i64; let q: i64 = t.q as i64; let r: i64 = t.r as i64;
+    let u: i64 = t.u; let v: i64 = t.v; let q: i64 = t.q; let r: i64 = t.r;
 
     let mut cf: i128; let mut cg: i128;
     /* Start computing t*[f,g]. */
@@ -465,10 +468,8 @@ fn secp256k1_modinv64_update_fg_62(f: &mut secp256k1_modinv64_signed62, g: &mut
     cg = (q as i128) * (f0 as i128) + (r as i128) * (g0 as i128);
 
     /* Verify that the bottom 62 bits of the result are zero, and then throw them away. */
-    #[cfg(feature = "verify")] {
-        VERIFY_CHECK!((cf as i64 & M62) == 0); cf >>= 62;
-        VERIFY_CHECK!((cg as i64 & M62) == 0); cg >>= 62;
-    }
+    VERIFY_CHECK!((cf as i64 & M62) == 0); cf >>= 62;
+    VERIFY_CHECK!((cg as i64 & M62) == 0); cg >>= 62;
     /* Compute limb 1 of t*[f,g], and store it as output limb 0 (= down shift). */
     cf += u as i128 * f1 as i128 + v as i128 * g1 as i128;
     cg += q as i128 * f1 as i128 + r as i128 * g1 as i128;
@@ -502,7 +503,7 @@ fn secp256k1_modinv64_update_fg_62(f: &mut secp256k1_modinv64_signed62, g: &mut
  */
 fn secp256k1_modinv64_update_fg_62_var(len: usize, f: &mut secp256k1_modinv64_signed62, g: &mut secp256k1_modinv64_signed62, t: &secp256k1_modinv64_trans2x2) {
     let M62: i64 = (u64::MAX >> 2) as i64;
-    let u: i64 = t.u as i64; let v: i64 = t.v as i64; let q: i64 = t.q as i64; let r: i64 = t.r as i64;
+    let u: i64 = t.u; let v: i64 = t.v; let q: i64 = t.q; let r: i64 = t.r;
     let mut fi: i64; let mut gi: i64;
     let mut cf: i128; let mut cg: i128;
     let mut i: usize;
@@ -515,10 +516,8 @@ fn secp256k1_modinv64_update_fg_62_var(len: usize, f: &mut secp256k1_modinv64_si
     cf = (u as i128) * (fi as i128) + (v as i128) * (gi as i128);
     cg = (q as i128) * (fi as i128) + (r as i128) * (gi as i128);
     /* Verify that the bottom 62 bits of the result are zero, and then throw them away. */
-    #[cfg(feature = "verify")] {
-        VERIFY_CHECK!(((cf as i64) & M62) == 0); cf >>= 62;
-        VERIFY_CHECK!(((cg as i64) & M62) == 0); cg >>= 62;
-    }
+    VERIFY_CHECK!(((cf as i64) & M62) == 0); cf >>= 62;
+    VERIFY_CHECK!(((cg as i64) & M62) == 0); cg >>= 62;
     /* Now iteratively compute limb i=1..len of t*[f,g], and store them in output limb i-1 (shifting
      * down by 62 bits). */
     for i in 1..len {
@@ -597,7 +596,7 @@ pub fn secp256k1_modinv64_var(x: &mut secp256k1_modinv64_signed62, modinfo: &sec
     let mut j: usize;
     let mut len: usize = 5;
     
-    let eta: i64 = -1; /* eta = -delta; delta is initially 1 */
+    let mut eta: i64 = -1; /* eta = -delta; delta is initially 1 */
     //int64_t cond, fn, gn;
     let mut cond: i64;
     let mut fN: i64;
@@ -608,7 +607,7 @@ pub fn secp256k1_modinv64_var(x: &mut secp256k1_modinv64_signed62, modinfo: &sec
     loop {
         /* Compute transition matrix and new eta after 62 divsteps. */
         let mut t: secp256k1_modinv64_trans2x2 = secp256k1_modinv64_trans2x2 { u: 0, v: 0, q: 0, r: 0 };
-        let eta = secp256k1_modinv64_divsteps_62_var(eta, f.v[0] as u64, g.v[0] as u64, &mut t);
+        eta = secp256k1_modinv64_divsteps_62_var(eta, f.v[0] as u64, g.v[0] as u64, &mut t);
         /* Update d,e using that transition matrix. */
         secp256k1_modinv64_update_de_62(&mut d, &mut e, &t, modinfo);
         /* Update f,g using that transition matrix. */
@@ -0,0 +1,234 @@
+/***********************************************************************
+ * Copyright (c) 2013-2015 Pieter Wuille                               *
+ * Distributed under the MIT software license, see the accompanying    *
+ * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
+ ***********************************************************************/
+
+use super::{secp256k1_context, secp256k1_pubkey, secp256k1_pubkey_save, secp256k1_ecdsa_signature,
+            secp256k1_ecdsa_signature_save, secp256k1_ecdsa_sign_inner, secp256k1_nonce_function};
+use super::ecdsa::{secp256k1_ecdsa_const_order_as_fe, secp256k1_ecdsa_const_p_minus_order};
+use super::field_5x52::{secp256k1_fe, secp256k1_fe_set_b32, secp256k1_fe_add, secp256k1_fe_cmp_var};
+use super::group::{secp256k1_ge, secp256k1_gej, secp256k1_ge_set_xo_var, secp256k1_gej_set_ge, secp256k1_ge_set_gej_var,
+                   secp256k1_gej_is_infinity};
+use super::scalar_4x64::{secp256k1_scalar, secp256k1_scalar_set_b32, secp256k1_scalar_get_b32, secp256k1_scalar_is_zero,
+                         secp256k1_scalar_inverse_var, secp256k1_scalar_mul, secp256k1_scalar_negate};
+use super::ecmult_impl::secp256k1_ecmult;
+
+/** Opaque data structured that holds a parsed ECDSA signature,
+ *  supporting pubkey recovery.
+ *
+ *  The exact representation of data inside is implementation defined and not
+ *  guaranteed to be portable between different platforms or versions. It is
+ *  however guaranteed to be 65 bytes in size, and can be safely copied/moved.
+ *  If you need to convert to a format suitable for storage or transmission, use
+ *  the secp256k1_ecdsa_recoverable_signature_serialize_* and
+ *  secp256k1_ecdsa_recoverable_signature_parse_* functions.
+ *
+ *  Furthermore, it is guaranteed that identical signatures (including their
+ *  recoverability) will have identical representation, so they can be
+ *  memcmp'ed.
+ */
+#[derive(Clone)]
+pub struct secp256k1_ecdsa_recoverable_signature {
+    pub data: [u8; 65],
+}
+
+impl secp256k1_ecdsa_recoverable_signature {
+    pub fn new() -> Self {
+        secp256k1_ecdsa_recoverable_signature { data: [0u8; 65] }
+    }
+}
+
+//static void secp256k1_ecdsa_recoverable_signature_load(const secp256k1_context* ctx, secp256k1_scalar* r, secp256k1_scalar* s, int* recid, const secp256k1_ecdsa_recoverable_signature* sig) {
+fn secp256k1_ecdsa_recoverable_signature_load(ctx: &secp256k1_context, r: &mut secp256k1_scalar, s: &mut secp256k1_scalar, recid: &mut i32, sig: &secp256k1_ecdsa_recoverable_signature) {
+    if std::mem::size_of::<secp256k1_scalar>() == 32 {
+        /* When the secp256k1_scalar type is exactly 32 byte, use its
+         * representation inside secp256k1_ecdsa_signature, as conversion is very fast.
+         * Note that secp256k1_ecdsa_signature_save must use the same representation. */
+        //memcpy(r, &sig->data[0], 32);
+        //memcpy(s, &sig->data[32], 32);
+        for i in 0..4 {
+            r.d[i] = u64::from_ne_bytes(sig.data[8*i..8*i + 8].try_into().unwrap());
+            s.d[i] = u64::from_ne_bytes(sig.data[32 + 8*i..32 + 8*i + 8].try_into().unwrap());
+        }
+    } else {
+        let mut overflow: i32 = 0;
+        secp256k1_scalar_set_b32(r, &sig.data[0..32], &mut overflow);
+        secp256k1_scalar_set_b32(s, &sig.data[32..64], &mut overflow);
+    }
+    *recid = sig.data[64] as i32;
+}
+
+//static void secp256k1_ecdsa_recoverable_signature_save(secp256k1_ecdsa_recoverable_signature* sig, const secp256k1_scalar* r, const secp256k1_scalar* s, int recid) {
+fn secp256k1_ecdsa_recoverable_signature_save(sig: &mut secp256k1_ecdsa_recoverable_signature, r: &secp256k1_scalar, s: &secp256k1_scalar, recid: i32) {
+    if std::mem::size_of::<secp256k1_scalar>() == 32 {
+        //memcpy(&sig->data[0], r, 32);
+        //memcpy(&sig->data[32], s, 32);
+        for i in 0..4 {
+            sig.data[8*i..8*i + 8].copy_from_slice(&r.d[i].to_ne_bytes());
+            sig.data[32 + 8*i..32 + 8*i + 8].copy_from_slice(&s.d[i].to_ne_bytes());
+        }
+    } else {
+        secp256k1_scalar_get_b32(&mut sig.data[0..32], &mut r.clone());
+        secp256k1_scalar_get_b32(&mut sig.data[32..64], &mut s.clone());
+    }
+    sig.data[64] = recid as u8;
+}
+
+/** Parse a compact ECDSA signature (64 bytes + recovery id).
+ *
+ *  Returns: 1 when the signature could be parsed, 0 otherwise
+ *  Out:  sig:     the parsed signature
+ *  In:   input64: the 64-byte compact signature
+ *        recid:   the recovery id (0, 1, 2 or 3)
+ */
+//int secp256k1_ecdsa_recoverable_signature_parse_compact(const secp256k1_context* ctx, secp256k1_ecdsa_recoverable_signature* sig, const unsigned char *input64, int recid) {
+pub fn secp256k1_ecdsa_recoverable_signature_parse_compact(ctx: &secp256k1_context, sig: &mut secp256k1_ecdsa_recoverable_signature, input64: &[u8; 64], recid: i32) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut ret: i32 = 1;
+    let mut overflow: i32 = 0;
+
+    //ARG_CHECK(recid >= 0 && recid <= 3);
+    if !(0..=3).contains(&recid) {
+        return 0;
+    }
+
+    secp256k1_scalar_set_b32(&mut r, &input64[0..32], &mut overflow);
+    ret &= (overflow == 0) as i32;
+    secp256k1_scalar_set_b32(&mut s, &input64[32..64], &mut overflow);
+    ret &= (overflow == 0) as i32;
+    if ret != 0 {
+        secp256k1_ecdsa_recoverable_signature_save(sig, &r, &s, recid);
+    } else {
+        sig.data = [0u8; 65];
+    }
+    return ret;
+}
+
+/** Serialize an ECDSA signature in compact format (64 bytes + recovery id).
+ *
+ *  Returns: 1
+ *  Out:  output64: the 64-byte array of the compact signature
+ *        recid:    the recovery id (0, 1, 2 or 3)
+ *  In:   sig:      the initialized signature
+ */
+//int secp256k1_ecdsa_recoverable_signature_serialize_compact(const secp256k1_context* ctx, unsigned char *output64, int *recid, const secp256k1_ecdsa_recoverable_signature* sig) {
+pub fn secp256k1_ecdsa_recoverable_signature_serialize_compact(ctx: &secp256k1_context, output64: &mut [u8; 64], recid: &mut i32, sig: &secp256k1_ecdsa_recoverable_signature) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+
+    secp256k1_ecdsa_recoverable_signature_load(ctx, &mut r, &mut s, recid, sig);
+    secp256k1_scalar_get_b32(&mut output64[0..32], &mut r);
+    secp256k1_scalar_get_b32(&mut output64[32..64], &mut s);
+    return 1;
+}
+
+/** Convert a recoverable signature into a normal signature.
+ *
+ *  Returns: 1
+ *  Out: sig:   the converted normal signature
+ *  In:  sigin: the recoverable signature
+ */
+//int secp256k1_ecdsa_recoverable_signature_convert(const secp256k1_context* ctx, secp256k1_ecdsa_signature* sig, const secp256k1_ecdsa_recoverable_signature* sigin) {
+pub fn secp256k1_ecdsa_recoverable_signature_convert(ctx: &secp256k1_context, sig: &mut secp256k1_ecdsa_signature, sigin: &secp256k1_ecdsa_recoverable_signature) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut recid: i32 = 0;
+
+    secp256k1_ecdsa_recoverable_signature_load(ctx, &mut r, &mut s, &mut recid, sigin);
+    secp256k1_ecdsa_signature_save(sig, &r, &s);
+    return 1;
+}
+
+//static int secp256k1_ecdsa_sig_recover(const secp256k1_scalar *sigr, const secp256k1_scalar* sigs, secp256k1_ge *pubkey, const secp256k1_scalar *message, int recid) {
+fn secp256k1_ecdsa_sig_recover(sigr: &secp256k1_scalar, sigs: &secp256k1_scalar, pubkey: &mut secp256k1_ge, message: &secp256k1_scalar, recid: i32) -> i32 {
+    let mut brx = [0u8; 32];
+    let mut fx = secp256k1_fe::new();
+    let mut x = secp256k1_ge::new();
+    let mut xj = secp256k1_gej::new();
+    let mut rn = secp256k1_scalar::new();
+    let mut u1 = secp256k1_scalar::new();
+    let mut u2 = secp256k1_scalar::new();
+    let mut qj = secp256k1_gej::new();
+
+    if secp256k1_scalar_is_zero(sigr) != 0 || secp256k1_scalar_is_zero(sigs) != 0 {
+        return 0;
+    }
+
+    secp256k1_scalar_get_b32(&mut brx, &mut sigr.clone());
+    let r = secp256k1_fe_set_b32(&mut fx, &brx);
+    /* brx comes from a scalar, so is less than the order; certainly less than p */
+    assert!(r != 0);
+    if (recid & 2) != 0 {
+        if secp256k1_fe_cmp_var(&fx, &secp256k1_ecdsa_const_p_minus_order) >= 0 {
+            return 0;
+        }
+        secp256k1_fe_add(&mut fx, &secp256k1_ecdsa_const_order_as_fe);
+    }
+    if secp256k1_ge_set_xo_var(&mut x, &fx, recid & 1) == 0 {
+        return 0;
+    }
+    secp256k1_gej_set_ge(&mut xj, &x);
+    secp256k1_scalar_inverse_var(&mut rn, sigr);
+    secp256k1_scalar_mul(&mut u1, &rn, message);
+    let u1c = u1.clone();
+    secp256k1_scalar_negate(&mut u1, &u1c);
+    secp256k1_scalar_mul(&mut u2, &rn, sigs);
+    secp256k1_ecmult(&mut qj, &mut xj, &u2, &[u1]);
+    secp256k1_ge_set_gej_var(pubkey, &mut qj);
+    return (secp256k1_gej_is_infinity(&qj) == 0) as i32;
+}
+
+/** Create a recoverable ECDSA signature.
+ *
+ *  Returns: 1: signature created
+ *           0: the nonce generation function failed, or the secret key was invalid.
+ *  Out:    sig:       the resulting signature
+ *  In:     msghash32: the 32-byte message hash being signed
+ *          seckey:    the 32-byte secret key
+ *          noncefp:   nonce generation function. If None,
+ *                     secp256k1_nonce_function_default is used.
+ *          ndata:     arbitrary data used by the nonce generation function
+ *                     (can be None for secp256k1_nonce_function_default).
+ */
+//int secp256k1_ecdsa_sign_recoverable(const secp256k1_context* ctx, secp256k1_ecdsa_recoverable_signature *signature, const unsigned char *msghash32, const unsigned char *seckey, secp256k1_nonce_function noncefp, const void* noncedata) {
+pub fn secp256k1_ecdsa_sign_recoverable(ctx: &mut secp256k1_context, signature: &mut secp256k1_ecdsa_recoverable_signature, msghash32: &[u8; 32], seckey: &[u8; 32], noncefp: Option<secp256k1_nonce_function>, noncedata: Option<&[u8]>) -> i32 {
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut recid: i32 = 0;
+
+    let ret = secp256k1_ecdsa_sign_inner(ctx, &mut r, &mut s, Some(&mut recid), msghash32, seckey, noncefp, noncedata);
+    secp256k1_ecdsa_recoverable_signature_save(signature, &r, &s, recid);
+    return ret;
+}
+
+/** Recover an ECDSA public key from a signature.
+ *
+ *  Returns: 1: public key successfully recovered (which guarantees a correct signature).
+ *           0: otherwise.
+ *  Out:    pubkey:    the recovered public key.
+ *  In:     sig:       the initialized signature that supports pubkey recovery.
+ *          msghash32: the 32-byte message hash assumed to be signed.
+ */
+//int secp256k1_ecdsa_recover(const secp256k1_context* ctx, secp256k1_pubkey *pubkey, const secp256k1_ecdsa_recoverable_signature *signature, const unsigned char *msghash32) {
+pub fn secp256k1_ecdsa_recover(ctx: &secp256k1_context, pubkey: &mut secp256k1_pubkey, signature: &secp256k1_ecdsa_recoverable_signature, msghash32: &[u8; 32]) -> i32 {
+    let mut q = secp256k1_ge::new();
+    let mut r = secp256k1_scalar::new();
+    let mut s = secp256k1_scalar::new();
+    let mut m = secp256k1_scalar::new();
+    let mut recid: i32 = 0;
+    let mut overflow: i32 = 0;
+
+    secp256k1_ecdsa_recoverable_signature_load(ctx, &mut r, &mut s, &mut recid, signature);
+    /* should have been caught in parse_compact */
+    assert!((0..4).contains(&recid));
+    secp256k1_scalar_set_b32(&mut m, msghash32, &mut overflow);
+    if secp256k1_ecdsa_sig_recover(&r, &s, &mut q, &m, recid) != 0 {
+        secp256k1_pubkey_save(pubkey, &mut q);
+        return 1;
+    } else {
+        pubkey.data = [0u8; 64];
+        return 0;
+    }
+}
@@ -100,7 +100,7 @@ pub fn secp256k1_scalar_set_int(r: &mut secp256k1_scalar, v: u32) {
 // }
 pub fn secp256k1_scalar_get_bits(a: &secp256k1_scalar, offset: u32, count: u32) -> u32 {
     // VERIFY_CHECK((offset + count - 1) >> 6 == offset >> 6);
-    (a.d[(offset >> 6) as usize] as u32 >> (offset & 0x3F)) & (((1 as u32) << count) - 1)
+    ((a.d[(offset >> 6) as usize] >> (offset & 0x3F)) & (((1 as u64) << count) - 1)) as u32
 }
 
 // SECP256K1_INLINE static unsigned int secp256k1_scalar_get_bits_var(const secp256k1_scalar *a, unsigned int offset, unsigned int count) {
@@ -121,7 +121,7 @@ pub fn secp256k1_scalar_get_bits_var(a: &secp256k1_scalar, offset: u32, count: u
         return secp256k1_scalar_get_bits(a, offset, count);
     }
     // VERIFY_CHECK((offset >> 6) + 1 < 4);
-    ((a.d[offset as usize >> 6] as u32 >> (offset & 0x3F)) | ((a.d[(offset as usize >> 6) + 1] as u32) << (64 - (offset & 0x3F)))) & (((1 as u32) << count) - 1)
+    (((a.d[offset as usize >> 6] >> (offset & 0x3F)) | (a.d[(offset as usize >> 6) + 1] << (64 - (offset & 0x3F)))) & (((1 as u64) << count) - 1)) as u32
 }
 
 // SECP256K1_INLINE static int secp256k1_scalar_check_overflow(const secp256k1_scalar *a) {
@@ -259,9 +259,7 @@ pub fn secp256k1_scalar_set_b32 (r: &mut secp256k1_scalar, b32: &[u8], overflow:
     r.d[2] = (b32[15] as u64) | (b32[14] as u64) << 8 | (b32[13] as u64) << 16 | (b32[12] as u64) << 24 | (b32[11] as u64) << 32 | (b32[10] as u64) << 40 | (b32[9] as u64) << 48 | (b32[8] as u64) << 56;
     r.d[3] = (b32[7] as u64) | (b32[6] as u64) << 8 | (b32[5] as u64) << 16 | (b32[4] as u64) << 24 | (b32[3] as u64) << 32 | (b32[2] as u64) << 40 | (b32[1] as u64) << 48 | (b32[0] as u64) << 56;
     over = secp256k1_scalar_reduce(r, secp256k1_scalar_check_overflow(r));
-    if *overflow != 0 {
-        *overflow = over;
-    }
+    *overflow = over;
 }
 
 // static void secp256k1_scalar_get_b32(unsigned char *bin, const secp256k1_scalar* a) {
@@ -354,11 +352,11 @@ pub fn secp256k1_scalar_is_high(a: &secp256k1_scalar) -> i32 {
 //     r->d[3] = t & nonzero;
 //     return 2 * (mask == 0) - 1;
 // }
-fn secp256k1_scalar_cond_negate(r: &mut secp256k1_scalar, flag: i32) -> i32 {
+pub fn secp256k1_scalar_cond_negate(r: &mut secp256k1_scalar, flag: i32) -> i32 {
     /* If we are flag = 0, mask = 00...00 and this is a no-op;
      * if we are flag = 1, mask = 11...11 and this is identical to secp256k1_scalar_negate */
-    let mut mask: u64 = !flag as u64 - 1;
-    let mut nonzero: u64 = (secp256k1_scalar_is_zero(r) != 0) as u64 - 1;
+    let mut mask: u64 = (flag as u64).wrapping_neg();
+    let mut nonzero: u64 = ((secp256k1_scalar_is_zero(r) != 0) as u64).wrapping_sub(1);
     let mut t: u128 = ((r.d[0] as u128) ^ mask as u128) + ((SECP256K1_N_0 as u128 + 1) & mask as u128);
     r.d[0] = t as u64 & nonzero; t >>= 64;
     t += (r.d[1] ^ mask) as u128 + (SECP256K1_N_1 & mask) as u128;
@@ -397,11 +395,10 @@ macro_rules! muladd {
             th = (t >> 64) as u64;
             tl = t as u64;
         }
-        $c0 += tl;
+        $c0 = $c0.wrapping_add(tl);
         th += ($c0 < tl) as u64;
-        $c1 += th;
-        todo!();
-        //$c2 += ($c1 < th);
+        $c1 = $c1.wrapping_add(th);
+        $c2 += ($c1 < th) as u64;
         //VERIFY_CHECK(($c1 >= th) || ($c2 != 0));
     }}
 }
@@ -428,7 +425,7 @@ macro_rules! muladd_fast {
             th = (t >> 64) as u64;
             tl = t as u64;
         }
-        $c0 += tl;
+        $c0 = $c0.wrapping_add(tl);
         th += ($c0 < tl) as u64;
         $c1 += th;
         //VERIFY_CHECK($c1 >= th);
@@ -446,9 +443,9 @@ macro_rules! muladd_fast {
 // }
 macro_rules! sumadd {
     ($a:expr, $c0:expr, $c1:expr, $c2:expr) => {{
-        $c0 += ($a);
+        $c0 = $c0.wrapping_add($a);
         let over = ($c0 < ($a)) as u64;
-        $c1 += over;
+        $c1 = $c1.wrapping_add(over);
         $c2 += ($c1 < over) as u64;
     }}
 }
@@ -463,7 +460,7 @@ macro_rules! sumadd {
 // }
 macro_rules! sumadd_fast {
     ($a:expr, $c0:expr, $c1:expr, $c2:expr) => {{
-        $c0 += ($a) as u64;
+        $c0 = $c0.wrapping_add(($a) as u64);
         $c1 += ($c0 < ($a)) as u64;
         //VERIFY_CHECK(($c1 != 0) | ($c0 >= ($a)));
         //VERIFY_CHECK($c2 == 0);
@@ -759,7 +756,7 @@ pub fn secp256k1_scalar_mul(r: &mut secp256k1_scalar, a: &secp256k1_scalar, b: &
 //     r->d[3] = (r->d[3] >> n);
 //     return ret;
 // }
-fn secp256k1_scalar_shr_int(r: &mut secp256k1_scalar, n: i32) -> i32 {
+pub fn secp256k1_scalar_shr_int(r: &mut secp256k1_scalar, n: i32) -> i32 {
     let mut ret: i32;
     //VERIFY_CHECK(n > 0);
     //VERIFY_CHECK(n < 16);
@@ -825,9 +822,9 @@ pub fn secp256k1_scalar_mul_shift_var(r: &mut secp256k1_scalar, a: &secp256k1_sc
     shiftlimbs = shift >> 6;
     shiftlow = shift & 0x3F;
     shifthigh = 64 - shiftlow;
-    r.d[0] = if shift < 512 { (l[0 + shiftlimbs as usize] >> shiftlow | (shift < 448 && shiftlow != 0) as u64 * (l[1 + shiftlimbs as usize] << shifthigh)) } else { 0 };
-    r.d[1] = if shift < 448 { (l[1 + shiftlimbs as usize] >> shiftlow | (shift < 384 && shiftlow != 0) as u64 * (l[2 + shiftlimbs as usize] << shifthigh)) } else { 0 };
-    r.d[2] = if shift < 384 { (l[2 + shiftlimbs as usize] >> shiftlow | (shift < 320 && shiftlow != 0) as u64 * (l[3 + shiftlimbs as usize] << shifthigh)) } else { 0 };
+    r.d[0] = if shift < 512 { l[0 + shiftlimbs as usize] >> shiftlow | if shift < 448 && shiftlow != 0 { l[1 + shiftlimbs as usize] << shifthigh } else { 0 } } else { 0 };
+    r.d[1] = if shift < 448 { l[1 + shiftlimbs as usize] >> shiftlow | if shift < 384 && shiftlow != 0 { l[2 + shiftlimbs as usize] << shifthigh } else { 0 } } else { 0 };
+    r.d[2] = if shift < 384 { l[2 + shiftlimbs as usize] >> shiftlow | if shift < 320 && shiftlow != 0 { l[3 + shiftlimbs as usize] << shifthigh } else { 0 } } else { 0 };
     r.d[3] = if shift < 320 { (l[3 + shiftlimbs as usize] >> shiftlow) } else { 0 };
     secp256k1_scalar_cadd_bit(r, 0, (l[((shift - 1) >> 6) as usize] >> ((shift - 1) & 0x3f)) as i32 & 1);
 }
@@ -846,7 +843,7 @@ pub fn secp256k1_scalar_cmov(r: &mut secp256k1_scalar, a: &secp256k1_scalar, fla
     let mut mask0: u64;
     let mut mask1: u64;
     //VG_CHECK_VERIFY(r.d, std::mem::size_of_val(&r.d));
-    mask0 = flag as u64 + !((0 as u64) as u64);
+    mask0 = (flag as u64).wrapping_add(!(0 as u64));
     mask1 = !mask0;
     r.d[0] = (r.d[0] & mask0) | (a.d[0] & mask1);
     r.d[1] = (r.d[1] & mask0) | (a.d[1] & mask1);
@@ -916,7 +913,7 @@ fn secp256k1_scalar_from_signed62(r: &mut secp256k1_scalar, a: &secp256k1_modinv
 //     r->v[4] =  a3 >> 56;
 // }
 fn secp256k1_scalar_to_signed62(r: &mut secp256k1_modinv64_signed62, a: &secp256k1_scalar) {
-    let M62: u64 = (0 as u64) >> 2;
+    let M62: u64 = u64::MAX >> 2;
     let a0: u64 = a.d[0];
     let a1: u64 = a.d[1];
     let a2: u64 = a.d[2];
@@ -956,7 +953,7 @@ const secp256k1_const_modinfo_scalar: secp256k1_modinv64_modinfo = secp256k1_mod
 //     VERIFY_CHECK(secp256k1_scalar_is_zero(r) == zero_in);
 // #endif
 // }
-fn secp256k1_scalar_inverse(r: &mut secp256k1_scalar, x: &secp256k1_scalar) {
+pub fn secp256k1_scalar_inverse(r: &mut secp256k1_scalar, x: &secp256k1_scalar) {
     let mut s: secp256k1_modinv64_signed62 = secp256k1_modinv64_signed62::new();
     let zero_in: i32;
     secp256k1_scalar_to_signed62(&mut s, x);
@@ -979,7 +976,7 @@ fn secp256k1_scalar_inverse(r: &mut secp256k1_scalar, x: &secp256k1_scalar) {
 //     VERIFY_CHECK(secp256k1_scalar_is_zero(r) == zero_in);
 // #endif
 // }
-fn secp256k1_scalar_inverse_var(r: &mut secp256k1_scalar, x: &secp256k1_scalar) {
+pub fn secp256k1_scalar_inverse_var(r: &mut secp256k1_scalar, x: &secp256k1_scalar) {
     let mut s = secp256k1_modinv64_signed62::new();
     let zero_in: i32;
     secp256k1_scalar_to_signed62(&mut s, x);
@@ -993,5 +990,5 @@ fn secp256k1_scalar_inverse_var(r: &mut secp256k1_scalar, x: &secp256k1_scalar)
 //     return !(a->d[0] & 1);
 // }
 pub fn secp256k1_scalar_is_even(a: &secp256k1_scalar) -> i32 {
-    !(a.d[0] & 1) as i32
+    ((a.d[0] & 1) == 0) as i32
 }
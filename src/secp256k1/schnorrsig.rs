@@ -0,0 +1,360 @@
+/***********************************************************************
+ * Copyright (c) 2018-2020 Andrew Poelstra, Jonas Nick                 *
+ * Distributed under the MIT software license, see the accompanying    *
+ * file COPYING or https://www.opensource.org/licenses/mit-license.php.*
+ ***********************************************************************/
+
+use super::secp256k1_context;
+use super::extrakeys::{secp256k1_keypair, secp256k1_keypair_load, secp256k1_xonly_pubkey, secp256k1_xonly_pubkey_load};
+use super::field::secp256k1_fe_equal_var;
+use super::field_5x52::{secp256k1_fe, secp256k1_fe_set_b32, secp256k1_fe_get_b32, secp256k1_fe_is_odd, secp256k1_fe_normalize_var};
+use super::group::{secp256k1_ge, secp256k1_gej, secp256k1_ge_set_gej, secp256k1_ge_set_gej_var, secp256k1_gej_set_ge,
+                   secp256k1_ge_is_infinity};
+use super::scalar_4x64::{secp256k1_scalar, secp256k1_scalar_set_b32, secp256k1_scalar_get_b32, secp256k1_scalar_is_zero,
+                         secp256k1_scalar_cmov, secp256k1_scalar_negate, secp256k1_scalar_mul, secp256k1_scalar_add,
+                         secp256k1_scalar_clear};
+use super::scalar_impl::secp256k1_scalar_one;
+use super::ecmult_impl::secp256k1_ecmult;
+use super::hash::{secp256k1_sha256, secp256k1_sha256_initialize, secp256k1_sha256_initialize_tagged,
+                  secp256k1_sha256_write, secp256k1_sha256_finalize};
+use super::util::{secp256k1_memcmp_var, secp256k1_memczero};
+
+/** A pointer to a function to deterministically generate a nonce.
+ *
+ *  Same as secp256k1_nonce function with the exception of accepting an
+ *  additional pubkey argument and not requiring an attempt argument. The pubkey
+ *  argument can protect signature schemes with key-prefixed challenge hash
+ *  inputs against reusing the nonce when signing with the wrong precomputed
+ *  pubkey.
+ *
+ *  Returns: 1 if a nonce was successfully generated. 0 will cause signing to
+ *           return an error.
+ *  Out:  nonce32:   the 32-byte array to be filled by the function
+ *  In:       msg:   the message being verified
+ *          key32:   the 32-byte secret key
+ *     xonly_pk32:   the 32-byte serialized xonly pubkey corresponding to key32
+ *           algo:   the algorithm description, or None
+ *           data:   arbitrary data that is passed through
+ *
+ *  Except for test cases, this function should compute some cryptographic hash of
+ *  the message, the key, the pubkey, the algorithm description, and data.
+ */
+//typedef int (*secp256k1_nonce_function_hardened)(unsigned char *nonce32, const unsigned char *msg, size_t msglen, const unsigned char *key32, const unsigned char *xonly_pk32, const unsigned char *algo, size_t algolen, void *data);
+pub type secp256k1_nonce_function_hardened = fn(nonce32: &mut [u8; 32], msg: &[u8], key32: &[u8; 32], xonly_pk32: &[u8; 32], algo: Option<&[u8]>, data: Option<&[u8; 32]>) -> i32;
+
+/* Initializes SHA256 with fixed midstate. This midstate was computed by applying
+ * SHA256 to SHA256("BIP0340/nonce")||SHA256("BIP0340/nonce"). */
+//static void secp256k1_nonce_function_bip340_sha256_tagged(secp256k1_sha256 *sha) {
+fn secp256k1_nonce_function_bip340_sha256_tagged(sha: &mut secp256k1_sha256) {
+    secp256k1_sha256_initialize(sha);
+    sha.s[0] = 0x46615b35_u32;
+    sha.s[1] = 0xf4bfbff7_u32;
+    sha.s[2] = 0x9f8dc671_u32;
+    sha.s[3] = 0x83627ab3_u32;
+    sha.s[4] = 0x60217180_u32;
+    sha.s[5] = 0x57358661_u32;
+    sha.s[6] = 0x21a29e54_u32;
+    sha.s[7] = 0x68b07b4c_u32;
+
+    sha.bytes = 64;
+}
+
+/* Initializes SHA256 with fixed midstate. This midstate was computed by applying
+ * SHA256 to SHA256("BIP0340/aux")||SHA256("BIP0340/aux"). */
+//static void secp256k1_nonce_function_bip340_sha256_tagged_aux(secp256k1_sha256 *sha) {
+fn secp256k1_nonce_function_bip340_sha256_tagged_aux(sha: &mut secp256k1_sha256) {
+    secp256k1_sha256_initialize(sha);
+    sha.s[0] = 0x24dd3219_u32;
+    sha.s[1] = 0x4eba7e70_u32;
+    sha.s[2] = 0xca0fabb9_u32;
+    sha.s[3] = 0x0fa3166d_u32;
+    sha.s[4] = 0x3afbe4b1_u32;
+    sha.s[5] = 0x4c44df97_u32;
+    sha.s[6] = 0x4aac2739_u32;
+    sha.s[7] = 0x249e850a_u32;
+
+    sha.bytes = 64;
+}
+
+/* algo argument for nonce_function_bip340 to derive the nonce exactly as stated in BIP-340
+ * by using the correct tagged hash function. */
+//static const unsigned char bip340_algo[13] = {'B', 'I', 'P', '0', '3', '4', '0', '/', 'n', 'o', 'n', 'c', 'e'};
+const bip340_algo: [u8; 13] = *b"BIP0340/nonce";
+
+//static int nonce_function_bip340(unsigned char *nonce32, const unsigned char *msg, size_t msglen, const unsigned char *key32, const unsigned char *xonly_pk32, const unsigned char *algo, size_t algolen, void *data) {
+fn nonce_function_bip340(nonce32: &mut [u8; 32], msg: &[u8], key32: &[u8; 32], xonly_pk32: &[u8; 32], algo: Option<&[u8]>, data: Option<&[u8; 32]>) -> i32 {
+    let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+    let mut masked_key = [0u8; 32];
+
+    let algo = match algo {
+        Some(algo) => algo,
+        None => return 0,
+    };
+
+    if let Some(data) = data {
+        secp256k1_nonce_function_bip340_sha256_tagged_aux(&mut sha);
+        secp256k1_sha256_write(&mut sha, data);
+        secp256k1_sha256_finalize(&mut sha, &mut masked_key);
+        for i in 0..32 {
+            masked_key[i] ^= key32[i];
+        }
+    } else {
+        /* Precomputed TaggedHash("BIP0340/aux", 0x0000...00); */
+        const ZERO_MASK: [u8; 32] = [
+              84, 241, 105, 207, 201, 226, 229, 114,
+             116, 128,  68,  31, 144, 186,  37, 196,
+             136, 244,  97, 199,  11,  94, 165, 220,
+             170, 247, 175, 105,  39,  10, 165,  20
+        ];
+        for i in 0..32 {
+            masked_key[i] = key32[i] ^ ZERO_MASK[i];
+        }
+    }
+
+    /* Tag the hash with algo which is important to avoid nonce reuse across
+     * algorithms. If this nonce function is used in BIP-340 signing as defined
+     * in the spec, an optimized tagging implementation is used. */
+    if algo.len() == bip340_algo.len()
+            && secp256k1_memcmp_var(algo, &bip340_algo, algo.len()) == 0 {
+        secp256k1_nonce_function_bip340_sha256_tagged(&mut sha);
+    } else {
+        secp256k1_sha256_initialize_tagged(&mut sha, algo);
+    }
+
+    /* Hash masked-key||pk||msg using the tagged hash as per the spec */
+    secp256k1_sha256_write(&mut sha, &masked_key);
+    secp256k1_sha256_write(&mut sha, xonly_pk32);
+    secp256k1_sha256_write(&mut sha, msg);
+    secp256k1_sha256_finalize(&mut sha, nonce32);
+    return 1;
+}
+
+/** An implementation of the nonce generation function as defined in Bitcoin
+ *  Improvement Proposal 340 "Schnorr Signatures for secp256k1"
+ *  (https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki).
+ *
+ *  If a data argument is passed to this nonce function, it needs to be 32
+ *  bytes of auxiliary random data as defined in BIP-340. If the data is None,
+ *  the nonce derivation procedure follows BIP-340 by setting the auxiliary
+ *  random data to zero.
+ */
+//SECP256K1_API const secp256k1_nonce_function_hardened secp256k1_nonce_function_bip340;
+pub const secp256k1_nonce_function_bip340: secp256k1_nonce_function_hardened = nonce_function_bip340;
+
+/* Initializes SHA256 with fixed midstate. This midstate was computed by applying
+ * SHA256 to SHA256("BIP0340/challenge")||SHA256("BIP0340/challenge"). */
+//static void secp256k1_schnorrsig_sha256_tagged(secp256k1_sha256 *sha) {
+fn secp256k1_schnorrsig_sha256_tagged(sha: &mut secp256k1_sha256) {
+    secp256k1_sha256_initialize(sha);
+    sha.s[0] = 0x9cecba11_u32;
+    sha.s[1] = 0x23925381_u32;
+    sha.s[2] = 0x11679112_u32;
+    sha.s[3] = 0xd1627e0f_u32;
+    sha.s[4] = 0x97c87550_u32;
+    sha.s[5] = 0x003cc765_u32;
+    sha.s[6] = 0x90f61164_u32;
+    sha.s[7] = 0x33e9b66a_u32;
+    sha.bytes = 64;
+}
+
+//static void secp256k1_schnorrsig_challenge(secp256k1_scalar* e, const unsigned char *r32, const unsigned char *msg, size_t msglen, const unsigned char *pubkey32)
+fn secp256k1_schnorrsig_challenge(e: &mut secp256k1_scalar, r32: &[u8], msg: &[u8], pubkey32: &[u8; 32]) {
+    let mut buf = [0u8; 32];
+    let mut sha = secp256k1_sha256 { s: [0u32; 8], buf: [0u8; 64], bytes: 0u64 };
+    let mut overflow: i32 = 0;
+
+    /* tagged hash(r.x, pk.x, msg) */
+    secp256k1_schnorrsig_sha256_tagged(&mut sha);
+    secp256k1_sha256_write(&mut sha, &r32[..32]);
+    secp256k1_sha256_write(&mut sha, pubkey32);
+    secp256k1_sha256_write(&mut sha, msg);
+    secp256k1_sha256_finalize(&mut sha, &mut buf);
+    /* Set scalar e to the challenge hash modulo the curve order as per
+     * BIP340. */
+    secp256k1_scalar_set_b32(e, &buf, &mut overflow);
+}
+
+//static int secp256k1_schnorrsig_sign_internal(const secp256k1_context* ctx, unsigned char *sig64, const unsigned char *msg, size_t msglen, const secp256k1_keypair *keypair, secp256k1_nonce_function_hardened noncefp, void *ndata) {
+fn secp256k1_schnorrsig_sign_internal(ctx: &mut secp256k1_context, sig64: &mut [u8; 64], msg: &[u8], keypair: &secp256k1_keypair, noncefp: Option<secp256k1_nonce_function_hardened>, ndata: Option<&[u8; 32]>) -> i32 {
+    let mut sk = secp256k1_scalar::new();
+    let mut e = secp256k1_scalar::new();
+    let mut k = secp256k1_scalar::new();
+    let mut rj = secp256k1_gej::new();
+    let mut pk = secp256k1_ge::new();
+    let mut r = secp256k1_ge::new();
+    let mut buf = [0u8; 32];
+    let mut pk_buf = [0u8; 32];
+    let mut seckey = [0u8; 32];
+    let mut overflow: i32 = 0;
+    let mut ret: i32 = 1;
+
+    let noncefp = noncefp.unwrap_or(secp256k1_nonce_function_bip340);
+
+    ret &= secp256k1_keypair_load(ctx, Some(&mut sk), &mut pk, keypair);
+    /* Because we are signing for a x-only pubkey, the secret key is negated
+     * before signing if the point corresponding to the secret key does not
+     * have an even Y. */
+    if secp256k1_fe_is_odd(&pk.y) != 0 {
+        let sk1 = sk.clone();
+        secp256k1_scalar_negate(&mut sk, &sk1);
+    }
+
+    secp256k1_scalar_get_b32(&mut seckey, &mut sk.clone());
+    secp256k1_fe_get_b32(&mut pk_buf, &pk.x);
+    ret &= (noncefp(&mut buf, msg, &seckey, &pk_buf, Some(&bip340_algo), ndata) != 0) as i32;
+    secp256k1_scalar_set_b32(&mut k, &buf, &mut overflow);
+    ret &= (secp256k1_scalar_is_zero(&k) == 0) as i32;
+    secp256k1_scalar_cmov(&mut k, &secp256k1_scalar_one, (ret == 0) as i32);
+
+    ctx.ecmult_gen_ctx.secp256k1_ecmult_gen(&mut rj, &k);
+    secp256k1_ge_set_gej(&mut r, &mut rj);
+
+    secp256k1_fe_normalize_var(&mut r.y);
+    if secp256k1_fe_is_odd(&r.y) != 0 {
+        let k1 = k.clone();
+        secp256k1_scalar_negate(&mut k, &k1);
+    }
+    secp256k1_fe_normalize_var(&mut r.x);
+    secp256k1_fe_get_b32(&mut sig64[0..32], &r.x);
+
+    secp256k1_schnorrsig_challenge(&mut e, &sig64[0..32], msg, &pk_buf);
+    let e1 = e.clone();
+    secp256k1_scalar_mul(&mut e, &e1, &sk);
+    let e1 = e.clone();
+    secp256k1_scalar_add(&mut e, &e1, &k);
+    secp256k1_scalar_get_b32(&mut sig64[32..], &mut e);
+
+    secp256k1_memczero(sig64, ret == 0);
+    secp256k1_scalar_clear(&mut k);
+    secp256k1_scalar_clear(&mut sk);
+    seckey.fill(0);
+
+    return ret;
+}
+
+/** Create a Schnorr signature.
+ *
+ *  Does _not_ strictly follow BIP-340 because it does not verify the resulting
+ *  signature. Instead, you can manually use secp256k1_schnorrsig_verify and
+ *  abort if it fails.
+ *
+ *  This function only signs 32-byte messages. If you have messages of a
+ *  different size (or the same size but without a context-specific tag
+ *  prefix), it is recommended to create a 32-byte message hash with
+ *  secp256k1_tagged_sha256 and then sign the hash.
+ *
+ *  Returns 1 on success, 0 on failure.
+ *  Out:     sig64: the 64-byte array where the signature will be placed.
+ *  In:      msg32: the 32-byte message being signed.
+ *         keypair: an initialized keypair.
+ *      aux_rand32: 32 bytes of fresh randomness. While recommended to provide
+ *                  this, it is only supplemental to security and can be None. A
+ *                  None argument is treated the same as an all-zero one. See
+ *                  BIP-340 "Default Signing" for a full explanation of this
+ *                  argument and for guidance if randomness is expensive.
+ */
+//int secp256k1_schnorrsig_sign32(const secp256k1_context* ctx, unsigned char *sig64, const unsigned char *msg32, const secp256k1_keypair *keypair, const unsigned char *aux_rand32) {
+pub fn secp256k1_schnorrsig_sign32(ctx: &mut secp256k1_context, sig64: &mut [u8; 64], msg32: &[u8; 32], keypair: &secp256k1_keypair, aux_rand32: Option<&[u8; 32]>) -> i32 {
+    return secp256k1_schnorrsig_sign_internal(ctx, sig64, msg32, keypair, Some(secp256k1_nonce_function_bip340), aux_rand32);
+}
+
+/** Data structure that contains additional arguments for schnorrsig_sign_custom.
+ *
+ *  The magic number of the C struct is not needed here, the type already
+ *  guarantees the fields are initialized.
+ *
+ *  Members:
+ *      noncefp: pointer to a nonce generation function. If None,
+ *               secp256k1_nonce_function_bip340 is used
+ *        ndata: 32 bytes of data used by the nonce generation function
+ *               (can be None). If secp256k1_nonce_function_bip340 is used,
+ *               this is the auxiliary randomness as per BIP-340. The type
+ *               enforces the length the C API only documents.
+ */
+//typedef struct {
+//    unsigned char magic[4];
+//    secp256k1_nonce_function_hardened noncefp;
+//    void *ndata;
+//} secp256k1_schnorrsig_extraparams;
+pub struct secp256k1_schnorrsig_extraparams<'a> {
+    pub noncefp: Option<secp256k1_nonce_function_hardened>,
+    pub ndata: Option<&'a [u8; 32]>,
+}
+
+/** Create a Schnorr signature with a more flexible API.
+ *
+ *  Same arguments as secp256k1_schnorrsig_sign except that it allows signing
+ *  variable length messages and accepts a pointer to an extraparams object that
+ *  allows customizing signing by passing additional arguments.
+ *
+ *  Equivalent to secp256k1_schnorrsig_sign32(..., aux_rand32) if msg is 32
+ *  bytes and extraparams.ndata = aux_rand32.
+ *
+ *  In:     msg: the message being signed. Can only be empty if no nonce function
+ *               is set in extraparams.
+ *  extraparams: pointer to an extraparams object (can be None).
+ */
+//int secp256k1_schnorrsig_sign_custom(const secp256k1_context* ctx, unsigned char *sig64, const unsigned char *msg, size_t msglen, const secp256k1_keypair *keypair, secp256k1_schnorrsig_extraparams *extraparams) {
+pub fn secp256k1_schnorrsig_sign_custom(ctx: &mut secp256k1_context, sig64: &mut [u8; 64], msg: &[u8], keypair: &secp256k1_keypair, extraparams: Option<&secp256k1_schnorrsig_extraparams>) -> i32 {
+    let mut noncefp: Option<secp256k1_nonce_function_hardened> = None;
+    let mut ndata: Option<&[u8; 32]> = None;
+
+    if let Some(extraparams) = extraparams {
+        noncefp = extraparams.noncefp;
+        ndata = extraparams.ndata;
+    }
+    return secp256k1_schnorrsig_sign_internal(ctx, sig64, msg, keypair, noncefp, ndata);
+}
+
+/** Verify a Schnorr signature.
+ *
+ *  Returns: 1: correct signature
+ *           0: incorrect signature
+ *  In:     sig64: the 64-byte signature being verified.
+ *            msg: the message being verified.
+ *         pubkey: an initialized x-only public key to verify with.
+ */
+//int secp256k1_schnorrsig_verify(const secp256k1_context* ctx, const unsigned char *sig64, const unsigned char *msg, size_t msglen, const secp256k1_xonly_pubkey *pubkey) {
+pub fn secp256k1_schnorrsig_verify(ctx: &secp256k1_context, sig64: &[u8; 64], msg: &[u8], pubkey: &secp256k1_xonly_pubkey) -> i32 {
+    let mut s = secp256k1_scalar::new();
+    let mut e = secp256k1_scalar::new();
+    let mut rj = secp256k1_gej::new();
+    let mut pk = secp256k1_ge::new();
+    let mut pkj = secp256k1_gej::new();
+    let mut rx = secp256k1_fe::new();
+    let mut r = secp256k1_ge::new();
+    let mut buf = [0u8; 32];
+    let mut overflow: i32 = 0;
+
+    if secp256k1_fe_set_b32(&mut rx, &sig64[0..32]) == 0 {
+        return 0;
+    }
+
+    secp256k1_scalar_set_b32(&mut s, &sig64[32..], &mut overflow);
+    if overflow != 0 {
+        return 0;
+    }
+
+    if secp256k1_xonly_pubkey_load(ctx, &mut pk, pubkey) == 0 {
+        return 0;
+    }
+
+    /* Compute e. */
+    secp256k1_fe_get_b32(&mut buf, &pk.x);
+    secp256k1_schnorrsig_challenge(&mut e, &sig64[0..32], msg, &buf);
+
+    /* Compute rj =  s*G + (-e)*pkj */
+    let e1 = e.clone();
+    secp256k1_scalar_negate(&mut e, &e1);
+    secp256k1_gej_set_ge(&mut pkj, &pk);
+    secp256k1_ecmult(&mut rj, &mut pkj, &e, &[s]);
+
+    secp256k1_ge_set_gej_var(&mut r, &mut rj);
+    if secp256k1_ge_is_infinity(&r) {
+        return 0;
+    }
+
+    secp256k1_fe_normalize_var(&mut r.y);
+    return (secp256k1_fe_is_odd(&r.y) == 0 &&
+            secp256k1_fe_equal_var(&rx, &r.x) != 0) as i32;
+}
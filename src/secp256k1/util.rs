@@ -74,7 +74,7 @@ pub fn secp256k1_memczero(s: &mut [u8], flag: bool) {
         mask = 0x00;
     }
     for i in 0..p.len() {
-        p[i] &= mask;
+        p[i] &= !mask;
     }
 }
 
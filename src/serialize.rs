@@ -5,7 +5,20 @@
 
 use std::ops::{Shl, ShlAssign};
 use std::ops::Add;
+use std::io;
+use std::sync::Arc;
+use primitive_types::{H160, H256};
 
+/**
+ * The maximum size of a serialized object in bytes or number of elements
+ * (for eg vectors) when the size is encoded as CompactSize.
+ */
+pub const MAX_SIZE: u64 = 0x02000000;
+
+/** Maximum amount of memory (in bytes) to allocate at once when deserializing vectors. */
+pub const MAX_VECTOR_ALLOCATE: usize = 5000000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum SER {
     // primary actions
     NETWORK         = (1 << 0),
@@ -17,6 +30,356 @@ pub trait AsBytes {
     fn as_bytes(&self) -> &[u8];
 }
 
+/**
+ * A stream that objects can be serialized into. The type and version are
+ * consulted by objects whose encoding depends on them (e.g. the
+ * SERIALIZE_TRANSACTION_NO_WITNESS version flag, or SER::GETHASH).
+ */
+pub trait WriteStream {
+    fn write(&mut self, src: &[u8]);
+    fn GetType(&self) -> i32;
+    fn GetVersion(&self) -> i32;
+}
+
+/** A stream that objects can be deserialized from. */
+pub trait ReadStream {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<()>;
+    fn GetType(&self) -> i32;
+    fn GetVersion(&self) -> i32;
+}
+
+//template<typename Stream, typename T>
+//inline void Serialize(Stream& os, const T& a)
+pub trait Serialize {
+    fn Serialize<S: WriteStream>(&self, s: &mut S);
+
+    /// Serialize a run of elements back to back. Overridden for u8 so byte
+    /// vectors and arrays are written with a single call.
+    fn SerializeMany<S: WriteStream>(items: &[Self], s: &mut S) where Self: Sized {
+        for item in items {
+            item.Serialize(s);
+        }
+    }
+}
+
+//template<typename Stream, typename T>
+//inline void Unserialize(Stream& is, T&& a)
+pub trait Unserialize {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()>;
+
+    /// Deserialize a run of elements back to back, see Serialize::SerializeMany.
+    fn UnserializeMany<S: ReadStream>(items: &mut [Self], s: &mut S) -> io::Result<()> where Self: Sized {
+        for item in items.iter_mut() {
+            item.Unserialize(s)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_serialize_int {
+    ($($t:ty),*) => {
+        $(
+        impl Serialize for $t {
+            fn Serialize<S: WriteStream>(&self, s: &mut S) {
+                s.write(&self.to_le_bytes());
+            }
+        }
+
+        impl Unserialize for $t {
+            fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+                let mut buf = [0u8; std::mem::size_of::<$t>()];
+                s.read(&mut buf)?;
+                *self = <$t>::from_le_bytes(buf);
+                Ok(())
+            }
+        }
+        )*
+    };
+}
+
+impl_serialize_int!(i8, i16, u16, i32, u32, i64, u64);
+
+impl Serialize for u8 {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        s.write(&[*self]);
+    }
+
+    fn SerializeMany<S: WriteStream>(items: &[Self], s: &mut S) {
+        s.write(items);
+    }
+}
+
+impl Unserialize for u8 {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        let mut buf = [0u8; 1];
+        s.read(&mut buf)?;
+        *self = buf[0];
+        Ok(())
+    }
+
+    fn UnserializeMany<S: ReadStream>(items: &mut [Self], s: &mut S) -> io::Result<()> {
+        s.read(items)
+    }
+}
+
+impl Serialize for bool {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        s.write(&[*self as u8]);
+    }
+}
+
+impl Unserialize for bool {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        let mut a: u8 = 0;
+        a.Unserialize(s)?;
+        *self = a != 0;
+        Ok(())
+    }
+}
+
+/**
+ * Compact Size
+ * size <  253        -- 1 byte
+ * size <= USHRT_MAX  -- 3 bytes  (253 + 2 bytes)
+ * size <= UINT_MAX   -- 5 bytes  (254 + 4 bytes)
+ * size >  UINT_MAX   -- 9 bytes  (255 + 8 bytes)
+ */
+//inline unsigned int GetSizeOfCompactSize(uint64_t nSize)
+pub fn GetSizeOfCompactSize(nSize: u64) -> usize
+{
+    if nSize < 253 {
+        1
+    } else if nSize <= u16::MAX as u64 {
+        3
+    } else if nSize <= u32::MAX as u64 {
+        5
+    } else {
+        9
+    }
+}
+
+//template<typename Stream>
+//void WriteCompactSize(Stream& os, uint64_t nSize)
+pub fn WriteCompactSize<S: WriteStream>(os: &mut S, nSize: u64)
+{
+    if nSize < 253 {
+        (nSize as u8).Serialize(os);
+    } else if nSize <= u16::MAX as u64 {
+        253u8.Serialize(os);
+        (nSize as u16).Serialize(os);
+    } else if nSize <= u32::MAX as u64 {
+        254u8.Serialize(os);
+        (nSize as u32).Serialize(os);
+    } else {
+        255u8.Serialize(os);
+        nSize.Serialize(os);
+    }
+}
+
+/**
+ * Decode a CompactSize-encoded variable-length integer.
+ *
+ * As these are primarily used to encode the size of vector-like serializations, by default a range
+ * check is performed. When used as a generic number encoding, range_check should be set to false.
+ */
+//template<typename Stream>
+//uint64_t ReadCompactSize(Stream& is, bool range_check = true)
+pub fn ReadCompactSize<S: ReadStream>(is: &mut S, range_check: bool) -> io::Result<u64>
+{
+    let mut chSize: u8 = 0;
+    chSize.Unserialize(is)?;
+    let nSizeRet: u64;
+    if chSize < 253 {
+        nSizeRet = chSize as u64;
+    } else if chSize == 253 {
+        let mut n: u16 = 0;
+        n.Unserialize(is)?;
+        nSizeRet = n as u64;
+        if nSizeRet < 253 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "non-canonical ReadCompactSize()"));
+        }
+    } else if chSize == 254 {
+        let mut n: u32 = 0;
+        n.Unserialize(is)?;
+        nSizeRet = n as u64;
+        if nSizeRet < 0x10000 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "non-canonical ReadCompactSize()"));
+        }
+    } else {
+        let mut n: u64 = 0;
+        n.Unserialize(is)?;
+        nSizeRet = n;
+        if nSizeRet < 0x100000000 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "non-canonical ReadCompactSize()"));
+        }
+    }
+    if range_check && nSizeRet > MAX_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "ReadCompactSize(): size too large"));
+    }
+    Ok(nSizeRet)
+}
+
+/**
+ * vector
+ */
+impl<T: Serialize> Serialize for Vec<T> {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        WriteCompactSize(s, self.len() as u64);
+        T::SerializeMany(&self[..], s);
+    }
+}
+
+impl<T: Unserialize + Default> Unserialize for Vec<T> {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        // Limit size per read so bogus size value won't cause out of memory
+        self.clear();
+        let nSize = ReadCompactSize(s, true)? as usize;
+        let nMaxBatch = std::cmp::max(1, MAX_VECTOR_ALLOCATE / std::cmp::max(1, std::mem::size_of::<T>()));
+        let mut i: usize = 0;
+        while i < nSize {
+            let blk = std::cmp::min(nSize - i, nMaxBatch);
+            self.resize_with(i + blk, T::default);
+            T::UnserializeMany(&mut self[i..i + blk], s)?;
+            i += blk;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * array
+ */
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        T::SerializeMany(&self[..], s);
+    }
+}
+
+impl<T: Unserialize, const N: usize> Unserialize for [T; N] {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        T::UnserializeMany(&mut self[..], s)
+    }
+}
+
+/**
+ * string
+ */
+impl Serialize for String {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        WriteCompactSize(s, self.len() as u64);
+        s.write(self.as_bytes());
+    }
+}
+
+impl Unserialize for String {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        let mut v: Vec<u8> = vec![];
+        v.Unserialize(s)?;
+        *self = String::from_utf8(v).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(())
+    }
+}
+
+/**
+ * uint256 / uint160 are serialized as their raw 32 / 20 bytes.
+ */
+impl Serialize for H256 {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        s.write(self.as_bytes());
+    }
+}
+
+impl Unserialize for H256 {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        s.read(self.as_bytes_mut())
+    }
+}
+
+impl Serialize for H160 {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        s.write(self.as_bytes());
+    }
+}
+
+impl Unserialize for H160 {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        s.read(self.as_bytes_mut())
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for &T {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        (**self).Serialize(s);
+    }
+}
+
+/**
+ * shared_ptr
+ */
+impl<T: Serialize + ?Sized> Serialize for Arc<T> {
+    fn Serialize<S: WriteStream>(&self, s: &mut S) {
+        (**self).Serialize(s);
+    }
+}
+
+impl<T: Unserialize + Default> Unserialize for Arc<T> {
+    fn Unserialize<S: ReadStream>(&mut self, s: &mut S) -> io::Result<()> {
+        let mut obj = T::default();
+        obj.Unserialize(s)?;
+        *self = Arc::new(obj);
+        Ok(())
+    }
+}
+
+/** GetSerializeSize implementations
+ *
+ * Computing the serialized size of objects is done through a special stream
+ * object of type CSizeComputer, which only records the number of bytes written
+ * to it.
+ */
+pub struct CSizeComputer {
+    nSize: usize,
+    nVersion: i32,
+}
+
+impl CSizeComputer {
+    pub fn new(nVersionIn: i32) -> Self {
+        Self { nSize: 0, nVersion: nVersionIn }
+    }
+
+    /** Pretend _nSize bytes are written, without specifying them. */
+    pub fn seek(&mut self, _nSize: usize) {
+        self.nSize += _nSize;
+    }
+
+    pub fn size(&self) -> usize {
+        self.nSize
+    }
+}
+
+impl WriteStream for CSizeComputer {
+    fn write(&mut self, src: &[u8]) {
+        self.nSize += src.len();
+    }
+
+    fn GetType(&self) -> i32 {
+        0
+    }
+
+    fn GetVersion(&self) -> i32 {
+        self.nVersion
+    }
+}
+
+//template <typename T>
+//size_t GetSerializeSize(const T& t, int nVersion = 0)
+pub fn GetSerializeSize<T: Serialize + ?Sized>(t: &T, nVersion: i32) -> usize
+{
+    let mut s = CSizeComputer::new(nVersion);
+    t.Serialize(&mut s);
+    s.size()
+}
+
 #[derive(Debug)]
 struct Wrapper<T> {
     value: T,
@@ -99,6 +462,10 @@ impl<T> ShlAssign<T> for Ser  where T: Serializer {
 mod tests {
     use super::Serializer;
     use super::Ser;
+    use super::{Serialize, Unserialize, WriteCompactSize, ReadCompactSize, GetSizeOfCompactSize, GetSerializeSize, MAX_SIZE, SER};
+    use crate::streams::CDataStream;
+    use crate::script::CScript;
+    use primitive_types::H256;
 
     
     struct TestSer {
@@ -144,4 +511,136 @@ mod tests {
         assert!(d.to_vec() == vec![42, 0, 0, 0, 69, 0, 0, 0, 7, 0, 0, 0]);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_sizes() {
+        assert_eq!(GetSerializeSize(&0u8, 0), 1);
+        assert_eq!(GetSerializeSize(&0i16, 0), 2);
+        assert_eq!(GetSerializeSize(&0u32, 0), 4);
+        assert_eq!(GetSerializeSize(&0i64, 0), 8);
+        assert_eq!(GetSerializeSize(&false, 0), 1);
+        assert_eq!(GetSerializeSize(&H256::zero(), 0), 32);
+        assert_eq!(GetSerializeSize(&vec![0u8; 300], 0), 303);
+        assert_eq!(GetSerializeSize(&[0u32; 4], 0), 16);
+    }
+
+    #[test]
+    fn test_integers() {
+        let mut ss = CDataStream::new(SER::DISK, 0);
+        ss <<= &0x12345678u32;
+        ss <<= &(-2i16);
+        ss <<= &0x0102030405060708u64;
+        ss <<= &true;
+        assert_eq!(ss.data(), &[0x78, 0x56, 0x34, 0x12, 0xfe, 0xff, 8, 7, 6, 5, 4, 3, 2, 1, 1]);
+
+        let (mut a, mut b, mut c, mut d) = (0u32, 0i16, 0u64, false);
+        ss.unserialize(&mut a).unwrap().unserialize(&mut b).unwrap().unserialize(&mut c).unwrap().unserialize(&mut d).unwrap();
+        assert_eq!((a, b, c, d), (0x12345678, -2, 0x0102030405060708, true));
+        assert!(ss.empty());
+        assert!(ss.unserialize(&mut a).is_err());
+    }
+
+    #[test]
+    fn test_compactsize() {
+        let mut ss = CDataStream::new(SER::DISK, 0);
+        let mut i: u64 = 1;
+        while i <= MAX_SIZE {
+            WriteCompactSize(&mut ss, i - 1);
+            WriteCompactSize(&mut ss, i);
+            i *= 2;
+        }
+        i = 1;
+        while i <= MAX_SIZE {
+            let j = ReadCompactSize(&mut ss, true).unwrap();
+            assert_eq!(i - 1, j, "decoded:{} expected:{}", j, i - 1);
+            let j = ReadCompactSize(&mut ss, true).unwrap();
+            assert_eq!(i, j, "decoded:{} expected:{}", j, i);
+            i *= 2;
+        }
+        assert_eq!(GetSizeOfCompactSize(252), 1);
+        assert_eq!(GetSizeOfCompactSize(253), 3);
+        assert_eq!(GetSizeOfCompactSize(0x10000), 5);
+        assert_eq!(GetSizeOfCompactSize(0x100000000), 9);
+    }
+
+    fn isCanonicalException(ss: &mut CDataStream) -> bool {
+        match ReadCompactSize(ss, true) {
+            Err(e) => e.to_string() == "non-canonical ReadCompactSize()",
+            Ok(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_noncanonical() {
+        // Write some non-canonical CompactSize encodings, and
+        // make sure an exception is thrown when read back.
+        let mut ss = CDataStream::new(SER::DISK, 0);
+
+        // zero encoded with three bytes:
+        ss.write(&[0xfd, 0x00, 0x00]);
+        assert!(isCanonicalException(&mut ss));
+        ss.clear();
+
+        // 0xfc encoded with three bytes:
+        ss.write(&[0xfd, 0xfc, 0x00]);
+        assert!(isCanonicalException(&mut ss));
+        ss.clear();
+
+        // 0xfd encoded with three bytes is OK:
+        ss.write(&[0xfd, 0xfd, 0x00]);
+        assert_eq!(ReadCompactSize(&mut ss, true).unwrap(), 0xfd);
+
+        // zero encoded with five bytes:
+        ss.write(&[0xfe, 0x00, 0x00, 0x00, 0x00]);
+        assert!(isCanonicalException(&mut ss));
+        ss.clear();
+
+        // 0xffff encoded with five bytes:
+        ss.write(&[0xfe, 0xff, 0xff, 0x00, 0x00]);
+        assert!(isCanonicalException(&mut ss));
+        ss.clear();
+
+        // zero encoded with nine bytes:
+        ss.write(&[0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert!(isCanonicalException(&mut ss));
+        ss.clear();
+
+        // 0x01ffffff encoded with nine bytes:
+        ss.write(&[0xff, 0xff, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00, 0x00]);
+        assert!(isCanonicalException(&mut ss));
+        ss.clear();
+
+        // sizes above MAX_SIZE are rejected when range checked
+        ss.write(&[0xfe, 0x01, 0x00, 0x00, 0x02]);
+        assert!(ReadCompactSize(&mut ss, true).is_err());
+    }
+
+    #[test]
+    fn test_vector_roundtrip() {
+        let v: Vec<u32> = (0..1000).collect();
+        let bytes: Vec<u8> = (0..=255).collect();
+        let script = CScript::new(vec![0x51, 0x87]);
+        let mut hash = H256::zero();
+        hash.as_bytes_mut()[0] = 1;
+        hash.as_bytes_mut()[31] = 0xff;
+
+        let mut ss = CDataStream::new(SER::NETWORK, 0) << &v << &bytes << &script << &hash << &[7u8; 3];
+        assert_eq!(ss.size(), 3 + 4000 + 3 + 256 + 1 + 2 + 32 + 3);
+
+        let mut v2: Vec<u32> = vec![];
+        let mut bytes2: Vec<u8> = vec![];
+        let mut script2 = CScript::default();
+        let mut hash2 = H256::zero();
+        let mut arr = [0u8; 3];
+        ss.unserialize(&mut v2).unwrap()
+          .unserialize(&mut bytes2).unwrap()
+          .unserialize(&mut script2).unwrap()
+          .unserialize(&mut hash2).unwrap()
+          .unserialize(&mut arr).unwrap();
+        assert_eq!(v, v2);
+        assert_eq!(bytes, bytes2);
+        assert_eq!(script, script2);
+        assert_eq!(hash, hash2);
+        assert_eq!(arr, [7u8; 3]);
+        assert!(ss.empty());
+    }
+}
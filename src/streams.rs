@@ -3,29 +3,306 @@
 // Distributed under the MIT software license, see the accompanying
 // file COPYING or http://www.opensource.org/licenses/mit-license.php.
 
-use super::serialize::SER;
-use std::ops::Shl;
+use super::serialize::{SER, Serialize, Unserialize, WriteStream, ReadStream};
+use std::cmp::min;
+use std::io;
+use std::ops::{Shl, ShlAssign};
 
+/** Double ended buffer combining vector and stream-like interfaces.
+ *
+ * >> and << read and write unformatted data using the above serialization templates.
+ * Fills with data in linear time; some stringstream implementations take N^2 time.
+ */
+#[derive(Clone, Debug)]
 pub struct CDataStream {
-    nType: SER,
+    vch: Vec<u8>,
+    m_read_pos: usize,
+    nType: i32,
     nVersion: i32,
 }
 
 impl CDataStream {
     pub fn new(nTypeIn: SER, nVersionIn: i32) -> CDataStream
     {
-        CDataStream {nType: nTypeIn, nVersion: nVersionIn}
+        CDataStream {vch: Vec::new(), m_read_pos: 0, nType: nTypeIn as i32, nVersion: nVersionIn}
     }
+
+    //CDataStream(Span<const uint8_t> sp, int nTypeIn, int nVersionIn)
+    pub fn from_bytes(sp: &[u8], nTypeIn: SER, nVersionIn: i32) -> CDataStream
+    {
+        CDataStream {vch: sp.to_vec(), m_read_pos: 0, nType: nTypeIn as i32, nVersion: nVersionIn}
+    }
+
+    //
+    // Vector subset
+    //
+    /// The unread part of the buffer.
+    pub fn data(&self) -> &[u8] { &self.vch[self.m_read_pos..] }
+    pub fn size(&self) -> usize { self.vch.len() - self.m_read_pos }
+    pub fn empty(&self) -> bool { self.vch.len() == self.m_read_pos }
+    pub fn clear(&mut self) { self.vch.clear(); self.m_read_pos = 0; }
+    pub fn to_vec(&self) -> Vec<u8> { self.data().to_vec() }
+
+    //
+    // Stream subset
+    //
+    pub fn eof(&self) -> bool { self.size() == 0 }
+    pub fn in_avail(&self) -> usize { self.size() }
+
+    pub fn SetType(&mut self, n: i32) { self.nType = n; }
+    pub fn GetType(&self) -> i32 { self.nType }
+    pub fn SetVersion(&mut self, n: i32) { self.nVersion = n; }
+    pub fn GetVersion(&self) -> i32 { self.nVersion }
+
+    //void read(Span<value_type> dst)
+    pub fn read(&mut self, dst: &mut [u8]) -> io::Result<()>
+    {
+        if dst.is_empty() {
+            return Ok(());
+        }
+
+        // Read from the beginning of the buffer
+        let next_read_pos = self.m_read_pos + dst.len();
+        if next_read_pos > self.vch.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "CDataStream::read(): end of data"));
+        }
+        dst.copy_from_slice(&self.vch[self.m_read_pos..next_read_pos]);
+        if next_read_pos == self.vch.len() {
+            self.m_read_pos = 0;
+            self.vch.clear();
+            return Ok(());
+        }
+        self.m_read_pos = next_read_pos;
+        Ok(())
+    }
+
+    //void ignore(size_t num_ignore)
+    pub fn ignore(&mut self, num_ignore: usize) -> io::Result<()>
+    {
+        // Ignore from the beginning of the buffer
+        let next_read_pos = self.m_read_pos + num_ignore;
+        if next_read_pos >= self.vch.len() {
+            if next_read_pos > self.vch.len() {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "CDataStream::ignore(): end of data"));
+            }
+            self.m_read_pos = 0;
+            self.vch.clear();
+            return Ok(());
+        }
+        self.m_read_pos = next_read_pos;
+        Ok(())
+    }
+
+    //void write(Span<const value_type> src)
+    pub fn write(&mut self, src: &[u8])
+    {
+        // Write to the end of the buffer
+        self.vch.extend_from_slice(src);
+    }
+
+    //template<typename T>
+    //CDataStream& operator<<(const T& obj)
+    pub fn serialize<T: Serialize + ?Sized>(&mut self, obj: &T) -> &mut Self
+    {
+        // Serialize to this stream
+        obj.Serialize(self);
+        self
+    }
+
+    //template<typename T>
+    //CDataStream& operator>>(T&& obj)
+    pub fn unserialize<T: Unserialize + ?Sized>(&mut self, obj: &mut T) -> io::Result<&mut Self>
+    {
+        // Unserialize from this stream
+        obj.Unserialize(self)?;
+        Ok(self)
+    }
+}
+
+impl WriteStream for CDataStream {
+    fn write(&mut self, src: &[u8]) { CDataStream::write(self, src); }
+    fn GetType(&self) -> i32 { self.nType }
+    fn GetVersion(&self) -> i32 { self.nVersion }
+}
+
+impl ReadStream for CDataStream {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<()> { CDataStream::read(self, dst) }
+    fn GetType(&self) -> i32 { self.nType }
+    fn GetVersion(&self) -> i32 { self.nVersion }
 }
 
-impl<T> Shl<T> for CDataStream {
+impl io::Read for CDataStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = min(buf.len(), self.size());
+        CDataStream::read(self, &mut buf[..n])?;
+        Ok(n)
+    }
+}
+
+impl<T: Serialize + ?Sized> Shl<&T> for CDataStream {
     type Output = Self;
 
-    fn shl(self, _rhs:T) -> Self::Output
+    fn shl(mut self, rhs: &T) -> Self::Output
     {
-        // TODO: figure out what to do here
-        //Serialize(self.S, rhs);
+        rhs.Serialize(&mut self);
         self
-    } 
+    }
+}
+
+impl<T: Serialize + ?Sized> ShlAssign<&T> for CDataStream {
+    fn shl_assign(&mut self, rhs: &T)
+    {
+        rhs.Serialize(self);
+    }
+}
+
+pub struct BitStreamReader<'a, S: ReadStream> {
+    m_istream: &'a mut S,
+
+    /// Buffered byte read in from the input stream. A new byte is read into the
+    /// buffer when m_offset reaches 8.
+    m_buffer: u8,
 
-}
\ No newline at end of file
+    /// Number of high order bits in m_buffer already returned by previous
+    /// Read() calls. The next bit to be returned is at this offset from the
+    /// most significant bit position.
+    m_offset: i32,
+}
+
+impl<'a, S: ReadStream> BitStreamReader<'a, S> {
+    pub fn new(istream: &'a mut S) -> Self
+    {
+        Self { m_istream: istream, m_buffer: 0, m_offset: 8 }
+    }
+
+    /** Read the specified number of bits from the stream. The data is returned
+     * in the nbits least significant bits of a 64-bit uint.
+     */
+    //uint64_t Read(int nbits) {
+    pub fn Read(&mut self, mut nbits: i32) -> io::Result<u64>
+    {
+        if !(0..=64).contains(&nbits) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "nbits must be between 0 and 64"));
+        }
+
+        let mut data: u64 = 0;
+        while nbits > 0 {
+            if self.m_offset == 8 {
+                self.m_buffer.Unserialize(self.m_istream)?;
+                self.m_offset = 0;
+            }
+
+            let bits = min(8 - self.m_offset, nbits);
+            data <<= bits;
+            data |= ((((self.m_buffer as u32) << self.m_offset) & 0xff) >> (8 - bits)) as u64;
+            self.m_offset += bits;
+            nbits -= bits;
+        }
+        Ok(data)
+    }
+}
+
+pub struct BitStreamWriter<'a, S: WriteStream> {
+    m_ostream: &'a mut S,
+
+    /// Buffered byte waiting to be written to the output stream. The byte is
+    /// written buffer when m_offset reaches 8 or Flush() is called.
+    m_buffer: u8,
+
+    /// Number of high order bits in m_buffer already written by previous
+    /// Write() calls and not yet flushed to the stream. The next bit to be
+    /// written to is at this offset from the most significant bit position.
+    m_offset: i32,
+}
+
+impl<'a, S: WriteStream> BitStreamWriter<'a, S> {
+    pub fn new(ostream: &'a mut S) -> Self
+    {
+        Self { m_ostream: ostream, m_buffer: 0, m_offset: 0 }
+    }
+
+    /** Write the nbits least significant bits of a 64-bit int to the output
+     * stream. Data is buffered until it completes an octet.
+     */
+    //void Write(uint64_t data, int nbits) {
+    pub fn Write(&mut self, data: u64, mut nbits: i32)
+    {
+        assert!((0..=64).contains(&nbits), "nbits must be between 0 and 64");
+
+        while nbits > 0 {
+            let bits = min(8 - self.m_offset, nbits);
+            self.m_buffer |= ((data << (64 - nbits)) >> (64 - 8 + self.m_offset)) as u8;
+            self.m_offset += bits;
+            nbits -= bits;
+
+            if self.m_offset == 8 {
+                self.Flush();
+            }
+        }
+    }
+
+    /** Flush any unwritten bits to the output stream, padding with 0's to the
+     * next byte boundary.
+     */
+    //void Flush() {
+    pub fn Flush(&mut self)
+    {
+        if self.m_offset == 0 {
+            return;
+        }
+
+        self.m_buffer.Serialize(self.m_ostream);
+        self.m_buffer = 0;
+        self.m_offset = 0;
+    }
+}
+
+impl<S: WriteStream> Drop for BitStreamWriter<'_, S> {
+    fn drop(&mut self)
+    {
+        self.Flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::PROTOCOL_VERSION;
+
+    #[test]
+    fn bitstream_reader_writer() {
+        let mut serialized_ss = CDataStream::new(SER::NETWORK, PROTOCOL_VERSION);
+
+        {
+            let mut bit_writer = BitStreamWriter::new(&mut serialized_ss);
+            bit_writer.Write(0, 1);
+            bit_writer.Write(2, 2);
+            bit_writer.Write(6, 3);
+            bit_writer.Write(11, 4);
+            bit_writer.Write(1, 5);
+            bit_writer.Write(32, 6);
+            bit_writer.Write(7, 7);
+            bit_writer.Write(30497, 16);
+            bit_writer.Flush();
+        }
+
+        let mut serialized_copy = serialized_ss.clone();
+        let mut serialized_int1: u32 = 0;
+        serialized_copy.unserialize(&mut serialized_int1).unwrap();
+        assert_eq!(serialized_int1, 0x7700C35A); // NOTE: Serialized as LE
+        let mut serialized_int2: u16 = 0;
+        serialized_copy.unserialize(&mut serialized_int2).unwrap();
+        assert_eq!(serialized_int2, 0x1072); // NOTE: Serialized as LE
+
+        let mut bit_reader = BitStreamReader::new(&mut serialized_ss);
+        assert_eq!(bit_reader.Read(1).unwrap(), 0);
+        assert_eq!(bit_reader.Read(2).unwrap(), 2);
+        assert_eq!(bit_reader.Read(3).unwrap(), 6);
+        assert_eq!(bit_reader.Read(4).unwrap(), 11);
+        assert_eq!(bit_reader.Read(5).unwrap(), 1);
+        assert_eq!(bit_reader.Read(6).unwrap(), 32);
+        assert_eq!(bit_reader.Read(7).unwrap(), 7);
+        assert_eq!(bit_reader.Read(16).unwrap(), 30497);
+        assert!(bit_reader.Read(8).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2021 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use primitive_types::H256;
+
+/** uint256 is kept as a H256 holding the little-endian bytes of the number,
+ * so its hex representation is the byte-reversed encoding of the storage.
+ */
+
+//std::string base_blob<BITS>::GetHex() const
+pub fn GetHex(h: &H256) -> String
+{
+    let mut rev = h.to_fixed_bytes();
+    rev.reverse();
+    hex::encode(rev)
+}
+
+//void base_blob<BITS>::SetHex(const char* psz)
+pub fn SetHex(h: &mut H256, psz: &str)
+{
+    *h = H256::zero();
+
+    // skip leading spaces and 0x prefix
+    let mut psz = psz.trim_start();
+    if psz.starts_with("0x") || psz.starts_with("0X") {
+        psz = &psz[2..];
+    }
+
+    // hex string to uint, least significant digits first
+    let digits: Vec<u8> = psz.bytes().take_while(|c| c.is_ascii_hexdigit()).collect();
+    let data = h.as_bytes_mut();
+    let mut p1 = 0;
+    let mut i = digits.len();
+    while i > 0 && p1 < data.len() {
+        i -= 1;
+        let mut b = (digits[i] as char).to_digit(16).unwrap() as u8;
+        if i > 0 {
+            i -= 1;
+            b |= ((digits[i] as char).to_digit(16).unwrap() as u8) << 4;
+        }
+        data[p1] = b;
+        p1 += 1;
+    }
+}
+
+/* uint256 from const char *.
+ * This is a separate function because the constructor uint256(const char*) can result
+ * in dangerously catching uint256(0).
+ */
+//inline uint256 uint256S(const char *str)
+pub fn uint256S(str: &str) -> H256
+{
+    let mut rv = H256::zero();
+    SetHex(&mut rv, str);
+    rv
+}
@@ -0,0 +1,184 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+use primitive_types::H256;
+use crate::hash::HashWriter;
+use crate::key::CKey;
+use crate::pubkey::CPubKey;
+use crate::script::standard::{CTxDestination, IsValidDestination};
+use super::strencodings::{DecodeBase64, EncodeBase64};
+
+/**
+ * Text used to signify that a signed message follows and to prevent
+ * inadvertently signing a transaction.
+ */
+//extern const std::string MESSAGE_MAGIC;
+pub const MESSAGE_MAGIC: &str = "Bitcoin Signed Message:\n";
+
+/** The result of a signed message verification.
+ * Message verification takes as an input:
+ * - address (with whose private key the message is supposed to have been signed)
+ * - signature
+ * - message
+ */
+//enum class MessageVerificationResult {
+#[derive(Debug, PartialEq)]
+pub enum MessageVerificationResult {
+    /** The provided address is invalid. */
+    ERR_INVALID_ADDRESS,
+
+    /** The provided address is valid but does not refer to a public key. */
+    ERR_ADDRESS_NO_KEY,
+
+    /** The provided signature couldn't be parsed (maybe invalid base64). */
+    ERR_MALFORMED_SIGNATURE,
+
+    /** A public key could not be recovered from the provided signature and message. */
+    ERR_PUBKEY_NOT_RECOVERED,
+
+    /** The message was not signed with the private key of the provided address. */
+    ERR_NOT_SIGNED,
+
+    /** The message verification was successful. */
+    OK,
+}
+
+/** Verify a signed message.
+ * Core takes the address as a string; there is no address decoding in this
+ * tree yet, so the caller passes the already decoded destination.
+ * @param[in] destination Signer's bitcoin address, it must refer to a public key.
+ * @param[in] signature The signature in base64 format.
+ * @param[in] message The message that was signed.
+ * @return result code */
+//MessageVerificationResult MessageVerify(
+//    const std::string& address,
+//    const std::string& signature,
+//    const std::string& message)
+pub fn MessageVerify(destination: &CTxDestination, signature: &str, message: &str) -> MessageVerificationResult
+{
+    if !IsValidDestination(destination) {
+        return MessageVerificationResult::ERR_INVALID_ADDRESS;
+    }
+
+    let keyID = match destination {
+        CTxDestination::PKHash(keyID) => keyID,
+        _ => return MessageVerificationResult::ERR_ADDRESS_NO_KEY,
+    };
+
+    let signature_bytes = match DecodeBase64(signature) {
+        Some(bytes) => bytes,
+        None => return MessageVerificationResult::ERR_MALFORMED_SIGNATURE,
+    };
+
+    let mut pubkey = CPubKey { vch: [0u8; 65] };
+    if !pubkey.RecoverCompact(&MessageHash(message), &signature_bytes) {
+        return MessageVerificationResult::ERR_PUBKEY_NOT_RECOVERED;
+    }
+
+    if pubkey.GetID() != *keyID {
+        return MessageVerificationResult::ERR_NOT_SIGNED;
+    }
+
+    MessageVerificationResult::OK
+}
+
+/** Sign a message.
+ * @param[in] privkey Private key to sign with.
+ * @param[in] message The message to sign.
+ * @param[out] signature Signature, base64 encoded, only set if true is returned.
+ * @return true if signing was successful. */
+//bool MessageSign(
+//    const CKey& privkey,
+//    const std::string& message,
+//    std::string& signature)
+pub fn MessageSign(privkey: &mut CKey, message: &str, signature: &mut String) -> bool
+{
+    let mut signature_bytes = vec![];
+
+    if !privkey.SignCompact(&MessageHash(message), &mut signature_bytes) {
+        return false;
+    }
+
+    *signature = EncodeBase64(&signature_bytes);
+
+    true
+}
+
+/**
+ * Hashes a message for signing and verification in a manner that prevents
+ * inadvertently signing a transaction.
+ */
+//uint256 MessageHash(const std::string& message)
+pub fn MessageHash(message: &str) -> H256
+{
+    let mut hasher = HashWriter::new() << &MESSAGE_MAGIC.to_string() << &message.to_string();
+    hasher.GetHash()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H160;
+
+    fn privkey() -> CKey {
+        let mut privkey = CKey::new();
+        privkey.set(&hex::decode("D97F5108F11CDA6EEEBAAA420FEF0726B1F898060B98489FA3098463C0032866").unwrap().try_into().unwrap(), true);
+        privkey
+    }
+
+    #[test]
+    fn test_message_sign() {
+        let message = "Trust no one";
+        let expected_signature = "IPojfrX2dfPnH26UegfbGQQLrdK844DlHq5157/P6h57WyuS/Qsl+h/WSVGDF4MUi4rWSswW38oimDYfNNUBUOk=";
+
+        let mut empty_key = CKey::new();
+        let mut generated_signature = String::new();
+        assert!(!MessageSign(&mut empty_key, message, &mut generated_signature));
+
+        let mut privkey = privkey();
+        assert!(MessageSign(&mut privkey, message, &mut generated_signature));
+        assert_eq!(generated_signature, expected_signature);
+    }
+
+    #[test]
+    fn test_message_verify() {
+        let message = "Trust no one";
+        let mut privkey = privkey();
+        let keyID = privkey.GetPubKey().GetID();
+        let mut signature = String::new();
+        assert!(MessageSign(&mut privkey, message, &mut signature));
+
+        let dest = CTxDestination::PKHash(keyID);
+        assert_eq!(MessageVerify(&CTxDestination::CNoDestination, &signature, message),
+                   MessageVerificationResult::ERR_INVALID_ADDRESS);
+        assert_eq!(MessageVerify(&CTxDestination::ScriptHash(keyID), &signature, message),
+                   MessageVerificationResult::ERR_ADDRESS_NO_KEY);
+        assert_eq!(MessageVerify(&dest, "invalid signature, not in base64 encoding", message),
+                   MessageVerificationResult::ERR_MALFORMED_SIGNATURE);
+        assert_eq!(MessageVerify(&dest, "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=", message),
+                   MessageVerificationResult::ERR_PUBKEY_NOT_RECOVERED);
+        assert_eq!(MessageVerify(&dest, &signature, "I never signed this"),
+                   MessageVerificationResult::ERR_NOT_SIGNED);
+        assert_eq!(MessageVerify(&CTxDestination::PKHash(H160::zero()), &signature, message),
+                   MessageVerificationResult::ERR_NOT_SIGNED);
+        assert_eq!(MessageVerify(&dest, &signature, message), MessageVerificationResult::OK);
+    }
+
+    #[test]
+    fn test_message_hash() {
+        let unsigned_tx = "...";
+        let prefixed_message = format!("{}{}", "\x18Bitcoin Signed Message:\n\x03", unsigned_tx);
+
+        let mut hasher = HashWriter::new();
+        hasher.write(prefixed_message.as_bytes());
+        let message_hash1 = hasher.GetHash();
+        let message_hash2 = MessageHash(unsigned_tx);
+
+        assert_eq!(message_hash1, message_hash2);
+        let mut hasher = HashWriter::new();
+        hasher.write(unsigned_tx.as_bytes());
+        assert!(message_hash1 != hasher.GetHash());
+    }
+}
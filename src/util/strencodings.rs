@@ -0,0 +1,121 @@
+// Copyright (c) 2009-2010 Satoshi Nakamoto
+// Copyright (c) 2009-2022 The Bitcoin Core developers
+// Distributed under the MIT software license, see the accompanying
+// file COPYING or http://www.opensource.org/licenses/mit-license.php.
+
+/**
+ * Utilities for converting data from/to strings.
+ */
+
+/** Convert from one power-of-2 number base to another. */
+//template<int frombits, int tobits, bool pad, typename O, typename It, typename I = IntIdentity>
+//bool ConvertBits(O outfn, It it, It end, I infn = {}) {
+pub fn ConvertBits<const frombits: usize, const tobits: usize, const pad: bool, O, It, I>(mut outfn: O, it: It, infn: I) -> bool
+    where O: FnMut(usize), It: Iterator, I: Fn(It::Item) -> i32
+{
+    let mut acc: usize = 0;
+    let mut bits: usize = 0;
+    let maxv: usize = (1 << tobits) - 1;
+    let max_acc: usize = (1 << (frombits + tobits - 1)) - 1;
+    for item in it {
+        let v = infn(item);
+        if v < 0 {
+            return false;
+        }
+        acc = ((acc << frombits) | v as usize) & max_acc;
+        bits += frombits;
+        while bits >= tobits {
+            bits -= tobits;
+            outfn((acc >> bits) & maxv);
+        }
+    }
+    if pad {
+        if bits != 0 {
+            outfn((acc << (tobits - bits)) & maxv);
+        }
+    } else if bits >= frombits || ((acc << (tobits - bits)) & maxv) != 0 {
+        return false;
+    }
+    true
+}
+
+//std::string EncodeBase64(Span<const unsigned char> input)
+pub fn EncodeBase64(input: &[u8]) -> String
+{
+    const pbase64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut str = String::with_capacity(((input.len() + 2) / 3) * 4);
+    ConvertBits::<8, 6, true, _, _, _>(|v| str.push(pbase64[v] as char), input.iter(), |c: &u8| *c as i32);
+    while str.len() % 4 != 0 {
+        str.push('=');
+    }
+    str
+}
+
+//std::optional<std::vector<unsigned char>> DecodeBase64(std::string_view str)
+pub fn DecodeBase64(str: &str) -> Option<Vec<u8>>
+{
+    const decode64_table: [i8; 256] = [
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 62, -1, -1, -1, 63,
+        52, 53, 54, 55, 56, 57, 58, 59, 60, 61, -1, -1, -1, -1, -1, -1,
+        -1, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+        15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, -1, -1, -1, -1, -1,
+        -1, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40,
+        41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+    ];
+
+    let mut str = str.as_bytes();
+    if str.len() % 4 != 0 {
+        return None;
+    }
+    /* One or two = characters at the end are permitted. */
+    if str.last() == Some(&b'=') {
+        str = &str[..str.len() - 1];
+    }
+    if str.last() == Some(&b'=') {
+        str = &str[..str.len() - 1];
+    }
+
+    let mut ret = Vec::with_capacity((str.len() * 3) / 4);
+    let valid = ConvertBits::<6, 8, false, _, _, _>(|c| ret.push(c as u8), str.iter(), |c: &u8| decode64_table[*c as usize] as i32);
+    if !valid {
+        return None;
+    }
+    Some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let vstrIn = ["", "f", "fo", "foo", "foob", "fooba", "foobar"];
+        let vstrOut = ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy"];
+        for i in 0..vstrIn.len() {
+            let strEnc = EncodeBase64(vstrIn[i].as_bytes());
+            assert_eq!(strEnc, vstrOut[i]);
+            let strDec = DecodeBase64(&strEnc).unwrap();
+            assert_eq!(strDec, vstrIn[i].as_bytes());
+        }
+
+        // Decoding strings with embedded NUL characters should fail
+        assert!(DecodeBase64("invalid\0").is_none());
+        assert!(DecodeBase64("nQB/pZw=").is_some());
+        assert!(DecodeBase64("nQB/pZw=\0invalid").is_none());
+        assert!(DecodeBase64("nQB/pZw=invalid\0").is_none());
+        // Wrong length and trailing bits
+        assert!(DecodeBase64("Zm9").is_none());
+        assert!(DecodeBase64("Zh==").is_none());
+    }
+}